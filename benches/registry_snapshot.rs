@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This bench resolves the packages of recorded registry snapshots.
+//!
+//! Snapshots are in the format of the crates.io index, one JSON object per version:
+//! `{"name": "foo", "vers": "1.0.0", "deps": [{"name": "bar", "req": "^1.2"}], "yanked": false}`,
+//! any other field being ignored. Concatenating the files of a clone of
+//! <https://github.com/rust-lang/crates.io-index> gives a snapshot of the whole registry.
+//!
+//! The snapshots are the `*.jsonl` files in `test-examples`, plus the file in the
+//! `PUBGRUB_REGISTRY_SNAPSHOT` environment variable if it is set. Yanked versions, pre-releases,
+//! dev-dependencies and optional dependencies are left out, so that the newest version of each
+//! package usually resolves.
+
+use std::time::Duration;
+
+use criterion::*;
+use serde::Deserialize;
+
+use pubgrub::{
+    parse_requirement, resolve, DependencyConstraints, OfflineDependencyProvider, Ranges,
+    SemanticVersion,
+};
+
+#[derive(Deserialize)]
+struct IndexVersion {
+    name: String,
+    vers: String,
+    #[serde(default)]
+    deps: Vec<IndexDependency>,
+    #[serde(default)]
+    yanked: bool,
+}
+
+#[derive(Deserialize)]
+struct IndexDependency {
+    name: String,
+    req: String,
+    #[serde(default)]
+    optional: bool,
+    kind: Option<String>,
+    /// The name of the package when the dependency is renamed.
+    package: Option<String>,
+}
+
+type RegistryProvider = OfflineDependencyProvider<String, Ranges<SemanticVersion>>;
+
+/// Reads a snapshot, skipping the versions that can't be parsed.
+fn load_snapshot(data: &str) -> RegistryProvider {
+    let mut dependency_provider = RegistryProvider::new();
+    for line in data.lines().filter(|line| !line.trim().is_empty()) {
+        let version: IndexVersion = serde_json::from_str(line).unwrap();
+        if version.yanked {
+            continue;
+        }
+        // Build metadata doesn't matter for the order of versions.
+        let vers = version.vers.split('+').next().unwrap();
+        let Ok(vers) = vers.parse::<SemanticVersion>() else {
+            continue;
+        };
+        let mut constraints = DependencyConstraints::default();
+        let mut parsed = true;
+        for dependency in version.deps {
+            if dependency.optional || dependency.kind.as_deref() == Some("dev") {
+                continue;
+            }
+            let Ok(range) = parse_requirement(&dependency.req) else {
+                parsed = false;
+                break;
+            };
+            // Target-specific dependencies may require the same package several times.
+            let name = dependency.package.unwrap_or(dependency.name);
+            let range = match constraints.remove(&name) {
+                Some(existing) => range.intersection(&existing),
+                None => range,
+            };
+            constraints.insert(name, range);
+        }
+        if parsed {
+            dependency_provider.add_dependencies(version.name, vers, constraints);
+        }
+    }
+    dependency_provider
+}
+
+fn bench_snapshot(b: &mut Bencher, dependency_provider: &RegistryProvider) {
+    let newest: Vec<_> = dependency_provider
+        .packages()
+        .filter_map(|p| Some((p.clone(), *dependency_provider.versions(p)?.last()?)))
+        .collect();
+    b.iter(|| {
+        for (package, version) in &newest {
+            let _ = resolve(dependency_provider, package.clone(), *version);
+        }
+    });
+}
+
+fn bench_registry_snapshots(c: &mut Criterion) {
+    let mut group = c.benchmark_group("registry_snapshots");
+    group.measurement_time(Duration::from_secs(20));
+
+    let mut snapshots: Vec<_> = std::fs::read_dir("test-examples")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    snapshots.extend(std::env::var_os("PUBGRUB_REGISTRY_SNAPSHOT").map(Into::into));
+    for snapshot in snapshots {
+        let name = snapshot.file_name().unwrap().to_string_lossy().into_owned();
+        let dependency_provider = load_snapshot(&std::fs::read_to_string(&snapshot).unwrap());
+        group.bench_function(name, |b| bench_snapshot(b, &dependency_provider));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_registry_snapshots);
+criterion_main!(benches);
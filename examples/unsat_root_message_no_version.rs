@@ -81,6 +81,18 @@ impl ReportFormatter<Package, Ranges<SemanticVersion>, String> for CustomReportF
                     format!("dependencies of {package} at version {set} are unavailable because {reason}")
                 }
             }
+            External::Denied(package, set, reason) => {
+                if set == &Ranges::full() {
+                    format!("{package} is denied because {reason}")
+                } else {
+                    format!("{package} {set} is denied because {reason}")
+                }
+            }
+            External::Conflict(package, package_set, other, other_set, reason) => {
+                format!(
+                    "{package} {package_set} conflicts with {other} {other_set} because {reason}"
+                )
+            }
             External::FromDependencyOf(package, package_set, dependency, dependency_set) => {
                 if package_set == &Ranges::full() && dependency_set == &Ranges::full() {
                     format!("{package} depends on {dependency}")
@@ -0,0 +1,56 @@
+/* SPDX-License-Identifier: MPL-2.0 */
+
+/* C interface of the pubgrub crate, built with the `capi` feature. */
+
+#ifndef PUBGRUB_H
+#define PUBGRUB_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The callback succeeded, or a solution was found. */
+#define PUBGRUB_OK 0
+/* Returned by the dependencies callback when the dependencies of a version are unknown. */
+#define PUBGRUB_UNAVAILABLE 1
+/* There is no solution, the message of the resolution explains why. */
+#define PUBGRUB_NO_SOLUTION 2
+/* A callback failed or an argument was invalid. */
+#define PUBGRUB_ERROR 3
+
+typedef struct PubgrubVersions PubgrubVersions;
+typedef struct PubgrubDependencies PubgrubDependencies;
+typedef struct PubgrubResolution PubgrubResolution;
+
+/* Lists the versions of `package` with pubgrub_versions_push, in any order. */
+typedef int (*PubgrubVersionsCallback)(void *context, const char *package,
+                                       PubgrubVersions *versions);
+
+/* Lists the dependencies of `package` at `version` with pubgrub_dependencies_add. */
+typedef int (*PubgrubDependenciesCallback)(void *context, const char *package, uint64_t version,
+                                           PubgrubDependencies *dependencies);
+
+void pubgrub_versions_push(PubgrubVersions *versions, uint64_t version);
+
+/* Allows the versions `lower <= v < upper`, without upper bound if `upper` is UINT64_MAX. */
+int pubgrub_dependencies_add(PubgrubDependencies *dependencies, const char *package,
+                             uint64_t lower, uint64_t upper);
+
+int pubgrub_resolve(void *context, PubgrubVersionsCallback versions,
+                    PubgrubDependenciesCallback dependencies, const char *package,
+                    uint64_t version, PubgrubResolution **resolution);
+
+size_t pubgrub_resolution_len(const PubgrubResolution *resolution);
+const char *pubgrub_resolution_package(const PubgrubResolution *resolution, size_t index);
+uint64_t pubgrub_resolution_version(const PubgrubResolution *resolution, size_t index);
+const char *pubgrub_resolution_message(const PubgrubResolution *resolution);
+void pubgrub_resolution_free(PubgrubResolution *resolution);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PUBGRUB_H */
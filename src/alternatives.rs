@@ -0,0 +1,172 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Enumerate the solutions of a resolution, not only the first one.
+//!
+//! An upgrade tool may want to offer a few candidate plans, such as the newest versions, then
+//! the solutions keeping an older version of some package. [resolve_iter] yields each solution
+//! once, starting with the solution of [resolve](crate::resolve). After a solution with the
+//! decisions `p1 = v1, ..., pn = vn`, the remaining solutions are split into the ones without
+//! `v1`, the ones with `v1` but without `v2`, and so on, each resolved with these constraints.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::internal::Incompatibility;
+use crate::solver::{Solver, SolverHooks};
+use crate::{
+    Dependencies, DependencyProvider, PubGrubError, SelectedDependencies, Term, VersionSet,
+};
+
+/// Keeps the dependencies retrieved so far, so that the provider is asked once for each version
+/// over all the resolutions.
+pub(crate) struct Recorder<DP: DependencyProvider> {
+    #[allow(clippy::type_complexity)]
+    pub(crate) dependencies: Vec<(DP::P, DP::V, Dependencies<DP::P, DP::VS, DP::M>)>,
+}
+
+impl<DP: DependencyProvider> SolverHooks<DP> for Recorder<DP> {
+    fn on_dependencies(
+        &mut self,
+        _solver: &Solver<DP>,
+        package: &DP::P,
+        version: &DP::V,
+        dependencies: &Dependencies<DP::P, DP::VS, DP::M>,
+    ) {
+        self.dependencies
+            .push((package.clone(), version.clone(), dependencies.clone()));
+    }
+}
+
+/// A part of the solutions: the versions fixed, and the versions excluded.
+struct Subspace<DP: DependencyProvider> {
+    fixed: Vec<(DP::P, DP::V)>,
+    excluded: Vec<(DP::P, DP::V)>,
+}
+
+/// The solutions of a resolution, returned by [resolve_iter].
+pub struct Solutions<'a, DP: DependencyProvider> {
+    dependency_provider: &'a DP,
+    package: DP::P,
+    version: DP::V,
+    recorder: Recorder<DP>,
+    /// The parts of the solutions left to resolve, in the order they will be resolved.
+    subspaces: VecDeque<Subspace<DP>>,
+    /// The solutions yielded so far.
+    ///
+    /// The subspaces only constrain the packages that are selected, so a solution without one of
+    /// the fixed packages can be found again in another subspace.
+    found: Vec<SelectedDependencies<DP>>,
+}
+
+impl<DP: DependencyProvider> Solutions<'_, DP> {
+    /// The first solution of `subspace`, with the decisions in order.
+    #[allow(clippy::type_complexity)]
+    fn resolve(
+        &mut self,
+        subspace: &Subspace<DP>,
+    ) -> Result<(SelectedDependencies<DP>, Vec<(DP::P, DP::V)>), PubGrubError<DP>> {
+        let mut solver = Solver::new(self.package.clone(), self.version.clone());
+        solver
+            .replay(&self.recorder.dependencies, &[])
+            .map_err(PubGrubError::NoSolution)?;
+        let state = &mut solver.state;
+        for (package, version) in &subspace.fixed {
+            let package = state.package_store.alloc(package.clone());
+            let others = DP::VS::singleton(version.clone()).complement();
+            state.add_incompatibility(Incompatibility::no_versions(
+                package,
+                Term::Positive(others),
+            ));
+        }
+        for (package, version) in &subspace.excluded {
+            let package = state.package_store.alloc(package.clone());
+            let excluded = DP::VS::singleton(version.clone());
+            state.add_incompatibility(Incompatibility::no_versions(
+                package,
+                Term::Positive(excluded),
+            ));
+        }
+        let solution = solver.solve_with_hooks(self.dependency_provider, &mut self.recorder)?;
+        Ok((solution, solver.decisions()))
+    }
+}
+
+impl<DP: DependencyProvider> Iterator for Solutions<'_, DP> {
+    type Item = Result<SelectedDependencies<DP>, PubGrubError<DP>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(subspace) = self.subspaces.pop_front() {
+            let first = self.found.is_empty() && subspace.excluded.is_empty();
+            let (solution, decisions) = match self.resolve(&subspace) {
+                Ok(solved) => solved,
+                Err(PubGrubError::NoSolution(_)) if !first => continue,
+                Err(err) => {
+                    self.subspaces.clear();
+                    return Some(Err(err));
+                }
+            };
+            // Split the other solutions of the subspace on the decisions, in order.
+            let mut fixed = subspace.fixed;
+            for (package, version) in decisions {
+                if package == self.package || fixed.iter().any(|(p, _)| *p == package) {
+                    continue;
+                }
+                let mut excluded = subspace.excluded.clone();
+                excluded.push((package.clone(), version.clone()));
+                self.subspaces.push_back(Subspace {
+                    fixed: fixed.clone(),
+                    excluded,
+                });
+                fixed.push((package, version));
+            }
+            if !self.found.contains(&solution) {
+                self.found.push(solution.clone());
+                return Some(Ok(solution));
+            }
+        }
+        None
+    }
+}
+
+/// Lazily enumerate the solutions for a given package + version pair.
+///
+/// The first item is the result of [resolve](crate::resolve), including its error if there is
+/// no solution. The next ones are the other solutions, each yielded once, in breadth-first
+/// order: the alternatives to the first decisions of a solution, usually its direct
+/// dependencies, come before the alternatives to its later decisions.
+/// Resolving each alternative takes a whole resolution, but the dependencies of each version
+/// are only retrieved once.
+///
+/// ```
+/// # use pubgrub::{resolve_iter, OfflineDependencyProvider, Ranges};
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+/// dependency_provider.add_dependencies("foo", 1u32, []);
+/// dependency_provider.add_dependencies("foo", 2u32, [("bar", Ranges::full())]);
+/// dependency_provider.add_dependencies("bar", 1u32, []);
+///
+/// let solutions: Vec<_> = resolve_iter(&dependency_provider, "root", 1u32)
+///     .map(|solution| solution.unwrap())
+///     .collect();
+/// assert_eq!(solutions.len(), 2);
+/// assert_eq!((solutions[0]["foo"], solutions[1]["foo"]), (2, 1));
+/// ```
+pub fn resolve_iter<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Solutions<'_, DP> {
+    Solutions {
+        dependency_provider,
+        package,
+        version: version.into(),
+        recorder: Recorder {
+            dependencies: Vec::new(),
+        },
+        subspaces: VecDeque::from([Subspace {
+            fixed: Vec::new(),
+            excluded: Vec::new(),
+        }]),
+        found: Vec::new(),
+    }
+}
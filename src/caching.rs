@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Cache the dependencies retrieved from a provider.
+//!
+//! The solver doesn't ask twice for the dependencies of the same version within a resolution,
+//! but resolving again, after a retry, for another environment or with other root
+//! dependencies, starts from scratch. A [CachingProvider] keeps the answers of the provider
+//! between resolutions, including the versions whose dependencies are
+//! [Unavailable](Dependencies::Unavailable), so that slow providers don't each have to
+//! implement the same cache.
+
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::delegate::delegate_provider;
+use crate::{Dependencies, DependencyProvider, Map};
+
+/// A [DependencyProvider] forwarding to another one, remembering the dependencies it answered.
+///
+/// Errors are not cached, the provider is asked again next time. Entries stay until
+/// [invalidated](Self::invalidate), for example when a version is published again with new
+/// metadata.
+///
+/// ```
+/// # use pubgrub::{resolve, CachingProvider, OfflineDependencyProvider, Ranges};
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+/// dependency_provider.add_dependencies("foo", 1u32, []);
+///
+/// let cached = CachingProvider::new(&dependency_provider);
+/// resolve(&cached, "root", 1u32).unwrap();
+/// assert_eq!(cached.len(), 2);
+/// // Resolving again only uses the cache.
+/// resolve(&cached, "root", 1u32).unwrap();
+/// cached.invalidate_package(&"foo");
+/// assert_eq!(cached.len(), 1);
+/// ```
+pub struct CachingProvider<'a, DP: DependencyProvider> {
+    provider: &'a DP,
+    #[allow(clippy::type_complexity)]
+    cache: RefCell<Map<DP::P, BTreeMap<DP::V, Dependencies<DP::P, DP::VS, DP::M>>>>,
+}
+
+impl<'a, DP: DependencyProvider> CachingProvider<'a, DP> {
+    /// Cache the answers of `provider`, starting empty.
+    pub fn new(provider: &'a DP) -> Self {
+        Self {
+            provider,
+            cache: RefCell::new(Map::default()),
+        }
+    }
+
+    /// The number of versions whose dependencies are cached.
+    pub fn len(&self) -> usize {
+        self.cache.borrow().values().map(BTreeMap::len).sum()
+    }
+
+    /// Whether no dependencies are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget the dependencies of `version` of `package`.
+    pub fn invalidate(&self, package: &DP::P, version: &DP::V) {
+        if let Some(versions) = self.cache.borrow_mut().get_mut(package) {
+            versions.remove(version);
+        }
+    }
+
+    /// Forget the dependencies of all the versions of `package`.
+    pub fn invalidate_package(&self, package: &DP::P) {
+        self.cache.borrow_mut().remove(package);
+    }
+
+    /// Forget all the dependencies.
+    pub fn clear(&self) {
+        self.cache.borrow_mut().clear();
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn cached(
+        &self,
+        package: &DP::P,
+        version: &DP::V,
+    ) -> Option<Dependencies<DP::P, DP::VS, DP::M>> {
+        self.cache.borrow().get(package)?.get(version).cloned()
+    }
+
+    fn insert(
+        &self,
+        package: &DP::P,
+        version: &DP::V,
+        dependencies: &Dependencies<DP::P, DP::VS, DP::M>,
+    ) {
+        self.cache
+            .borrow_mut()
+            .entry(package.clone())
+            .or_default()
+            .insert(version.clone(), dependencies.clone());
+    }
+}
+
+impl<DP: DependencyProvider> DependencyProvider for CachingProvider<'_, DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, choose_version,
+        choose_version_in, should_cancel, prefetch, on_conflict, on_backtrack, known_versions,
+        versions_sharing_dependencies,
+    );
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        if let Some(dependencies) = self.cached(package, version) {
+            return Ok(dependencies);
+        }
+        let dependencies = self.provider.get_dependencies(package, version)?;
+        self.insert(package, version, &dependencies);
+        Ok(dependencies)
+    }
+
+    fn get_dependencies_batch(
+        &self,
+        package: &Self::P,
+        versions: &[Self::V],
+    ) -> Result<Vec<Dependencies<Self::P, Self::VS, Self::M>>, Self::Err> {
+        if let Some(dependencies) = self.cached(package, &versions[0]) {
+            return Ok(vec![dependencies]);
+        }
+        let batch = self.provider.get_dependencies_batch(package, versions)?;
+        for (version, dependencies) in versions.iter().zip(&batch) {
+            self.insert(package, version, dependencies);
+        }
+        Ok(batch)
+    }
+}
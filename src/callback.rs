@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A dependency provider backed by callbacks.
+//!
+//! Bindings to other languages, like a JavaScript playground compiled to WebAssembly, can't
+//! implement [DependencyProvider] directly. They provide two functions instead, one listing the
+//! versions of a package and one returning the dependencies of a version, and
+//! [CallbackDependencyProvider] implements the rest like [OfflineDependencyProvider] does.
+//!
+//! [OfflineDependencyProvider]: crate::OfflineDependencyProvider
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::Reverse;
+use core::fmt::{self, Debug, Display};
+
+use thiserror::Error;
+
+use crate::{
+    Dependencies, DependencyProvider, ListVersions, Map, Package, PackageResolutionStatistics,
+    VersionSet,
+};
+
+/// An error returned by a callback of [CallbackDependencyProvider].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{0}")]
+pub struct CallbackError(pub String);
+
+type VersionsCallback<'a, P, V> = Box<dyn Fn(&P) -> Result<Vec<V>, CallbackError> + 'a>;
+type DependenciesCallback<'a, P, VS, M> =
+    Box<dyn Fn(&P, &<VS as VersionSet>::V) -> Result<Dependencies<P, VS, M>, CallbackError> + 'a>;
+
+/// A [DependencyProvider] calling a function to list the versions of a package, and another one
+/// to get the dependencies of a version.
+///
+/// The versions of each package are only requested once per provider. Packages are picked and
+/// versions chosen as in [OfflineDependencyProvider](crate::OfflineDependencyProvider).
+///
+/// ```
+/// # use pubgrub::{resolve, CallbackDependencyProvider, Dependencies, Ranges};
+/// let provider = CallbackDependencyProvider::<&str, Ranges<u32>, String>::new(
+///     |package| Ok(if *package == "root" { vec![1] } else { vec![1, 2] }),
+///     |package, _version| {
+///         Ok(Dependencies::Available(match *package {
+///             "root" => [("menu", Ranges::full())].into_iter().collect(),
+///             _ => Default::default(),
+///         }))
+///     },
+/// );
+/// let solution = resolve(&provider, "root", 1u32).unwrap();
+/// assert_eq!(solution["menu"], 2);
+/// ```
+pub struct CallbackDependencyProvider<
+    'a,
+    P: Package,
+    VS: VersionSet,
+    M: Eq + Clone + Debug + Display,
+> {
+    versions: VersionsCallback<'a, P, VS::V>,
+    dependencies: DependenciesCallback<'a, P, VS, M>,
+    /// The sorted versions of the packages requested so far.
+    cache: RefCell<Map<P, Vec<VS::V>>>,
+}
+
+impl<'a, P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display>
+    CallbackDependencyProvider<'a, P, VS, M>
+{
+    /// Resolve with `versions` listing the versions of a package in any order, and
+    /// `dependencies` returning the dependencies of a package version.
+    pub fn new(
+        versions: impl Fn(&P) -> Result<Vec<VS::V>, CallbackError> + 'a,
+        dependencies: impl Fn(&P, &VS::V) -> Result<Dependencies<P, VS, M>, CallbackError> + 'a,
+    ) -> Self {
+        Self {
+            versions: Box::new(versions),
+            dependencies: Box::new(dependencies),
+            cache: RefCell::new(Map::default()),
+        }
+    }
+
+    /// Calls `f` with the sorted versions of `package`, calling the callback if needed.
+    fn with_versions<T>(
+        &self,
+        package: &P,
+        f: impl FnOnce(&[VS::V]) -> T,
+    ) -> Result<T, CallbackError> {
+        if let Some(versions) = self.cache.borrow().get(package) {
+            return Ok(f(versions));
+        }
+        let mut versions = (self.versions)(package)?;
+        versions.sort_unstable();
+        versions.dedup();
+        let result = f(&versions);
+        self.cache.borrow_mut().insert(package.clone(), versions);
+        Ok(result)
+    }
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Debug
+    for CallbackDependencyProvider<'_, P, VS, M>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackDependencyProvider")
+            .field("cache", &self.cache)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> DependencyProvider
+    for CallbackDependencyProvider<'_, P, VS, M>
+{
+    type P = P;
+    type V = VS::V;
+    type VS = VS;
+    type M = M;
+
+    type Err = CallbackError;
+
+    fn choose_version(&self, package: &P, range: &VS) -> Result<Option<VS::V>, CallbackError> {
+        self.with_versions(package, |versions| {
+            versions.iter().rev().find(|v| range.contains(v)).cloned()
+        })
+    }
+
+    type Priority = (u32, Reverse<usize>);
+
+    fn prioritize(
+        &self,
+        package: &P,
+        range: &VS,
+        package_statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        let version_count = self
+            .with_versions(package, |versions| {
+                versions.iter().filter(|v| range.contains(v)).count()
+            })
+            // Pick the package first, so that `choose_version` reports the error.
+            .unwrap_or(0);
+        if version_count == 0 {
+            return (u32::MAX, Reverse(0));
+        }
+        (package_statistics.conflict_count(), Reverse(version_count))
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &P,
+        version: &VS::V,
+    ) -> Result<Dependencies<P, VS, M>, CallbackError> {
+        (self.dependencies)(package, version)
+    }
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> ListVersions
+    for CallbackDependencyProvider<'_, P, VS, M>
+{
+    fn list_versions(
+        &self,
+        package: &P,
+    ) -> Result<impl Iterator<Item = VS::V> + '_, CallbackError> {
+        self.with_versions(package, |versions| versions.to_vec())
+            .map(IntoIterator::into_iter)
+    }
+}
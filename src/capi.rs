@@ -0,0 +1,281 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A C interface to the solver, for package managers written in other languages.
+//!
+//! The header is `include/pubgrub.h`. Packages are NUL-terminated UTF-8 strings and versions are
+//! `uint64_t`, ordered as integers. The caller provides two callbacks through [pubgrub_resolve],
+//! one listing the versions of a package and one listing the dependencies of a version, and the
+//! solver picks the newest version allowed by the constraints.
+//!
+//! The library is built as a `cdylib` or `staticlib` with the `capi` feature, for example with
+//! `cargo rustc --release --features capi --crate-type cdylib`. A panic aborts the process
+//! instead of unwinding into the caller.
+
+use alloc::boxed::Box;
+use alloc::ffi::CString;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_int, c_void, CStr};
+use core::ptr;
+
+use crate::{
+    resolve, CallbackDependencyProvider, CallbackError, DefaultStringReporter, Dependencies,
+    DependencyConstraints, PubGrubError, Ranges, Reporter,
+};
+
+/// The callback succeeded, or a solution was found.
+pub const PUBGRUB_OK: c_int = 0;
+/// Returned by the dependencies callback when the dependencies of a version are unknown, the
+/// solver then avoids that version.
+pub const PUBGRUB_UNAVAILABLE: c_int = 1;
+/// There is no solution, the message of the resolution explains why.
+pub const PUBGRUB_NO_SOLUTION: c_int = 2;
+/// A callback failed or an argument was invalid.
+pub const PUBGRUB_ERROR: c_int = 3;
+
+/// The versions of a package, filled by the versions callback.
+pub struct PubgrubVersions(Vec<u64>);
+
+/// The dependencies of a version, filled by the dependencies callback.
+pub struct PubgrubDependencies(DependencyConstraints<String, Ranges<u64>>);
+
+/// The outcome of [pubgrub_resolve].
+pub struct PubgrubResolution {
+    solution: Vec<(CString, u64)>,
+    message: Option<CString>,
+}
+
+/// Lists the versions of `package` with [pubgrub_versions_push], in any order.
+///
+/// Returns [PUBGRUB_OK], or any other value to abort the resolution with [PUBGRUB_ERROR].
+pub type PubgrubVersionsCallback = extern "C" fn(
+    context: *mut c_void,
+    package: *const c_char,
+    versions: *mut PubgrubVersions,
+) -> c_int;
+
+/// Lists the dependencies of `package` at `version` with [pubgrub_dependencies_add].
+///
+/// Returns [PUBGRUB_OK], [PUBGRUB_UNAVAILABLE], or any other value to abort the resolution with
+/// [PUBGRUB_ERROR].
+pub type PubgrubDependenciesCallback = extern "C" fn(
+    context: *mut c_void,
+    package: *const c_char,
+    version: u64,
+    dependencies: *mut PubgrubDependencies,
+) -> c_int;
+
+/// Adds `version` to the versions of a package.
+///
+/// # Safety
+///
+/// `versions` must be the pointer received by the versions callback, during that call.
+#[no_mangle]
+pub unsafe extern "C" fn pubgrub_versions_push(versions: *mut PubgrubVersions, version: u64) {
+    if let Some(versions) = versions.as_mut() {
+        versions.0.push(version);
+    }
+}
+
+/// Allows the versions `lower <= v < upper` of the dependency `package`, there is no upper bound
+/// if `upper` is `UINT64_MAX`.
+///
+/// Adding the same dependency several times allows the union of the ranges. Returns
+/// [PUBGRUB_OK], or [PUBGRUB_ERROR] if `package` is not valid UTF-8.
+///
+/// # Safety
+///
+/// `dependencies` must be the pointer received by the dependencies callback, during that call,
+/// and `package` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pubgrub_dependencies_add(
+    dependencies: *mut PubgrubDependencies,
+    package: *const c_char,
+    lower: u64,
+    upper: u64,
+) -> c_int {
+    let (Some(dependencies), Some(package)) = (dependencies.as_mut(), to_str(package)) else {
+        return PUBGRUB_ERROR;
+    };
+    let range = if upper == u64::MAX {
+        Ranges::higher_than(lower)
+    } else {
+        Ranges::between(lower, upper)
+    };
+    let entry = dependencies
+        .0
+        .entry(package.to_string())
+        .or_insert_with(Ranges::empty);
+    *entry = entry.union(&range);
+    PUBGRUB_OK
+}
+
+/// Resolves the dependencies of `package` at `version`, calling the callbacks with `context`.
+///
+/// Returns [PUBGRUB_OK], [PUBGRUB_NO_SOLUTION] or [PUBGRUB_ERROR]. Unless an argument is
+/// invalid, `resolution` is set to the outcome, to be freed with [pubgrub_resolution_free].
+///
+/// # Safety
+///
+/// `package` must be a NUL-terminated string and `resolution` a valid pointer. The callbacks
+/// receive a package string valid during the call only.
+#[no_mangle]
+pub unsafe extern "C" fn pubgrub_resolve(
+    context: *mut c_void,
+    versions: PubgrubVersionsCallback,
+    dependencies: PubgrubDependenciesCallback,
+    package: *const c_char,
+    version: u64,
+    resolution: *mut *mut PubgrubResolution,
+) -> c_int {
+    let Some(package) = to_str(package) else {
+        return PUBGRUB_ERROR;
+    };
+    if resolution.is_null() {
+        return PUBGRUB_ERROR;
+    }
+
+    let callback_error = |code| CallbackError(format!("the callback returned {code}"));
+    let provider = CallbackDependencyProvider::<String, Ranges<u64>, String>::new(
+        |package| {
+            let package = to_c_string(package);
+            let mut list = PubgrubVersions(Vec::new());
+            match versions(context, package.as_ptr(), &mut list) {
+                PUBGRUB_OK => Ok(list.0),
+                code => Err(callback_error(code)),
+            }
+        },
+        |package, version| {
+            let package = to_c_string(package);
+            let mut list = PubgrubDependencies(DependencyConstraints::default());
+            match dependencies(context, package.as_ptr(), *version, &mut list) {
+                PUBGRUB_OK => Ok(Dependencies::Available(list.0)),
+                PUBGRUB_UNAVAILABLE => Ok(Dependencies::Unavailable(
+                    "its dependencies could not be determined".to_string(),
+                )),
+                code => Err(callback_error(code)),
+            }
+        },
+    );
+
+    let (code, outcome) = match resolve(&provider, package.to_string(), version) {
+        Ok(solution) => {
+            let mut solution: Vec<_> = solution
+                .into_iter()
+                .map(|(package, version)| (to_c_string(&package), version))
+                .collect();
+            solution.sort_unstable();
+            (
+                PUBGRUB_OK,
+                PubgrubResolution {
+                    solution,
+                    message: None,
+                },
+            )
+        }
+        Err(PubGrubError::NoSolution(mut derivation_tree)) => {
+            derivation_tree.collapse_no_versions();
+            let message = DefaultStringReporter::report(&derivation_tree);
+            (PUBGRUB_NO_SOLUTION, PubgrubResolution::failed(&message))
+        }
+        Err(err) => (PUBGRUB_ERROR, PubgrubResolution::failed(&err.to_string())),
+    };
+    *resolution = Box::into_raw(Box::new(outcome));
+    code
+}
+
+/// The number of packages in the solution, zero if the resolution failed.
+///
+/// # Safety
+///
+/// `resolution` must come from [pubgrub_resolve] and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pubgrub_resolution_len(resolution: *const PubgrubResolution) -> usize {
+    resolution.as_ref().map_or(0, |r| r.solution.len())
+}
+
+/// The package at `index` in the solution, sorted by package, or `NULL` if out of bounds.
+///
+/// The string lives as long as the resolution.
+///
+/// # Safety
+///
+/// `resolution` must come from [pubgrub_resolve] and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pubgrub_resolution_package(
+    resolution: *const PubgrubResolution,
+    index: usize,
+) -> *const c_char {
+    resolution
+        .as_ref()
+        .and_then(|r| r.solution.get(index))
+        .map_or(ptr::null(), |(package, _)| package.as_ptr())
+}
+
+/// The version of the package at `index` in the solution, zero if out of bounds.
+///
+/// # Safety
+///
+/// `resolution` must come from [pubgrub_resolve] and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pubgrub_resolution_version(
+    resolution: *const PubgrubResolution,
+    index: usize,
+) -> u64 {
+    resolution
+        .as_ref()
+        .and_then(|r| r.solution.get(index))
+        .map_or(0, |(_, version)| *version)
+}
+
+/// Why the resolution failed, `NULL` if it succeeded.
+///
+/// The string lives as long as the resolution.
+///
+/// # Safety
+///
+/// `resolution` must come from [pubgrub_resolve] and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pubgrub_resolution_message(
+    resolution: *const PubgrubResolution,
+) -> *const c_char {
+    resolution
+        .as_ref()
+        .and_then(|r| r.message.as_ref())
+        .map_or(ptr::null(), |message| message.as_ptr())
+}
+
+/// Frees a resolution, doing nothing if it is `NULL`.
+///
+/// # Safety
+///
+/// `resolution` must come from [pubgrub_resolve] and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pubgrub_resolution_free(resolution: *mut PubgrubResolution) {
+    if !resolution.is_null() {
+        drop(Box::from_raw(resolution));
+    }
+}
+
+impl PubgrubResolution {
+    fn failed(message: &str) -> Self {
+        Self {
+            solution: Vec::new(),
+            message: Some(to_c_string(message)),
+        }
+    }
+}
+
+/// The string behind `ptr`, `None` if it is `NULL` or not UTF-8.
+unsafe fn to_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    CStr::from_ptr(ptr).to_str().ok()
+}
+
+/// Strings only come from C strings or from our messages, dropping NUL bytes is a safeguard.
+fn to_c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).expect("NUL bytes were removed")
+}
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Save the progress of a resolution and resume it later.
+//!
+//! The work of a long resolution is mostly in the provider, retrieving the dependencies of
+//! versions one at a time from a registry. A [Checkpoint] keeps these dependencies along with
+//! the decisions of the solver, and [resolve_from_checkpoint] resumes the resolution from there,
+//! without asking the provider for them again. With the `serde` feature, checkpoints can be
+//! saved to disk to survive a restart of the process, or be sent to another machine.
+
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+
+use crate::solver::{Solver, SolverHooks};
+use crate::{
+    Dependencies, DependencyProvider, Package, PubGrubError, SelectedDependencies, VersionSet,
+};
+
+/// The progress of a resolution: the dependencies retrieved from the provider and the versions
+/// decided so far.
+///
+/// The incompatibilities learned from conflicts are not saved, since they are derived from the
+/// dependencies again without querying the provider. Resuming assumes that the provider gives
+/// the same answers as when the checkpoint was saved.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "P: serde::Serialize, VS: serde::Serialize, VS::V: serde::Serialize, M: serde::Serialize",
+        deserialize = "P: serde::Deserialize<'de>, VS: serde::Deserialize<'de>, VS::V: serde::Deserialize<'de>, M: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Checkpoint<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
+    root_package: P,
+    root_version: VS::V,
+    #[allow(clippy::type_complexity)]
+    dependencies: Vec<(P, VS::V, Dependencies<P, VS, M>)>,
+    decisions: Vec<(P, VS::V)>,
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Checkpoint<P, VS, M> {
+    /// The checkpoint of a resolution of a root package that didn't start yet.
+    pub fn new(root_package: P, root_version: impl Into<VS::V>) -> Self {
+        Self {
+            root_package,
+            root_version: root_version.into(),
+            dependencies: Vec::new(),
+            decisions: Vec::new(),
+        }
+    }
+
+    /// The root package and version of the resolution.
+    pub fn root(&self) -> (&P, &VS::V) {
+        (&self.root_package, &self.root_version)
+    }
+
+    /// The dependencies retrieved from the provider, in the order of the calls.
+    #[allow(clippy::type_complexity)]
+    pub fn dependencies(&self) -> &[(P, VS::V, Dependencies<P, VS, M>)] {
+        &self.dependencies
+    }
+
+    /// The versions decided when the checkpoint was saved, in the order of the decisions.
+    pub fn decisions(&self) -> &[(P, VS::V)] {
+        &self.decisions
+    }
+}
+
+/// Resume the resolution of `checkpoint`, calling `save` with a new checkpoint each time the
+/// dependencies of a version are retrieved from the provider.
+///
+/// A resolution starts from [Checkpoint::new]. Saving a checkpoint to disk at every call can be
+/// slow, `save` can skip them, for example keeping one in a hundred calls.
+pub fn resolve_from_checkpoint<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    checkpoint: Checkpoint<DP::P, DP::VS, DP::M>,
+    mut save: impl FnMut(&Checkpoint<DP::P, DP::VS, DP::M>),
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let mut solver = Solver::new(
+        checkpoint.root_package.clone(),
+        checkpoint.root_version.clone(),
+    );
+    solver
+        .replay(&checkpoint.dependencies, &checkpoint.decisions)
+        .map_err(PubGrubError::NoSolution)?;
+    solver.solve_with_hooks(
+        dependency_provider,
+        &mut Saving {
+            checkpoint,
+            save: &mut save,
+        },
+    )
+}
+
+/// Updates the checkpoint each time dependencies are retrieved.
+struct Saving<'a, P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
+    checkpoint: Checkpoint<P, VS, M>,
+    save: &'a mut dyn FnMut(&Checkpoint<P, VS, M>),
+}
+
+impl<DP: DependencyProvider> SolverHooks<DP> for Saving<'_, DP::P, DP::VS, DP::M> {
+    fn on_dependencies(
+        &mut self,
+        solver: &Solver<DP>,
+        package: &DP::P,
+        version: &DP::V,
+        dependencies: &Dependencies<DP::P, DP::VS, DP::M>,
+    ) {
+        self.checkpoint
+            .dependencies
+            .push((package.clone(), version.clone(), dependencies.clone()));
+        self.checkpoint.decisions = solver.decisions();
+        (self.save)(&self.checkpoint);
+    }
+}
@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Packages that can't be selected together.
+//!
+//! Some packages exclude each other without depending on each other: only one of `openssl` and
+//! `boringssl` may be linked, a package declaring `Conflicts: foo (<< 2)` can't be installed
+//! next to an old `foo`. Such conflicts could be encoded with a dependency on a made-up
+//! package, at the cost of confusing reports. [resolve_with_conflicts] adds them to the solver as
+//! incompatibilities of their own, reported as [External::Conflict] with their reason:
+//! "openssl conflicts with boringssl (both provide libssl)".
+//!
+//! [External::Conflict]: crate::External::Conflict
+
+use alloc::vec::Vec;
+
+use crate::internal::Incompatibility;
+use crate::solver::Solver;
+use crate::{DependencyProvider, Package, PubGrubError, SelectedDependencies, VersionSet};
+
+/// Versions of two packages that can't both be selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclaredConflict<P: Package, VS: VersionSet, M> {
+    /// The first package.
+    pub package: P,
+    /// The conflicting versions of [package](Self::package).
+    pub versions: VS,
+    /// The second package, distinct from the first one.
+    pub other: P,
+    /// The conflicting versions of [other](Self::other).
+    pub other_versions: VS,
+    /// Why the packages conflict, displayed in reports.
+    pub reason: M,
+}
+
+impl<P: Package, VS: VersionSet, M: Clone> DeclaredConflict<P, VS, M> {
+    /// `package` at `versions` conflicts with `other` at `other_versions`.
+    pub fn new(package: P, versions: VS, other: P, other_versions: VS, reason: M) -> Self {
+        Self {
+            package,
+            versions,
+            other,
+            other_versions,
+            reason,
+        }
+    }
+
+    /// Any version of each package conflicts with any version of the others, so at most one of
+    /// them is selected.
+    pub fn mutually_exclusive(packages: &[P], reason: M) -> Vec<Self> {
+        let mut conflicts = Vec::new();
+        for (i, package) in packages.iter().enumerate() {
+            for other in &packages[i + 1..] {
+                conflicts.push(Self::new(
+                    package.clone(),
+                    VS::full(),
+                    other.clone(),
+                    VS::full(),
+                    reason.clone(),
+                ));
+            }
+        }
+        conflicts
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// never selecting conflicting versions together.
+///
+/// ```
+/// # use pubgrub::{
+/// #     resolve_with_conflicts, DeclaredConflict, DefaultStringReporter, OfflineDependencyProvider,
+/// #     PubGrubError, Ranges, Reporter,
+/// # };
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("curl", Ranges::full()), ("grpc", Ranges::full())]);
+/// dependency_provider.add_dependencies("curl", 1u32, [("openssl", Ranges::full())]);
+/// dependency_provider.add_dependencies("grpc", 1u32, [("boringssl", Ranges::full())]);
+/// dependency_provider.add_dependencies("openssl", 3u32, []);
+/// dependency_provider.add_dependencies("boringssl", 1u32, []);
+///
+/// let conflicts = DeclaredConflict::mutually_exclusive(
+///     &["openssl", "boringssl"],
+///     "both provide libssl".to_string(),
+/// );
+/// let Err(PubGrubError::NoSolution(tree)) =
+///     resolve_with_conflicts(&dependency_provider, "root", 1u32, &conflicts)
+/// else {
+///     unreachable!()
+/// };
+/// let report = DefaultStringReporter::report(&tree);
+/// assert!(report.contains("openssl conflicts with boringssl (both provide libssl)"));
+/// ```
+pub fn resolve_with_conflicts<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    conflicts: &[DeclaredConflict<DP::P, DP::VS, DP::M>],
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let mut solver = Solver::new(package, version.into());
+    let state = &mut solver.state;
+    for conflict in conflicts {
+        assert!(
+            conflict.package != conflict.other,
+            "{} can't conflict with itself",
+            conflict.package
+        );
+        let package = state.package_store.alloc(conflict.package.clone());
+        let other = state.package_store.alloc(conflict.other.clone());
+        state.add_incompatibility(Incompatibility::conflict(
+            package,
+            conflict.versions.clone(),
+            other,
+            conflict.other_versions.clone(),
+            conflict.reason.clone(),
+        ));
+    }
+    solver.solve(dependency_provider)
+}
@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Detect dependency cycles among the selected packages.
+//!
+//! The solver is happy to select packages that depend on each other, which is fine for runtime
+//! dependencies in most ecosystems. Build dependencies usually can't be cyclic, since each
+//! package must be built before its dependents: [find_cycles] lists the cycles of a solution,
+//! and [resolve_acyclic] rejects solutions containing one with
+//! [PubGrubError::CyclicDependency].
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{resolve, Dependencies, DependencyProvider, Map, PubGrubError, SelectedDependencies};
+
+/// The dependency cycles among the packages of `solution`.
+///
+/// Each cycle is a path of packages where every package depends on the next one, and the last
+/// one depends on the first one. A package depending on itself is a cycle of length one. The
+/// list is empty if and only if the dependencies are acyclic, but when packages depend on each
+/// other through several paths, not all the cycles are listed.
+pub fn find_cycles<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    solution: &SelectedDependencies<DP>,
+) -> Result<Vec<Vec<DP::P>>, PubGrubError<DP>> {
+    let mut graph: Map<&DP::P, Vec<&DP::P>> = Map::default();
+    for (package, version) in solution {
+        let dependencies = dependency_provider
+            .get_dependencies(package, version)
+            .map_err(|err| PubGrubError::ErrorRetrievingDependencies {
+                package: package.clone(),
+                version: version.clone(),
+                source: err,
+                required_by: Vec::new(),
+                decided: Vec::new(),
+            })?;
+        let edges = match dependencies {
+            Dependencies::Available(dependencies) => dependencies
+                .keys()
+                .filter_map(|dependency| solution.get_key_value(dependency).map(|(p, _)| p))
+                .collect(),
+            Dependencies::Unavailable(_) => Vec::new(),
+        };
+        graph.insert(package, edges);
+    }
+
+    // Depth-first search, a dependency on a package of the current path closes a cycle.
+    let mut cycles = Vec::new();
+    let mut done: Map<&DP::P, bool> = Map::default();
+    for start in solution.keys() {
+        if done.contains_key(start) {
+            continue;
+        }
+        done.insert(start, false);
+        let mut path: Vec<(&DP::P, usize)> = vec![(start, 0)];
+        while let Some(&(package, index)) = path.last() {
+            let Some(&dependency) = graph[package].get(index) else {
+                done.insert(package, true);
+                path.pop();
+                continue;
+            };
+            path.last_mut().unwrap().1 += 1;
+            match done.get(dependency) {
+                None => {
+                    done.insert(dependency, false);
+                    path.push((dependency, 0));
+                }
+                Some(false) => {
+                    let cycle_start = path.iter().position(|(p, _)| *p == dependency).unwrap();
+                    cycles.push(
+                        path[cycle_start..]
+                            .iter()
+                            .map(|(p, _)| (*p).clone())
+                            .collect(),
+                    );
+                }
+                Some(true) => {}
+            }
+        }
+    }
+    Ok(cycles)
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// failing with [PubGrubError::CyclicDependency] if the selected packages depend on each other.
+///
+/// The cycle is found once the resolution is done, other solutions without cycles are not
+/// searched for.
+pub fn resolve_acyclic<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let solution = resolve(dependency_provider, package, version)?;
+    match find_cycles(dependency_provider, &solution)?
+        .into_iter()
+        .next()
+    {
+        Some(cycle) => Err(PubGrubError::CyclicDependency { cycle }),
+        None => Ok(solution),
+    }
+}
@@ -0,0 +1,360 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! [Debian](https://www.debian.org/doc/debian-policy/ch-controlfields.html#version) versions and
+//! version relations.
+//!
+//! [DebianVersion] implements the ordering of `dpkg --compare-versions`, with epochs, upstream
+//! versions and Debian revisions, and [parse_debian_relation] turns a relation like `(>= 1.2-1)`
+//! into a [`Ranges<DebianVersion>`](Ranges), which is a [VersionSet].
+//!
+//! [VersionSet]: crate::VersionSet
+
+use alloc::string::{String, ToString};
+use core::cmp::Ordering;
+use core::fmt::{self, Display};
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
+
+use thiserror::Error;
+
+use crate::Ranges;
+
+/// Error parsing a [DebianVersion] or a relation.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DebianParseError {
+    /// The version is not a valid Debian version.
+    #[error("'{version}' is not a valid Debian version")]
+    InvalidVersion {
+        /// Version that was being parsed.
+        version: String,
+    },
+    /// The relation doesn't start with one of `<<`, `<=`, `=`, `>=` and `>>`, or its version is
+    /// invalid.
+    #[error("cannot parse the relation '{relation}'")]
+    InvalidRelation {
+        /// Relation that was being parsed.
+        relation: String,
+    },
+}
+
+/// A version of a Debian package, `[epoch:]upstream_version[-debian_revision]`.
+///
+/// Versions are compared like `dpkg --compare-versions`: the epochs as numbers, then the upstream
+/// versions and the revisions part by part, numbers as numbers and the other characters with
+/// letters before symbols and `~` before anything, even the end. A missing epoch is `0` and a
+/// missing revision is the same as `0`, so `1.0`, `0:1.0` and `1.0-0` are equal.
+///
+/// ```
+/// # use pubgrub::DebianVersion;
+/// let version = |s: &str| s.parse::<DebianVersion>().unwrap();
+/// assert!(version("1.0~rc1-1") < version("1.0-1"));
+/// assert!(version("1.0-1") < version("1.0-1ubuntu1"));
+/// assert!(version("2.10") > version("2.9"));
+/// assert!(version("1:0.5") > version("9.9"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct DebianVersion {
+    epoch: u64,
+    upstream: String,
+    revision: String,
+}
+
+impl DebianVersion {
+    /// The epoch, `0` when not specified.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The upstream version, `1.2` in `1:1.2-3`.
+    pub fn upstream(&self) -> &str {
+        &self.upstream
+    }
+
+    /// The Debian revision, `3` in `1:1.2-3`, empty when not specified.
+    pub fn revision(&self) -> &str {
+        &self.revision
+    }
+}
+
+/// The parts of an upstream version or a revision: non-digits followed by a number, without its
+/// leading zeros.
+fn parts(s: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = s;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let digits = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (non_digits, after) = rest.split_at(digits);
+        let end = after
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after.len());
+        let (number, after) = after.split_at(end);
+        rest = after;
+        Some((non_digits, number.trim_start_matches('0')))
+    })
+}
+
+/// The order of a character of the non-digits, `None` for the end.
+fn order(c: Option<u8>) -> i32 {
+    match c {
+        Some(b'~') => -1,
+        None => 0,
+        Some(c) if c.is_ascii_alphabetic() => i32::from(c),
+        Some(c) => i32::from(c) + 256,
+    }
+}
+
+/// Compare upstream versions or revisions like dpkg.
+fn compare(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (parts(a), parts(b));
+    loop {
+        let ((a_non_digits, a_number), (b_non_digits, b_number)) = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (a, b) => (a.unwrap_or_default(), b.unwrap_or_default()),
+        };
+        let len = a_non_digits.len().max(b_non_digits.len());
+        for i in 0..len {
+            let a_order = order(a_non_digits.as_bytes().get(i).copied());
+            let b_order = order(b_non_digits.as_bytes().get(i).copied());
+            match a_order.cmp(&b_order) {
+                Ordering::Equal => {}
+                ordering => return ordering,
+            }
+        }
+        match (a_number.len(), a_number).cmp(&(b_number.len(), b_number)) {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+    }
+}
+
+/// Hash the parts that [compare] doesn't ignore: the end compares like an empty part.
+fn hash_parts<H: Hasher>(s: &str, state: &mut H) {
+    let mut empty_parts = 0usize;
+    for part in parts(s) {
+        if part == ("", "") {
+            empty_parts += 1;
+        } else {
+            for _ in 0..empty_parts {
+                ("", "").hash(state);
+            }
+            empty_parts = 0;
+            part.hash(state);
+        }
+    }
+    // Separate the upstream version from the revision.
+    0xffu8.hash(state);
+}
+
+impl PartialEq for DebianVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for DebianVersion {}
+
+impl PartialOrd for DebianVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DebianVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch
+            .cmp(&other.epoch)
+            .then_with(|| compare(&self.upstream, &other.upstream))
+            .then_with(|| compare(&self.revision, &other.revision))
+    }
+}
+
+impl Hash for DebianVersion {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.epoch.hash(state);
+        hash_parts(&self.upstream, state);
+        hash_parts(&self.revision, state);
+    }
+}
+
+impl Display for DebianVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}:", self.epoch)?;
+        }
+        write!(f, "{}", self.upstream)?;
+        if !self.revision.is_empty() {
+            write!(f, "-{}", self.revision)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DebianVersion {
+    type Err = DebianParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DebianParseError::InvalidVersion {
+            version: s.to_string(),
+        };
+        let version = s.trim();
+        let (epoch, rest) = match version.split_once(':') {
+            Some((epoch, rest)) => (epoch.parse().map_err(|_| invalid())?, rest),
+            None => (0, version),
+        };
+        let (upstream, revision) = match rest.rsplit_once('-') {
+            Some((upstream, revision)) => (upstream, revision),
+            None => (rest, ""),
+        };
+        let valid = |part: &str, symbols: &str| {
+            part.chars()
+                .all(|c| c.is_ascii_alphanumeric() || symbols.contains(c))
+        };
+        if upstream.is_empty()
+            || !valid(upstream, ".+~-")
+            || (rest.contains('-') && revision.is_empty())
+            || !valid(revision, ".+~")
+        {
+            return Err(invalid());
+        }
+        Ok(Self {
+            epoch,
+            upstream: upstream.to_string(),
+            revision: revision.to_string(),
+        })
+    }
+}
+
+/// Parse a Debian version relation, like `(>= 1.2-1)` in `Depends: foo (>= 1.2-1)`, into the
+/// set of matching versions.
+///
+/// The operators are `<<`, `<=`, `=`, `>=` and `>>`, and the obsolete `<` and `>`, which mean
+/// `<=` and `>=`. The parentheses are optional, and an empty relation matches any version.
+///
+/// ```
+/// # use pubgrub::{parse_debian_relation, DebianVersion};
+/// let relation = parse_debian_relation("(>> 1.2-1)").unwrap();
+/// let version = |s: &str| s.parse::<DebianVersion>().unwrap();
+/// assert!(relation.contains(&version("1.2-1ubuntu1")));
+/// assert!(!relation.contains(&version("1.2-1")));
+/// ```
+pub fn parse_debian_relation(relation: &str) -> Result<Ranges<DebianVersion>, DebianParseError> {
+    let invalid = || DebianParseError::InvalidRelation {
+        relation: relation.to_string(),
+    };
+    let trimmed = relation.trim();
+    let inner = match trimmed.strip_prefix('(') {
+        Some(rest) => rest.strip_suffix(')').ok_or_else(invalid)?.trim(),
+        None => trimmed,
+    };
+    if inner.is_empty() {
+        return Ok(Ranges::full());
+    }
+    // Longest operators first so that `<<` is not parsed as `<`.
+    let operators = ["<<", "<=", ">>", ">=", "=", "<", ">"];
+    let (operator, version) = operators
+        .iter()
+        .find_map(|op| inner.strip_prefix(op).map(|rest| (*op, rest)))
+        .ok_or_else(invalid)?;
+    let version: DebianVersion = version.parse().map_err(|_| invalid())?;
+    Ok(match operator {
+        "<<" => Ranges::strictly_lower_than(version),
+        "<=" | "<" => Ranges::lower_than(version),
+        "=" => Ranges::singleton(version),
+        ">=" | ">" => Ranges::higher_than(version),
+        ">>" => Ranges::strictly_higher_than(version),
+        _ => unreachable!(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> DebianVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for (input, normalized) in [
+            ("1.0", "1.0"),
+            ("0:1.0", "1.0"),
+            ("2:1.0-1", "2:1.0-1"),
+            ("1.0-beta-2", "1.0-beta-2"),
+            ("1.0+dfsg~rc1-0ubuntu1", "1.0+dfsg~rc1-0ubuntu1"),
+        ] {
+            assert_eq!(version(input).to_string(), normalized, "{input}");
+        }
+        for invalid in ["", "a:1.0", "1.0-", "-1", "1.0_1", "1:1.0-1:2", "1.0-a-"] {
+            assert!(invalid.parse::<DebianVersion>().is_err(), "{invalid}");
+        }
+        assert_eq!(version("1.0-beta-2").upstream(), "1.0-beta");
+    }
+
+    #[test]
+    fn ordering() {
+        // From the dpkg test suite and the Debian policy.
+        let ordered = [
+            "~~",
+            "~~a",
+            "~",
+            "0",
+            "1.0~rc1",
+            "1.0",
+            "1.0-0.1",
+            "1.0-1",
+            "1.0-1ubuntu1",
+            "1.0-1+b1",
+            "1.0a",
+            "1.0+dfsg",
+            "1.0.1",
+            "1.01.2",
+            "1.2",
+            "1.10",
+            "1:0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{:?}", pair);
+        }
+        for (a, b) in [("1.0", "1.0-0"), ("0:1.0", "1.0"), ("1.001", "1.1")] {
+            assert_eq!(version(a), version(b));
+            let mut a_hasher = rustc_hash::FxHasher::default();
+            let mut b_hasher = rustc_hash::FxHasher::default();
+            version(a).hash(&mut a_hasher);
+            version(b).hash(&mut b_hasher);
+            assert_eq!(a_hasher.finish(), b_hasher.finish(), "{a} {b}");
+        }
+    }
+
+    #[test]
+    fn relations() {
+        let matches = |relation: &str, v: &str| {
+            parse_debian_relation(relation)
+                .unwrap()
+                .contains(&version(v))
+        };
+        assert!(matches("(<< 1.0)", "1.0~rc1"));
+        assert!(!matches("(<< 1.0)", "1.0"));
+        assert!(matches("(<= 1.0)", "1.0-0"));
+        assert!(matches("(= 1.0-1)", "0:1.0-1"));
+        assert!(!matches("(= 1.0)", "1.0-1"));
+        assert!(matches(">= 1.0", "1.0+b1"));
+        assert!(matches("(>> 1.0)", "1.0-1"));
+        assert!(matches("(> 1.0)", "1.0"));
+        assert!(matches("", "1.0"));
+        assert!(matches("(>=1.0)", "1.1"));
+
+        assert_eq!(
+            parse_debian_relation("(~ 1.0)"),
+            Err(DebianParseError::InvalidRelation {
+                relation: "(~ 1.0)".to_owned(),
+            })
+        );
+        assert!(parse_debian_relation("(>= 1.0").is_err());
+        assert!(parse_debian_relation("(>= 1.0-)").is_err());
+    }
+}
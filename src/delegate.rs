@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Forward the methods of a [DependencyProvider](crate::DependencyProvider) to a wrapped one.
+
+/// Implements the listed methods of [DependencyProvider](crate::DependencyProvider) by calling
+/// the method of the same name on the provider in the given field of `self`.
+///
+/// Used inside the `impl` of a wrapper, as in `delegate_provider!(provider => prioritize,
+/// should_cancel);`, so that the wrapper only writes the methods it changes. The methods that
+/// are not listed keep their default implementation.
+macro_rules! delegate_provider {
+    ($field:tt => $($method:ident),* $(,)?) => {
+        $($crate::delegate::delegate_provider!(@method $field $method);)*
+    };
+    (@method $field:tt prioritize) => {
+        fn prioritize(
+            &self,
+            package: &Self::P,
+            range: &Self::VS,
+            package_conflicts_counts: &$crate::PackageResolutionStatistics,
+        ) -> Self::Priority {
+            self.$field.prioritize(package, range, package_conflicts_counts)
+        }
+    };
+    (@method $field:tt reprioritize_on_conflict) => {
+        fn reprioritize_on_conflict(&self) -> bool {
+            self.$field.reprioritize_on_conflict()
+        }
+    };
+    (@method $field:tt tie_break_key) => {
+        fn tie_break_key(&self, package: &Self::P) -> u64 {
+            self.$field.tie_break_key(package)
+        }
+    };
+    (@method $field:tt choose_version) => {
+        fn choose_version(
+            &self,
+            package: &Self::P,
+            range: &Self::VS,
+        ) -> Result<Option<Self::V>, Self::Err> {
+            self.$field.choose_version(package, range)
+        }
+    };
+    (@method $field:tt get_dependencies) => {
+        fn get_dependencies(
+            &self,
+            package: &Self::P,
+            version: &Self::V,
+        ) -> Result<$crate::Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+            self.$field.get_dependencies(package, version)
+        }
+    };
+    (@method $field:tt choose_version_in) => {
+        fn choose_version_in(
+            &self,
+            package: &Self::P,
+            range: &Self::VS,
+            solution: &dyn $crate::PartialSolutionView<Self::P, Self::VS>,
+        ) -> Result<Option<Self::V>, Self::Err> {
+            self.$field.choose_version_in(package, range, solution)
+        }
+    };
+    (@method $field:tt should_cancel) => {
+        fn should_cancel(&self) -> Result<(), Self::Err> {
+            self.$field.should_cancel()
+        }
+    };
+    (@method $field:tt on_conflict) => {
+        fn on_conflict(&self, terms: &[(Self::P, $crate::Term<Self::VS>)]) {
+            self.$field.on_conflict(terms)
+        }
+    };
+    (@method $field:tt on_backtrack) => {
+        fn on_backtrack(&self, to_level: u32) {
+            self.$field.on_backtrack(to_level)
+        }
+    };
+    (@method $field:tt prefetch) => {
+        fn prefetch(&self, dependencies: &$crate::DependencyConstraints<Self::P, Self::VS>) {
+            self.$field.prefetch(dependencies)
+        }
+    };
+    (@method $field:tt known_versions) => {
+        fn known_versions(&self, package: &Self::P) -> Option<alloc::vec::Vec<Self::V>> {
+            self.$field.known_versions(package)
+        }
+    };
+    (@method $field:tt get_dependencies_batch) => {
+        fn get_dependencies_batch(
+            &self,
+            package: &Self::P,
+            versions: &[Self::V],
+        ) -> Result<
+            alloc::vec::Vec<$crate::Dependencies<Self::P, Self::VS, Self::M>>,
+            Self::Err,
+        > {
+            self.$field.get_dependencies_batch(package, versions)
+        }
+    };
+    (@method $field:tt versions_sharing_dependencies) => {
+        fn versions_sharing_dependencies(&self, package: &Self::P, version: &Self::V) -> Self::VS {
+            self.$field.versions_sharing_dependencies(package, version)
+        }
+    };
+}
+
+pub(crate) use delegate_provider;
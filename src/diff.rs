@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Compare two solutions.
+//!
+//! After updating a lockfile, package managers summarize what changed, like `cargo update`
+//! printing `Updating foo v1.0.0 -> v1.1.0`. [SolutionDiff] classifies the packages of two
+//! solutions for such summaries.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+
+use crate::{Map, Package};
+
+/// The packages added, removed, upgraded and downgraded between two solutions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolutionDiff<P: Package, V> {
+    /// Packages only in the new solution, with their version.
+    pub added: Map<P, V>,
+    /// Packages only in the old solution, with their version.
+    pub removed: Map<P, V>,
+    /// Packages selected at a higher version, with the old and new versions.
+    pub upgraded: Map<P, (V, V)>,
+    /// Packages selected at a lower version, with the old and new versions.
+    pub downgraded: Map<P, (V, V)>,
+}
+
+impl<P: Package, V: Ord + Clone> SolutionDiff<P, V> {
+    /// The changes from `old` to `new`. Packages selected at the same version are left out.
+    pub fn new(old: &Map<P, V>, new: &Map<P, V>) -> Self {
+        let mut diff = Self {
+            added: Map::default(),
+            removed: Map::default(),
+            upgraded: Map::default(),
+            downgraded: Map::default(),
+        };
+        for (package, new_version) in new {
+            let Some(old_version) = old.get(package) else {
+                diff.added.insert(package.clone(), new_version.clone());
+                continue;
+            };
+            let versions = (old_version.clone(), new_version.clone());
+            if new_version > old_version {
+                diff.upgraded.insert(package.clone(), versions);
+            } else if new_version < old_version {
+                diff.downgraded.insert(package.clone(), versions);
+            }
+        }
+        for (package, old_version) in old {
+            if !new.contains_key(package) {
+                diff.removed.insert(package.clone(), old_version.clone());
+            }
+        }
+        diff
+    }
+
+    /// Whether both solutions select the same versions.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.upgraded.is_empty()
+            && self.downgraded.is_empty()
+    }
+}
+
+/// One line per changed package, sorted by package, such as `Updating foo 1.0.0 -> 1.1.0`.
+impl<P: Package, V: Display> Display for SolutionDiff<P, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines: Vec<(String, String)> = Vec::new();
+        for (package, version) in &self.added {
+            lines.push((package.to_string(), format!("Adding {package} {version}")));
+        }
+        for (package, version) in &self.removed {
+            lines.push((package.to_string(), format!("Removing {package} {version}")));
+        }
+        for (package, (old, new)) in &self.upgraded {
+            lines.push((
+                package.to_string(),
+                format!("Updating {package} {old} -> {new}"),
+            ));
+        }
+        for (package, (old, new)) in &self.downgraded {
+            lines.push((
+                package.to_string(),
+                format!("Downgrading {package} {old} -> {new}"),
+            ));
+        }
+        lines.sort();
+        for (_, line) in lines {
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
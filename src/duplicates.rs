@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Selecting more than one version of a package.
+//!
+//! By default, the solver selects at most one version per package. Some ecosystems allow
+//! duplicates instead: Cargo can link one version per semver-compatible range of a crate, and npm
+//! can install a separate copy for dependents with incompatible requirements.
+//!
+//! A [MultiVersionDependencyProvider] sorts the requirements on a package into slots. Each slot
+//! is solved as its own package, so requirements in the same slot must agree on a single version
+//! while requirements in different slots can be satisfied by distinct versions. Returning the same
+//! slot for every requirement on a package keeps the single version invariant for that package.
+
+use core::fmt::{self, Debug, Display};
+use core::hash::Hash;
+
+use crate::delegate::delegate_provider;
+use crate::{
+    resolve, Dependencies, DependencyConstraints, DependencyProvider, PackageResolutionStatistics,
+    PubGrubError, SelectedDependencies, VersionSet,
+};
+
+/// A [DependencyProvider] that allows selecting multiple versions of the same package.
+pub trait MultiVersionDependencyProvider: DependencyProvider {
+    /// Identifies which copy of a package a requirement is resolved against.
+    type Slot: Clone + Eq + Hash + Debug + Display;
+
+    /// The slot in which a requirement on `package` for versions in `range` is resolved.
+    ///
+    /// Requirements in the same slot share a single version.
+    fn slot(&self, package: &Self::P, range: &Self::VS) -> Self::Slot;
+
+    /// The versions that can be selected for a slot.
+    ///
+    /// Requirements resolved in a slot are restricted to these versions, so that a version
+    /// belongs to a single slot. By default all versions are allowed.
+    fn slot_range(&self, package: &Self::P, slot: &Self::Slot) -> Self::VS {
+        let _ = (package, slot);
+        Self::VS::full()
+    }
+}
+
+/// A package in a given slot, the unit of selection of [resolve_with_duplicates].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PackageSlot<P, S> {
+    /// The package.
+    pub package: P,
+    /// The slot of the package.
+    pub slot: S,
+}
+
+impl<P, S> PackageSlot<P, S> {
+    /// A package in a given slot.
+    pub fn new(package: P, slot: S) -> Self {
+        Self { package, slot }
+    }
+}
+
+impl<P: Display, S: Display> Display for PackageSlot<P, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.package, self.slot)
+    }
+}
+
+/// A [DependencyProvider] resolving each slot of a [MultiVersionDependencyProvider] as its own
+/// package.
+pub struct WithDuplicates<'a, DP>(pub &'a DP);
+
+impl<DP: MultiVersionDependencyProvider> DependencyProvider for WithDuplicates<'_, DP> {
+    type P = PackageSlot<DP::P, DP::Slot>;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(0 => reprioritize_on_conflict, should_cancel);
+
+    fn prioritize(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        package_conflicts_counts: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.0
+            .prioritize(&package.package, range, package_conflicts_counts)
+    }
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.0.choose_version(&package.package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        let dependencies = match self.0.get_dependencies(&package.package, version)? {
+            Dependencies::Unavailable(reason) => return Ok(Dependencies::Unavailable(reason)),
+            Dependencies::Available(dependencies) => dependencies,
+        };
+        let slotted: DependencyConstraints<Self::P, Self::VS> = dependencies
+            .into_iter()
+            .map(|(dependency, range)| {
+                let slot = self.0.slot(&dependency, &range);
+                let range = range.intersection(&self.0.slot_range(&dependency, &slot));
+                (PackageSlot::new(dependency, slot), range)
+            })
+            .collect();
+        Ok(Dependencies::Available(slotted))
+    }
+
+    fn prefetch(&self, dependencies: &DependencyConstraints<Self::P, Self::VS>) {
+        let mut packages: DependencyConstraints<DP::P, DP::VS> = DependencyConstraints::default();
+        for (dependency, range) in dependencies {
+            // Requirements in different slots of a package are prefetched together.
+            let range = match packages.remove(&dependency.package) {
+                Some(previous) => previous.union(range),
+                None => range.clone(),
+            };
+            packages.insert(dependency.package.clone(), range);
+        }
+        self.0.prefetch(&packages)
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// possibly selecting several versions of the same package in different slots.
+#[allow(clippy::type_complexity)]
+pub fn resolve_with_duplicates<DP: MultiVersionDependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<SelectedDependencies<WithDuplicates<'_, DP>>, PubGrubError<WithDuplicates<'_, DP>>> {
+    let version = version.into();
+    let slot = dependency_provider.slot(&package, &DP::VS::singleton(version.clone()));
+    resolve(
+        &WithDuplicates(dependency_provider),
+        PackageSlot::new(package, slot),
+        version,
+    )
+}
@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Version sets over versions numbered by the provider.
+//!
+//! Most registries have a few dozens of versions per package at most. When the provider knows
+//! all of them, it can number them, and the sets of versions the solver works with become sets
+//! of numbers: an [EnumeratedSet] stores them as a bitset, so that intersections and unions are
+//! a few word operations, instead of merging the bounds of [Ranges](crate::Ranges).
+
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display};
+use core::marker::PhantomData;
+
+use crate::VersionSet;
+
+/// A version identified by its position in the list of versions of its package.
+///
+/// The positions should be small, since an [EnumeratedSet] takes one bit per position up to the
+/// highest one it contains.
+pub trait VersionIndex: Debug + Display + Clone + Ord {
+    /// The position of the version.
+    fn index(&self) -> usize;
+
+    /// The version at `index`.
+    fn from_index(index: usize) -> Self;
+}
+
+macro_rules! impl_version_index {
+    ($($t:ty),*) => {
+        $(
+            impl VersionIndex for $t {
+                fn index(&self) -> usize {
+                    *self as usize
+                }
+
+                fn from_index(index: usize) -> Self {
+                    index as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_version_index!(u8, u16, u32, u64, usize);
+
+/// A set of versions stored as a bitset over their [index](VersionIndex::index).
+///
+/// Sets are either finite, or the complement of a finite set, since [VersionSet] assumes that
+/// any version may exist: the full set contains the versions the provider doesn't know about
+/// too.
+///
+/// ```
+/// # use pubgrub::{EnumeratedSet, VersionSet};
+/// let old = EnumeratedSet::from_versions([0u32, 1, 2]);
+/// let recent = EnumeratedSet::from_versions([2u32, 3]);
+/// assert_eq!(old.intersection(&recent), EnumeratedSet::singleton(2));
+/// assert!(old.complement().contains(&3));
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumeratedSet<V> {
+    /// One bit per index, without trailing zero words so that equal sets are equal.
+    words: Vec<u64>,
+    /// Whether the set contains the versions whose bit is unset, instead of the set ones.
+    complement: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    versions: PhantomData<V>,
+}
+
+impl<V: VersionIndex> EnumeratedSet<V> {
+    /// The set of the given versions.
+    pub fn from_versions(versions: impl IntoIterator<Item = V>) -> Self {
+        let mut words = Vec::new();
+        for version in versions {
+            let index = version.index();
+            if words.len() <= index / 64 {
+                words.resize(index / 64 + 1, 0);
+            }
+            words[index / 64] |= 1 << (index % 64);
+        }
+        Self::new(words, false)
+    }
+
+    /// Whether the set only contains finitely many versions.
+    pub fn is_finite(&self) -> bool {
+        !self.complement
+    }
+
+    /// The versions of the set if it is finite, or the versions it does not contain otherwise,
+    /// in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = V> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| V::from_index(i * 64 + bit))
+        })
+    }
+
+    fn new(mut words: Vec<u64>, complement: bool) -> Self {
+        while words.last() == Some(&0) {
+            words.pop();
+        }
+        Self {
+            words,
+            complement,
+            versions: PhantomData,
+        }
+    }
+
+    /// Combine the words of both sets, the missing words being zero.
+    fn zip(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Vec<u64> {
+        let len = self.words.len().max(other.words.len());
+        (0..len)
+            .map(|i| {
+                f(
+                    self.words.get(i).copied().unwrap_or(0),
+                    other.words.get(i).copied().unwrap_or(0),
+                )
+            })
+            .collect()
+    }
+}
+
+impl<V: VersionIndex> VersionSet for EnumeratedSet<V> {
+    type V = V;
+
+    fn empty() -> Self {
+        Self::new(Vec::new(), false)
+    }
+
+    fn singleton(v: Self::V) -> Self {
+        Self::from_versions([v])
+    }
+
+    fn complement(&self) -> Self {
+        Self::new(self.words.clone(), !self.complement)
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        match (self.complement, other.complement) {
+            (false, false) => Self::new(self.zip(other, |a, b| a & b), false),
+            (false, true) => Self::new(self.zip(other, |a, b| a & !b), false),
+            (true, false) => Self::new(self.zip(other, |a, b| !a & b), false),
+            (true, true) => Self::new(self.zip(other, |a, b| a | b), true),
+        }
+    }
+
+    fn contains(&self, v: &Self::V) -> bool {
+        let index = v.index();
+        let set = self
+            .words
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0);
+        set != self.complement
+    }
+
+    fn full() -> Self {
+        Self::new(Vec::new(), true)
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        match (self.complement, other.complement) {
+            (false, false) => Self::new(self.zip(other, |a, b| a | b), false),
+            (false, true) => Self::new(self.zip(other, |a, b| !a & b), true),
+            (true, false) => Self::new(self.zip(other, |a, b| a & !b), true),
+            (true, true) => Self::new(self.zip(other, |a, b| a & b), true),
+        }
+    }
+}
+
+impl<V: VersionIndex> Debug for EnumeratedSet<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl<V: VersionIndex> Display for EnumeratedSet<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.complement, self.words.is_empty()) {
+            (false, true) => return write!(f, "∅"),
+            (true, true) => return write!(f, "*"),
+            (true, false) => write!(f, "not ")?,
+            (false, false) => {}
+        }
+        for (i, version) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            write!(f, "=={}", version)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeSet;
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Sets over the versions `0..100`, with the versions they contain in that domain.
+    fn strategy() -> impl Strategy<Value = (EnumeratedSet<u32>, BTreeSet<u32>)> {
+        (prop::collection::btree_set(0u32..100, 0..10), any::<bool>()).prop_map(
+            |(versions, complement)| {
+                let set = EnumeratedSet::from_versions(versions.iter().copied());
+                if complement {
+                    let all = (0..100).filter(|v| !versions.contains(v)).collect();
+                    (set.complement(), all)
+                } else {
+                    (set, versions)
+                }
+            },
+        )
+    }
+
+    fn members(set: &EnumeratedSet<u32>) -> BTreeSet<u32> {
+        (0..100).filter(|v| set.contains(v)).collect()
+    }
+
+    proptest! {
+        #[test]
+        fn operations_match_sets((a, a_members) in strategy(), (b, b_members) in strategy()) {
+            let intersection = a.intersection(&b);
+            prop_assert_eq!(members(&intersection), &a_members & &b_members);
+            let union = a.union(&b);
+            prop_assert_eq!(members(&union), &a_members | &b_members);
+            prop_assert_eq!(union, a.complement().intersection(&b.complement()).complement());
+            prop_assert_eq!(a.is_disjoint(&b), (&a_members & &b_members).is_empty());
+            prop_assert_eq!(a.subset_of(&b), a_members.is_subset(&b_members));
+            prop_assert_eq!(a.complement().complement(), a);
+        }
+    }
+}
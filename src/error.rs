@@ -2,9 +2,13 @@
 
 //! Handling pubgrub errors.
 
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
 use thiserror::Error;
 
-use crate::{DependencyProvider, DerivationTree};
+use crate::{DependencyProvider, DerivationTree, ExceededLimit, ResolutionStats};
 
 /// There is no solution for this set of dependencies.
 pub type NoSolutionError<DP> = DerivationTree<
@@ -17,12 +21,18 @@ pub type NoSolutionError<DP> = DerivationTree<
 #[derive(Error)]
 pub enum PubGrubError<DP: DependencyProvider> {
     /// There is no solution for this set of dependencies.
+    ///
+    /// The derivation tree is the source of the error, displayed with the
+    /// [DefaultStringReporter](crate::DefaultStringReporter).
     #[error("There is no solution")]
-    NoSolution(NoSolutionError<DP>),
+    NoSolution(#[source] NoSolutionError<DP>),
 
     /// Error arising when the implementer of [DependencyProvider] returned an error in the method
     /// [`get_dependencies`](DependencyProvider::get_dependencies).
-    #[error("Retrieving dependencies of {package} {version} failed")]
+    #[error(
+        "Retrieving dependencies of {package} {version} failed{}",
+        display_required_by(required_by)
+    )]
     ErrorRetrievingDependencies {
         /// Package whose dependencies we want.
         package: DP::P,
@@ -30,22 +40,83 @@ pub enum PubGrubError<DP: DependencyProvider> {
         version: DP::V,
         /// Error raised by the implementer of [DependencyProvider].
         source: DP::Err,
+        /// The decided version depending on [package](Self::ErrorRetrievingDependencies::package),
+        /// then the one depending on it and so on up to the root package.
+        ///
+        /// Empty when the error comes from outside of the solver loop.
+        required_by: Vec<(DP::P, DP::V)>,
+        /// The packages decided before the error, in the order of the decisions.
+        decided: Vec<(DP::P, DP::V)>,
     },
 
     /// Error arising when the implementer of [DependencyProvider] returned an error in the method
     /// [`choose_version`](DependencyProvider::choose_version).
-    #[error("Choosing a version for {package} failed")]
+    #[error(
+        "Choosing a version for {package} failed{}",
+        display_required_by(required_by)
+    )]
     ErrorChoosingVersion {
         /// Package to choose a version for.
         package: DP::P,
         /// Error raised by the implementer of [DependencyProvider].
         source: DP::Err,
+        /// The decided version depending on [package](Self::ErrorChoosingVersion::package), then
+        /// the one depending on it and so on up to the root package.
+        ///
+        /// Empty when the error comes from outside of the solver loop.
+        required_by: Vec<(DP::P, DP::V)>,
+        /// The packages decided before the error, in the order of the decisions.
+        decided: Vec<(DP::P, DP::V)>,
     },
 
     /// Error arising when the implementer of [DependencyProvider]
     /// returned an error in the method [`should_cancel`](DependencyProvider::should_cancel).
     #[error("The solver was cancelled")]
     ErrorInShouldCancel(#[source] DP::Err),
+
+    /// The selected packages depend on each other, returned by
+    /// [resolve_acyclic](crate::resolve_acyclic).
+    #[error("There is a dependency cycle: {}", display_cycle(cycle))]
+    CyclicDependency {
+        /// Packages where each one depends on the next one, and the last one on the first one.
+        cycle: Vec<DP::P>,
+    },
+
+    /// The resolution grew beyond one of the limits given to
+    /// [resolve_with_limits](crate::resolve_with_limits).
+    #[error("The resolution exceeded {0}")]
+    LimitExceeded(ExceededLimit),
+
+    /// The resolution ran out of the time or steps given to
+    /// [resolve_with_options](crate::resolve_with_options).
+    #[error("The resolution timed out")]
+    Timeout {
+        /// The work done by the solver until then.
+        ///
+        /// Resolutions usually time out while backtracking between the versions of the
+        /// [conflicting packages](ResolutionStats::conflicting_packages), they are a good
+        /// starting point for an error message.
+        stats: ResolutionStats<DP::P>,
+    },
+}
+
+fn display_cycle<P: core::fmt::Display>(cycle: &[P]) -> String {
+    let mut path: Vec<String> = cycle.iter().map(|p| p.to_string()).collect();
+    path.extend(cycle.first().map(|p| p.to_string()));
+    path.join(" -> ")
+}
+
+fn display_required_by<P: core::fmt::Display, V: core::fmt::Display>(
+    required_by: &[(P, V)],
+) -> String {
+    if required_by.is_empty() {
+        return String::new();
+    }
+    let path: Vec<String> = required_by
+        .iter()
+        .map(|(p, v)| format!("{p} {v}"))
+        .collect();
+    format!(", needed by {}", path.join(" -> "))
 }
 
 impl<DP: DependencyProvider> From<NoSolutionError<DP>> for PubGrubError<DP> {
@@ -54,31 +125,157 @@ impl<DP: DependencyProvider> From<NoSolutionError<DP>> for PubGrubError<DP> {
     }
 }
 
-impl<DP> std::fmt::Debug for PubGrubError<DP>
+/// The variant of a [PubGrubError], without its fields.
+///
+/// Code that only needs to tell the errors apart, like choosing an exit code or whether to retry,
+/// can match on it without naming the provider type, see [PubGrubError::kind].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// [PubGrubError::NoSolution].
+    NoSolution,
+    /// [PubGrubError::ErrorRetrievingDependencies].
+    RetrievingDependencies,
+    /// [PubGrubError::ErrorChoosingVersion].
+    ChoosingVersion,
+    /// [PubGrubError::ErrorInShouldCancel].
+    Cancelled,
+    /// [PubGrubError::CyclicDependency].
+    CyclicDependency,
+    /// [PubGrubError::LimitExceeded].
+    LimitExceeded,
+    /// [PubGrubError::Timeout].
+    Timeout,
+}
+
+impl ErrorKind {
+    /// Whether the error comes from the provider, which returned an error.
+    pub fn is_provider_error(self) -> bool {
+        matches!(
+            self,
+            Self::RetrievingDependencies | Self::ChoosingVersion | Self::Cancelled
+        )
+    }
+}
+
+impl<DP: DependencyProvider> PubGrubError<DP> {
+    /// The variant of the error.
+    ///
+    /// ```
+    /// # use pubgrub::{resolve, ErrorKind, OfflineDependencyProvider, Ranges};
+    /// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+    /// dependency_provider.add_dependencies("root", 1u32, [("menu", Ranges::singleton(2u32))]);
+    ///
+    /// let err = resolve(&dependency_provider, "root", 1u32).unwrap_err();
+    /// let exit_code = match err.kind() {
+    ///     ErrorKind::NoSolution => 1,
+    ///     kind if kind.is_provider_error() => 2,
+    ///     _ => 3,
+    /// };
+    /// assert_eq!(exit_code, 1);
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NoSolution(_) => ErrorKind::NoSolution,
+            Self::ErrorRetrievingDependencies { .. } => ErrorKind::RetrievingDependencies,
+            Self::ErrorChoosingVersion { .. } => ErrorKind::ChoosingVersion,
+            Self::ErrorInShouldCancel(_) => ErrorKind::Cancelled,
+            Self::CyclicDependency { .. } => ErrorKind::CyclicDependency,
+            Self::LimitExceeded(_) => ErrorKind::LimitExceeded,
+            Self::Timeout { .. } => ErrorKind::Timeout,
+        }
+    }
+
+    /// The error returned by the provider, for the variants where it returned one.
+    pub fn provider_error(&self) -> Option<&DP::Err> {
+        match self {
+            Self::ErrorRetrievingDependencies { source, .. }
+            | Self::ErrorChoosingVersion { source, .. }
+            | Self::ErrorInShouldCancel(source) => Some(source),
+            _ => None,
+        }
+    }
+
+    /// Convert the error of a provider wrapping another one with the same types.
+    pub(crate) fn into_provider<Inner>(self) -> PubGrubError<Inner>
+    where
+        Inner: DependencyProvider<P = DP::P, V = DP::V, VS = DP::VS, M = DP::M, Err = DP::Err>,
+    {
+        match self {
+            Self::NoSolution(tree) => PubGrubError::NoSolution(tree),
+            Self::ErrorRetrievingDependencies {
+                package,
+                version,
+                source,
+                required_by,
+                decided,
+            } => PubGrubError::ErrorRetrievingDependencies {
+                package,
+                version,
+                source,
+                required_by,
+                decided,
+            },
+            Self::ErrorChoosingVersion {
+                package,
+                source,
+                required_by,
+                decided,
+            } => PubGrubError::ErrorChoosingVersion {
+                package,
+                source,
+                required_by,
+                decided,
+            },
+            Self::ErrorInShouldCancel(source) => PubGrubError::ErrorInShouldCancel(source),
+            Self::CyclicDependency { cycle } => PubGrubError::CyclicDependency { cycle },
+            Self::LimitExceeded(limit) => PubGrubError::LimitExceeded(limit),
+            Self::Timeout { stats } => PubGrubError::Timeout { stats },
+        }
+    }
+}
+
+impl<DP> core::fmt::Debug for PubGrubError<DP>
 where
     DP: DependencyProvider,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::NoSolution(err) => f.debug_tuple("NoSolution").field(&err).finish(),
             Self::ErrorRetrievingDependencies {
                 package,
                 version,
                 source,
+                required_by,
+                decided,
             } => f
                 .debug_struct("ErrorRetrievingDependencies")
                 .field("package", package)
                 .field("version", version)
                 .field("source", source)
+                .field("required_by", required_by)
+                .field("decided", decided)
                 .finish(),
-            Self::ErrorChoosingVersion { package, source } => f
+            Self::ErrorChoosingVersion {
+                package,
+                source,
+                required_by,
+                decided,
+            } => f
                 .debug_struct("ErrorChoosingVersion")
                 .field("package", package)
                 .field("source", source)
+                .field("required_by", required_by)
+                .field("decided", decided)
                 .finish(),
             Self::ErrorInShouldCancel(arg0) => {
                 f.debug_tuple("ErrorInShouldCancel").field(arg0).finish()
             }
+            Self::CyclicDependency { cycle } => f
+                .debug_struct("CyclicDependency")
+                .field("cycle", cycle)
+                .finish(),
+            Self::LimitExceeded(limit) => f.debug_tuple("LimitExceeded").field(limit).finish(),
+            Self::Timeout { stats } => f.debug_struct("Timeout").field("stats", stats).finish(),
         }
     }
 }
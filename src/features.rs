@@ -0,0 +1,349 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Optional dependencies activated by features.
+//!
+//! Ecosystems like Cargo (features) or Python (extras) let a package declare optional
+//! dependencies, that are only required when a dependent asks for a given feature of the
+//! package. Features requested by all dependents are unified: a package is selected at a single
+//! version, with the union of all the features requested for it.
+//!
+//! The solver itself only knows about packages and versions. Features are modeled with one
+//! virtual package per (package, feature) pair, which is the classic encoding, but done here
+//! so that providers don't have to:
+//!  - [`FeaturePackage::Package(p)`](FeaturePackage::Package) at version `v` depends on the
+//!    unconditional dependencies of `p` at version `v`.
+//!  - [`FeaturePackage::Feature(p, f)`](FeaturePackage::Feature) at version `v` depends on
+//!    `p` at exactly version `v`, plus the optional dependencies enabled by `f`.
+//!  - Depending on `p` in range `r` with features `f1` and `f2` means depending on
+//!    `Package(p)`, `Feature(p, f1)` and `Feature(p, f2)`, all in range `r`.
+//!
+//! Implement [FeatureDependencyProvider], then resolve with [resolve_with_features].
+//!
+//! ```
+//! # use std::convert::Infallible;
+//! # use pubgrub::{
+//! #     resolve_with_features, FeatureDependencies, FeatureDependencyConstraints,
+//! #     FeatureDependencyProvider, FeaturePackage, Map, PackageResolutionStatistics, Ranges, Set,
+//! # };
+//! #
+//! type NumVS = Ranges<u32>;
+//!
+//! /// `root` depends on `http` with the `tls` feature, which enables `openssl`.
+//! struct Registry;
+//!
+//! impl FeatureDependencyProvider for Registry {
+//!     type P = &'static str;
+//!     type V = u32;
+//!     type VS = NumVS;
+//!     type F = &'static str;
+//!     type M = String;
+//!     type Priority = u32;
+//!     type Err = Infallible;
+//!
+//!     fn prioritize(
+//!         &self,
+//!         _package: &FeaturePackage<&'static str, &'static str>,
+//!         _range: &NumVS,
+//!         _statistics: &PackageResolutionStatistics,
+//!     ) -> u32 {
+//!         0
+//!     }
+//!
+//!     fn choose_version(&self, _package: &&'static str, range: &NumVS) -> Result<Option<u32>, Infallible> {
+//!         Ok(range.contains(&1).then_some(1))
+//!     }
+//!
+//!     fn get_dependencies(
+//!         &self,
+//!         package: &&'static str,
+//!         _version: &u32,
+//!     ) -> Result<FeatureDependencies<&'static str, NumVS, String, &'static str>, Infallible> {
+//!         let mut dependencies = FeatureDependencyConstraints::default();
+//!         let mut features = Map::default();
+//!         match *package {
+//!             "root" => {
+//!                 dependencies.insert("http", (Ranges::full(), Set::from_iter(["tls"])));
+//!             }
+//!             "http" => {
+//!                 let mut tls = FeatureDependencyConstraints::default();
+//!                 tls.insert("openssl", (Ranges::full(), Set::default()));
+//!                 features.insert("tls", tls);
+//!             }
+//!             _ => {}
+//!         }
+//!         Ok(FeatureDependencies::Available { dependencies, features })
+//!     }
+//! }
+//!
+//! let solution = resolve_with_features(&Registry, "root", 1u32).unwrap();
+//! assert_eq!(solution["http"], (1, Set::from_iter(["tls"])));
+//! assert!(solution.contains_key("openssl"));
+//! ```
+
+use core::error::Error;
+use core::fmt::{self, Debug, Display};
+
+use crate::delegate::delegate_provider;
+use crate::{
+    resolve, Dependencies, DependencyConstraints, DependencyProvider, Map, Package,
+    PackageResolutionStatistics, PubGrubError, Set, VersionSet,
+};
+
+/// A package or one of its features, as seen by the solver.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FeaturePackage<P, F> {
+    /// The package itself, with its unconditional dependencies.
+    Package(P),
+    /// A feature of the package, with the optional dependencies it enables.
+    Feature(P, F),
+}
+
+impl<P, F> FeaturePackage<P, F> {
+    /// The underlying package.
+    pub fn package(&self) -> &P {
+        match self {
+            Self::Package(p) | Self::Feature(p, _) => p,
+        }
+    }
+}
+
+impl<P: Display, F: Display> Display for FeaturePackage<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Package(package) => write!(f, "{}", package),
+            Self::Feature(package, feature) => write!(f, "{}[{}]", package, feature),
+        }
+    }
+}
+
+/// Dependencies on packages, each with a version set and the features it requires.
+pub type FeatureDependencyConstraints<P, VS, F> = Map<P, (VS, Set<F>)>;
+
+/// The dependencies of a package version, as returned by a [FeatureDependencyProvider].
+#[derive(Debug, Clone)]
+pub enum FeatureDependencies<P: Package, VS: VersionSet, M, F: Package> {
+    /// Package dependencies are unavailable with the reason why they are missing.
+    Unavailable(M),
+    /// The dependencies of the package version.
+    Available {
+        /// Dependencies that are always required.
+        dependencies: FeatureDependencyConstraints<P, VS, F>,
+        /// The optional dependencies enabled by each feature of the package.
+        ///
+        /// A feature can enable other features of the same package by depending on the package
+        /// itself with those features.
+        features: Map<F, FeatureDependencyConstraints<P, VS, F>>,
+    },
+}
+
+/// Why the dependencies of a package or feature are unavailable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeatureUnavailable<M, F> {
+    /// The provider returned [FeatureDependencies::Unavailable].
+    Unavailable(M),
+    /// The package version does not have the requested feature.
+    UnknownFeature(F),
+}
+
+impl<M: Display, F: Display> Display for FeatureUnavailable<M, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unavailable(reason) => write!(f, "{}", reason),
+            Self::UnknownFeature(feature) => write!(f, "(no feature {})", feature),
+        }
+    }
+}
+
+/// Trait that allows the algorithm to retrieve available packages, their dependencies and their
+/// features.
+///
+/// This is the same as [DependencyProvider], except that dependencies carry features and that
+/// [get_dependencies](Self::get_dependencies) returns optional dependencies keyed by feature.
+/// `get_dependencies` is called once for the package and once for each enabled feature, so
+/// providers doing expensive queries may want to cache its result.
+pub trait FeatureDependencyProvider {
+    /// How this provider stores the name of the packages.
+    type P: Package;
+
+    /// How this provider stores the versions of the packages.
+    type V: Debug + Display + Clone + Ord;
+
+    /// How this provider stores the version requirements for the packages.
+    type VS: VersionSet<V = Self::V>;
+
+    /// How this provider stores the name of the features.
+    type F: Package;
+
+    /// Type for custom incompatibilities, see [DependencyProvider::M].
+    type M: Eq + Clone + Debug + Display;
+
+    /// The type returned from `prioritize`, see [DependencyProvider::Priority].
+    type Priority: Ord + Clone;
+
+    /// The kind of error returned from these methods.
+    type Err: Error + 'static;
+
+    /// Determine the order in which versions are chosen for packages and features,
+    /// see [DependencyProvider::prioritize].
+    fn prioritize(
+        &self,
+        package: &FeaturePackage<Self::P, Self::F>,
+        range: &Self::VS,
+        package_conflicts_counts: &PackageResolutionStatistics,
+    ) -> Self::Priority;
+
+    /// Whether to prioritize all the undecided packages and features again after each conflict,
+    /// see [DependencyProvider::reprioritize_on_conflict].
+    fn reprioritize_on_conflict(&self) -> bool {
+        false
+    }
+
+    /// Select the version to use for a package, see [DependencyProvider::choose_version].
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err>;
+
+    /// Retrieves the dependencies and features of a package version.
+    #[allow(clippy::type_complexity)]
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<FeatureDependencies<Self::P, Self::VS, Self::M, Self::F>, Self::Err>;
+
+    /// This is called fairly regularly during the resolution,
+    /// if it returns an Err then resolution will be terminated.
+    fn should_cancel(&self) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+/// A [DependencyProvider] resolving the virtual packages of a [FeatureDependencyProvider].
+pub struct WithFeatures<'a, DP>(pub &'a DP);
+
+impl<DP: FeatureDependencyProvider> WithFeatures<'_, DP> {
+    fn add_constraints(
+        constraints: FeatureDependencyConstraints<DP::P, DP::VS, DP::F>,
+        dependencies: &mut DependencyConstraints<FeaturePackage<DP::P, DP::F>, DP::VS>,
+    ) {
+        for (package, (range, features)) in constraints {
+            for feature in features {
+                Self::add_constraint(
+                    FeaturePackage::Feature(package.clone(), feature),
+                    range.clone(),
+                    dependencies,
+                );
+            }
+            Self::add_constraint(FeaturePackage::Package(package), range, dependencies);
+        }
+    }
+
+    fn add_constraint(
+        package: FeaturePackage<DP::P, DP::F>,
+        range: DP::VS,
+        dependencies: &mut DependencyConstraints<FeaturePackage<DP::P, DP::F>, DP::VS>,
+    ) {
+        // Features can add requirements on packages already depended on.
+        let range = match dependencies.remove(&package) {
+            Some(previous) => previous.intersection(&range),
+            None => range,
+        };
+        dependencies.insert(package, range);
+    }
+}
+
+impl<DP: FeatureDependencyProvider> DependencyProvider for WithFeatures<'_, DP> {
+    type P = FeaturePackage<DP::P, DP::F>;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = FeatureUnavailable<DP::M, DP::F>;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(0 => prioritize, reprioritize_on_conflict, should_cancel);
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.0.choose_version(package.package(), range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        let (dependencies, mut features) =
+            match self.0.get_dependencies(package.package(), version)? {
+                FeatureDependencies::Unavailable(reason) => {
+                    return Ok(Dependencies::Unavailable(FeatureUnavailable::Unavailable(
+                        reason,
+                    )))
+                }
+                FeatureDependencies::Available {
+                    dependencies,
+                    features,
+                } => (dependencies, features),
+            };
+        let mut constraints = DependencyConstraints::default();
+        match package {
+            FeaturePackage::Package(_) => Self::add_constraints(dependencies, &mut constraints),
+            FeaturePackage::Feature(p, f) => {
+                let Some(optional) = features.remove(f) else {
+                    return Ok(Dependencies::Unavailable(
+                        FeatureUnavailable::UnknownFeature(f.clone()),
+                    ));
+                };
+                // A feature is the package itself with more dependencies, so all features of a
+                // package are unified on the same version.
+                Self::add_constraint(
+                    FeaturePackage::Package(p.clone()),
+                    DP::VS::singleton(version.clone()),
+                    &mut constraints,
+                );
+                Self::add_constraints(optional, &mut constraints);
+            }
+        }
+        Ok(Dependencies::Available(constraints))
+    }
+}
+
+/// The packages selected by [resolve_with_features], with their versions and enabled features.
+pub type FeatureSelectedDependencies<P, V, F> = Map<P, (V, Set<F>)>;
+
+/// Finds a set of packages and features satisfying dependency bounds
+/// for a given package + version pair.
+///
+/// Each selected package is returned once, with the union of the features enabled by its
+/// dependents.
+#[allow(clippy::type_complexity)]
+pub fn resolve_with_features<DP: FeatureDependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<FeatureSelectedDependencies<DP::P, DP::V, DP::F>, PubGrubError<WithFeatures<'_, DP>>> {
+    let provider = WithFeatures(dependency_provider);
+    let solution = resolve(&provider, FeaturePackage::Package(package), version)?;
+    let mut unified: FeatureSelectedDependencies<DP::P, DP::V, DP::F> = Map::default();
+    for (package, version) in solution {
+        match package {
+            FeaturePackage::Package(p) => {
+                unified
+                    .entry(p)
+                    .or_insert_with(|| (version, Set::default()));
+            }
+            FeaturePackage::Feature(p, f) => {
+                unified
+                    .entry(p)
+                    .or_insert_with(|| (version, Set::default()))
+                    .1
+                    .insert(f);
+            }
+        }
+    }
+    Ok(unified)
+}
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Resolve without discovering new packages.
+//!
+//! With `--offline` or `--frozen`, package managers must not reach the network: only the
+//! packages of the lockfile, or already in a local cache, can be selected. [resolve_frozen]
+//! restricts the solver to a fixed set of packages and versions, and fails as soon as a
+//! dependency requires a package outside of it, instead of asking the provider about it.
+
+use thiserror::Error;
+
+use crate::delegate::delegate_provider;
+use crate::{
+    resolve, Dependencies, DependencyProvider, Map, PartialSolutionView, PubGrubError,
+    SelectedDependencies, VersionSet,
+};
+
+/// The error of [WithFrozen]: a dependency on a package that isn't known, or an error of the
+/// wrapped provider.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FrozenError<P, V, E> {
+    /// A version depends on a package that isn't in the known packages.
+    #[error("{dependent} {version} depends on {package}, which is not available offline")]
+    UnknownPackage {
+        /// The unknown package.
+        package: P,
+        /// The package depending on it.
+        dependent: P,
+        /// The version of the package depending on it.
+        version: V,
+    },
+    /// The wrapped provider failed.
+    #[error(transparent)]
+    Provider(E),
+}
+
+/// A [DependencyProvider] only considering a fixed set of packages and versions.
+///
+/// The wrapped provider is never asked about other packages, and only chooses versions in the
+/// known versions of each package.
+pub struct WithFrozen<'a, DP: DependencyProvider> {
+    provider: &'a DP,
+    packages: &'a Map<DP::P, DP::VS>,
+}
+
+impl<'a, DP: DependencyProvider> WithFrozen<'a, DP> {
+    /// Only allow the versions of `packages` from `provider`.
+    ///
+    /// The root package must be in `packages` too.
+    pub fn new(provider: &'a DP, packages: &'a Map<DP::P, DP::VS>) -> Self {
+        Self { provider, packages }
+    }
+}
+
+impl<DP: DependencyProvider> DependencyProvider for WithFrozen<'_, DP>
+where
+    DP::P: 'static,
+    DP::V: 'static,
+{
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = FrozenError<DP::P, DP::V, DP::Err>;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, prefetch, on_conflict,
+        on_backtrack, known_versions, versions_sharing_dependencies,
+    );
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        let Some(known) = self.packages.get(package) else {
+            return Ok(None);
+        };
+        self.provider
+            .choose_version(package, &range.intersection(known))
+            .map_err(FrozenError::Provider)
+    }
+
+    fn choose_version_in(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        solution: &dyn PartialSolutionView<Self::P, Self::VS>,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        let Some(known) = self.packages.get(package) else {
+            return Ok(None);
+        };
+        self.provider
+            .choose_version_in(package, &range.intersection(known), solution)
+            .map_err(FrozenError::Provider)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        let dependencies = self
+            .provider
+            .get_dependencies(package, version)
+            .map_err(FrozenError::Provider)?;
+        if let Dependencies::Available(constraints) = &dependencies {
+            if let Some(unknown) = constraints
+                .keys()
+                .find(|dependency| !self.packages.contains_key(*dependency))
+            {
+                return Err(FrozenError::UnknownPackage {
+                    package: unknown.clone(),
+                    dependent: package.clone(),
+                    version: version.clone(),
+                });
+            }
+        }
+        Ok(dependencies)
+    }
+
+    fn should_cancel(&self) -> Result<(), Self::Err> {
+        self.provider.should_cancel().map_err(FrozenError::Provider)
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// only selecting the versions of `packages`.
+///
+/// Resolution fails with [FrozenError::UnknownPackage] as soon as the dependencies of a
+/// version require a package outside of `packages`, even if another version could have been
+/// selected instead: most of the time, that means that the lockfile or the cache is outdated.
+pub fn resolve_frozen<'a, DP: DependencyProvider>(
+    dependency_provider: &'a DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    packages: &'a Map<DP::P, DP::VS>,
+) -> Result<SelectedDependencies<DP>, PubGrubError<WithFrozen<'a, DP>>>
+where
+    DP::P: 'static,
+    DP::V: 'static,
+{
+    let provider = WithFrozen::new(dependency_provider, packages);
+    resolve(&provider, package, version)
+}
@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The dependency graph of a solution.
+//!
+//! Installers usually need more than the selected versions: a package must be built or installed
+//! after its dependencies. The solver already retrieved the dependencies of every selected
+//! package, so [resolve_graph] returns them with the solution, in topological order, instead of
+//! querying the provider again. [ResolvedGraph::why] explains why a package is in the
+//! solution, like `cargo tree --invert`.
+
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::internal::Kind;
+use crate::solver::Solver;
+use crate::{DependencyProvider, Map, Package, PubGrubError, Set, VersionSet};
+
+/// The selected packages with the dependencies between them, returned by [resolve_graph].
+#[derive(Debug, Clone)]
+pub struct ResolvedGraph<P: Package, V, VS> {
+    root: P,
+    solution: Map<P, V>,
+    order: Vec<(P, V)>,
+    dependencies: Map<P, Vec<(P, VS)>>,
+}
+
+impl<P: Package, V, VS> ResolvedGraph<P, V, VS> {
+    /// The selected packages and versions, each package after its dependencies.
+    ///
+    /// Packages that depend on each other, directly or not, can't all come after their
+    /// dependencies, their relative order is arbitrary.
+    pub fn topological_order(&self) -> &[(P, V)] {
+        &self.order
+    }
+
+    /// The selected dependencies of a package, with the versions it requires for them.
+    ///
+    /// Empty if the package is not part of the solution.
+    pub fn dependencies(&self, package: &P) -> &[(P, VS)] {
+        self.dependencies
+            .get(package)
+            .map(|dependencies| dependencies.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The dependency closure of the solution: each selected package and version in
+    /// [topological order](Self::topological_order), with the selected version of each of its
+    /// dependencies.
+    ///
+    /// The edges come from the dependencies the solver retrieved, the provider is not queried
+    /// again, so a lockfile can record which version satisfied each requirement.
+    ///
+    /// ```
+    /// # use pubgrub::{resolve_graph, OfflineDependencyProvider, Ranges};
+    /// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+    /// dependency_provider.add_dependencies("root", 1u32, [("menu", Ranges::higher_than(2u32))]);
+    /// dependency_provider.add_dependencies("menu", 2u32, [("icons", Ranges::full())]);
+    /// dependency_provider.add_dependencies("menu", 3u32, [("icons", Ranges::full())]);
+    /// dependency_provider.add_dependencies("icons", 1u32, []);
+    ///
+    /// let graph = resolve_graph(&dependency_provider, "root", 1u32).unwrap();
+    /// assert_eq!(
+    ///     graph.closure(),
+    ///     [
+    ///         (&"icons", &1, vec![]),
+    ///         (&"menu", &3, vec![(&"icons", &1)]),
+    ///         (&"root", &1, vec![(&"menu", &3)]),
+    ///     ]
+    /// );
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn closure(&self) -> Vec<(&P, &V, Vec<(&P, &V)>)> {
+        self.order
+            .iter()
+            .map(|(package, version)| {
+                let dependencies = self
+                    .dependencies(package)
+                    .iter()
+                    .filter_map(|(dependency, _)| self.solution.get_key_value(dependency))
+                    .collect();
+                (package, version, dependencies)
+            })
+            .collect()
+    }
+
+    /// The selected packages depending on `package`, with the versions they require for it.
+    pub fn dependents(&self, package: &P) -> Vec<(&P, &VS)> {
+        self.dependencies
+            .iter()
+            .flat_map(|(dependent, dependencies)| {
+                dependencies
+                    .iter()
+                    .filter(|(dependency, _)| dependency == package)
+                    .map(move |(_, range)| (dependent, range))
+            })
+            .collect()
+    }
+
+    /// Why `package` is in the solution: a shortest chain of requirements from the root package
+    /// to it.
+    ///
+    /// Each step is a selected package, its version, and the versions it requires for the
+    /// package of the next step, the last step requiring `package`. The chain is empty for the
+    /// root package, and `None` if `package` is not part of the solution. Use
+    /// [dependents](Self::dependents) for all the packages requiring it.
+    pub fn why(&self, package: &P) -> Option<Vec<(&P, &V, &VS)>> {
+        self.solution.get(package)?;
+        // Breadth-first search from the root, remembering the requirement that reached each
+        // package.
+        let mut reached_by: Map<&P, Option<(&P, &VS)>> = Map::default();
+        reached_by.insert(&self.root, None);
+        let mut queue = VecDeque::from([&self.root]);
+        while let Some(current) = queue.pop_front() {
+            if current == package {
+                break;
+            }
+            for (dependency, range) in self.dependencies(current) {
+                if !reached_by.contains_key(dependency) {
+                    reached_by.insert(dependency, Some((current, range)));
+                    queue.push_back(dependency);
+                }
+            }
+        }
+
+        let mut chain = Vec::new();
+        let mut current = package;
+        while let Some((dependent, range)) = *reached_by.get(current)? {
+            chain.push((dependent, &self.solution[dependent], range));
+            current = dependent;
+        }
+        chain.reverse();
+        Some(chain)
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// with the dependencies between the selected packages.
+#[allow(clippy::type_complexity)]
+pub fn resolve_graph<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<ResolvedGraph<DP::P, DP::V, DP::VS>, PubGrubError<DP>> {
+    let mut solver = Solver::new(package.clone(), version.into());
+    let solution = solver.solve(dependency_provider)?;
+    let state = &solver.state;
+
+    // The dependencies of every version the solver retrieved are kept as incompatibilities,
+    // possibly merged with the same dependency of other versions.
+    let mut dependencies: Map<DP::P, Vec<(DP::P, DP::VS)>> = solution
+        .keys()
+        .map(|package| (package.clone(), Vec::new()))
+        .collect();
+    let mut seen = Set::default();
+    for (_, incompat) in state.incompatibility_store.iter() {
+        let Kind::FromDependencyOf(p, versions, dep, range) = &incompat.kind else {
+            continue;
+        };
+        let package = &state.package_store[*p];
+        let dependency = &state.package_store[*dep];
+        let Some(version) = solution.get(package) else {
+            continue;
+        };
+        if versions.contains(version)
+            && solution.contains_key(dependency)
+            && seen.insert((*p, *dep))
+        {
+            dependencies
+                .get_mut(package)
+                .unwrap()
+                .push((dependency.clone(), range.clone()));
+        }
+    }
+
+    // Depth-first search, a package is added once all its dependencies were.
+    let mut order = Vec::with_capacity(solution.len());
+    let mut visited = Set::default();
+    for start in solution.keys() {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut path = vec![(start, 0)];
+        while let Some(&(package, index)) = path.last() {
+            match dependencies[package].get(index) {
+                Some((dependency, _)) => {
+                    path.last_mut().unwrap().1 += 1;
+                    // Borrow from the solution, so that `dependencies` can be moved out.
+                    let (dependency, _) = solution.get_key_value(dependency).unwrap();
+                    if visited.insert(dependency) {
+                        path.push((dependency, 0));
+                    }
+                }
+                None => {
+                    order.push((package.clone(), solution[package].clone()));
+                    path.pop();
+                }
+            }
+        }
+    }
+    drop(visited);
+
+    Ok(ResolvedGraph {
+        root: package,
+        solution,
+        order,
+        dependencies,
+    })
+}
@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Re-resolve after small changes to the dependencies of the root package.
+
+use crate::solver::Solver;
+use crate::{DependencyConstraints, DependencyProvider, Map, PubGrubError, SelectedDependencies};
+
+/// Resolve the dependencies of a root package repeatedly while they change,
+/// reusing what was learned by previous resolutions.
+///
+/// The dependencies of the root package are owned by the resolver instead of being retrieved
+/// from the [DependencyProvider]. After a resolution, they can be edited with
+/// [add_dependency](Self::add_dependency) and [remove_dependency](Self::remove_dependency), and
+/// the next call to [resolve](Self::resolve) starts from the incompatibilities learned so far
+/// that do not depend on the root, instead of starting from scratch. The dependencies already
+/// retrieved for other packages are not requested again either. The
+/// [constraints](Self::add_constraint) are owned by the resolver too, and can be changed the
+/// same way.
+///
+/// This assumes that the provider keeps giving the same answers between resolutions. If that is
+/// not the case, call [reset](Self::reset) to forget what was learned.
+///
+/// ```
+/// # use pubgrub::{IncrementalResolver, OfflineDependencyProvider, Ranges};
+/// #
+/// type NumVS = Ranges<u32>;
+///
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+/// dependency_provider.add_dependencies("menu", 1u32, [("icons", Ranges::full())]);
+/// dependency_provider.add_dependencies("icons", 1u32, []);
+///
+/// let mut resolver = IncrementalResolver::new("root", 1u32);
+/// resolver.add_dependency("menu", Ranges::full());
+/// let solution = resolver.resolve(&dependency_provider).unwrap();
+/// assert_eq!(solution.len(), 3);
+///
+/// resolver.remove_dependency(&"menu");
+/// resolver.add_dependency("icons", Ranges::full());
+/// let solution = resolver.resolve(&dependency_provider).unwrap();
+/// assert_eq!(solution.len(), 2);
+/// ```
+pub struct IncrementalResolver<DP: DependencyProvider> {
+    root_package: DP::P,
+    root_version: DP::V,
+    root_dependencies: DependencyConstraints<DP::P, DP::VS>,
+    constraints: Map<DP::P, (DP::VS, DP::M)>,
+    /// The solver of the last resolution, if any.
+    previous: Option<Solver<DP>>,
+}
+
+impl<DP: DependencyProvider> IncrementalResolver<DP> {
+    /// Create a resolver for a root package without dependencies.
+    pub fn new(root_package: DP::P, root_version: impl Into<DP::V>) -> Self {
+        Self {
+            root_package,
+            root_version: root_version.into(),
+            root_dependencies: DependencyConstraints::default(),
+            constraints: Map::default(),
+            previous: None,
+        }
+    }
+
+    /// The current dependencies of the root package.
+    pub fn root_dependencies(&self) -> &DependencyConstraints<DP::P, DP::VS> {
+        &self.root_dependencies
+    }
+
+    /// Add a dependency of the root package, replacing the previous constraint on that package.
+    ///
+    /// Returns the previous constraint, if any.
+    pub fn add_dependency(&mut self, package: DP::P, version_set: DP::VS) -> Option<DP::VS> {
+        self.root_dependencies.insert(package, version_set)
+    }
+
+    /// Remove a dependency of the root package.
+    ///
+    /// Returns the removed constraint, if any.
+    pub fn remove_dependency(&mut self, package: &DP::P) -> Option<DP::VS> {
+        self.root_dependencies.remove(package)
+    }
+
+    /// Forbid the versions of `package` outside of `versions`, for `reason`, replacing the
+    /// previous constraint on that package.
+    ///
+    /// See [State::add_constraint](crate::State::add_constraint). Returns the previous
+    /// constraint, if any.
+    pub fn add_constraint(
+        &mut self,
+        package: DP::P,
+        versions: DP::VS,
+        reason: DP::M,
+    ) -> Option<(DP::VS, DP::M)> {
+        self.constraints.insert(package, (versions, reason))
+    }
+
+    /// Remove the constraint on a package.
+    ///
+    /// Returns the removed constraint, if any.
+    pub fn remove_constraint(&mut self, package: &DP::P) -> Option<(DP::VS, DP::M)> {
+        self.constraints.remove(package)
+    }
+
+    /// Forget the incompatibilities learned by previous resolutions.
+    ///
+    /// This is needed if the answers of the dependency provider changed.
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    /// Resolve the current dependencies of the root package.
+    pub fn resolve(
+        &mut self,
+        dependency_provider: &DP,
+    ) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+        let mut solver = self.solver();
+        let result = solver
+            .add_root_dependencies(self.root_dependencies.clone())
+            .map_err(PubGrubError::NoSolution)
+            .and_then(|()| solver.solve(dependency_provider));
+        self.previous = Some(solver);
+        result
+    }
+
+    /// Resolve as if the root package required `version_set` of `package`, without changing
+    /// the dependencies of the resolver.
+    ///
+    /// Like [resolve](Self::resolve), this starts from what the last resolution learned, so
+    /// trying a few alternative requirements after a resolution is much cheaper than resolving
+    /// each of them from scratch. What the hypothetical resolution learns is not kept. Compare
+    /// the solution with the current one using [SolutionDiff](crate::SolutionDiff) to find out
+    /// what the change would upgrade.
+    ///
+    /// ```
+    /// # use pubgrub::{IncrementalResolver, OfflineDependencyProvider, Ranges};
+    /// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+    /// dependency_provider.add_dependencies("foo", 1u32, []);
+    /// dependency_provider.add_dependencies("foo", 2u32, []);
+    /// dependency_provider.add_dependencies("bar", 1u32, [("foo", Ranges::higher_than(2u32))]);
+    ///
+    /// let mut resolver = IncrementalResolver::new("root", 1u32);
+    /// resolver.add_dependency("foo", Ranges::strictly_lower_than(2u32));
+    /// resolver.add_dependency("bar", Ranges::full());
+    /// assert!(resolver.resolve(&dependency_provider).is_err());
+    ///
+    /// // Widening the requirement on foo would fix the resolution.
+    /// let solution = resolver.what_if(&dependency_provider, "foo", Ranges::full()).unwrap();
+    /// assert_eq!(solution["foo"], 2);
+    /// assert_eq!(resolver.root_dependencies()["foo"], Ranges::strictly_lower_than(2u32));
+    /// ```
+    pub fn what_if(
+        &self,
+        dependency_provider: &DP,
+        package: DP::P,
+        version_set: DP::VS,
+    ) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+        let mut solver = self.solver();
+        let mut root_dependencies = self.root_dependencies.clone();
+        root_dependencies.insert(package, version_set);
+        solver
+            .add_root_dependencies(root_dependencies)
+            .map_err(PubGrubError::NoSolution)
+            .and_then(|()| solver.solve(dependency_provider))
+    }
+
+    /// A solver starting from what the last resolution learned, with the current constraints.
+    fn solver(&self) -> Solver<DP> {
+        let mut solver = match &self.previous {
+            Some(previous) => previous.carry_over(),
+            None => Solver::new(self.root_package.clone(), self.root_version.clone()),
+        };
+        for (package, (versions, reason)) in &self.constraints {
+            solver
+                .state
+                .add_constraint(package.clone(), versions.clone(), reason.clone());
+        }
+        solver
+    }
+}
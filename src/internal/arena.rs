@@ -1,7 +1,11 @@
-use std::fmt;
-use std::hash::{Hash, Hasher};
-use std::marker::PhantomData;
-use std::ops::{Index, Range};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::mem::size_of;
+use core::ops::{Index, Range};
 
 type FnvIndexSet<V> = indexmap::IndexSet<V, rustc_hash::FxBuildHasher>;
 
@@ -12,6 +16,11 @@ type FnvIndexSet<V> = indexmap::IndexSet<V, rustc_hash::FxBuildHasher>;
 /// that we actually don't need since it is phantom.
 ///
 /// <https://github.com/rust-lang/rust/issues/26925>
+///
+/// Ids of a [HashArena] are cheap to copy, hash and compare, and they implement
+/// [Package](crate::Package): a provider can intern its package names once and resolve with
+/// `Id<Name>` packages, mapping them back to names with a [ReportStyle](crate::ReportStyle)
+/// in error messages. Ids are ordered by allocation.
 pub struct Id<T> {
     raw: u32,
     _ty: PhantomData<fn() -> T>,
@@ -33,6 +42,18 @@ impl<T> PartialEq for Id<T> {
 
 impl<T> Eq for Id<T> {}
 
+impl<T> PartialOrd for Id<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Id<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.raw.cmp(&other.raw)
+    }
+}
+
 impl<T> Hash for Id<T> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.raw.hash(state)
@@ -41,7 +62,7 @@ impl<T> Hash for Id<T> {
 
 impl<T> fmt::Debug for Id<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut type_name = std::any::type_name::<T>();
+        let mut type_name = core::any::type_name::<T>();
         if let Some(id) = type_name.rfind(':') {
             type_name = &type_name[id + 1..]
         }
@@ -49,8 +70,16 @@ impl<T> fmt::Debug for Id<T> {
     }
 }
 
+/// The index alone, without the type name of [Debug](fmt::Debug).
+impl<T> fmt::Display for Id<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
 impl<T> Id<T> {
-    pub(crate) fn into_raw(self) -> usize {
+    /// The index of the value in its arena, the number of values allocated before it.
+    pub fn into_raw(self) -> usize {
         self.raw as usize
     }
     fn from(n: u32) -> Self {
@@ -111,6 +140,27 @@ impl<T> Arena<T> {
         let end = Id::from(self.data.len() as u32);
         Range { start, end }
     }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Id<T>, &T)> {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(raw, value)| (Id::from(raw as u32), value))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Reserve room for `additional` more values.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.data.reserve(additional);
+    }
+
+    /// The number of bytes allocated for the values, not counting what they own.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.data.capacity() * size_of::<T>()
+    }
 }
 
 impl<T> Index<Id<T>> for Arena<T> {
@@ -149,14 +199,55 @@ impl<T: Hash + Eq + fmt::Debug> fmt::Debug for HashArena<T> {
 }
 
 impl<T: Hash + Eq> HashArena<T> {
+    /// An empty arena.
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// The id of `value`, allocating it if it is not in the arena yet.
     pub fn alloc(&mut self, value: T) -> Id<T> {
         let (raw, _) = self.data.insert_full(value);
         Id::from(raw as u32)
     }
+
+    /// The id of `value`, if it was allocated.
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, value: &Q) -> Option<Id<T>>
+    where
+        T: Borrow<Q>,
+    {
+        self.data
+            .get_index_of(value)
+            .map(|raw| Id::from(raw as u32))
+    }
+
+    /// The number of distinct values allocated.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether no value was allocated.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// The values with their ids, in the order of allocation.
+    pub fn iter(&self) -> impl Iterator<Item = (Id<T>, &T)> {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(raw, value)| (Id::from(raw as u32), value))
+    }
+
+    /// Reserve room for `additional` more values.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.data.reserve(additional);
+    }
+
+    /// The number of bytes allocated for the values and their index, not counting what the
+    /// values own.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.data.capacity() * (size_of::<T>() + 2 * size_of::<usize>())
+    }
 }
 
 impl<T: Hash + Eq> Default for HashArena<T> {
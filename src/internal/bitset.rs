@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Bitsets over the known versions of packages, to answer most relations between terms without
+//! version set operations.
+//!
+//! A term is compiled into the bits of the known versions it allows, plus whether it allows not
+//! selecting the package. Since the known versions are real versions, a version allowed by one
+//! term and not by the other proves that the first term is not a subset of the second one, and a
+//! version allowed by both proves that they are not disjoint. This is the most common answer
+//! during unit propagation: neither satisfied nor contradicted. Other answers still need the
+//! version sets, since there may be versions that are not known.
+
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+
+use crate::internal::{Id, IncompId, Incompatibility, SmallVec};
+use crate::{Map, Package, Term, VersionSet};
+
+/// A term as the known versions it allows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct TermBits {
+    /// One bit per known version, set if the term allows it.
+    words: SmallVec<u64>,
+    /// Whether the term allows not selecting the package, i.e. the term is negative.
+    negative: bool,
+}
+
+impl TermBits {
+    /// The term allowing the versions that `self` does not allow.
+    pub(crate) fn negate(&self, len: usize) -> Self {
+        let mut words = SmallVec::empty();
+        for (i, word) in self.words.iter().enumerate() {
+            let remaining = len - i * 64;
+            let mask = if remaining >= 64 {
+                u64::MAX
+            } else {
+                (1 << remaining) - 1
+            };
+            words.push(!word & mask);
+        }
+        Self {
+            words,
+            negative: !self.negative,
+        }
+    }
+
+    /// The term allowing the versions that both terms allow.
+    pub(crate) fn intersection(&self, other: &Self) -> Self {
+        let mut words = SmallVec::empty();
+        for (a, b) in self.words.iter().zip(other.words.iter()) {
+            words.push(a & b);
+        }
+        Self {
+            words,
+            negative: self.negative && other.negative,
+        }
+    }
+
+    /// Whether `self`, an intersection of terms of the partial solution, is proven to neither
+    /// satisfy nor contradict `incompat_term`.
+    pub(crate) fn is_inconclusive_for(&self, incompat_term: &Self) -> bool {
+        let mut not_subset = self.negative && !incompat_term.negative;
+        let mut not_disjoint = self.negative && incompat_term.negative;
+        for (a, t) in self.words.iter().zip(incompat_term.words.iter()) {
+            not_subset |= a & !t != 0;
+            not_disjoint |= a & t != 0;
+            if not_subset && not_disjoint {
+                return true;
+            }
+        }
+        not_subset && not_disjoint
+    }
+}
+
+/// The versions of a package known up front, in increasing order.
+#[derive(Clone, Debug)]
+pub(crate) struct KnownVersions<V> {
+    versions: Vec<V>,
+}
+
+impl<V: Ord> KnownVersions<V> {
+    pub(crate) fn new(mut versions: Vec<V>) -> Self {
+        versions.sort_unstable();
+        versions.dedup();
+        Self { versions }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.versions.len()
+    }
+
+    pub(crate) fn versions(&self) -> &[V] {
+        &self.versions
+    }
+
+    /// The known versions allowed by `term`.
+    pub(crate) fn compile<VS: VersionSet<V = V>>(&self, term: &Term<VS>) -> TermBits {
+        let mut words = SmallVec::empty();
+        for chunk in self.versions.chunks(64) {
+            let mut word = 0;
+            for (i, version) in chunk.iter().enumerate() {
+                if term.contains(version) {
+                    word |= 1 << i;
+                }
+            }
+            words.push(word);
+        }
+        TermBits {
+            words,
+            negative: !term.is_positive(),
+        }
+    }
+}
+
+/// The known versions of packages, and the terms of incompatibilities compiled against them.
+#[derive(Clone, Debug)]
+pub(crate) struct Bitsets<P: Package, VS: VersionSet> {
+    /// The known versions of each package the provider was asked about, if it knew them.
+    known: Map<Id<P>, Option<KnownVersions<VS::V>>>,
+    /// The compiled terms of each incompatibility, in the order of its terms, indexed by
+    /// incompatibility id. `None` until the incompatibility is compiled.
+    incompatibilities: Vec<Option<SmallVec<Option<TermBits>>>>,
+}
+
+impl<P: Package, VS: VersionSet> Default for Bitsets<P, VS> {
+    fn default() -> Self {
+        Self {
+            known: Map::default(),
+            incompatibilities: Vec::new(),
+        }
+    }
+}
+
+impl<P: Package, VS: VersionSet> Bitsets<P, VS> {
+    /// Whether the provider was already asked about the versions of `package`.
+    pub(crate) fn is_queried(&self, package: Id<P>) -> bool {
+        self.known.contains_key(&package)
+    }
+
+    /// Use bitsets for `package` if its versions are known.
+    ///
+    /// The incompatibilities already compiled keep working without bitsets for it.
+    pub(crate) fn add_known_versions(&mut self, package: Id<P>, versions: Option<Vec<VS::V>>) {
+        self.known.insert(package, versions.map(KnownVersions::new));
+    }
+
+    pub(crate) fn known(&self, package: Id<P>) -> Option<&KnownVersions<VS::V>> {
+        self.known.get(&package)?.as_ref()
+    }
+
+    /// The compiled terms of an incompatibility, compiling them the first time.
+    pub(crate) fn incompatibility<M: Eq + Clone + Debug + Display>(
+        &mut self,
+        id: IncompId<P, VS, M>,
+        incompat: &Incompatibility<P, VS, M>,
+    ) -> &[Option<TermBits>] {
+        let index = id.into_raw();
+        if self.incompatibilities.len() <= index {
+            self.incompatibilities.resize(index + 1, None);
+        }
+        let known = &self.known;
+        self.incompatibilities[index].get_or_insert_with(|| {
+            let mut terms = SmallVec::empty();
+            for (package, term) in incompat.iter() {
+                let known = known.get(&package).and_then(Option::as_ref);
+                terms.push(known.map(|known| known.compile(term)));
+            }
+            terms
+        })
+    }
+}
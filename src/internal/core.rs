@@ -3,16 +3,29 @@
 //! Core model and functions
 //! to write a functional PubGrub algorithm.
 
-use std::collections::HashSet as Set;
-use std::sync::Arc;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::mem::size_of;
+use core::time::Duration;
 
 use crate::internal::{
-    Arena, DecisionLevel, HashArena, Id, IncompDpId, IncompId, Incompatibility, PartialSolution,
-    Relation, SatisfierSearch, SmallVec,
+    Arena, DecisionLevel, HashArena, Id, IncompDpId, IncompId, Incompatibility, Kind,
+    PartialSolution, Relation, SatisfierSearch, SmallVec,
+};
+use crate::trace::{TraceEvent, TraceLog, TracePoint};
+use crate::{
+    Conflict, ConflictLearning, DefaultConflictLearning, DependencyProvider, DerivationTree, Map,
+    MemoryStats, NoSolutionError, PackageResolutionStatistics, PartialSolutionView, PubGrubError,
+    ResolutionStats, Set, Term, VersionSet,
 };
-use crate::{DependencyProvider, DerivationTree, Map, NoSolutionError, VersionSet};
 
 /// Current state of the PubGrub algorithm.
+///
+/// The state is [Send] and [Sync] when the packages, versions, version sets, metadata and
+/// priorities of the provider are, so that a resolution can be prepared on one thread and
+/// continued on another, for example with `tokio::task::spawn_blocking` from an async task.
 #[derive(Clone)]
 pub struct State<DP: DependencyProvider> {
     /// The root package and version.
@@ -34,6 +47,14 @@ pub struct State<DP: DependencyProvider> {
     #[allow(clippy::type_complexity)]
     merged_dependencies: Map<(Id<DP::P>, Id<DP::P>), SmallVec<IncompDpId<DP>>>,
 
+    /// The [NoVersions](Kind::NoVersions) incompatibility of each package, with the versions of
+    /// all those found so far merged.
+    merged_no_versions: Map<Id<DP::P>, IncompDpId<DP>>,
+
+    /// The learned incompatibilities in `incompatibilities`, with the number of the last
+    /// conflict they took part in.
+    learned: Map<IncompDpId<DP>, u32>,
+
     /// Partial solution.
     pub partial_solution: PartialSolution<DP>,
 
@@ -47,6 +68,22 @@ pub struct State<DP: DependencyProvider> {
     /// It can definitely be a local variable to that method, but
     /// this way we can reuse the same allocation for better performance.
     unit_propagation_buffer: SmallVec<Id<DP::P>>,
+
+    /// Counters of the work done so far.
+    pub(crate) stats: ResolutionStats<Id<DP::P>>,
+
+    /// How often each package was involved in a conflict.
+    pub(crate) conflict_tracker: Map<Id<DP::P>, PackageResolutionStatistics>,
+
+    /// What to learn from conflicts, shareable between threads to keep the state [Send].
+    pub(crate) learning: Arc<dyn ConflictLearning + Send + Sync>,
+
+    /// The incompatibilities learned from conflicts and the decision levels backtracked to
+    /// since the solver last told the provider, see [DependencyProvider::on_conflict].
+    pub(crate) backtracks: Vec<(IncompDpId<DP>, DecisionLevel)>,
+
+    /// What the solver did so far, if it is recorded, see [crate::resolve_with_trace].
+    trace: Option<TraceLog<DP>>,
 }
 
 impl<DP: DependencyProvider> State<DP> {
@@ -61,6 +98,11 @@ impl<DP: DependencyProvider> State<DP> {
         ));
         let mut incompatibilities = Map::default();
         incompatibilities.insert(root_package, vec![not_root_id]);
+        let mut conflict_tracker = Map::default();
+        conflict_tracker.insert(
+            root_package,
+            PackageResolutionStatistics::with_depth(Some(0)),
+        );
         Self {
             root_package,
             root_version,
@@ -71,24 +113,358 @@ impl<DP: DependencyProvider> State<DP> {
             package_store,
             unit_propagation_buffer: SmallVec::Empty,
             merged_dependencies: Map::default(),
+            merged_no_versions: Map::default(),
+            learned: Map::default(),
+            stats: ResolutionStats::default(),
+            conflict_tracker,
+            learning: Arc::new(DefaultConflictLearning),
+            backtracks: Vec::new(),
+            trace: None,
+        }
+    }
+
+    /// Initialize the state for a new resolution of the same root package and version.
+    ///
+    /// Incompatibilities that do not depend on the root package, such as the dependencies of
+    /// other packages and everything derived only from those, stay valid as long as the
+    /// dependency provider gives the same answers. They are carried over to the new state,
+    /// keeping their derivations for error reporting. The constraints, conflicts and denied
+    /// versions added by the caller are not, nor anything derived from them.
+    pub(crate) fn carry_over(&self) -> Self {
+        let mut state = Self {
+            root_package: self.root_package,
+            root_version: self.root_version.clone(),
+            incompatibilities: Map::default(),
+            contradicted_incompatibilities: Map::default(),
+            partial_solution: PartialSolution::empty(),
+            incompatibility_store: Arena::new(),
+            // Keeping the package store keeps the package ids of the incompatibilities valid.
+            package_store: self.package_store.clone(),
+            unit_propagation_buffer: SmallVec::Empty,
+            merged_dependencies: Map::default(),
+            merged_no_versions: Map::default(),
+            learned: Map::default(),
+            stats: ResolutionStats::default(),
+            // The dependencies of the packages are not added again, keep their depth.
+            conflict_tracker: self
+                .conflict_tracker
+                .iter()
+                .map(|(&p, statistics)| {
+                    (p, PackageResolutionStatistics::with_depth(statistics.depth))
+                })
+                .collect(),
+            learning: self.learning.clone(),
+            backtracks: Vec::new(),
+            trace: None,
+        };
+        let not_root_id = state.incompatibility_store.alloc(Incompatibility::not_root(
+            state.root_package,
+            state.root_version.clone(),
+        ));
+        state
+            .incompatibilities
+            .insert(state.root_package, vec![not_root_id]);
+
+        let active: Set<IncompDpId<DP>> =
+            self.incompatibilities.values().flatten().copied().collect();
+        let mut carried_over = Map::default();
+        // Causes are always allocated before the incompatibility derived from them.
+        for (id, incompat) in self.incompatibility_store.iter() {
+            let kind = match &incompat.kind {
+                // Only what the provider said holds for the next resolution, what the caller
+                // added is given again if it still applies.
+                Kind::NotRoot(..)
+                | Kind::Constraint(..)
+                | Kind::Denied(..)
+                | Kind::Conflict(..) => continue,
+                Kind::NoVersions(p, _)
+                | Kind::FromDependencyOf(p, _, _, _)
+                | Kind::Custom(p, _, _) => {
+                    if *p == self.root_package {
+                        continue;
+                    }
+                    incompat.kind.clone()
+                }
+                Kind::DerivedFrom(cause1, cause2) => {
+                    match (carried_over.get(cause1), carried_over.get(cause2)) {
+                        (Some(&cause1), Some(&cause2)) => Kind::DerivedFrom(cause1, cause2),
+                        _ => continue,
+                    }
+                }
+            };
+            let mut incompat = incompat.clone();
+            incompat.kind = kind;
+            let new_id = state.incompatibility_store.alloc(incompat);
+            carried_over.insert(id, new_id);
+            if active.contains(&id) {
+                state.merge_incompatibility(new_id);
+                if let Some(&last_conflict) = self.learned.get(&id) {
+                    state.learned.insert(new_id, last_conflict);
+                }
+            }
+        }
+        state
+    }
+
+    /// Require another root package at the given version, solved together with
+    /// [root_package](Self::root_package).
+    ///
+    /// Like the root package, it is selected at that version, and reported with
+    /// [External::NotRoot](crate::External::NotRoot) when it causes a conflict. The root has to
+    /// be added before the first decision, and its own
+    /// [unit propagation](Self::unit_propagation) run to derive it.
+    pub fn add_root(&mut self, package: DP::P, version: DP::V) -> Id<DP::P> {
+        let package = self.package_store.alloc(package);
+        self.add_incompatibility(Incompatibility::not_root(package, version));
+        self.conflict_tracker
+            .insert(package, PackageResolutionStatistics::with_depth(Some(0)));
+        package
+    }
+
+    /// The version of the root package.
+    pub(crate) fn root_version(&self) -> &DP::V {
+        &self.root_version
+    }
+
+    /// Statistics on the work done so far.
+    pub fn stats(&self) -> ResolutionStats<DP::P> {
+        let time_per_package = self
+            .stats
+            .time_per_package
+            .iter()
+            .map(|(p, time)| (self.package_store[*p].clone(), *time))
+            .collect();
+        ResolutionStats {
+            decisions: self.stats.decisions,
+            conflicts: self.stats.conflicts,
+            backtracks: self.stats.backtracks,
+            unit_propagations: self.stats.unit_propagations,
+            incompatibilities_learned: self.stats.incompatibilities_learned,
+            restarts: self.stats.restarts,
+            incompatibilities_removed: self.stats.incompatibilities_removed,
+            time_per_package,
+            conflicting_packages: self.conflict_counts(),
+            memory: self.memory_stats(),
         }
     }
 
+    /// The memory held by the state.
+    pub fn memory_stats(&self) -> MemoryStats {
+        fn map_bytes<K, V>(map: &Map<K, V>) -> usize {
+            // A hash table entry, plus one control byte.
+            map.capacity() * (size_of::<(K, V)>() + 1)
+        }
+        let incompatibility_references: usize = self.incompatibilities.values().map(Vec::len).sum();
+        let incompatibility_lists: usize = self
+            .incompatibilities
+            .values()
+            .map(|ids| ids.capacity() * size_of::<IncompDpId<DP>>())
+            .sum();
+        let estimated_bytes = self.incompatibility_store.allocated_bytes()
+            + self.package_store.allocated_bytes()
+            + self.partial_solution.allocated_bytes()
+            + map_bytes(&self.incompatibilities)
+            + incompatibility_lists
+            + map_bytes(&self.contradicted_incompatibilities)
+            + map_bytes(&self.merged_dependencies)
+            + map_bytes(&self.merged_no_versions)
+            + map_bytes(&self.learned)
+            + map_bytes(&self.conflict_tracker);
+        MemoryStats {
+            packages: self.package_store.len() as u32,
+            incompatibilities: self.incompatibility_store.len() as u32,
+            incompatibility_references: incompatibility_references as u32,
+            assignments: self.partial_solution.assignments_len() as u32,
+            estimated_bytes,
+        }
+    }
+
+    /// Reserve room for `packages` more packages, and a few incompatibilities for each.
+    pub(crate) fn reserve(&mut self, packages: usize) {
+        self.package_store.reserve(packages);
+        // Most packages have a few versions, each with a few dependencies.
+        self.incompatibility_store
+            .reserve(packages.saturating_mul(4));
+        self.incompatibilities.reserve(packages);
+        self.conflict_tracker.reserve(packages);
+        self.partial_solution.reserve(packages);
+    }
+
+    /// The packages involved in conflicts so far, with their
+    /// [conflict count](PackageResolutionStatistics::conflict_count), the most conflicting
+    /// first.
+    ///
+    /// When a resolution fails or times out, these packages are usually the ones worth
+    /// mentioning in the error message.
+    pub fn conflict_counts(&self) -> Vec<(DP::P, u32)> {
+        let mut packages: Vec<_> = self
+            .conflict_tracker
+            .iter()
+            .map(|(p, statistics)| (*p, statistics.conflict_count()))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        packages.sort_unstable_by_key(|(p, count)| (Reverse(*count), p.into_raw()));
+        packages
+            .into_iter()
+            .map(|(p, count)| (self.package_store[p].clone(), count))
+            .collect()
+    }
+
+    /// Add time spent in the dependency provider for a package to the statistics.
+    pub(crate) fn record_time(&mut self, package: Id<DP::P>, time: Duration) {
+        *self.stats.time_per_package.entry(package).or_default() += time;
+    }
+
     /// Add the dependencies for the current version of the current package as incompatibilities.
     pub fn add_package_version_dependencies(
         &mut self,
         package: Id<DP::P>,
         version: DP::V,
         dependencies: impl IntoIterator<Item = (DP::P, DP::VS)>,
+    ) -> Option<IncompId<DP::P, DP::VS, DP::M>> {
+        let versions = DP::VS::singleton(version.clone());
+        self.add_shared_dependencies(package, version, versions, dependencies)
+    }
+
+    /// Add the dependencies of `version` of `package`, shared by all the `versions` of the
+    /// package, then decide `version` like
+    /// [add_package_version_dependencies](Self::add_package_version_dependencies).
+    pub(crate) fn add_shared_dependencies(
+        &mut self,
+        package: Id<DP::P>,
+        version: DP::V,
+        versions: DP::VS,
+        dependencies: impl IntoIterator<Item = (DP::P, DP::VS)>,
     ) -> Option<IncompId<DP::P, DP::VS, DP::M>> {
         let dep_incompats =
-            self.add_incompatibility_from_dependencies(package, version.clone(), dependencies);
-        self.partial_solution.add_package_version_incompatibilities(
+            self.add_incompatibility_from_dependencies(package, versions, dependencies);
+        let conflict = self.partial_solution.add_package_version_incompatibilities(
             package,
-            version.clone(),
+            version,
             dep_incompats,
             &self.incompatibility_store,
-        )
+        );
+        match conflict {
+            Some(_) => self.stats.conflicts += 1,
+            None => {
+                self.stats.decisions += 1;
+                self.trace_decision(package);
+            }
+        }
+        conflict
+    }
+
+    /// Decide `version` of `package`, whose dependencies were already added.
+    pub(crate) fn add_decision(&mut self, package: Id<DP::P>, version: DP::V) {
+        self.partial_solution.add_decision(package, version);
+        self.stats.decisions += 1;
+        self.trace_decision(package);
+    }
+
+    /// The error of the provider choosing a version of `package`, with where the solver was.
+    pub(crate) fn error_choosing_version(
+        &self,
+        package: Id<DP::P>,
+        source: DP::Err,
+    ) -> PubGrubError<DP> {
+        PubGrubError::ErrorChoosingVersion {
+            package: self.package_store[package].clone(),
+            source,
+            required_by: self.required_by(package),
+            decided: self.decided_except(package),
+        }
+    }
+
+    /// The error of the provider retrieving the dependencies of `version` of `package`, with
+    /// where the solver was.
+    pub(crate) fn error_retrieving_dependencies(
+        &self,
+        package: Id<DP::P>,
+        version: DP::V,
+        source: DP::Err,
+    ) -> PubGrubError<DP> {
+        PubGrubError::ErrorRetrievingDependencies {
+            package: self.package_store[package].clone(),
+            version,
+            source,
+            required_by: self.required_by(package),
+            decided: self.decided_except(package),
+        }
+    }
+
+    /// The chain of decided versions from the one depending on `package` up to the root package.
+    ///
+    /// Each package is required by the first decided version whose dependencies include it.
+    fn required_by(&self, package: Id<DP::P>) -> Vec<(DP::P, DP::V)> {
+        let mut chain = Vec::new();
+        let mut visited = Set::default();
+        visited.insert(package);
+        let mut current = package;
+        while current != self.root_package {
+            let dependent = self.incompatibilities.get(&current).and_then(|ids| {
+                ids.iter().find_map(|&id| {
+                    let Kind::FromDependencyOf(dependent, versions, dependency, _) =
+                        &self.incompatibility_store[id].kind
+                    else {
+                        return None;
+                    };
+                    if *dependency != current || visited.contains(dependent) {
+                        return None;
+                    }
+                    let version = self.partial_solution.decision(*dependent)?;
+                    versions
+                        .contains(version)
+                        .then(|| (*dependent, version.clone()))
+                })
+            });
+            let Some((dependent, version)) = dependent else {
+                break;
+            };
+            visited.insert(dependent);
+            chain.push((self.package_store[dependent].clone(), version));
+            current = dependent;
+        }
+        chain
+    }
+
+    /// The decided packages other than `package`, in the order of the decisions.
+    fn decided_except(&self, package: Id<DP::P>) -> Vec<(DP::P, DP::V)> {
+        self.partial_solution
+            .extract_solution()
+            .filter(|(p, _)| *p != package)
+            .map(|(p, v)| (self.package_store[p].clone(), v))
+            .collect()
+    }
+
+    /// Record what the solver does from now on, see [take_trace](Self::take_trace).
+    pub(crate) fn record_trace(&mut self) {
+        self.trace.get_or_insert_with(TraceLog::new);
+    }
+
+    /// The events recorded since the last call, if the trace is recorded.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn take_trace(&mut self) -> Vec<TraceEvent<DP::P, DP::VS, DP::M>> {
+        match &mut self.trace {
+            Some(trace) => trace.take(&self.incompatibility_store, &self.package_store),
+            None => Vec::new(),
+        }
+    }
+
+    fn trace(&mut self, point: TracePoint<DP>) {
+        if let Some(trace) = &mut self.trace {
+            trace.push(point);
+        }
+    }
+
+    fn trace_decision(&mut self, package: Id<DP::P>) {
+        if let Some(trace) = &mut self.trace {
+            let version = self
+                .partial_solution
+                .decision(package)
+                .expect("the package was just decided");
+            let level = self.partial_solution.current_decision_level();
+            trace.push(TracePoint::Decision(package, version.clone(), level));
+        }
     }
 
     /// Add an incompatibility to the state.
@@ -97,24 +473,54 @@ impl<DP: DependencyProvider> State<DP> {
         self.merge_incompatibility(id);
     }
 
+    /// Forbid the versions of `package` outside of `versions`, for `reason`.
+    ///
+    /// Unlike a dependency of the root package, the constraint doesn't require `package` to be
+    /// selected, it only applies when something depends on it. If no allowed version works, the
+    /// error reports the constraint as an [External::Custom](crate::External::Custom) with
+    /// `reason`. Constraints should be added before the first decision.
+    pub fn add_constraint(&mut self, package: DP::P, versions: DP::VS, reason: DP::M) {
+        let package = self.package_store.alloc(package);
+        self.add_incompatibility(Incompatibility::constraint(package, &versions, reason));
+    }
+
     /// Add an incompatibility to the state.
     #[cold]
     pub(crate) fn add_incompatibility_from_dependencies(
         &mut self,
         package: Id<DP::P>,
-        version: DP::V,
+        versions: DP::VS,
         deps: impl IntoIterator<Item = (DP::P, DP::VS)>,
-    ) -> std::ops::Range<IncompDpId<DP>> {
+    ) -> core::ops::Range<IncompDpId<DP>> {
+        // Packages are at one more dependency from the root than the shallowest package
+        // requiring them, packages without a depth are roots.
+        let depth = *self
+            .conflict_tracker
+            .entry(package)
+            .or_default()
+            .depth
+            .get_or_insert(0);
+        let conflict_tracker = &mut self.conflict_tracker;
+        // The last dependency takes the versions, the others get a copy.
+        let mut versions = Some(versions);
+        let mut deps = deps.into_iter().peekable();
         // Create incompatibilities and allocate them in the store.
         let new_incompats_id_range =
             self.incompatibility_store
-                .alloc_iter(deps.into_iter().map(|(dep_p, dep_vs)| {
+                .alloc_iter(core::iter::from_fn(|| {
+                    let (dep_p, dep_vs) = deps.next()?;
+                    let versions = match deps.peek() {
+                        Some(_) => versions.clone(),
+                        None => versions.take(),
+                    }?;
                     let dep_pid = self.package_store.alloc(dep_p);
-                    Incompatibility::from_dependency(
+                    let dep_depth = &mut conflict_tracker.entry(dep_pid).or_default().depth;
+                    *dep_depth = Some(dep_depth.map_or(depth + 1, |d| d.min(depth + 1)));
+                    Some(Incompatibility::from_dependency(
                         package,
-                        <DP::VS as VersionSet>::singleton(version.clone()),
+                        versions,
                         (dep_pid, dep_vs),
-                    )
+                    ))
                 }));
         // Merge the newly created incompatibilities with the older ones.
         for id in IncompDpId::<DP>::range_to_iter(new_incompats_id_range.clone()) {
@@ -134,6 +540,7 @@ impl<DP: DependencyProvider> State<DP> {
         &mut self,
         package: Id<DP::P>,
     ) -> Result<SmallVec<(Id<DP::P>, IncompDpId<DP>)>, NoSolutionError<DP>> {
+        self.stats.unit_propagations += 1;
         let mut satisfier_causes = SmallVec::default();
         self.unit_propagation_buffer.clear();
         self.unit_propagation_buffer.push(package);
@@ -150,7 +557,10 @@ impl<DP: DependencyProvider> State<DP> {
                     continue;
                 }
                 let current_incompat = &self.incompatibility_store[incompat_id];
-                match self.partial_solution.relation(current_incompat) {
+                match self
+                    .partial_solution
+                    .relation(incompat_id, &self.incompatibility_store)
+                {
                     // If the partial solution satisfies the incompatibility
                     // we must perform conflict resolution.
                     Relation::Satisfied => {
@@ -159,6 +569,10 @@ impl<DP: DependencyProvider> State<DP> {
                             current_incompat.display(&self.package_store)
                         );
                         conflict_id = Some(incompat_id);
+                        self.stats.conflicts += 1;
+                        if let Some(trace) = &mut self.trace {
+                            trace.push(TracePoint::Conflict(incompat_id));
+                        }
                         break;
                     }
                     Relation::AlmostSatisfied(package_almost) => {
@@ -175,6 +589,10 @@ impl<DP: DependencyProvider> State<DP> {
                             incompat_id,
                             &self.incompatibility_store,
                         );
+                        if let Some(trace) = &mut self.trace {
+                            let level = self.partial_solution.current_decision_level();
+                            trace.push(TracePoint::Derivation(package_almost, incompat_id, level));
+                        }
                         // With the partial solution updated, the incompatibility is now contradicted.
                         self.contradicted_incompatibilities
                             .insert(incompat_id, self.partial_solution.current_decision_level());
@@ -187,19 +605,26 @@ impl<DP: DependencyProvider> State<DP> {
                 }
             }
             if let Some(incompat_id) = conflict_id {
-                let (package_almost, root_cause) = self
+                let (package_almost, root_cause, restarted) = self
                     .conflict_resolution(incompat_id, &mut satisfier_causes)
                     .map_err(|terminal_incompat_id| {
                         self.build_derivation_tree(terminal_incompat_id)
                     })?;
                 self.unit_propagation_buffer.clear();
                 self.unit_propagation_buffer.push(package_almost);
+                if restarted {
+                    // The root cause may not be almost satisfied anymore,
+                    // propagating from `package_almost` checks it again.
+                    continue;
+                }
                 // Add to the partial solution with incompat as cause.
                 self.partial_solution.add_derivation(
                     package_almost,
                     root_cause,
                     &self.incompatibility_store,
                 );
+                let level = self.partial_solution.current_decision_level();
+                self.trace(TracePoint::Derivation(package_almost, root_cause, level));
                 // After conflict resolution and the partial solution update,
                 // the root cause incompatibility is now contradicted.
                 self.contradicted_incompatibilities
@@ -246,10 +671,13 @@ impl<DP: DependencyProvider> State<DP> {
         &mut self,
         incompatibility: IncompDpId<DP>,
         satisfier_causes: &mut SmallVec<(Id<DP::P>, IncompDpId<DP>)>,
-    ) -> Result<(Id<DP::P>, IncompDpId<DP>), IncompDpId<DP>> {
+    ) -> Result<(Id<DP::P>, IncompDpId<DP>, bool), IncompDpId<DP>> {
         let mut current_incompat_id = incompatibility;
         let mut current_incompat_changed = false;
+        let learn_intermediate = self.learning.learn_intermediate();
+        let mut derivations = 0;
         loop {
+            self.touch_learned(current_incompat_id);
             if self.incompatibility_store[current_incompat_id]
                 .is_terminal(self.root_package, &self.root_version)
             {
@@ -263,16 +691,37 @@ impl<DP: DependencyProvider> State<DP> {
                     SatisfierSearch::DifferentDecisionLevels {
                         previous_satisfier_level,
                     } => {
+                        let current_level = self.partial_solution.current_decision_level();
+                        let conflict = Conflict {
+                            decision_level: current_level.0,
+                            backtrack_level: previous_satisfier_level.0,
+                            derivations,
+                            conflicts: self.stats.conflicts,
+                        };
+                        // Never undo the decision on the root package.
+                        let backtrack_level =
+                            DecisionLevel(self.learning.backtrack_level(&conflict).clamp(
+                                previous_satisfier_level.0.min(1),
+                                previous_satisfier_level.0,
+                            ));
                         self.backtrack(
                             current_incompat_id,
-                            current_incompat_changed,
-                            previous_satisfier_level,
+                            current_incompat_changed && !learn_intermediate,
+                            backtrack_level,
+                        );
+                        log::info!(
+                            "backtrack to {:?}, undoing {} decisions, because of {}",
+                            backtrack_level,
+                            current_level.0 - backtrack_level.0,
+                            self.incompatibility_store[current_incompat_id]
+                                .display(&self.package_store)
                         );
-                        log::info!("backtrack to {:?}", previous_satisfier_level);
                         satisfier_causes.push((package, current_incompat_id));
-                        return Ok((package, current_incompat_id));
+                        let restarted = backtrack_level != previous_satisfier_level;
+                        return Ok((package, current_incompat_id, restarted));
                     }
                     SatisfierSearch::SameDecisionLevels { satisfier_cause } => {
+                        self.touch_learned(satisfier_cause);
                         let prior_cause = Incompatibility::prior_cause(
                             current_incompat_id,
                             satisfier_cause,
@@ -281,6 +730,12 @@ impl<DP: DependencyProvider> State<DP> {
                         );
                         log::info!("prior cause: {}", prior_cause.display(&self.package_store));
                         current_incompat_id = self.incompatibility_store.alloc(prior_cause);
+                        self.trace(TracePoint::Learned(current_incompat_id));
+                        self.stats.incompatibilities_learned += 1;
+                        derivations += 1;
+                        if learn_intermediate {
+                            self.learn(current_incompat_id);
+                        }
                         satisfier_causes.push((package, current_incompat_id));
                         current_incompat_changed = true;
                     }
@@ -298,12 +753,117 @@ impl<DP: DependencyProvider> State<DP> {
         decision_level: DecisionLevel,
     ) {
         self.partial_solution.backtrack(decision_level);
+        self.stats.backtracks += 1;
+        self.backtracks.push((incompat, decision_level));
+        self.trace(TracePoint::Backtrack(decision_level));
         // Remove contradicted incompatibilities that depend on decisions we just backtracked away.
         self.contradicted_incompatibilities
             .retain(|_, dl| *dl <= decision_level);
         if incompat_changed {
-            self.merge_incompatibility(incompat);
+            self.learn(incompat);
+        }
+    }
+
+    /// Add an incompatibility derived during conflict resolution.
+    fn learn(&mut self, incompat: IncompDpId<DP>) {
+        self.merge_incompatibility(incompat);
+        self.learned.insert(incompat, self.stats.conflicts);
+    }
+
+    /// Record that a learned incompatibility took part in the current conflict.
+    fn touch_learned(&mut self, incompat: IncompDpId<DP>) {
+        if let Some(last_conflict) = self.learned.get_mut(&incompat) {
+            *last_conflict = self.stats.conflicts;
+        }
+    }
+
+    /// The number of learned incompatibilities checked by unit propagation.
+    pub(crate) fn learned_count(&self) -> usize {
+        self.learned.len()
+    }
+
+    /// Stop checking the learned incompatibilities that are satisfied whenever a newer learned
+    /// incompatibility is, since the newer one finds the same conflicts.
+    ///
+    /// Returns the number of incompatibilities removed.
+    pub(crate) fn remove_subsumed(&mut self) -> usize {
+        let mut newest_first: Vec<IncompDpId<DP>> = self.learned.keys().copied().collect();
+        newest_first.sort_unstable_by_key(|id| Reverse(id.into_raw()));
+        let mut subsumed: Set<IncompDpId<DP>> = Set::default();
+        for general in newest_first {
+            if subsumed.contains(&general) {
+                continue;
+            }
+            let incompat = &self.incompatibility_store[general];
+            // A subsumed incompatibility has all the packages of the general one, looking at the
+            // incompatibilities of any of them is enough.
+            let Some((package, _)) = incompat.iter().next() else {
+                continue;
+            };
+            for &specific in &self.incompatibilities[&package] {
+                if specific.into_raw() < general.into_raw()
+                    && self.learned.contains_key(&specific)
+                    && incompat.subsumes(&self.incompatibility_store[specific])
+                {
+                    subsumed.insert(specific);
+                }
+            }
+        }
+        for &incompat in &subsumed {
+            self.forget(incompat);
+        }
+        subsumed.len()
+    }
+
+    /// Stop checking the learned incompatibilities that took part in the fewest recent
+    /// conflicts, keeping `keep` of them.
+    ///
+    /// Returns the number of incompatibilities removed.
+    pub(crate) fn evict_learned(&mut self, keep: usize) -> usize {
+        let mut least_active_first: Vec<(u32, IncompDpId<DP>)> = self
+            .learned
+            .iter()
+            .map(|(&id, &last_conflict)| (last_conflict, id))
+            .collect();
+        let evicted = least_active_first.len().saturating_sub(keep);
+        least_active_first
+            .sort_unstable_by_key(|(last_conflict, id)| (*last_conflict, id.into_raw()));
+        for &(_, incompat) in &least_active_first[..evicted] {
+            self.forget(incompat);
         }
+        evicted
+    }
+
+    /// Remove a learned incompatibility from unit propagation.
+    ///
+    /// It is implied by the incompatibilities it was derived from, so this doesn't change the
+    /// solution, and it stays in the store for error reporting and the derivations that it
+    /// caused.
+    fn forget(&mut self, incompat: IncompDpId<DP>) {
+        self.learned.remove(&incompat);
+        self.contradicted_incompatibilities.remove(&incompat);
+        for (package, _) in self.incompatibility_store[incompat].iter() {
+            if let Some(incompats) = self.incompatibilities.get_mut(&package) {
+                incompats.retain(|id| *id != incompat);
+            }
+        }
+        self.stats.incompatibilities_removed += 1;
+    }
+
+    /// Undo all the decisions but the root package, keeping the learned incompatibilities.
+    ///
+    /// Returns whether there were decisions to undo.
+    pub(crate) fn restart(&mut self) -> bool {
+        let root_level = DecisionLevel(1);
+        if self.partial_solution.current_decision_level() <= root_level {
+            return false;
+        }
+        self.partial_solution.backtrack(root_level);
+        self.stats.restarts += 1;
+        self.trace(TracePoint::Backtrack(root_level));
+        self.contradicted_incompatibilities
+            .retain(|_, dl| *dl <= root_level);
+        true
     }
 
     /// Manually backtrack before the given package was selected.
@@ -315,6 +875,7 @@ impl<DP: DependencyProvider> State<DP> {
     pub fn backtrack_package(&mut self, package: Id<DP::P>) -> Option<u32> {
         let base_decision_level = self.partial_solution.current_decision_level();
         let new_decision_level = self.partial_solution.backtrack_package(package).ok()?;
+        self.stats.backtracks += 1;
         // Remove contradicted incompatibilities that depend on decisions we just backtracked away.
         self.contradicted_incompatibilities
             .retain(|_, dl| *dl <= new_decision_level);
@@ -357,6 +918,24 @@ impl<DP: DependencyProvider> State<DP> {
             } else {
                 deps_lookup.push(id);
             }
+        } else if let Kind::NoVersions(package, _) = self.incompatibility_store[id].kind {
+            // Versions found missing at different times make a single incompatibility.
+            match self.merged_no_versions.get(&package).copied() {
+                Some(past) => {
+                    let merged = self.incompatibility_store[id]
+                        .merge_no_versions(&self.incompatibility_store[past])
+                        .expect("the incompatibilities are about the same package");
+                    id = self.incompatibility_store.alloc(merged);
+                    self.incompatibilities
+                        .entry(package)
+                        .or_default()
+                        .retain(|other| *other != past);
+                    self.merged_no_versions.insert(package, id);
+                }
+                None => {
+                    self.merged_no_versions.insert(package, id);
+                }
+            }
         }
         for (pkg, term) in self.incompatibility_store[id].iter() {
             if cfg!(debug_assertions) {
@@ -406,3 +985,26 @@ impl<DP: DependencyProvider> State<DP> {
         Arc::into_inner(precomputed.remove(&incompat).unwrap()).unwrap()
     }
 }
+
+impl<DP: DependencyProvider> PartialSolutionView<DP::P, DP::VS> for State<DP> {
+    fn decisions(&self) -> Vec<(&DP::P, &DP::V)> {
+        self.partial_solution
+            .decisions()
+            .map(|(p, v)| (&self.package_store[p], v))
+            .collect()
+    }
+
+    fn decision(&self, package: &DP::P) -> Option<&DP::V> {
+        let package = self.package_store.get(package)?;
+        self.partial_solution.decision(package)
+    }
+
+    fn term(&self, package: &DP::P) -> Option<&Term<DP::VS>> {
+        let package = self.package_store.get(package)?;
+        self.partial_solution.term_intersection_for_package(package)
+    }
+
+    fn decision_level(&self) -> u32 {
+        self.partial_solution.current_decision_level().0
+    }
+}
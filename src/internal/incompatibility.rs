@@ -3,8 +3,11 @@
 //! An incompatibility is a set of terms for different packages
 //! that should never be satisfied all together.
 
-use std::fmt::{Debug, Display};
-use std::sync::Arc;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
 
 use crate::internal::{Arena, HashArena, Id, SmallMap};
 use crate::{
@@ -74,6 +77,16 @@ pub enum Kind<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
     /// * The version would require building the package, but builds are disabled.
     /// * The package is not available in the cache, but internet access has been disabled.
     Custom(Id<P>, VS, M),
+    /// The versions are forbidden by a [constraint](crate::State::add_constraint) of the caller.
+    ///
+    /// It is reported like [Custom](Self::Custom), but it is not carried over to the next
+    /// incremental resolution.
+    Constraint(Id<P>, VS, M),
+    /// The versions are denied by a policy of the caller.
+    Denied(Id<P>, VS, M),
+    /// The versions of two packages can't be selected together, for a reason declared by the
+    /// caller.
+    Conflict(Id<P>, VS, Id<P>, VS, M),
 }
 
 /// A Relation describes how a set of terms can be compared to an incompatibility.
@@ -140,6 +153,34 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Incompatibilit
         }
     }
 
+    /// Create an incompatibility forbidding the versions of a package outside of `versions`.
+    pub(crate) fn constraint(package: Id<P>, versions: &VS, reason: M) -> Self {
+        let set = versions.complement();
+        Self {
+            package_terms: SmallMap::One([(package, Term::Positive(set.clone()))]),
+            kind: Kind::Constraint(package, set, reason),
+        }
+    }
+
+    /// Create an incompatibility for versions denied by a policy.
+    pub fn denied(package: Id<P>, set: VS, reason: M) -> Self {
+        Self {
+            package_terms: SmallMap::One([(package, Term::Positive(set.clone()))]),
+            kind: Kind::Denied(package, set, reason),
+        }
+    }
+
+    /// Create an incompatibility for versions of two packages declared to conflict.
+    pub fn conflict(package: Id<P>, set: VS, other: Id<P>, other_set: VS, reason: M) -> Self {
+        Self {
+            package_terms: SmallMap::Two([
+                (package, Term::Positive(set.clone())),
+                (other, Term::Positive(other_set.clone())),
+            ]),
+            kind: Kind::Conflict(package, set, other, other_set, reason),
+        }
+    }
+
     /// Build an incompatibility from a given dependency.
     pub fn from_dependency(package: Id<P>, versions: VS, dep: (Id<P>, VS)) -> Self {
         let (p2, set2) = dep;
@@ -201,6 +242,22 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Incompatibilit
         ))
     }
 
+    /// Merge the versions of two [NoVersions](Kind::NoVersions) incompatibilities of the same
+    /// package.
+    ///
+    /// If there are no versions of a in 1 and no versions of a in 2, there are no versions of a
+    /// in 1||2.
+    pub(crate) fn merge_no_versions(&self, other: &Self) -> Option<Self> {
+        let (Kind::NoVersions(p1, set1), Kind::NoVersions(p2, set2)) = (&self.kind, &other.kind)
+        else {
+            return None;
+        };
+        if p1 != p2 {
+            return None;
+        }
+        Some(Self::no_versions(*p1, Term::Positive(set1.union(set2))))
+    }
+
     /// Prior cause of two incompatibilities using the rule of resolution.
     pub(crate) fn prior_cause(
         incompat: Id<Self>,
@@ -247,6 +304,17 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Incompatibilit
         self.package_terms.get(&package)
     }
 
+    /// Whether `other` is satisfied whenever `self` is, i.e. `self` is more general.
+    ///
+    /// Every package of `self` is in `other`, and its term in `other` is a subset of the term in
+    /// `self`.
+    pub(crate) fn subsumes(&self, other: &Self) -> bool {
+        self.package_terms.len() <= other.package_terms.len()
+            && self
+                .iter()
+                .all(|(package, term)| other.get(package).is_some_and(|t| t.subset_of(term)))
+    }
+
     /// Iterate over packages.
     pub fn iter(&self) -> impl Iterator<Item = (Id<P>, &Term<VS>)> {
         self.package_terms
@@ -264,6 +332,48 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Incompatibilit
         }
     }
 
+    /// The external incompatibility this is, if it is not derived from others.
+    pub(crate) fn external(&self, package_store: &HashArena<P>) -> Option<External<P, VS, M>> {
+        match &self.kind {
+            Kind::DerivedFrom(..) => None,
+            Kind::NotRoot(package, version) => Some(External::NotRoot(
+                package_store[*package].clone(),
+                version.clone(),
+            )),
+            Kind::NoVersions(package, set) => Some(External::NoVersions(
+                package_store[*package].clone(),
+                set.clone(),
+            )),
+            Kind::FromDependencyOf(package, set, dep_package, dep_set) => {
+                Some(External::FromDependencyOf(
+                    package_store[*package].clone(),
+                    set.clone(),
+                    package_store[*dep_package].clone(),
+                    dep_set.clone(),
+                ))
+            }
+            Kind::Custom(package, set, metadata) | Kind::Constraint(package, set, metadata) => {
+                Some(External::Custom(
+                    package_store[*package].clone(),
+                    set.clone(),
+                    metadata.clone(),
+                ))
+            }
+            Kind::Denied(package, set, reason) => Some(External::Denied(
+                package_store[*package].clone(),
+                set.clone(),
+                reason.clone(),
+            )),
+            Kind::Conflict(package, set, other, other_set, reason) => Some(External::Conflict(
+                package_store[*package].clone(),
+                set.clone(),
+                package_store[*other].clone(),
+                other_set.clone(),
+                reason.clone(),
+            )),
+        }
+    }
+
     /// Build a derivation tree for error reporting.
     pub(crate) fn build_derivation_tree(
         self_id: Id<Self>,
@@ -292,26 +402,11 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Incompatibilit
                 };
                 DerivationTree::Derived(derived)
             }
-            Kind::NotRoot(package, version) => {
-                DerivationTree::External(External::NotRoot(package_store[package].clone(), version))
-            }
-            Kind::NoVersions(package, set) => DerivationTree::External(External::NoVersions(
-                package_store[package].clone(),
-                set.clone(),
-            )),
-            Kind::FromDependencyOf(package, set, dep_package, dep_set) => {
-                DerivationTree::External(External::FromDependencyOf(
-                    package_store[package].clone(),
-                    set.clone(),
-                    package_store[dep_package].clone(),
-                    dep_set.clone(),
-                ))
-            }
-            Kind::Custom(package, set, metadata) => DerivationTree::External(External::Custom(
-                package_store[package].clone(),
-                set.clone(),
-                metadata.clone(),
-            )),
+            _ => DerivationTree::External(
+                store[self_id]
+                    .external(package_store)
+                    .expect("an incompatibility without causes is external"),
+            ),
         }
     }
 }
@@ -321,9 +416,20 @@ impl<'a, P: Package, VS: VersionSet + 'a, M: Eq + Clone + Debug + Display + 'a>
 {
     /// CF definition of Relation enum.
     pub(crate) fn relation(&self, terms: impl Fn(Id<P>) -> Option<&'a Term<VS>>) -> Relation<P> {
+        self.relation_by(|_, package, incompat_term| {
+            terms(package).map(|term| incompat_term.relation_with(term))
+        })
+    }
+
+    /// Same as [Self::relation], with the relation of each term of the incompatibility computed
+    /// by `term_relation`, from the index of the term, its package and the term itself.
+    pub(crate) fn relation_by(
+        &self,
+        term_relation: impl Fn(usize, Id<P>, &Term<VS>) -> Option<term::Relation>,
+    ) -> Relation<P> {
         let mut relation = Relation::Satisfied;
-        for (&package, incompat_term) in self.package_terms.iter() {
-            match terms(package).map(|term| incompat_term.relation_with(term)) {
+        for (i, (&package, incompat_term)) in self.package_terms.iter().enumerate() {
+            match term_relation(i, package, incompat_term) {
                 Some(term::Relation::Satisfied) => {}
                 Some(term::Relation::Contradicted) => {
                     return Relation::Contradicted(package);
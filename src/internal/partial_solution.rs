@@ -3,22 +3,31 @@
 //! A Memory acts like a structured partial solution
 //! where terms are regrouped by package in a [Map](crate::type_aliases::Map).
 
-use std::cmp::Reverse;
-use std::fmt::{Debug, Display};
-use std::hash::BuildHasherDefault;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::fmt::{Debug, Display};
+use core::hash::BuildHasherDefault;
+use core::mem::size_of;
 
 use log::debug;
 use priority_queue::PriorityQueue;
 use rustc_hash::FxHasher;
 
 use crate::internal::{
-    Arena, HashArena, Id, IncompDpId, IncompId, Incompatibility, Relation, SmallMap, SmallVec,
+    Arena, Bitsets, HashArena, Id, IncompDpId, IncompId, Incompatibility, Relation, SmallMap,
+    SmallVec, TermBits,
 };
-use crate::{DependencyProvider, Package, Term, VersionSet};
+use crate::{term, DependencyProvider, Package, Term, VersionSet};
 
 type FnvIndexMap<K, V> = indexmap::IndexMap<K, V, BuildHasherDefault<FxHasher>>;
 type FnvIndexSet<T> = indexmap::IndexSet<T, BuildHasherDefault<FxHasher>>;
 
+/// The order of an undecided package in the priority queue, see
+/// [prioritized_potential_packages](PartialSolution::prioritized_potential_packages).
+type QueuedPriority<Priority> = (u64, Priority, Reverse<(u64, u32)>);
+
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub(crate) struct DecisionLevel(pub(crate) u32);
 
@@ -57,16 +66,21 @@ pub struct PartialSolution<DP: DependencyProvider> {
     ///
     /// The max heap allows quickly `pop`ing the highest priority package.
     ///
-    /// The `Reverse<u32>` is the discovery order of packages used as tiebreaker. Its order is that
-    /// of a breadth-first search.
+    /// The `u64` comes first and is the activity of the package with the activity heuristic of
+    /// the solver, zero otherwise.
+    ///
+    /// The `Reverse<(u64, u32)>` is the tiebreaker between packages of the same priority, see
+    /// [TieBreak](crate::TieBreak).
     #[allow(clippy::type_complexity)]
     prioritized_potential_packages:
-        PriorityQueue<Id<DP::P>, (DP::Priority, Reverse<u32>), BuildHasherDefault<FxHasher>>,
+        PriorityQueue<Id<DP::P>, QueuedPriority<DP::Priority>, BuildHasherDefault<FxHasher>>,
     /// Packages whose derivations changed since the last time `prioritize` was called and need
     /// their priorities to be updated.
     outdated_priorities: FnvIndexSet<Id<DP::P>>,
     /// Whether we have never backtracked, to enable fast path optimizations.
     has_ever_backtracked: bool,
+    /// The terms of packages with known versions, as bitsets.
+    bitsets: Bitsets<DP::P, DP::VS>,
 }
 
 /// A package assignment is either a decision or a list of (accumulated) derivations without a
@@ -75,6 +89,10 @@ pub struct PartialSolution<DP: DependencyProvider> {
 struct PackageAssignments<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
     /// Whether the assigment is a decision or a derivation.
     assignments_intersection: AssignmentsIntersection<VS>,
+    /// The intersection of the derivations as a bitset, if the versions of the package are known.
+    ///
+    /// Only used while there is no decision.
+    bits: Option<TermBits>,
     /// All constraints on the package version from previous decisions, accumulated by decision
     /// level.
     dated_derivations: SmallVec<DatedDerivation<P, VS, M>>,
@@ -87,7 +105,7 @@ struct PackageAssignments<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Di
 impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Display
     for PackageAssignments<P, VS, M>
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let derivations: Vec<_> = self
             .dated_derivations
             .iter()
@@ -115,12 +133,14 @@ struct DatedDerivation<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Displ
     /// It may not contain all terms of this `decision_level`, there may be more than one
     /// `DatedDerivation` per decision level.
     accumulated_intersection: Term<VS>,
+    /// The accumulated intersection as a bitset, if the versions of the package are known.
+    accumulated_bits: Option<TermBits>,
 }
 
 impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Display
     for DatedDerivation<P, VS, M>
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:?}, cause: {:?}", self.decision_level, self.cause)
     }
 }
@@ -138,7 +158,7 @@ enum AssignmentsIntersection<VS: VersionSet> {
 }
 
 impl<VS: VersionSet> Display for AssignmentsIntersection<VS> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::Decision {
                 decision_level,
@@ -174,14 +194,84 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
             prioritized_potential_packages: PriorityQueue::default(),
             outdated_priorities: FnvIndexSet::default(),
             has_ever_backtracked: false,
+            bitsets: Bitsets::default(),
         }
     }
 
+    /// Reserve room for `packages` more packages.
+    pub(crate) fn reserve(&mut self, packages: usize) {
+        self.package_assignments.reserve(packages);
+        self.prioritized_potential_packages.reserve(packages);
+    }
+
+    /// The number of packages with a decision or derivations.
+    pub(crate) fn assignments_len(&self) -> usize {
+        self.package_assignments.len()
+    }
+
+    /// An estimate of the number of bytes allocated for the assignments and priorities, not
+    /// counting what the terms own.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        let derivations: usize = self
+            .package_assignments
+            .values()
+            .map(|pa| match &pa.dated_derivations {
+                SmallVec::Flexible(derivations) => {
+                    derivations.capacity() * size_of::<DatedDerivation<DP::P, DP::VS, DP::M>>()
+                }
+                _ => 0,
+            })
+            .sum();
+        let assignments = self.package_assignments.capacity()
+            * (size_of::<(Id<DP::P>, PackageAssignments<DP::P, DP::VS, DP::M>)>()
+                + 2 * size_of::<usize>());
+        let priorities = self.prioritized_potential_packages.capacity()
+            * (size_of::<(Id<DP::P>, QueuedPriority<DP::Priority>)>() + 3 * size_of::<usize>());
+        assignments + derivations + priorities
+    }
+
+    /// Whether the versions of `package` were already given, see [Self::add_known_versions].
+    pub(crate) fn has_known_versions(&self, package: Id<DP::P>) -> bool {
+        self.bitsets.is_queried(package)
+    }
+
+    /// Compile the terms on `package` into bitsets over `versions`, for faster relations.
+    ///
+    /// `None` records that the versions are unknown.
+    pub(crate) fn add_known_versions(&mut self, package: Id<DP::P>, versions: Option<Vec<DP::V>>) {
+        self.bitsets.add_known_versions(package, versions);
+    }
+
+    /// The version `package` must be decided at, when `range` is a single known version, see
+    /// [VersionSet::is_singleton].
+    pub(crate) fn pinned_version(&self, package: Id<DP::P>, range: &DP::VS) -> Option<DP::V> {
+        Self::pinned(&self.bitsets, package, range).cloned()
+    }
+
+    fn pinned<'a>(
+        bitsets: &Bitsets<DP::P, DP::VS>,
+        package: Id<DP::P>,
+        range: &'a DP::VS,
+    ) -> Option<&'a DP::V> {
+        let version = range.is_singleton()?;
+        bitsets
+            .known(package)?
+            .versions()
+            .binary_search(version)
+            .is_ok()
+            .then_some(version)
+    }
+
+    /// The known versions of `package` in increasing order, see [Self::add_known_versions].
+    pub(crate) fn known_versions(&self, package: Id<DP::P>) -> Option<&[DP::V]> {
+        self.bitsets.known(package).map(|known| known.versions())
+    }
+
     pub(crate) fn display<'a>(&'a self, package_store: &'a HashArena<DP::P>) -> impl Display + 'a {
         struct PSDisplay<'a, DP: DependencyProvider>(&'a PartialSolution<DP>, &'a HashArena<DP::P>);
 
         impl<DP: DependencyProvider> Display for PSDisplay<'_, DP> {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
                 let mut assignments: Vec<_> = self
                     .0
                     .package_assignments
@@ -251,12 +341,7 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
     #[allow(clippy::type_complexity)]
     pub fn undecided_packages(
         &self,
-    ) -> impl Iterator<
-        Item = (
-            &Id<DP::P>,
-            &(<DP as DependencyProvider>::Priority, Reverse<u32>),
-        ),
-    > {
+    ) -> impl Iterator<Item = (&Id<DP::P>, &QueuedPriority<DP::Priority>)> {
         self.prioritized_potential_packages.iter()
     }
 
@@ -268,11 +353,18 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
         store: &Arena<Incompatibility<DP::P, DP::VS, DP::M>>,
     ) {
         use indexmap::map::Entry;
+        let known = self.bitsets.known(package).map(|known| known.len());
+        let derived_bits = known.and_then(|len| {
+            let index = store[cause].iter().position(|(p, _)| p == package)?;
+            let bits = self.bitsets.incompatibility(cause, &store[cause])[index].as_ref()?;
+            Some(bits.negate(len))
+        });
         let mut dated_derivation = DatedDerivation {
             global_index: self.next_global_index,
             decision_level: self.current_decision_level,
             cause,
             accumulated_intersection: store[cause].get(package).unwrap().negate(),
+            accumulated_bits: None,
         };
         self.next_global_index += 1;
         match self.package_assignments.entry(package) {
@@ -290,6 +382,13 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
                         if t.is_positive() {
                             self.outdated_priorities.insert(package);
                         }
+                        pa.bits = match (&pa.bits, derived_bits) {
+                            (Some(bits), Some(derived)) => Some(bits.intersection(&derived)),
+                            // The bits are missing when the versions became known after
+                            // the first derivations.
+                            _ => self.bitsets.known(package).map(|known| known.compile(t)),
+                        };
+                        dated_derivation.accumulated_bits = pa.bits.clone();
                     }
                 }
                 pa.dated_derivations.push(dated_derivation);
@@ -299,7 +398,9 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
                 if term.is_positive() {
                     self.outdated_priorities.insert(package);
                 }
+                dated_derivation.accumulated_bits = derived_bits.clone();
                 v.insert(PackageAssignments {
+                    bits: derived_bits,
                     smallest_decision_level: self.current_decision_level,
                     highest_decision_level: self.current_decision_level,
                     dated_derivations: SmallVec::One([dated_derivation]),
@@ -323,10 +424,33 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
             })
     }
 
+    /// The allowed versions of a package that must be selected but is not decided yet.
+    pub(crate) fn undecided_range(&self, package: Id<DP::P>) -> Option<&DP::VS> {
+        self.package_assignments
+            .get(&package)?
+            .assignments_intersection
+            .potential_package_filter()
+    }
+
+    /// The packages that must be selected but are not decided yet, with their allowed versions.
+    pub(crate) fn required_packages(&self) -> impl Iterator<Item = (Id<DP::P>, &DP::VS)> {
+        self.package_assignments.iter().filter_map(|(&p, pa)| {
+            Some((p, pa.assignments_intersection.potential_package_filter()?))
+        })
+    }
+
+    /// Prioritize all the undecided packages again at the next pick.
+    pub(crate) fn outdate_priorities(&mut self) {
+        for (p, _) in self.prioritized_potential_packages.drain() {
+            self.outdated_priorities.insert(p);
+        }
+    }
+
     #[cold]
     pub fn pick_highest_priority_pkg(
         &mut self,
-        mut prioritizer: impl FnMut(Id<DP::P>, &DP::VS) -> DP::Priority,
+        pinned_first: bool,
+        mut prioritizer: impl FnMut(Id<DP::P>, &DP::VS) -> QueuedPriority<DP::Priority>,
     ) -> Option<(Id<DP::P>, &DP::VS)> {
         let prioritized_potential_packages = &mut self.prioritized_potential_packages;
         while let Some(p) = self.outdated_priorities.pop() {
@@ -336,8 +460,12 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
             let Some(r) = pa.assignments_intersection.potential_package_filter() else {
                 continue;
             };
-            let priority = prioritizer(p, r);
-            prioritized_potential_packages.push(p, (priority, Reverse(p.into_raw() as u32)));
+            if pinned_first && Self::pinned(&self.bitsets, p, r).is_some() {
+                // Decide it now, without prioritizing it.
+                prioritized_potential_packages.remove(&p);
+                return Some((p, r));
+            }
+            prioritized_potential_packages.push(p, prioritizer(p, r));
         }
         while let Some(p) = self.prioritized_potential_packages.pop().map(|(p, _)| p) {
             let Some(pa) = self.package_assignments.get(&p) else {
@@ -350,6 +478,29 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
         None
     }
 
+    /// The decided versions, in the order of the decisions.
+    pub(crate) fn decisions(&self) -> impl Iterator<Item = (Id<DP::P>, &DP::V)> {
+        self.package_assignments
+            .iter()
+            .take(self.current_decision_level.0 as usize)
+            .filter_map(|(&p, pa)| match &pa.assignments_intersection {
+                AssignmentsIntersection::Decision { version, .. } => Some((p, version)),
+                AssignmentsIntersection::Derivations(_) => None,
+            })
+    }
+
+    /// The version decided for a package, if any.
+    pub(crate) fn decision(&self, package: Id<DP::P>) -> Option<&DP::V> {
+        match &self
+            .package_assignments
+            .get(&package)?
+            .assignments_intersection
+        {
+            AssignmentsIntersection::Decision { version, .. } => Some(version),
+            AssignmentsIntersection::Derivations(_) => None,
+        }
+    }
+
     /// If a partial solution has, for every positive derivation,
     /// a corresponding decision that satisfies that assignment,
     /// it's a total solution and version solving has succeeded.
@@ -425,6 +576,7 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
                 // Reset the assignments intersection.
                 pa.assignments_intersection =
                     AssignmentsIntersection::Derivations(last.accumulated_intersection.clone());
+                pa.bits = last.accumulated_bits.clone();
 
                 self.prioritized_potential_packages.remove(p);
                 if pa.assignments_intersection.term().is_positive() {
@@ -470,7 +622,7 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
         &mut self,
         package: Id<DP::P>,
         version: DP::V,
-        new_incompatibilities: std::ops::Range<IncompId<DP::P, DP::VS, DP::M>>,
+        new_incompatibilities: core::ops::Range<IncompId<DP::P, DP::VS, DP::M>>,
         store: &Arena<Incompatibility<DP::P, DP::VS, DP::M>>,
     ) -> Option<IncompId<DP::P, DP::VS, DP::M>> {
         if !self.has_ever_backtracked {
@@ -509,11 +661,38 @@ impl<DP: DependencyProvider> PartialSolution<DP> {
     }
 
     /// Check if the terms in the partial solution satisfy the incompatibility.
+    ///
+    /// Decided packages only need to check whether the decided version is in the term, and the
+    /// bitsets of packages with known versions often prove that the relation is inconclusive
+    /// without version set operations.
     pub(crate) fn relation(
-        &self,
-        incompat: &Incompatibility<DP::P, DP::VS, DP::M>,
+        &mut self,
+        incompat_id: IncompDpId<DP>,
+        store: &Arena<Incompatibility<DP::P, DP::VS, DP::M>>,
     ) -> Relation<DP::P> {
-        incompat.relation(|package| self.term_intersection_for_package(package))
+        let incompat = &store[incompat_id];
+        let incompat_bits = self.bitsets.incompatibility(incompat_id, incompat);
+        let package_assignments = &self.package_assignments;
+        incompat.relation_by(|i, package, incompat_term| {
+            let pa = package_assignments.get(&package)?;
+            match &pa.assignments_intersection {
+                AssignmentsIntersection::Decision { version, .. } => {
+                    Some(if incompat_term.contains(version) {
+                        term::Relation::Satisfied
+                    } else {
+                        term::Relation::Contradicted
+                    })
+                }
+                AssignmentsIntersection::Derivations(term) => {
+                    if let (Some(bits), Some(incompat_bits)) = (&pa.bits, &incompat_bits[i]) {
+                        if bits.is_inconclusive_for(incompat_bits) {
+                            return Some(term::Relation::Inconclusive);
+                        }
+                    }
+                    Some(incompat_term.relation_with(term))
+                }
+            }
+        })
     }
 
     /// Retrieve intersection of terms related to package.
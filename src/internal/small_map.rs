@@ -1,9 +1,10 @@
-use std::hash::Hash;
+use core::hash::Hash;
 
 use crate::Map;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub(crate) enum SmallMap<K, V> {
+    #[default]
     Empty,
     One([(K, V); 1]),
     Two([(K, V); 2]),
@@ -37,7 +38,7 @@ impl<K: PartialEq + Eq + Hash, V> SmallMap<K, V> {
 
     pub(crate) fn remove(&mut self, key: &K) -> Option<V> {
         let out;
-        *self = match std::mem::take(self) {
+        *self = match core::mem::take(self) {
             Self::Empty => {
                 out = None;
                 Self::Empty
@@ -72,7 +73,7 @@ impl<K: PartialEq + Eq + Hash, V> SmallMap<K, V> {
     }
 
     pub(crate) fn insert(&mut self, key: K, value: V) {
-        *self = match std::mem::take(self) {
+        *self = match core::mem::take(self) {
             Self::Empty => Self::One([(key, value)]),
             Self::One([(k, v)]) => {
                 if key == k {
@@ -173,12 +174,6 @@ impl<K: Clone + PartialEq + Eq + Hash, V: Clone> SmallMap<K, V> {
     }
 }
 
-impl<K, V> Default for SmallMap<K, V> {
-    fn default() -> Self {
-        Self::Empty
-    }
-}
-
 impl<K, V> SmallMap<K, V> {
     pub(crate) fn len(&self) -> usize {
         match self {
@@ -191,8 +186,8 @@ impl<K, V> SmallMap<K, V> {
 }
 
 enum IterSmallMap<'a, K, V> {
-    Inline(std::slice::Iter<'a, (K, V)>),
-    Map(std::collections::hash_map::Iter<'a, K, V>),
+    Inline(core::slice::Iter<'a, (K, V)>),
+    Map(<&'a crate::Map<K, V> as IntoIterator>::IntoIter),
 }
 
 impl<'a, K: 'a, V: 'a> Iterator for IterSmallMap<'a, K, V> {
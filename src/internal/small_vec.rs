@@ -1,9 +1,12 @@
-use std::fmt;
-use std::hash::{Hash, Hasher};
-use std::ops::Deref;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub enum SmallVec<T> {
+    #[default]
     Empty,
     One([T; 1]),
     Two([T; 2]),
@@ -38,7 +41,7 @@ impl<T> SmallVec<T> {
     }
 
     pub fn push(&mut self, new: T) {
-        *self = match std::mem::take(self) {
+        *self = match core::mem::take(self) {
             Self::Empty => Self::One([new]),
             Self::One([v1]) => Self::Two([v1, new]),
             Self::Two([v1, v2]) => Self::Flexible(vec![v1, v2, new]),
@@ -50,7 +53,7 @@ impl<T> SmallVec<T> {
     }
 
     pub fn pop(&mut self) -> Option<T> {
-        match std::mem::take(self) {
+        match core::mem::take(self) {
             Self::Empty => None,
             Self::One([v1]) => {
                 *self = Self::Empty;
@@ -69,23 +72,17 @@ impl<T> SmallVec<T> {
     }
 
     pub fn clear(&mut self) {
-        if let Self::Flexible(mut v) = std::mem::take(self) {
+        if let Self::Flexible(mut v) = core::mem::take(self) {
             v.clear();
             *self = Self::Flexible(v);
         } // else: self already eq Empty from the take
     }
 
-    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
         self.as_slice().iter()
     }
 }
 
-impl<T> Default for SmallVec<T> {
-    fn default() -> Self {
-        Self::Empty
-    }
-}
-
 impl<T> Deref for SmallVec<T> {
     type Target = [T];
 
@@ -97,7 +94,7 @@ impl<T> Deref for SmallVec<T> {
 impl<'a, T> IntoIterator for &'a SmallVec<T> {
     type Item = &'a T;
 
-    type IntoIter = std::slice::Iter<'a, T>;
+    type IntoIter = core::slice::Iter<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
@@ -136,7 +133,7 @@ impl<T: serde::Serialize> serde::Serialize for SmallVec<T> {
 impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for SmallVec<T> {
     fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
         struct SmallVecVisitor<T> {
-            marker: std::marker::PhantomData<T>,
+            marker: core::marker::PhantomData<T>,
         }
 
         impl<'de, T> serde::de::Visitor<'de> for SmallVecVisitor<T>
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Configure what the solver learns from a conflict.
+
+use alloc::sync::Arc;
+
+use crate::solver::Solver;
+use crate::{DependencyProvider, PubGrubError, SelectedDependencies};
+
+/// A conflict found by the solver, once its root cause was derived.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    /// The decision level when the conflict was found.
+    pub decision_level: u32,
+    /// The decision level the solver backtracks to by default, where the root cause is almost
+    /// satisfied.
+    pub backtrack_level: u32,
+    /// Number of incompatibilities derived to find the root cause.
+    pub derivations: u32,
+    /// Number of conflicts found so far in the resolution, including this one.
+    pub conflicts: u32,
+}
+
+/// How the solver learns from conflicts.
+///
+/// When the decisions made so far satisfy an incompatibility, the solver derives new
+/// incompatibilities from it until it finds a root cause involving an earlier decision, learns
+/// that root cause and backtracks to the decision level where it is almost satisfied. A strategy
+/// can also keep the intermediate incompatibilities, or backtrack further, which is called a
+/// restart in SAT solvers. This changes which paths are explored first, but not whether a
+/// solution is found.
+pub trait ConflictLearning {
+    /// Also learn the incompatibilities derived before reaching the root cause of a conflict.
+    ///
+    /// By default only the root cause is learned.
+    fn learn_intermediate(&self) -> bool {
+        false
+    }
+
+    /// The decision level to backtrack to after learning the root cause of `conflict`.
+    ///
+    /// Values above [backtrack_level](Conflict::backtrack_level) are lowered to it. Level 1 is
+    /// the decision on the root package, a restart backtracks to it. By default the solver
+    /// backtracks to [backtrack_level](Conflict::backtrack_level).
+    fn backtrack_level(&self, conflict: &Conflict) -> u32 {
+        conflict.backtrack_level
+    }
+}
+
+/// The strategy used by [resolve](crate::resolve), learning only the root cause of conflicts and
+/// backtracking as little as possible.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultConflictLearning;
+
+impl ConflictLearning for DefaultConflictLearning {}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// learning from conflicts according to `learning`.
+///
+/// The solution may differ from the one of [resolve](crate::resolve), but it exists if and only
+/// if [resolve](crate::resolve) finds one. The `learning` strategy is kept in the [State], which
+/// requires it to be [Send] and [Sync].
+///
+/// [State]: crate::State
+pub fn resolve_with_learning<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    learning: impl ConflictLearning + Send + Sync + 'static,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let mut solver = Solver::new(package, version.into());
+    solver.state.learning = Arc::new(learning);
+    solver.solve(dependency_provider)
+}
@@ -130,6 +130,10 @@
 //! type defined by the crate as guidance,
 //! but you are free to use whatever approach makes sense in your situation.
 //!
+//! If retrieving versions or dependencies requires network access,
+//! you can implement [AsyncDependencyProvider] instead and call [resolve_async],
+//! which awaits each provider call rather than blocking on it.
+//!
 //! # Solution and error reporting
 //!
 //! When everything goes well, the algorithm finds and returns the complete
@@ -208,36 +212,190 @@
 //! Beware though that if you are using some kind of offline mode
 //! with a cache, you may want to know that some versions
 //! do not exist in your cache.
+//!
+//! # Large packages and versions
+//!
+//! The solver stores each package once, in a [HashArena], when it first appears in the
+//! dependencies, and refers to it by its [Id] from then on: packages are only cloned out of the
+//! state for the solution, the [Step]s of a [SteppingResolver] and the error reports. Versions
+//! are cloned into the terms of the incompatibilities it derives, so versions that are expensive
+//! to clone slow down the resolution of large graphs. Versions holding more than a few integers,
+//! with pre-release and build metadata for example, can be wrapped in an
+//! [Arc](alloc::sync::Arc): `Ranges<Arc<V>>` is a [VersionSet] as long as `V` is a version.
+//! Likewise, packages can be [`Arc<str>`](alloc::sync::Arc) rather than [String] to make the
+//! clones of the provider cheap.
+//!
+//! # Reproducibility
+//!
+//! [Map] and [Set] use a fixed hasher rather than a random one, so resolving again with the same
+//! provider on the same platform makes the same decisions and gives the same report. Their
+//! iteration order still depends on the target, its pointer width in particular: lockfiles and
+//! summaries should be written from a sorted solution, as returned by [resolve_sorted] or
+//! collected into a [SortedDependencies]. The [DefaultStringReporter] sorts the terms it lists, so
+//! its reports don't depend on that order.
+//!
+//! # `no_std` support
+//!
+//! The solver only needs an allocator. Disabling the default `std` feature builds the crate with
+//! `#![no_std]` and the `alloc` crate, using [hashbrown](https://docs.rs/hashbrown) for
+//! [Map] and [Set]. Without `std`, the time per package in [ResolutionStats] is always zero and
+//! the [timeout](ResolveOptions::timeout) is ignored. The `serde` feature enables `std`.
+//!
+//! # WebAssembly
+//!
+//! The crate builds for `wasm32-unknown-unknown`, with or without `std`. There is no clock on
+//! that target either, so the time per package in [ResolutionStats] is always zero. Bindings to
+//! JavaScript can implement the provider with [CallbackDependencyProvider], calling back into
+//! JavaScript to list versions and dependencies.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![warn(missing_docs)]
 
+extern crate alloc;
+
+mod alternatives;
+mod caching;
+mod callback;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod checkpoint;
+mod conflicts;
+mod cycles;
+#[cfg(feature = "debian")]
+mod debian;
+mod delegate;
+mod diff;
+mod duplicates;
+mod enumerated;
 mod error;
+mod features;
+mod frozen;
+mod graph;
+mod incremental;
+mod learning;
+mod markers;
+mod optimize;
+mod overrides;
 mod package;
+#[cfg(feature = "std")]
+mod parallel;
+#[cfg(feature = "pep440")]
+mod pep440;
+mod policy;
+mod preferences;
+mod prerelease;
+mod progress;
 mod provider;
+mod provides;
+mod recommends;
+mod recording;
+mod relaxation;
 mod report;
+mod requirement;
+mod resolver;
+mod retry;
+#[cfg(feature = "rpm")]
+mod rpm;
+mod soft;
+mod solution_cache;
+#[cfg(feature = "solution_graph")]
+mod solution_graph;
 mod solver;
+mod stepping;
+mod strategy;
 mod term;
+#[cfg(feature = "test_utils")]
+pub mod test_utils;
+mod trace;
 mod type_aliases;
+#[cfg(feature = "verification")]
+pub mod verification;
 mod version;
 mod version_set;
+mod yanked;
 
-pub use error::{NoSolutionError, PubGrubError};
+pub use alternatives::{resolve_iter, Solutions};
+pub use caching::CachingProvider;
+pub use callback::{CallbackDependencyProvider, CallbackError};
+pub use checkpoint::{resolve_from_checkpoint, Checkpoint};
+pub use conflicts::{resolve_with_conflicts, DeclaredConflict};
+pub use cycles::{find_cycles, resolve_acyclic};
+#[cfg(feature = "debian")]
+pub use debian::{parse_debian_relation, DebianParseError, DebianVersion};
+pub use diff::SolutionDiff;
+pub use duplicates::{
+    resolve_with_duplicates, MultiVersionDependencyProvider, PackageSlot, WithDuplicates,
+};
+pub use enumerated::{EnumeratedSet, VersionIndex};
+pub use error::{ErrorKind, NoSolutionError, PubGrubError};
+pub use features::{
+    resolve_with_features, FeatureDependencies, FeatureDependencyConstraints,
+    FeatureDependencyProvider, FeaturePackage, FeatureSelectedDependencies, FeatureUnavailable,
+    WithFeatures,
+};
+pub use frozen::{resolve_frozen, FrozenError, WithFrozen};
+pub use graph::{resolve_graph, ResolvedGraph};
+pub use incremental::IncrementalResolver;
+pub use learning::{resolve_with_learning, Conflict, ConflictLearning, DefaultConflictLearning};
+pub use markers::{
+    resolve_universal, resolve_with_environment, Marker, MarkerDependencies,
+    MarkerDependencyProvider, MarkerPackage, UniversalSolution, WithEnvironment,
+};
+pub use optimize::{resolve_optimal, OptimalSolution};
+pub use overrides::{resolve_with_overrides, Override, WithOverrides};
 pub use package::Package;
+#[cfg(feature = "std")]
+pub use parallel::resolve_many;
+#[cfg(feature = "pep440")]
+pub use pep440::{parse_specifiers, Pep440ParseError, Pep440Version, PreRelease};
+pub use policy::{resolve_with_policies, VersionPolicies};
+pub use preferences::{resolve_with_preferences, PreferredSolution, WithPreferences};
+pub use prerelease::{Prerelease, PrereleaseRanges};
+pub use progress::{resolve_with_progress, Progress, ProgressObserver};
 pub use provider::OfflineDependencyProvider;
+pub use provides::{
+    resolve_with_provides, ProvidesDependencyProvider, ProvidesPackage, ProvidesSet,
+    ProvidesSolution, ProvidesVersion, WithProvides,
+};
+pub use recommends::{
+    resolve_with_recommendations, Recommendation, RecommendedSolution, RecommendsDependencyProvider,
+};
+pub use recording::RecordingProvider;
+pub use relaxation::{suggest_relaxations, Relaxation};
 pub use report::{
-    DefaultStringReportFormatter, DefaultStringReporter, DerivationTree, Derived, External,
-    ReportFormatter, Reporter,
+    AnsiStyle, DefaultStringReportFormatter, DefaultStringReporter, DerivationTree, Derived,
+    Explanation, External, IncompatibilityKind, LineRefs, NearMiss, Phrase, ReportFormatter,
+    ReportLimits, ReportLine, ReportStyle, Reporter, StructuredReporter,
+};
+pub use requirement::{parse_requirement, RequirementParseError};
+pub use resolver::Resolver;
+pub use retry::{resolve_with_error_retries, resolve_with_retries, TransientErrors, WithRetries};
+#[cfg(feature = "rpm")]
+pub use rpm::{parse_rpm_requirement, RpmParseError, RpmVersion};
+pub use soft::{
+    resolve_with_soft_requirements, SoftDependencyProvider, SoftSolution, WithSoftRequirements,
+};
+pub use solution_cache::{Fingerprint, SolutionCache};
+pub use solver::{
+    propagate, resolve, resolve_async, resolve_best_effort, resolve_roots, resolve_sorted,
+    resolve_with_constraints, resolve_with_limits, resolve_with_options, resolve_with_stats,
+    AsyncDependencyProvider, AsyncProviderTypes, BestEffortSolution, Dependencies,
+    DependencyProvider, ExceededLimit, MemoryStats, PackageResolutionStatistics,
+    PartialSolutionView, Propagation, ResolutionLimits, ResolutionStats, ResolveOptions, TieBreak,
 };
-pub use solver::{resolve, Dependencies, DependencyProvider, PackageResolutionStatistics};
-pub use term::Term;
-pub use type_aliases::{DependencyConstraints, Map, SelectedDependencies, Set};
-pub use version::{SemanticVersion, VersionParseError};
+pub use stepping::{resolve_state, Step, SteppingResolver};
+pub use strategy::{resolve_with_strategy, ListVersions, VersionStrategy, WithStrategy};
+pub use term::{Relation, Term};
+pub use trace::{replay_trace, resolve_with_trace, TraceEvent, TraceMismatch, TraceOrigin};
+pub use type_aliases::{DependencyConstraints, Map, SelectedDependencies, Set, SortedDependencies};
+pub use version::{KeyedVersion, SemanticVersion, VersionParseError};
 pub use version_ranges::Ranges;
 #[deprecated(note = "Use `Ranges` instead")]
 pub use version_ranges::Ranges as Range;
 pub use version_set::VersionSet;
+pub use yanked::{resolve_with_yanked, WithYanked, YankedSolution, YankedVersions};
 
 // uv-specific additions
-pub use internal::{Id, IncompId, Incompatibility, Kind, State};
+pub use internal::{HashArena, Id, IncompId, Incompatibility, Kind, State};
 
 mod internal;
@@ -0,0 +1,448 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Dependencies only required in some environments.
+//!
+//! Packages depend on other packages only on some platforms, or for some versions of the
+//! interpreter: `pywin32; sys_platform == "win32"` in Python, `[target.'cfg(unix)'.dependencies]`
+//! in Cargo. The predicate guarding such a dependency is a marker, evaluated against the
+//! environment the resolution is for, which is supplied by the caller.
+//!
+//! Like [features](crate::FeaturePackage), markers are modeled with virtual packages, so that
+//! the solver and its reports know about them: a dependency on `p` guarded by marker `m` whose
+//! marker holds is a dependency on [`MarkerPackage::Conditional(p, m)`](MarkerPackage::Conditional),
+//! which depends on `p` at the same version. Reports then read "app ==1 depends on
+//! openssl (on linux) >=3".
+//!
+//! Implement [MarkerDependencyProvider], then resolve with [resolve_with_environment], or with
+//! [resolve_universal] for several environments at once.
+//!
+//! ```
+//! # use std::convert::Infallible;
+//! # use pubgrub::{
+//! #     resolve_with_environment, DependencyConstraints, Marker, MarkerDependencies,
+//! #     MarkerDependencyProvider, MarkerPackage, PackageResolutionStatistics, Ranges,
+//! # };
+//! #
+//! type NumVS = Ranges<u32>;
+//!
+//! /// `root` depends on `openssl` on linux only.
+//! struct Registry;
+//!
+//! #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+//! struct Os(&'static str);
+//!
+//! impl std::fmt::Display for Os {
+//!     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+//!         write!(f, "{}", self.0)
+//!     }
+//! }
+//!
+//! impl Marker<&'static str> for Os {
+//!     fn evaluate(&self, os: &&'static str) -> bool {
+//!         self.0 == *os
+//!     }
+//! }
+//!
+//! impl MarkerDependencyProvider for Registry {
+//!     type P = &'static str;
+//!     type V = u32;
+//!     type VS = NumVS;
+//!     type Marker = Os;
+//!     type M = String;
+//!     type Priority = u32;
+//!     type Err = Infallible;
+//!
+//!     fn prioritize(
+//!         &self,
+//!         _package: &MarkerPackage<&'static str, Os>,
+//!         _range: &NumVS,
+//!         _statistics: &PackageResolutionStatistics,
+//!     ) -> u32 {
+//!         0
+//!     }
+//!
+//!     fn choose_version(&self, _package: &&'static str, range: &NumVS) -> Result<Option<u32>, Infallible> {
+//!         Ok(range.contains(&1).then_some(1))
+//!     }
+//!
+//!     fn get_dependencies(
+//!         &self,
+//!         package: &&'static str,
+//!         _version: &u32,
+//!     ) -> Result<MarkerDependencies<&'static str, NumVS, String, Os>, Infallible> {
+//!         let mut conditional = Vec::new();
+//!         if *package == "root" {
+//!             conditional.push(("openssl", Ranges::full(), Os("linux")));
+//!         }
+//!         Ok(MarkerDependencies::Available { dependencies: DependencyConstraints::default(), conditional })
+//!     }
+//! }
+//!
+//! let linux = resolve_with_environment(&Registry, "root", 1u32, &"linux").unwrap();
+//! assert!(linux.contains_key("openssl"));
+//! let windows = resolve_with_environment(&Registry, "root", 1u32, &"windows").unwrap();
+//! assert!(!windows.contains_key("openssl"));
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::error::Error;
+use core::fmt::{self, Debug, Display};
+
+use crate::delegate::delegate_provider;
+use crate::{
+    resolve, Dependencies, DependencyConstraints, DependencyProvider, Map, Package,
+    PackageResolutionStatistics, PubGrubError, VersionSet,
+};
+
+/// A predicate on environments, guarding a dependency.
+///
+/// The [Display] implementation is used in reports, after "on": "openssl (on linux) >=3".
+pub trait Marker<E>: Package {
+    /// Whether the dependencies guarded by this marker are required in `environment`.
+    fn evaluate(&self, environment: &E) -> bool;
+}
+
+/// A package or a dependency on it guarded by a marker, as seen by the solver.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MarkerPackage<P, Mk> {
+    /// The package itself.
+    Package(P),
+    /// The package, required because the marker holds in the environment.
+    Conditional(P, Mk),
+}
+
+impl<P, Mk> MarkerPackage<P, Mk> {
+    /// The underlying package.
+    pub fn package(&self) -> &P {
+        match self {
+            Self::Package(p) | Self::Conditional(p, _) => p,
+        }
+    }
+}
+
+impl<P: Display, Mk: Display> Display for MarkerPackage<P, Mk> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Package(package) => write!(f, "{}", package),
+            Self::Conditional(package, marker) => write!(f, "{} (on {})", package, marker),
+        }
+    }
+}
+
+/// The dependencies of a package version, as returned by a [MarkerDependencyProvider].
+#[derive(Debug, Clone)]
+pub enum MarkerDependencies<P: Package, VS: VersionSet, M, Mk> {
+    /// Package dependencies are unavailable with the reason why they are missing.
+    Unavailable(M),
+    /// The dependencies of the package version.
+    Available {
+        /// Dependencies that are required in all environments.
+        dependencies: DependencyConstraints<P, VS>,
+        /// Dependencies that are only required in the environments where their marker holds.
+        ///
+        /// A package can be listed several times with different markers.
+        conditional: Vec<(P, VS, Mk)>,
+    },
+}
+
+/// Trait that allows the algorithm to retrieve available packages and their dependencies,
+/// some of them guarded by markers.
+///
+/// This is the same as [DependencyProvider], except that
+/// [get_dependencies](Self::get_dependencies) returns the dependencies guarded by markers
+/// separately. Which of them are required is decided by [WithEnvironment], from the
+/// environment of the resolution.
+pub trait MarkerDependencyProvider {
+    /// How this provider stores the name of the packages.
+    type P: Package;
+
+    /// How this provider stores the versions of the packages.
+    type V: Debug + Display + Clone + Ord;
+
+    /// How this provider stores the version requirements for the packages.
+    type VS: VersionSet<V = Self::V>;
+
+    /// How this provider stores the markers guarding dependencies.
+    type Marker: Package;
+
+    /// Type for custom incompatibilities, see [DependencyProvider::M].
+    type M: Eq + Clone + Debug + Display;
+
+    /// The type returned from `prioritize`, see [DependencyProvider::Priority].
+    type Priority: Ord + Clone;
+
+    /// The kind of error returned from these methods.
+    type Err: Error + 'static;
+
+    /// Determine the order in which versions are chosen for packages and conditional
+    /// dependencies, see [DependencyProvider::prioritize].
+    fn prioritize(
+        &self,
+        package: &MarkerPackage<Self::P, Self::Marker>,
+        range: &Self::VS,
+        package_conflicts_counts: &PackageResolutionStatistics,
+    ) -> Self::Priority;
+
+    /// Whether to prioritize all the undecided packages again after each conflict,
+    /// see [DependencyProvider::reprioritize_on_conflict].
+    fn reprioritize_on_conflict(&self) -> bool {
+        false
+    }
+
+    /// Select the version to use for a package, see [DependencyProvider::choose_version].
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err>;
+
+    /// Retrieves the dependencies of a package version, with their markers.
+    #[allow(clippy::type_complexity)]
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<MarkerDependencies<Self::P, Self::VS, Self::M, Self::Marker>, Self::Err>;
+
+    /// This is called fairly regularly during the resolution,
+    /// if it returns an Err then resolution will be terminated.
+    fn should_cancel(&self) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+/// A [DependencyProvider] keeping the dependencies of a [MarkerDependencyProvider] required in
+/// an environment.
+///
+/// In [universal resolutions](resolve_universal), the same provider resolves a group of
+/// environments, as long as the markers it evaluates agree on all of them.
+pub struct WithEnvironment<'a, DP: MarkerDependencyProvider, E> {
+    provider: &'a DP,
+    environments: Vec<&'a E>,
+    /// The first marker holding in some of the environments, but not in all of them.
+    split: RefCell<Option<DP::Marker>>,
+}
+
+impl<'a, DP: MarkerDependencyProvider, E> WithEnvironment<'a, DP, E>
+where
+    DP::Marker: Marker<E>,
+{
+    /// Resolve the dependencies of `provider` required in `environment`.
+    pub fn new(provider: &'a DP, environment: &'a E) -> Self {
+        Self::with_group(provider, vec![environment])
+    }
+
+    fn with_group(provider: &'a DP, environments: Vec<&'a E>) -> Self {
+        Self {
+            provider,
+            environments,
+            split: RefCell::new(None),
+        }
+    }
+}
+
+impl<DP: MarkerDependencyProvider, E> DependencyProvider for WithEnvironment<'_, DP, E>
+where
+    DP::Marker: Marker<E>,
+{
+    type P = MarkerPackage<DP::P, DP::Marker>;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(provider => prioritize, reprioritize_on_conflict, should_cancel);
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.provider.choose_version(package.package(), range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        let mut constraints = DependencyConstraints::default();
+        if let MarkerPackage::Conditional(p, _) = package {
+            // A conditional dependency is the package itself, required in fewer environments.
+            constraints.insert(
+                MarkerPackage::Package(p.clone()),
+                DP::VS::singleton(version.clone()),
+            );
+            return Ok(Dependencies::Available(constraints));
+        }
+        let (dependencies, conditional) =
+            match self.provider.get_dependencies(package.package(), version)? {
+                MarkerDependencies::Unavailable(reason) => {
+                    return Ok(Dependencies::Unavailable(reason))
+                }
+                MarkerDependencies::Available {
+                    dependencies,
+                    conditional,
+                } => (dependencies, conditional),
+            };
+        for (p, range) in dependencies {
+            constraints.insert(MarkerPackage::Package(p), range);
+        }
+        for (p, range, marker) in conditional {
+            let holds = self
+                .environments
+                .iter()
+                .filter(|environment| marker.evaluate(environment))
+                .count();
+            if holds == 0 {
+                continue;
+            }
+            if holds < self.environments.len() {
+                // The environments need different resolutions. Keep resolving for the first
+                // one only, the result will be thrown away anyway.
+                let mut split = self.split.borrow_mut();
+                if split.is_none() {
+                    *split = Some(marker.clone());
+                }
+                if !marker.evaluate(self.environments[0]) {
+                    continue;
+                }
+            }
+            let package = MarkerPackage::Conditional(p, marker);
+            let range = match constraints.remove(&package) {
+                Some(previous) => previous.intersection(&range),
+                None => range,
+            };
+            constraints.insert(package, range);
+        }
+        Ok(Dependencies::Available(constraints))
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// with only the dependencies whose marker holds in `environment`.
+///
+/// The conditional dependencies are merged into their package in the solution, but they appear
+/// in the derivation tree of errors with their marker.
+#[allow(clippy::type_complexity)]
+pub fn resolve_with_environment<'a, DP: MarkerDependencyProvider, E>(
+    dependency_provider: &'a DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    environment: &'a E,
+) -> Result<Map<DP::P, DP::V>, PubGrubError<WithEnvironment<'a, DP, E>>>
+where
+    DP::Marker: Marker<E>,
+{
+    let provider = WithEnvironment::new(dependency_provider, environment);
+    let solution = resolve(&provider, MarkerPackage::Package(package), version)?;
+    Ok(without_conditional(solution))
+}
+
+/// Merge the conditional dependencies of a solution into their package.
+fn without_conditional<P: Package, V, Mk>(
+    solution: impl IntoIterator<Item = (MarkerPackage<P, Mk>, V)>,
+) -> Map<P, V> {
+    solution
+        .into_iter()
+        .filter_map(|(package, version)| match package {
+            MarkerPackage::Package(p) => Some((p, version)),
+            MarkerPackage::Conditional(..) => None,
+        })
+        .collect()
+}
+
+/// The solutions of a [universal resolution](resolve_universal), each for a group of
+/// environments.
+#[derive(Debug, Clone)]
+pub struct UniversalSolution<P: Package, V, E> {
+    forks: Vec<(Vec<E>, Map<P, V>)>,
+}
+
+impl<P: Package, V, E: PartialEq> UniversalSolution<P, V, E> {
+    /// The groups of environments sharing a solution, with the solution.
+    ///
+    /// Groups are disjoint, and each environment of the resolution is in one of them.
+    pub fn forks(&self) -> &[(Vec<E>, Map<P, V>)] {
+        &self.forks
+    }
+
+    /// The solution for `environment`, if it was one of the environments of the resolution.
+    pub fn for_environment(&self, environment: &E) -> Option<&Map<P, V>> {
+        self.forks
+            .iter()
+            .find(|(environments, _)| environments.contains(environment))
+            .map(|(_, solution)| solution)
+    }
+
+    /// The versions selected for `package`, each with the environments it is selected in.
+    ///
+    /// Environments not listed don't require the package.
+    pub fn versions<'s>(&'s self, package: &'s P) -> impl Iterator<Item = (&'s V, &'s [E])> + 's {
+        self.forks
+            .iter()
+            .filter_map(move |(environments, solution)| {
+                solution
+                    .get(package)
+                    .map(|version| (version, environments.as_slice()))
+            })
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// in each of `environments` at once, as needed for lockfiles that are valid on all platforms.
+///
+/// All the environments start in a single resolution. When a marker holds in some environments
+/// of a resolution but not in all of them, the resolution forks in two, one for the environments
+/// where the marker holds and one for the others, and each fork is resolved on its own.
+/// Environments end up in the same fork when the markers of the dependencies they share agree
+/// on them, so there are usually far fewer forks than environments.
+///
+/// Fails with the error of the first fork without a solution.
+#[allow(clippy::type_complexity)]
+pub fn resolve_universal<'a, DP: MarkerDependencyProvider, E: Clone>(
+    dependency_provider: &'a DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    environments: &'a [E],
+) -> Result<UniversalSolution<DP::P, DP::V, E>, PubGrubError<WithEnvironment<'a, DP, E>>>
+where
+    DP::Marker: Marker<E>,
+{
+    let version = version.into();
+    let mut forks = Vec::new();
+    let mut groups = vec![environments.iter().collect::<Vec<_>>()];
+    while let Some(group) = groups.pop() {
+        if group.is_empty() {
+            continue;
+        }
+        let provider = WithEnvironment::with_group(dependency_provider, group);
+        let solution = resolve(
+            &provider,
+            MarkerPackage::Package(package.clone()),
+            version.clone(),
+        )?;
+        let WithEnvironment {
+            environments: group,
+            split,
+            ..
+        } = provider;
+        match split.into_inner() {
+            Some(marker) => {
+                let (holds, other) = group
+                    .into_iter()
+                    .partition(|environment| marker.evaluate(environment));
+                groups.push(other);
+                groups.push(holds);
+            }
+            None => forks.push((
+                group.into_iter().cloned().collect(),
+                without_conditional(solution),
+            )),
+        }
+    }
+    Ok(UniversalSolution { forks })
+}
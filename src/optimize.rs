@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Find the best solution for an objective, instead of the first one.
+//!
+//! The solver decides the packages one at a time with the versions chosen by the provider, which
+//! gives good solutions but not the best one for a global objective: a newer `foo` may force a
+//! much older `bar`. [resolve_optimal] minimizes a cost summed over the selected versions with
+//! branch and bound: after a solution with the decisions `p1 = v1, ..., pn = vn`, the other
+//! solutions are split into the ones without `v1`, the ones with `v1` but without `v2`, and so
+//! on. The versions selected in a part give a lower bound on the cost of its solutions, and the
+//! parts that can't beat the best solution found so far are not resolved.
+
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::{Ordering, Reverse};
+
+use crate::alternatives::Recorder;
+use crate::internal::Incompatibility;
+use crate::solver::Solver;
+use crate::{DependencyProvider, PubGrubError, SelectedDependencies, Term, VersionSet};
+
+/// The best solution found by [resolve_optimal].
+#[derive(Debug, Clone)]
+pub struct OptimalSolution<DP: DependencyProvider> {
+    /// The selected packages.
+    pub solution: SelectedDependencies<DP>,
+    /// The sum of the costs of the selected versions.
+    pub cost: u64,
+    /// Whether no solution has a lower cost, or the budget ran out before that was proven.
+    pub optimal: bool,
+}
+
+/// A part of the solutions: the versions selected, and the versions excluded.
+struct Part<DP: DependencyProvider> {
+    /// The sum of the costs of the selected versions.
+    lower_bound: u64,
+    selected: Vec<(DP::P, DP::V)>,
+    excluded: Vec<(DP::P, DP::V)>,
+}
+
+impl<DP: DependencyProvider> PartialEq for Part<DP> {
+    fn eq(&self, other: &Self) -> bool {
+        self.lower_bound == other.lower_bound
+    }
+}
+
+impl<DP: DependencyProvider> Eq for Part<DP> {}
+
+impl<DP: DependencyProvider> PartialOrd for Part<DP> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<DP: DependencyProvider> Ord for Part<DP> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.lower_bound.cmp(&other.lower_bound)
+    }
+}
+
+/// Finds the solution for a given package + version pair minimizing the sum of `cost` over the
+/// selected versions, resolving at most `max_resolutions` times if given.
+///
+/// Costs are summed over the selected packages, including the root package. Common objectives
+/// are:
+/// * The recency of the versions, with the number of newer versions of the package as cost.
+/// * The number of packages, with a cost of one for each package.
+/// * The changes from a lockfile, with a cost of one for each version that is not locked.
+///
+/// The first resolution is the same as [resolve](crate::resolve), and fails with its error if
+/// there is no solution. Each resolution after it explores the part of the other solutions with
+/// the lowest bound, until no part can have a lower cost than the best solution, which is then
+/// [optimal](OptimalSolution::optimal). Otherwise, the best solution found within the budget is
+/// returned. The dependencies of each version are only retrieved once.
+///
+/// ```
+/// # use pubgrub::{resolve_optimal, OfflineDependencyProvider, Ranges};
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+/// dependency_provider.add_dependencies("foo", 1u32, []);
+/// dependency_provider.add_dependencies("foo", 2u32, [("bar", Ranges::full())]);
+/// dependency_provider.add_dependencies("bar", 1u32, []);
+///
+/// // The fewest packages, even if that means an older foo.
+/// let optimal = resolve_optimal(&dependency_provider, "root", 1u32, |_, _| 1, None).unwrap();
+/// assert!(optimal.optimal);
+/// assert_eq!((optimal.solution["foo"], optimal.cost), (1, 2));
+/// ```
+pub fn resolve_optimal<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    cost: impl Fn(&DP::P, &DP::V) -> u64,
+    max_resolutions: Option<u32>,
+) -> Result<OptimalSolution<DP>, PubGrubError<DP>> {
+    let version = version.into();
+    let mut recorder = Recorder::<DP> {
+        dependencies: Vec::new(),
+    };
+    let mut parts = BinaryHeap::from([Reverse(Part::<DP> {
+        lower_bound: 0,
+        selected: Vec::new(),
+        excluded: Vec::new(),
+    })]);
+    let mut best: Option<OptimalSolution<DP>> = None;
+    let mut resolutions = 0;
+    let mut optimal = false;
+    while let Some(Reverse(next)) = parts.peek() {
+        if let Some(best) = &best {
+            if next.lower_bound >= best.cost {
+                // No part left can beat the best solution.
+                optimal = true;
+                break;
+            }
+            if max_resolutions.is_some_and(|max| resolutions >= max) {
+                break;
+            }
+        }
+        let Some(Reverse(part)) = parts.pop() else {
+            unreachable!()
+        };
+        resolutions += 1;
+
+        let mut solver = Solver::new(package.clone(), version.clone());
+        let solved = solver
+            .replay(&recorder.dependencies, &[])
+            .and_then(|()| {
+                for (package, version) in &part.selected {
+                    solver.add_root(package.clone(), version.clone())?;
+                }
+                Ok(())
+            })
+            .map_err(PubGrubError::NoSolution)
+            .and_then(|()| {
+                for (package, version) in &part.excluded {
+                    let package = solver.state.package_store.alloc(package.clone());
+                    let excluded = DP::VS::singleton(version.clone());
+                    solver
+                        .state
+                        .add_incompatibility(Incompatibility::no_versions(
+                            package,
+                            Term::Positive(excluded),
+                        ));
+                }
+                solver.solve_with_hooks(dependency_provider, &mut recorder)
+            });
+        let solution = match solved {
+            Ok(solution) => solution,
+            Err(PubGrubError::NoSolution(_)) if best.is_some() => continue,
+            Err(err) => return Err(err),
+        };
+
+        // Split the other solutions of the part on the decisions, in order.
+        let mut lower_bound = part.lower_bound;
+        let mut selected = part.selected;
+        for (p, v) in solver.decisions() {
+            if p == package || selected.iter().any(|(s, _)| *s == p) {
+                continue;
+            }
+            let mut excluded = part.excluded.clone();
+            excluded.push((p.clone(), v.clone()));
+            parts.push(Reverse(Part {
+                lower_bound,
+                selected: selected.clone(),
+                excluded,
+            }));
+            lower_bound += cost(&p, &v);
+            selected.push((p, v));
+        }
+
+        let solution_cost = solution.iter().map(|(p, v)| cost(p, v)).sum();
+        if best.as_ref().is_none_or(|best| solution_cost < best.cost) {
+            best = Some(OptimalSolution {
+                solution,
+                cost: solution_cost,
+                optimal: false,
+            });
+        }
+    }
+    let mut best = best.expect("the first resolution either succeeds or returns its error");
+    best.optimal = optimal || parts.is_empty();
+    Ok(best)
+}
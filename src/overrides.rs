@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Redirect the requirements on a package.
+//!
+//! Cargo's `[patch]` and `[replace]` sections and npm's `overrides` change what a requirement on
+//! a package resolves to, for every package of the graph, without editing the metadata of the
+//! packages depending on it. [resolve_with_overrides] applies such a table to the dependencies
+//! returned by the provider, so providers don't have to rewrite their responses.
+
+use crate::delegate::delegate_provider;
+use crate::{
+    resolve, Dependencies, DependencyConstraints, DependencyProvider, Map, PubGrubError,
+    SelectedDependencies, VersionSet,
+};
+
+/// What a requirement on an overridden package becomes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Override<P, VS> {
+    /// A requirement on another package, for the same versions, like a `[patch]` pointing to a
+    /// fork.
+    Redirect(P),
+    /// A requirement on a package at the given versions, whatever the required versions were.
+    ///
+    /// The package can be the overridden package itself to pin it, like npm `overrides` do.
+    Pin(P, VS),
+}
+
+/// A [DependencyProvider] applying overrides to the dependencies of every package.
+///
+/// When several requirements of a package end up on the same package, they must all be
+/// satisfied.
+pub struct WithOverrides<'a, DP: DependencyProvider> {
+    provider: &'a DP,
+    overrides: &'a Map<DP::P, Override<DP::P, DP::VS>>,
+}
+
+impl<'a, DP: DependencyProvider> WithOverrides<'a, DP> {
+    /// Apply `overrides` to the dependencies returned by `provider`.
+    ///
+    /// Overrides are not transitive: the package of an override is not overridden again.
+    pub fn new(provider: &'a DP, overrides: &'a Map<DP::P, Override<DP::P, DP::VS>>) -> Self {
+        Self {
+            provider,
+            overrides,
+        }
+    }
+}
+
+impl<DP: DependencyProvider> DependencyProvider for WithOverrides<'_, DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, choose_version,
+        choose_version_in, should_cancel, prefetch, on_conflict, on_backtrack, known_versions,
+        versions_sharing_dependencies,
+    );
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        let constraints = match self.provider.get_dependencies(package, version)? {
+            Dependencies::Available(constraints) => constraints,
+            unavailable => return Ok(unavailable),
+        };
+        let mut overridden = DependencyConstraints::default();
+        for (dependency, range) in constraints {
+            let (dependency, range) = match self.overrides.get(&dependency) {
+                None => (dependency, range),
+                Some(Override::Redirect(to)) => (to.clone(), range),
+                Some(Override::Pin(to, pinned)) => (to.clone(), pinned.clone()),
+            };
+            match overridden.get_mut(&dependency) {
+                Some(existing) => *existing = range.intersection(existing),
+                None => {
+                    overridden.insert(dependency, range);
+                }
+            }
+        }
+        Ok(Dependencies::Available(overridden))
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// replacing the requirements on the packages of `overrides`.
+///
+/// Overridden packages are only selected when an override points to them, or when they are
+/// the root package.
+pub fn resolve_with_overrides<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    overrides: &Map<DP::P, Override<DP::P, DP::VS>>,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let provider = WithOverrides::new(dependency_provider, overrides);
+    resolve(&provider, package, version).map_err(PubGrubError::into_provider)
+}
@@ -4,8 +4,8 @@
 //! Automatically implemented for traits implementing
 //! [Clone] + [Eq] + [Hash] + [Debug] + [Display].
 
-use std::fmt::{Debug, Display};
-use std::hash::Hash;
+use core::fmt::{Debug, Display};
+use core::hash::Hash;
 
 /// Trait for identifying packages.
 /// Automatically implemented for types already implementing
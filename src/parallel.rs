@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Resolve several roots in parallel.
+//!
+//! Checking that each member of a workspace resolves on its own means one resolution per
+//! member, over mostly the same packages. [resolve_many] runs these resolutions on a pool of
+//! threads, sharing the dependencies retrieved from the provider between them like a
+//! [CachingProvider](crate::CachingProvider) shared by all the resolutions.
+
+use std::collections::BTreeMap;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::vec::Vec;
+
+use crate::delegate::delegate_provider;
+use crate::{resolve, Dependencies, DependencyProvider, Map, PubGrubError, SelectedDependencies};
+
+/// The dependencies retrieved by any of the resolutions.
+type SharedCache<DP> = Mutex<
+    Map<
+        <DP as DependencyProvider>::P,
+        BTreeMap<
+            <DP as DependencyProvider>::V,
+            Dependencies<
+                <DP as DependencyProvider>::P,
+                <DP as DependencyProvider>::VS,
+                <DP as DependencyProvider>::M,
+            >,
+        >,
+    >,
+>;
+
+/// A [DependencyProvider] forwarding to another one, through the cache of [resolve_many].
+struct SharedCachingProvider<'a, DP: DependencyProvider> {
+    provider: &'a DP,
+    cache: &'a SharedCache<DP>,
+}
+
+impl<DP: DependencyProvider> SharedCachingProvider<'_, DP> {
+    #[allow(clippy::type_complexity)]
+    fn cached(
+        &self,
+        package: &DP::P,
+        version: &DP::V,
+    ) -> Option<Dependencies<DP::P, DP::VS, DP::M>> {
+        let cache = self
+            .cache
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        cache.get(package)?.get(version).cloned()
+    }
+
+    fn insert(
+        &self,
+        package: &DP::P,
+        version: &DP::V,
+        dependencies: &Dependencies<DP::P, DP::VS, DP::M>,
+    ) {
+        self.cache
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .entry(package.clone())
+            .or_default()
+            .insert(version.clone(), dependencies.clone());
+    }
+}
+
+impl<DP: DependencyProvider> DependencyProvider for SharedCachingProvider<'_, DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, choose_version,
+        choose_version_in, should_cancel, prefetch, on_conflict, on_backtrack, known_versions,
+        versions_sharing_dependencies,
+    );
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        if let Some(dependencies) = self.cached(package, version) {
+            return Ok(dependencies);
+        }
+        let dependencies = self.provider.get_dependencies(package, version)?;
+        self.insert(package, version, &dependencies);
+        Ok(dependencies)
+    }
+}
+
+/// Resolve each of the `roots` on its own, in parallel, and return the results in the order of
+/// the roots.
+///
+/// The resolutions run on as many threads as the machine has cores, or fewer if there are
+/// fewer roots. The dependencies retrieved by one resolution are reused by the others, so the
+/// provider is usually asked once for each version, but two resolutions reaching the same
+/// version at the same time may both ask for it. Errors are not cached.
+///
+/// ```
+/// # use pubgrub::{resolve_many, OfflineDependencyProvider, PubGrubError, Ranges};
+/// #
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("cli", 1u32, [("core", Ranges::full())]);
+/// dependency_provider.add_dependencies("server", 1u32, [("core", Ranges::higher_than(2u32))]);
+/// dependency_provider.add_dependencies("core", 1u32, []);
+///
+/// let results = resolve_many(&dependency_provider, &[("cli", 1u32), ("server", 1u32)]);
+/// assert_eq!(results[0].as_ref().unwrap()["core"], 1);
+/// assert!(matches!(results[1], Err(PubGrubError::NoSolution(_))));
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn resolve_many<DP>(
+    dependency_provider: &DP,
+    roots: &[(DP::P, DP::V)],
+) -> Vec<Result<SelectedDependencies<DP>, PubGrubError<DP>>>
+where
+    DP: DependencyProvider + Sync,
+    DP::P: Send + Sync,
+    DP::V: Send + Sync,
+    DP::VS: Send + Sync,
+    DP::M: Send + Sync,
+    DP::Err: Send,
+{
+    let cache: SharedCache<DP> = Mutex::new(Map::default());
+    let next = AtomicUsize::new(0);
+    let threads = thread::available_parallelism()
+        .map_or(1, |threads| threads.get())
+        .min(roots.len());
+
+    let mut results: Vec<_> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let provider = SharedCachingProvider {
+                        provider: dependency_provider,
+                        cache: &cache,
+                    };
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((package, version)) = roots.get(index) else {
+                            return results;
+                        };
+                        let result = resolve(&provider, package.clone(), version.clone())
+                            .map_err(PubGrubError::into_provider);
+                        results.push((index, result));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
@@ -0,0 +1,633 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! [PEP 440](https://peps.python.org/pep-0440/) versions and version specifiers.
+//!
+//! [Pep440Version] implements the ordering of Python versions, including epochs, pre, post and
+//! development releases and local versions, and [parse_specifiers] turns a specifier set like
+//! `>=1.2, !=1.3.*, <2` into a [`Ranges<Pep440Version>`](Ranges), which is a [VersionSet].
+//!
+//! Local version labels are ignored by specifiers, except by `==` and `!=` with a local version
+//! and by `===`, so `==1.2` matches `1.2+ubuntu1`. Pre-releases are matched by specifiers like
+//! any other version: excluding them unless requested is left to
+//! [choose_version](crate::DependencyProvider::choose_version), with
+//! [is_prerelease](Pep440Version::is_prerelease), or to a
+//! [PrereleaseRanges](crate::PrereleaseRanges) version set.
+//!
+//! [VersionSet]: crate::VersionSet
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::{self, Display};
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
+
+use thiserror::Error;
+
+use crate::Ranges;
+
+/// Error parsing a [Pep440Version] or a specifier set.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Pep440ParseError {
+    /// The version is not a valid PEP 440 version.
+    #[error("'{version}' is not a valid PEP 440 version")]
+    InvalidVersion {
+        /// Version that was being parsed.
+        version: String,
+    },
+    /// A comma separated part of the specifier set could not be parsed.
+    #[error("cannot parse '{specifier}' in '{specifiers}'")]
+    InvalidSpecifier {
+        /// Specifier set that was being parsed.
+        specifiers: String,
+        /// Specifier where parsing failed.
+        specifier: String,
+    },
+}
+
+/// Kind of a pre-release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PreRelease {
+    /// Alpha release, `1.0a1`.
+    Alpha,
+    /// Beta release, `1.0b1`.
+    Beta,
+    /// Release candidate, `1.0rc1`.
+    Rc,
+}
+
+/// A segment of a local version label. Numeric segments sort after alphanumeric ones.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum LocalSegment {
+    Alpha(String),
+    Number(u64),
+}
+
+/// The local version label, with a marker greater than all the labels.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Local {
+    None,
+    Segments(Vec<LocalSegment>),
+    /// Only used as a range bound, greater than all the local versions of a version.
+    Max,
+}
+
+/// Sort key of the pre, post and dev parts of a version.
+type SuffixKey = ((u8, Option<(PreRelease, u64)>), Option<u64>, (u8, u64));
+
+/// A version as defined by [PEP 440](https://peps.python.org/pep-0440/).
+///
+/// Versions are compared with the ordering of PEP 440, in which trailing zeros of the release are
+/// insignificant: `1.0` and `1.0.0` are equal.
+#[derive(Debug, Clone)]
+pub struct Pep440Version {
+    epoch: u64,
+    release: Vec<u64>,
+    pre: Option<(PreRelease, u64)>,
+    post: Option<u64>,
+    dev: Option<u64>,
+    local: Local,
+}
+
+impl Pep440Version {
+    /// A final release, like `1.2.3` for `&[1, 2, 3]`.
+    pub fn new(release: &[u64]) -> Self {
+        Self {
+            epoch: 0,
+            release: release.to_vec(),
+            pre: None,
+            post: None,
+            dev: None,
+            local: Local::None,
+        }
+    }
+
+    /// The epoch, `0` when not specified.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The numbers of the release, `[1, 2, 3]` for `1.2.3rc1`.
+    pub fn release(&self) -> &[u64] {
+        &self.release
+    }
+
+    /// The pre-release kind and number, if any.
+    pub fn pre(&self) -> Option<(PreRelease, u64)> {
+        self.pre
+    }
+
+    /// The post-release number, if any.
+    pub fn post(&self) -> Option<u64> {
+        self.post
+    }
+
+    /// The development release number, if any.
+    pub fn dev(&self) -> Option<u64> {
+        self.dev
+    }
+
+    /// Whether this is a pre-release or a development release.
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    /// Whether the version has a local version label, like `1.0+ubuntu1`.
+    pub fn is_local(&self) -> bool {
+        matches!(self.local, Local::Segments(_))
+    }
+
+    /// The release numbers without trailing zeros, which are insignificant for comparisons.
+    fn significant_release(&self) -> &[u64] {
+        let len = self
+            .release
+            .iter()
+            .rposition(|n| *n != 0)
+            .map_or(0, |i| i + 1);
+        &self.release[..len]
+    }
+
+    /// Order of the pre, post and dev parts, following the reference implementation:
+    /// `1.0.dev0 < 1.0a0.dev0 < 1.0a0 < 1.0 < 1.0.post0.dev0 < 1.0.post0`.
+    fn suffix_key(&self) -> SuffixKey {
+        let pre = match (self.pre, self.post, self.dev) {
+            (None, None, Some(_)) => (0, None),
+            (Some(pre), _, _) => (1, Some(pre)),
+            (None, _, _) => (2, None),
+        };
+        let dev = match self.dev {
+            Some(dev) => (0, dev),
+            None => (1, 0),
+        };
+        (pre, self.post, dev)
+    }
+
+    /// The version with the same release, pre, post and dev parts, greater than all its local
+    /// versions.
+    fn with_max_local(&self) -> Self {
+        Self {
+            local: Local::Max,
+            ..self.clone()
+        }
+    }
+
+    /// The version greater than this one, its post-releases and all their local versions.
+    fn with_max_post(&self) -> Self {
+        Self {
+            post: Some(u64::MAX),
+            dev: None,
+            local: Local::Max,
+            ..self.clone()
+        }
+    }
+
+    /// The smallest version of a release, its first development release.
+    fn first_of_release(epoch: u64, release: Vec<u64>) -> Self {
+        Self {
+            epoch,
+            release,
+            pre: None,
+            post: None,
+            dev: Some(0),
+            local: Local::None,
+        }
+    }
+
+    /// The versions whose release starts with the release of this version, like `==1.2.*`.
+    fn prefix_range(&self) -> Ranges<Self> {
+        let mut upper = self.release.clone();
+        if let Some(last) = upper.last_mut() {
+            *last += 1;
+        }
+        Ranges::between(
+            Self::first_of_release(self.epoch, self.release.clone()),
+            Self::first_of_release(self.epoch, upper),
+        )
+    }
+}
+
+impl PartialEq for Pep440Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pep440Version {}
+
+impl PartialOrd for Pep440Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pep440Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch
+            .cmp(&other.epoch)
+            .then_with(|| self.significant_release().cmp(other.significant_release()))
+            .then_with(|| self.suffix_key().cmp(&other.suffix_key()))
+            .then_with(|| self.local.cmp(&other.local))
+    }
+}
+
+impl Hash for Pep440Version {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.epoch.hash(state);
+        self.significant_release().hash(state);
+        self.suffix_key().hash(state);
+        self.local.hash(state);
+    }
+}
+
+impl Display for Pep440Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        let release: Vec<_> = self.release.iter().map(|n| n.to_string()).collect();
+        write!(f, "{}", release.join("."))?;
+        match self.pre {
+            Some((PreRelease::Alpha, n)) => write!(f, "a{}", n)?,
+            Some((PreRelease::Beta, n)) => write!(f, "b{}", n)?,
+            Some((PreRelease::Rc, n)) => write!(f, "rc{}", n)?,
+            None => {}
+        }
+        if let Some(post) = self.post {
+            write!(f, ".post{}", post)?;
+        }
+        if let Some(dev) = self.dev {
+            write!(f, ".dev{}", dev)?;
+        }
+        match &self.local {
+            Local::None => Ok(()),
+            Local::Segments(segments) => {
+                let segments: Vec<_> = segments
+                    .iter()
+                    .map(|segment| match segment {
+                        LocalSegment::Alpha(s) => s.clone(),
+                        LocalSegment::Number(n) => n.to_string(),
+                    })
+                    .collect();
+                write!(f, "+{}", segments.join("."))
+            }
+            Local::Max => write!(f, "+<max>"),
+        }
+    }
+}
+
+/// A simple cursor over the characters of a lowercase version.
+struct Cursor<'a>(&'a str);
+
+impl Cursor<'_> {
+    fn eat(&mut self, prefix: &str) -> bool {
+        match self.0.strip_prefix(prefix) {
+            Some(rest) => {
+                self.0 = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn eat_separator(&mut self) -> bool {
+        self.eat(".") || self.eat("-") || self.eat("_")
+    }
+
+    fn number(&mut self) -> Option<u64> {
+        let len = self
+            .0
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.0.len());
+        let number = self.0[..len].parse().ok()?;
+        self.0 = &self.0[len..];
+        Some(number)
+    }
+
+    /// Parse an optional separator followed by one of the labels and an optional number.
+    fn label_number(&mut self, labels: &[&str]) -> Option<u64> {
+        let start = self.0;
+        self.eat_separator();
+        if labels.iter().any(|label| self.eat(label)) {
+            let before_number = self.0;
+            self.eat_separator();
+            Some(self.number().unwrap_or_else(|| {
+                self.0 = before_number;
+                0
+            }))
+        } else {
+            self.0 = start;
+            None
+        }
+    }
+}
+
+impl FromStr for Pep440Version {
+    type Err = Pep440ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Pep440ParseError::InvalidVersion {
+            version: s.to_string(),
+        };
+        let lowercase = s.trim().to_ascii_lowercase();
+        let (public, local) = match lowercase.split_once('+') {
+            Some((public, local)) => (public, Some(local)),
+            None => (lowercase.as_str(), None),
+        };
+        let mut cursor = Cursor(public);
+        cursor.eat("v");
+
+        let mut epoch = 0;
+        let mut release = vec![cursor.number().ok_or_else(invalid)?];
+        if cursor.eat("!") {
+            epoch = release[0];
+            release = vec![cursor.number().ok_or_else(invalid)?];
+        }
+        while cursor.0.starts_with('.') && cursor.0[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            cursor.eat(".");
+            release.push(cursor.number().ok_or_else(invalid)?);
+        }
+
+        let pre = [
+            (PreRelease::Alpha, &["alpha", "a"][..]),
+            (PreRelease::Beta, &["beta", "b"][..]),
+            (PreRelease::Rc, &["rc", "c", "preview", "pre"][..]),
+        ]
+        .into_iter()
+        .find_map(|(kind, labels)| cursor.label_number(labels).map(|n| (kind, n)));
+        let post = cursor.label_number(&["post", "rev", "r"]).or_else(|| {
+            let start = cursor.0;
+            if cursor.eat("-") {
+                cursor.number().or_else(|| {
+                    cursor.0 = start;
+                    None
+                })
+            } else {
+                None
+            }
+        });
+        let dev = cursor.label_number(&["dev"]);
+        if !cursor.0.is_empty() {
+            return Err(invalid());
+        }
+
+        let local = match local {
+            None => Local::None,
+            Some(local) => {
+                let segments: Vec<_> = local
+                    .split(['.', '-', '_'])
+                    .map(|segment| {
+                        if segment.is_empty() || !segment.chars().all(|c| c.is_ascii_alphanumeric())
+                        {
+                            None
+                        } else if let Ok(n) = segment.parse() {
+                            Some(LocalSegment::Number(n))
+                        } else {
+                            Some(LocalSegment::Alpha(segment.to_string()))
+                        }
+                    })
+                    .collect::<Option<_>>()
+                    .ok_or_else(invalid)?;
+                Local::Segments(segments)
+            }
+        };
+
+        Ok(Self {
+            epoch,
+            release,
+            pre,
+            post,
+            dev,
+            local,
+        })
+    }
+}
+
+/// Parse a comma separated PEP 440 specifier set, like `>=1.2, !=1.3.*, <2`,
+/// into the set of matching versions.
+///
+/// All the operators are supported: `~=`, `==`, `!=`, `<=`, `>=`, `<`, `>` and `===`, which is
+/// handled as `==` with an exact local version.
+///
+/// ```
+/// # use pubgrub::{parse_specifiers, Pep440Version};
+/// let specifiers = parse_specifiers("~=1.4.2, !=1.4.5").unwrap();
+/// let version = |s: &str| s.parse::<Pep440Version>().unwrap();
+/// assert!(specifiers.contains(&version("1.4.9")));
+/// assert!(!specifiers.contains(&version("1.4.5")));
+/// assert!(!specifiers.contains(&version("1.5.0")));
+/// ```
+pub fn parse_specifiers(specifiers: &str) -> Result<Ranges<Pep440Version>, Pep440ParseError> {
+    if specifiers.trim().is_empty() {
+        return Ok(Ranges::full());
+    }
+    specifiers
+        .split(',')
+        .try_fold(Ranges::full(), |set, specifier| {
+            let range = parse_specifier(specifier.trim()).ok_or_else(|| {
+                Pep440ParseError::InvalidSpecifier {
+                    specifiers: specifiers.to_string(),
+                    specifier: specifier.trim().to_string(),
+                }
+            })?;
+            Ok(set.intersection(&range))
+        })
+}
+
+fn parse_specifier(specifier: &str) -> Option<Ranges<Pep440Version>> {
+    // Longest operators first so that `===` is not parsed as `==`.
+    let operators = ["===", "~=", "==", "!=", "<=", ">=", "<", ">"];
+    let (operator, version) = operators
+        .iter()
+        .find_map(|op| specifier.strip_prefix(op).map(|rest| (*op, rest.trim())))?;
+    let (version, wildcard) = match version.strip_suffix(".*") {
+        Some(prefix) if operator == "==" || operator == "!=" => (prefix, true),
+        _ => (version, false),
+    };
+    let version: Pep440Version = version.parse().ok()?;
+    if version.is_local() && !matches!(operator, "==" | "!=" | "===") {
+        return None;
+    }
+    if wildcard && (version.pre.is_some() || version.post.is_some() || version.dev.is_some()) {
+        return None;
+    }
+
+    let equal = || {
+        if wildcard {
+            version.prefix_range()
+        } else if version.is_local() {
+            Ranges::singleton(version.clone())
+        } else {
+            Ranges::between(version.clone(), version.with_max_local())
+        }
+    };
+    Some(match operator {
+        "===" => Ranges::singleton(version),
+        "==" => equal(),
+        "!=" => equal().complement(),
+        "~=" => {
+            let len = version.release.len();
+            if len < 2 {
+                return None;
+            }
+            let prefix = Pep440Version {
+                epoch: version.epoch,
+                ..Pep440Version::new(&version.release[..len - 1])
+            };
+            Ranges::higher_than(version).intersection(&prefix.prefix_range())
+        }
+        ">=" => Ranges::higher_than(version),
+        "<=" => Ranges::lower_than(version.with_max_local()),
+        // `<V` excludes the pre-releases of `V`, unless `V` is a pre-release.
+        "<" if version.pre.is_none() && version.post.is_none() && version.dev.is_none() => {
+            Ranges::strictly_lower_than(Pep440Version::first_of_release(
+                version.epoch,
+                version.release,
+            ))
+        }
+        "<" => Ranges::strictly_lower_than(version),
+        // `>V` excludes the post-releases of `V`, unless `V` is a post-release,
+        // and the local versions of `V`.
+        ">" if version.post.is_none() && version.dev.is_none() => {
+            Ranges::strictly_higher_than(version.with_max_post())
+        }
+        ">" => Ranges::strictly_higher_than(version.with_max_local()),
+        _ => unreachable!(),
+    })
+}
+
+impl crate::Prerelease for Pep440Version {
+    fn is_prerelease(&self) -> bool {
+        Pep440Version::is_prerelease(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> Pep440Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for (input, normalized) in [
+            ("1.0", "1.0"),
+            ("v1.2.3", "1.2.3"),
+            ("1!2.0", "1!2.0"),
+            ("1.0a1", "1.0a1"),
+            ("1.0-alpha.1", "1.0a1"),
+            ("1.0.beta", "1.0b0"),
+            ("1.0c2", "1.0rc2"),
+            ("1.0-preview2", "1.0rc2"),
+            ("1.0.post1", "1.0.post1"),
+            ("1.0-1", "1.0.post1"),
+            ("1.0-r2", "1.0.post2"),
+            ("1.0.dev3", "1.0.dev3"),
+            ("1.0rc1.post2.dev3", "1.0rc1.post2.dev3"),
+            ("1.0+Ubuntu-1", "1.0+ubuntu.1"),
+        ] {
+            assert_eq!(version(input).to_string(), normalized, "{input}");
+        }
+        for invalid in ["", "a", "1.0+", "1.0foo", "1..0", "1.0+a..b"] {
+            assert!(invalid.parse::<Pep440Version>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let ordered = [
+            "1.0.dev0",
+            "1.0a1.dev1",
+            "1.0a1",
+            "1.0a1.post1",
+            "1.0b1",
+            "1.0rc1",
+            "1.0",
+            "1.0+abc.2",
+            "1.0+abc.10",
+            "1.0+1",
+            "1.0.post1.dev0",
+            "1.0.post1",
+            "1.0.1",
+            "1.1.dev1",
+            "2.0",
+            "1!0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{:?}", pair);
+        }
+        assert_eq!(version("1.0"), version("1.0.0"));
+    }
+
+    #[test]
+    fn specifiers() {
+        let matches =
+            |specifiers: &str, v: &str| parse_specifiers(specifiers).unwrap().contains(&version(v));
+        assert!(matches("==1.0", "1.0.0"));
+        assert!(matches("==1.0", "1.0+local"));
+        assert!(!matches("==1.0+a", "1.0+b"));
+        assert!(matches("==1.*", "1.9.post1"));
+        assert!(matches("==1.2.*", "1.2.0rc1"));
+        assert!(!matches("==1.2.*", "1.3"));
+        assert!(!matches("!=1.2.*", "1.2.5"));
+        assert!(matches("!=1.2.*", "1.10"));
+        assert!(matches("~=2.2", "2.9"));
+        assert!(!matches("~=2.2", "3.0"));
+        assert!(!matches("~=2.2.1", "2.3"));
+        assert!(matches("<=1.0", "1.0+local"));
+        assert!(!matches("<1.0", "1.0rc1"));
+        assert!(matches("<1.0rc2", "1.0rc1"));
+        assert!(matches("<1.0.post1", "1.0"));
+        assert!(!matches(">1.0", "1.0.post1"));
+        assert!(!matches(">1.0", "1.0+local"));
+        assert!(matches(">1.0", "1.0.1"));
+        assert!(matches(">1.0.post1", "1.0.post2"));
+        assert!(matches(">1.0.dev1", "1.0"));
+        assert!(matches("===1.0+local", "1.0+local"));
+        assert!(matches(">=1.2, !=1.3.*, <2", "1.4"));
+        assert!(!matches(">=1.2, !=1.3.*, <2", "1.3.1"));
+        assert!(matches("", "1.0"));
+        assert!(matches(">=1!0", "1!0.1"));
+        assert!(!matches(">=1!0", "5.0"));
+
+        assert_eq!(
+            parse_specifiers(">=1.0, ~=1"),
+            Err(Pep440ParseError::InvalidSpecifier {
+                specifiers: ">=1.0, ~=1".to_owned(),
+                specifier: "~=1".to_owned(),
+            })
+        );
+        assert!(parse_specifiers("<1.0+local").is_err());
+        assert!(parse_specifiers(">=1.*").is_err());
+        assert!(parse_specifiers("1.0").is_err());
+    }
+
+    #[test]
+    fn prerelease_opt_in() {
+        use crate::{PrereleaseRanges, VersionSet};
+
+        let ranges =
+            |specifiers: &str| PrereleaseRanges::new(parse_specifiers(specifiers).unwrap());
+        let at_least_one = ranges(">=1.0");
+        assert!(at_least_one.contains(&version("2.0")));
+        assert!(!at_least_one.contains(&version("2.0a1")));
+        assert!(!at_least_one.contains(&version("2.0.dev1")));
+        let opted_in = ranges(">=2.0b1");
+        assert!(opted_in.contains(&version("2.0b2")));
+        assert!(opted_in.contains(&version("3.0a1")));
+        assert!(!opted_in.contains(&version("2.0a1")));
+
+        // Set operations keep each their part.
+        let both = at_least_one.intersection(&ranges("<3.0a2"));
+        assert!(both.contains(&version("2.5")));
+        assert!(!both.contains(&version("3.0a1")));
+        assert!(both.complement().contains(&version("3.0a1")));
+        assert_eq!(both.to_string(), ">=1.0, <3.0a2");
+        assert_eq!(
+            ranges("<3.0a2").to_string(),
+            "<3.0a2 (including pre-releases)"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Allow or deny versions of packages, whatever the provider says.
+//!
+//! Organizations restrict which versions may be used: no versions before 1.0, no versions
+//! affected by a security advisory, only versions approved by a review. Filtering them out in
+//! the provider hides why they weren't selected. [VersionPolicies] are checked by the solver
+//! instead, and the versions they deny are reported as [External::Denied] with their reason.
+//!
+//! [External::Denied]: crate::External::Denied
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::solver::{Solver, SolverHooks};
+use crate::{DependencyProvider, Map, Package, PubGrubError, SelectedDependencies};
+
+/// A policy: the versions it denies, and why.
+struct Policy<'a, V, M> {
+    denies: Box<dyn Fn(&V) -> bool + 'a>,
+    reason: M,
+}
+
+/// Predicates on the versions of packages, denying the versions the solver may select.
+///
+/// ```
+/// # use pubgrub::{resolve_with_policies, OfflineDependencyProvider, Ranges, VersionPolicies};
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+/// dependency_provider.add_dependencies("foo", 1u32, []);
+/// dependency_provider.add_dependencies("foo", 2u32, []);
+///
+/// let mut policies = VersionPolicies::new();
+/// policies.deny("foo", "CVE-2024-0001".to_string(), |v: &u32| *v == 2);
+/// let solution = resolve_with_policies(&dependency_provider, "root", 1u32, &policies).unwrap();
+/// assert_eq!(solution["foo"], 1);
+/// ```
+pub struct VersionPolicies<'a, P: Package, V, M> {
+    policies: Map<P, Vec<Policy<'a, V, M>>>,
+}
+
+impl<'a, P: Package, V, M> VersionPolicies<'a, P, V, M> {
+    /// No policy, all versions are allowed.
+    pub fn new() -> Self {
+        Self {
+            policies: Map::default(),
+        }
+    }
+
+    /// Deny the versions of `package` matching `predicate`, for `reason`.
+    pub fn deny(&mut self, package: P, reason: M, predicate: impl Fn(&V) -> bool + 'a) {
+        self.policies.entry(package).or_default().push(Policy {
+            denies: Box::new(predicate),
+            reason,
+        });
+    }
+
+    /// Only allow the versions of `package` matching `predicate`, denying the others for
+    /// `reason`.
+    pub fn allow_only(&mut self, package: P, reason: M, predicate: impl Fn(&V) -> bool + 'a) {
+        self.deny(package, reason, move |v| !predicate(v));
+    }
+
+    /// The reason of the first policy denying `version` of `package`, if any.
+    pub fn denied(&self, package: &P, version: &V) -> Option<&M> {
+        self.policies
+            .get(package)?
+            .iter()
+            .find(|policy| (policy.denies)(version))
+            .map(|policy| &policy.reason)
+    }
+}
+
+impl<P: Package, V, M> Default for VersionPolicies<'_, P, V, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<DP: DependencyProvider> SolverHooks<DP> for &VersionPolicies<'_, DP::P, DP::V, DP::M> {
+    fn denied(&self, package: &DP::P, version: &DP::V) -> Option<DP::M> {
+        VersionPolicies::denied(self, package, version).cloned()
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// never selecting the versions denied by `policies`.
+///
+/// Denied versions are chosen by the provider like the others, then each one is forbidden, so
+/// the provider should be able to choose another version in the remaining range.
+pub fn resolve_with_policies<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    policies: &VersionPolicies<'_, DP::P, DP::V, DP::M>,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let mut hooks = policies;
+    Solver::new(package, version.into()).solve_with_hooks(dependency_provider, &mut hooks)
+}
@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Prefer previously locked versions.
+//!
+//! When a lockfile exists, the versions it records should be kept unless the new constraints
+//! forbid them, so that unrelated packages don't change on every resolution.
+//! [resolve_with_preferences] tries the locked version of a package first, falling back to
+//! [choose_version](DependencyProvider::choose_version) of the provider when the locked version
+//! is not in the allowed range or not available, and reports which locked versions were kept.
+
+use crate::delegate::delegate_provider;
+use crate::{
+    resolve, DependencyProvider, Map, Package, PartialSolutionView, PubGrubError, Set, VersionSet,
+};
+
+/// A [DependencyProvider] that chooses preferred versions first.
+pub struct WithPreferences<'a, DP: DependencyProvider> {
+    provider: &'a DP,
+    preferences: &'a Map<DP::P, DP::V>,
+}
+
+impl<'a, DP: DependencyProvider> WithPreferences<'a, DP> {
+    /// Prefer the given versions when choosing a version of a package.
+    pub fn new(provider: &'a DP, preferences: &'a Map<DP::P, DP::V>) -> Self {
+        Self {
+            provider,
+            preferences,
+        }
+    }
+
+    /// The preferred version of `package` if `choose` accepts it, otherwise the version
+    /// `choose` picks in `range`.
+    fn choose(
+        &self,
+        package: &DP::P,
+        range: &DP::VS,
+        choose: impl Fn(&DP::VS) -> Result<Option<DP::V>, DP::Err>,
+    ) -> Result<Option<DP::V>, DP::Err> {
+        if let Some(preferred) = self.preferences.get(package) {
+            if range.contains(preferred) {
+                if let Some(version) = choose(&DP::VS::singleton(preferred.clone()))? {
+                    return Ok(Some(version));
+                }
+            }
+        }
+        choose(range)
+    }
+}
+
+impl<DP: DependencyProvider> DependencyProvider for WithPreferences<'_, DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, get_dependencies,
+        should_cancel, prefetch, on_conflict, on_backtrack, known_versions,
+        versions_sharing_dependencies, get_dependencies_batch,
+    );
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.choose(package, range, |range| {
+            self.provider.choose_version(package, range)
+        })
+    }
+
+    fn choose_version_in(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        solution: &dyn PartialSolutionView<Self::P, Self::VS>,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.choose(package, range, |range| {
+            self.provider.choose_version_in(package, range, solution)
+        })
+    }
+}
+
+/// A solution found by [resolve_with_preferences], with how it compares to the preferences.
+#[derive(Debug, Clone)]
+pub struct PreferredSolution<P: Package, V> {
+    /// The selected packages and versions.
+    pub solution: Map<P, V>,
+    /// Packages selected at their preferred version.
+    pub kept: Set<P>,
+    /// Packages selected at another version than their preferred one,
+    /// with the preferred version.
+    pub changed: Map<P, V>,
+    /// Packages with a preferred version that are not part of the solution anymore,
+    /// with the preferred version.
+    pub removed: Map<P, V>,
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// selecting the version in `preferences` for a package whenever it is allowed.
+pub fn resolve_with_preferences<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    preferences: &Map<DP::P, DP::V>,
+) -> Result<PreferredSolution<DP::P, DP::V>, PubGrubError<DP>> {
+    let provider = WithPreferences::new(dependency_provider, preferences);
+    let solution = resolve(&provider, package, version).map_err(PubGrubError::into_provider)?;
+
+    let mut kept = Set::default();
+    let mut changed = Map::default();
+    let mut removed = Map::default();
+    for (package, preferred) in preferences {
+        match solution.get(package) {
+            Some(selected) if selected == preferred => {
+                kept.insert(package.clone());
+            }
+            Some(_) => {
+                changed.insert(package.clone(), preferred.clone());
+            }
+            None => {
+                removed.insert(package.clone(), preferred.clone());
+            }
+        }
+    }
+    Ok(PreferredSolution {
+        solution,
+        kept,
+        changed,
+        removed,
+    })
+}
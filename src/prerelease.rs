@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Exclude pre-releases unless a requirement opts in.
+//!
+//! In Cargo and npm, `>=1.0` doesn't match `2.0.0-alpha`: pre-releases are only matched by
+//! requirements mentioning a pre-release, like `>=2.0.0-alpha`. This is not a property of the
+//! versions alone, since `2.0.0-alpha` is between `1.0` and `3.0` in the order of versions, so
+//! it can't be expressed with [`Ranges`] of some version type. [PrereleaseRanges] keeps one range
+//! for releases and one for pre-releases instead.
+
+use core::fmt::{self, Display};
+use core::ops::Bound;
+
+use crate::{Ranges, VersionSet};
+
+/// A version that may be a pre-release.
+pub trait Prerelease {
+    /// Whether this version is a pre-release.
+    fn is_prerelease(&self) -> bool;
+}
+
+/// A [VersionSet] matching releases and pre-releases with distinct ranges.
+///
+/// Two sets are equal if both of their ranges are, assuming, like [`Ranges`], that any version
+/// may exist: the pre-release range may contain releases and the other way around, even though
+/// [contains](VersionSet::contains) ignores them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PrereleaseRanges<V> {
+    releases: Ranges<V>,
+    prereleases: Ranges<V>,
+}
+
+impl<V: Ord + Clone + Prerelease> PrereleaseRanges<V> {
+    /// The versions of `ranges`, excluding pre-releases unless a bound of `ranges` is a
+    /// pre-release.
+    ///
+    /// Cargo and npm only opt in to the pre-releases of the same `major.minor.patch` as the
+    /// bound, this opts in to all the pre-releases of `ranges`.
+    pub fn new(ranges: Ranges<V>) -> Self {
+        let opt_in = ranges.iter().any(|(start, end)| {
+            [start, end].into_iter().any(|bound| match bound {
+                Bound::Included(v) | Bound::Excluded(v) => v.is_prerelease(),
+                Bound::Unbounded => false,
+            })
+        });
+        if opt_in {
+            Self::with_prereleases(ranges)
+        } else {
+            Self::releases(ranges)
+        }
+    }
+
+    /// The versions of `ranges`, pre-releases included.
+    pub fn with_prereleases(ranges: Ranges<V>) -> Self {
+        Self {
+            releases: ranges.clone(),
+            prereleases: ranges,
+        }
+    }
+
+    /// The releases of `ranges`, without any pre-release.
+    pub fn releases(ranges: Ranges<V>) -> Self {
+        Self {
+            releases: ranges,
+            prereleases: Ranges::empty(),
+        }
+    }
+
+    /// The range matching releases.
+    pub fn release_ranges(&self) -> &Ranges<V> {
+        &self.releases
+    }
+
+    /// The range matching pre-releases.
+    pub fn prerelease_ranges(&self) -> &Ranges<V> {
+        &self.prereleases
+    }
+}
+
+impl<V: Display + Eq> Display for PrereleaseRanges<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.prereleases == self.releases {
+            write!(f, "{} (including pre-releases)", self.releases)
+        } else if self.prereleases == Ranges::empty() {
+            write!(f, "{}", self.releases)
+        } else {
+            write!(f, "{} (pre-releases: {})", self.releases, self.prereleases)
+        }
+    }
+}
+
+impl<V: fmt::Debug + Display + Clone + Ord + Prerelease> VersionSet for PrereleaseRanges<V> {
+    type V = V;
+
+    fn empty() -> Self {
+        Self::releases(Ranges::empty())
+    }
+
+    fn singleton(v: V) -> Self {
+        if v.is_prerelease() {
+            Self {
+                releases: Ranges::empty(),
+                prereleases: Ranges::singleton(v),
+            }
+        } else {
+            Self::releases(Ranges::singleton(v))
+        }
+    }
+
+    fn complement(&self) -> Self {
+        Self {
+            releases: self.releases.complement(),
+            prereleases: self.prereleases.complement(),
+        }
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        Self {
+            releases: self.releases.intersection(&other.releases),
+            prereleases: self.prereleases.intersection(&other.prereleases),
+        }
+    }
+
+    fn contains(&self, v: &V) -> bool {
+        if v.is_prerelease() {
+            self.prereleases.contains(v)
+        } else {
+            self.releases.contains(v)
+        }
+    }
+
+    fn full() -> Self {
+        Self::with_prereleases(Ranges::full())
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        Self {
+            releases: self.releases.union(&other.releases),
+            prereleases: self.prereleases.union(&other.prereleases),
+        }
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.releases.is_disjoint(&other.releases)
+            && self.prereleases.is_disjoint(&other.prereleases)
+    }
+
+    fn subset_of(&self, other: &Self) -> bool {
+        self.releases.subset_of(&other.releases) && self.prereleases.subset_of(&other.prereleases)
+    }
+}
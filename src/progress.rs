@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Report the progress of a resolution while it runs.
+//!
+//! Large resolutions can take seconds, and a spinner doesn't tell whether the solver is close to
+//! a solution or stuck backtracking. [resolve_with_progress] calls a [ProgressObserver] before
+//! each decision with the packages decided so far, the packages discovered, and the conflicts,
+//! so that a GUI or CLI can draw a progress bar, and stop the resolution if the user asks to.
+
+use crate::solver::{Solver, SolverHooks};
+use crate::{DependencyProvider, PubGrubError, SelectedDependencies};
+
+/// The state of a resolution, given to [ProgressObserver::on_progress].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The packages currently decided, including the root package.
+    ///
+    /// This goes down when the solver backtracks.
+    pub decided: u32,
+    /// The packages found so far, as the dependencies of decided versions or in conflicts.
+    ///
+    /// The resolution is done when all the packages still needed are decided, `decided` over
+    /// `known_packages` is a lower bound of the progress.
+    pub known_packages: u32,
+    /// The conflicts so far, each making the solver backtrack.
+    pub conflicts: u32,
+}
+
+/// Receives the progress of [resolve_with_progress].
+pub trait ProgressObserver<DP: DependencyProvider> {
+    /// Called before each decision.
+    ///
+    /// Returning an error stops the resolution with
+    /// [ErrorInShouldCancel](PubGrubError::ErrorInShouldCancel), like
+    /// [should_cancel](DependencyProvider::should_cancel).
+    fn on_progress(&mut self, progress: Progress) -> Result<(), DP::Err>;
+}
+
+impl<DP, F> ProgressObserver<DP> for F
+where
+    DP: DependencyProvider,
+    F: FnMut(Progress) -> Result<(), DP::Err>,
+{
+    fn on_progress(&mut self, progress: Progress) -> Result<(), DP::Err> {
+        self(progress)
+    }
+}
+
+/// Calls the observer at each step of the solver.
+struct Observing<'a, O>(&'a mut O);
+
+impl<DP: DependencyProvider, O: ProgressObserver<DP>> SolverHooks<DP> for Observing<'_, O> {
+    fn on_step(&mut self, solver: &Solver<DP>) -> Result<(), DP::Err> {
+        let state = &solver.state;
+        self.0.on_progress(Progress {
+            decided: state.partial_solution.current_decision_level().0,
+            known_packages: state.package_store.len() as u32,
+            conflicts: state.stats.conflicts,
+        })
+    }
+}
+
+/// Same as [resolve](crate::resolve), calling `observer` with the progress of the resolution
+/// before each decision.
+///
+/// ```
+/// # use std::convert::Infallible;
+/// # use pubgrub::{resolve_with_progress, OfflineDependencyProvider, Progress, Ranges};
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("menu", Ranges::full())]);
+/// dependency_provider.add_dependencies("menu", 1u32, []);
+///
+/// let mut last = None;
+/// resolve_with_progress(&dependency_provider, "root", 1u32, &mut |progress: Progress| {
+///     last = Some(progress);
+///     Ok::<_, Infallible>(())
+/// })
+/// .unwrap();
+/// // The last call finds nothing left to decide.
+/// assert_eq!(
+///     last,
+///     Some(Progress {
+///         decided: 2,
+///         known_packages: 2,
+///         conflicts: 0
+///     })
+/// );
+/// ```
+pub fn resolve_with_progress<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    observer: &mut impl ProgressObserver<DP>,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let mut solver = Solver::new(package, version.into());
+    solver.solve_with_hooks(dependency_provider, &mut Observing(observer))
+}
@@ -1,9 +1,11 @@
-use std::cmp::Reverse;
-use std::collections::BTreeMap;
-use std::convert::Infallible;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::convert::Infallible;
 
 use crate::{
-    Dependencies, DependencyConstraints, DependencyProvider, Map, Package,
+    Dependencies, DependencyConstraints, DependencyProvider, ListVersions, Map, Package,
     PackageResolutionStatistics, VersionSet,
 };
 
@@ -67,6 +69,53 @@ impl<P: Package, VS: VersionSet> OfflineDependencyProvider<P, VS> {
         self.dependencies.get(package).map(|k| k.keys())
     }
 
+    /// Parses a provider from JSON.
+    ///
+    /// The JSON object maps each package to an object mapping its versions to their
+    /// dependencies, themselves an object mapping each dependency to its allowed versions. The
+    /// allowed versions are a list of intervals, `[1, 3]` being `1 <= v < 3` and `[1, null]` being
+    /// `1 <= v`. The intervals can also be written with explicit bounds, like
+    /// `[{"Excluded": 1}, "Unbounded"]` for `1 < v`, which is the format of
+    /// [to_json_string](Self::to_json_string).
+    ///
+    /// ```
+    /// # use pubgrub::{resolve, OfflineDependencyProvider, Ranges};
+    /// let dependency_provider = OfflineDependencyProvider::<String, Ranges<u32>>::from_json_str(
+    ///     r#"{
+    ///         "root": { "1": { "menu": [[1, null]], "icons": [[1, 2]] } },
+    ///         "menu": { "1": {}, "2": { "icons": [[2, 3]] } },
+    ///         "icons": { "1": {}, "2": {} }
+    ///     }"#,
+    /// )
+    /// .unwrap();
+    /// let solution = resolve(&dependency_provider, "root".to_string(), 1u32).unwrap();
+    /// assert_eq!(solution["menu"], 1);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn from_json_str<'de>(json: &'de str) -> Result<Self, serde_json::Error>
+    where
+        P: serde::Deserialize<'de>,
+        VS: serde::Deserialize<'de>,
+        VS::V: serde::Deserialize<'de>,
+    {
+        serde_json::from_str(json)
+    }
+
+    /// Serializes the provider to JSON, in the format read by
+    /// [from_json_str](Self::from_json_str).
+    ///
+    /// Fails if packages or versions don't serialize to strings or numbers, since they are keys
+    /// of JSON objects.
+    #[cfg(feature = "json")]
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error>
+    where
+        P: serde::Serialize,
+        VS: serde::Serialize,
+        VS::V: serde::Serialize,
+    {
+        serde_json::to_string_pretty(self)
+    }
+
     /// Lists dependencies of a given package and version.
     /// Returns [None] if no information is available regarding that package and version pair.
     fn dependencies(&self, package: &P, version: &VS::V) -> Option<DependencyConstraints<P, VS>> {
@@ -128,4 +177,14 @@ impl<P: Package, VS: VersionSet> DependencyProvider for OfflineDependencyProvide
             Some(dependencies) => Dependencies::Available(dependencies),
         })
     }
+
+    fn known_versions(&self, package: &P) -> Option<Vec<VS::V>> {
+        Some(self.versions(package)?.cloned().collect())
+    }
+}
+
+impl<P: Package, VS: VersionSet> ListVersions for OfflineDependencyProvider<P, VS> {
+    fn list_versions(&self, package: &P) -> Result<impl Iterator<Item = VS::V> + '_, Infallible> {
+        Ok(self.versions(package).into_iter().flatten().cloned())
+    }
 }
@@ -0,0 +1,517 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Virtual packages: dependencies on a capability that several packages provide.
+//!
+//! Linux distributions let packages depend on a capability, like `mail-transport-agent`, which
+//! any of `postfix`, `exim` or `sendmail` provides, possibly at a version: `Provides:
+//! libfoo (= 1.2)`. The solver has to choose one of the providers, which a dependency on a single
+//! package can't express.
+//!
+//! A [ProvidesDependencyProvider] declares the providers of each capability, and the
+//! dependencies of the package versions on capabilities. [resolve_with_provides] models each
+//! capability as a virtual package,
+//! [`ProvidesPackage::Capability(c)`](ProvidesPackage::Capability), whose versions are its
+//! providers: depending on `c` for capability versions in `r` means depending on the providers
+//! providing a version in `r`, and the provider `p` at version `v` depends on `p` at exactly `v`.
+//! The solver backtracks over the providers like over the versions of a package, and reports
+//! read "root ==1 depends on mail-transport-agent (postfix 3.7 | exim 4)".
+//!
+//! ```
+//! # use std::convert::Infallible;
+//! # use pubgrub::{
+//! #     resolve_with_provides, Dependencies, DependencyProvider, OfflineDependencyProvider,
+//! #     PackageResolutionStatistics, ProvidesDependencyProvider, Ranges,
+//! # };
+//! #
+//! struct Distribution(OfflineDependencyProvider<&'static str, Ranges<u32>>);
+//!
+//! impl DependencyProvider for Distribution {
+//!     // Forward to the offline provider.
+//! #   type P = &'static str;
+//! #   type V = u32;
+//! #   type VS = Ranges<u32>;
+//! #   type M = String;
+//! #   type Priority = <OfflineDependencyProvider<&'static str, Ranges<u32>> as DependencyProvider>::Priority;
+//! #   type Err = Infallible;
+//! #
+//! #   fn prioritize(&self, p: &&'static str, r: &Ranges<u32>, s: &PackageResolutionStatistics) -> Self::Priority {
+//! #       self.0.prioritize(p, r, s)
+//! #   }
+//! #
+//! #   fn choose_version(&self, p: &&'static str, r: &Ranges<u32>) -> Result<Option<u32>, Infallible> {
+//! #       self.0.choose_version(p, r)
+//! #   }
+//! #
+//! #   fn get_dependencies(&self, p: &&'static str, v: &u32) -> Result<Dependencies<&'static str, Ranges<u32>, String>, Infallible> {
+//! #       self.0.get_dependencies(p, v)
+//! #   }
+//! }
+//!
+//! impl ProvidesDependencyProvider for Distribution {
+//!     type C = &'static str;
+//!
+//!     fn get_capability_dependencies(&self, package: &&'static str, _version: &u32) -> Result<Vec<(&'static str, Ranges<u32>)>, Infallible> {
+//!         // Only a recent mail transport agent will do.
+//!         Ok(match *package {
+//!             "root" => vec![("mta", Ranges::higher_than(2u32))],
+//!             _ => vec![],
+//!         })
+//!     }
+//!
+//!     fn get_providers(&self, capability: &&'static str) -> Result<Vec<(&'static str, u32, Option<u32>)>, Infallible> {
+//!         Ok(match *capability {
+//!             "mta" => vec![("postfix", 3, Some(1)), ("exim", 4, Some(2))],
+//!             _ => vec![],
+//!         })
+//!     }
+//! }
+//!
+//! let mut dependency_provider = OfflineDependencyProvider::new();
+//! dependency_provider.add_dependencies("root", 1u32, []);
+//! dependency_provider.add_dependencies("postfix", 3u32, []);
+//! dependency_provider.add_dependencies("exim", 4u32, []);
+//!
+//! let solution = resolve_with_provides(&Distribution(dependency_provider), "root", 1u32).unwrap();
+//! assert_eq!(solution.providers["mta"], ("exim", 4));
+//! assert!(!solution.solution.contains_key("postfix"));
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::fmt::{self, Debug, Display};
+
+use crate::delegate::delegate_provider;
+use crate::{
+    resolve, Dependencies, DependencyConstraints, DependencyProvider, Map, Package,
+    PackageResolutionStatistics, PartialSolutionView, PubGrubError, VersionSet,
+};
+
+/// A [DependencyProvider] whose packages can also provide and depend on capabilities.
+pub trait ProvidesDependencyProvider: DependencyProvider {
+    /// How this provider stores the name of the capabilities.
+    type C: Package;
+
+    /// The dependencies of `version` of `package` on capabilities, with the capability versions
+    /// they accept, in addition to its [dependencies](DependencyProvider::get_dependencies).
+    #[allow(clippy::type_complexity)]
+    fn get_capability_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Vec<(Self::C, Self::VS)>, Self::Err>;
+
+    /// The package versions providing `capability`, with the version of the capability they
+    /// provide, in the order they should be tried.
+    ///
+    /// A provider without capability version only satisfies the dependencies accepting any
+    /// version of the capability. The providers of a capability must not change during a
+    /// resolution, they are only retrieved once.
+    #[allow(clippy::type_complexity)]
+    fn get_providers(
+        &self,
+        capability: &Self::C,
+    ) -> Result<Vec<(Self::P, Self::V, Option<Self::V>)>, Self::Err>;
+}
+
+/// A package or a capability, as seen by the solver.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ProvidesPackage<P, C> {
+    /// The package itself.
+    Package(P),
+    /// A capability, whose versions are its providers.
+    Capability(C),
+}
+
+impl<P: Display, C: Display> Display for ProvidesPackage<P, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Package(package) => write!(f, "{}", package),
+            Self::Capability(capability) => write!(f, "{}", capability),
+        }
+    }
+}
+
+/// A version of a package, or the provider chosen for a capability.
+#[derive(Debug, Clone)]
+pub enum ProvidesVersion<P, V> {
+    /// A version of a package.
+    Version(V),
+    /// A provider of a capability, numbered by its position in
+    /// [get_providers](ProvidesDependencyProvider::get_providers), with its package and version.
+    Provider(usize, P, V),
+}
+
+impl<P, V: Ord> ProvidesVersion<P, V> {
+    fn key(&self) -> Result<&V, usize> {
+        match self {
+            Self::Version(version) => Ok(version),
+            Self::Provider(index, _, _) => Err(*index),
+        }
+    }
+}
+
+impl<P, V: Ord> PartialEq for ProvidesVersion<P, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<P, V: Ord> Eq for ProvidesVersion<P, V> {}
+
+impl<P, V: Ord> PartialOrd for ProvidesVersion<P, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<P, V: Ord> Ord for ProvidesVersion<P, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl<P: Display, V: Display> Display for ProvidesVersion<P, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Version(version) => write!(f, "{}", version),
+            Self::Provider(_, package, version) => write!(f, "{} {}", package, version),
+        }
+    }
+}
+
+/// A set of versions of a package, or of providers of a capability.
+///
+/// Both kinds of versions are in the same set, so that sets are closed under complement: the
+/// providers of a package and the versions of a capability are never used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvidesSet<P, VS: VersionSet> {
+    versions: VS,
+    /// The providers in the set, with their package and version for reports.
+    providers: BTreeMap<usize, (P, VS::V)>,
+    /// Whether the set contains the providers not in `providers`, instead of those in it.
+    complement: bool,
+}
+
+impl<P, VS: VersionSet> ProvidesSet<P, VS> {
+    /// The versions of a package in `versions`.
+    pub fn from_versions(versions: VS) -> Self {
+        Self {
+            versions,
+            providers: BTreeMap::new(),
+            complement: false,
+        }
+    }
+
+    /// The package versions in the set.
+    pub fn versions(&self) -> &VS {
+        &self.versions
+    }
+
+    /// Whether the set contains the provider at `index`.
+    fn contains_provider(&self, index: usize) -> bool {
+        self.providers.contains_key(&index) != self.complement
+    }
+}
+
+impl<P: Package, VS: VersionSet> VersionSet for ProvidesSet<P, VS> {
+    type V = ProvidesVersion<P, VS::V>;
+
+    fn empty() -> Self {
+        Self::from_versions(VS::empty())
+    }
+
+    fn singleton(v: Self::V) -> Self {
+        match v {
+            ProvidesVersion::Version(version) => Self::from_versions(VS::singleton(version)),
+            ProvidesVersion::Provider(index, package, version) => Self {
+                versions: VS::empty(),
+                providers: BTreeMap::from([(index, (package, version))]),
+                complement: false,
+            },
+        }
+    }
+
+    fn complement(&self) -> Self {
+        Self {
+            versions: self.versions.complement(),
+            providers: self.providers.clone(),
+            complement: !self.complement,
+        }
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        // The providers of `keep` that are in `filter`, or not in it.
+        let filtered = |keep: &Self, filter: &Self, contained: bool| {
+            keep.providers
+                .iter()
+                .filter(|(index, _)| filter.providers.contains_key(*index) == contained)
+                .map(|(index, provider)| (*index, provider.clone()))
+                .collect()
+        };
+        let (providers, complement) = match (self.complement, other.complement) {
+            (false, false) => (filtered(self, other, true), false),
+            (false, true) => (filtered(self, other, false), false),
+            (true, false) => (filtered(other, self, false), false),
+            (true, true) => {
+                let mut providers = self.providers.clone();
+                providers.extend(other.providers.clone());
+                (providers, true)
+            }
+        };
+        Self {
+            versions: self.versions.intersection(&other.versions),
+            providers,
+            complement,
+        }
+    }
+
+    fn contains(&self, v: &Self::V) -> bool {
+        match v {
+            ProvidesVersion::Version(version) => self.versions.contains(version),
+            ProvidesVersion::Provider(index, _, _) => self.contains_provider(*index),
+        }
+    }
+
+    fn full() -> Self {
+        Self {
+            versions: VS::full(),
+            providers: BTreeMap::new(),
+            complement: true,
+        }
+    }
+}
+
+impl<P: Display, VS: VersionSet> Display for ProvidesSet<P, VS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Only one of the parts is meaningful, depending on whether the set is for a package or
+        // for a capability.
+        if self.providers.is_empty() {
+            return write!(f, "{}", self.versions);
+        }
+        if self.complement {
+            write!(f, "not ")?;
+        }
+        write!(f, "(")?;
+        for (i, (package, version)) in self.providers.values().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            write!(f, "{} {}", package, version)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// A [DependencyProvider] resolving the capabilities of a [ProvidesDependencyProvider] as
+/// virtual packages.
+///
+/// Capabilities are decided after the packages, preferring a provider already selected, then the
+/// first provider allowed.
+pub struct WithProvides<'a, DP: ProvidesDependencyProvider> {
+    provider: &'a DP,
+    #[allow(clippy::type_complexity)]
+    providers: RefCell<Map<DP::C, Vec<(DP::P, DP::V, Option<DP::V>)>>>,
+}
+
+impl<'a, DP: ProvidesDependencyProvider> WithProvides<'a, DP> {
+    /// Resolve the capabilities of `provider`.
+    pub fn new(provider: &'a DP) -> Self {
+        Self {
+            provider,
+            providers: RefCell::new(Map::default()),
+        }
+    }
+
+    /// The providers of a capability, retrieved once.
+    #[allow(clippy::type_complexity)]
+    fn providers(&self, capability: &DP::C) -> Result<Vec<(DP::P, DP::V, Option<DP::V>)>, DP::Err> {
+        if let Some(providers) = self.providers.borrow().get(capability) {
+            return Ok(providers.clone());
+        }
+        let providers = self.provider.get_providers(capability)?;
+        self.providers
+            .borrow_mut()
+            .insert(capability.clone(), providers.clone());
+        Ok(providers)
+    }
+
+    /// The first provider of `capability` in `range` for which `prefer` holds, if any.
+    #[allow(clippy::type_complexity)]
+    fn choose_provider(
+        &self,
+        capability: &DP::C,
+        range: &ProvidesSet<DP::P, DP::VS>,
+        prefer: impl Fn(&DP::P, &DP::V) -> bool,
+    ) -> Result<Option<ProvidesVersion<DP::P, DP::V>>, DP::Err> {
+        let providers = self.providers(capability)?;
+        let allowed = || {
+            providers
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| range.contains_provider(*index))
+        };
+        let chosen = allowed()
+            .find(|(_, (package, version, _))| prefer(package, version))
+            .or_else(|| allowed().next());
+        Ok(chosen.map(|(index, (package, version, _))| {
+            ProvidesVersion::Provider(index, package.clone(), version.clone())
+        }))
+    }
+}
+
+impl<DP: ProvidesDependencyProvider> DependencyProvider for WithProvides<'_, DP> {
+    type P = ProvidesPackage<DP::P, DP::C>;
+    type V = ProvidesVersion<DP::P, DP::V>;
+    type VS = ProvidesSet<DP::P, DP::VS>;
+    type M = DP::M;
+    /// `None` for the capabilities, which are decided last.
+    type Priority = Option<DP::Priority>;
+    type Err = DP::Err;
+
+    delegate_provider!(provider => reprioritize_on_conflict, should_cancel);
+
+    fn prioritize(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        package_conflicts_counts: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        match package {
+            ProvidesPackage::Package(p) => Some(self.provider.prioritize(
+                p,
+                &range.versions,
+                package_conflicts_counts,
+            )),
+            ProvidesPackage::Capability(_) => None,
+        }
+    }
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        match package {
+            ProvidesPackage::Package(p) => Ok(self
+                .provider
+                .choose_version(p, &range.versions)?
+                .map(ProvidesVersion::Version)),
+            ProvidesPackage::Capability(c) => self.choose_provider(c, range, |_, _| false),
+        }
+    }
+
+    fn choose_version_in(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        solution: &dyn PartialSolutionView<Self::P, Self::VS>,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        let ProvidesPackage::Capability(c) = package else {
+            return self.choose_version(package, range);
+        };
+        self.choose_provider(c, range, |package, version| {
+            let decided = solution.decision(&ProvidesPackage::Package(package.clone()));
+            decided.is_some_and(|decided| decided.key() == Ok(version))
+        })
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        let mut constraints = DependencyConstraints::default();
+        match (package, version) {
+            (ProvidesPackage::Package(p), ProvidesVersion::Version(v)) => {
+                let dependencies = match self.provider.get_dependencies(p, v)? {
+                    Dependencies::Available(dependencies) => dependencies,
+                    Dependencies::Unavailable(reason) => {
+                        return Ok(Dependencies::Unavailable(reason));
+                    }
+                };
+                for (dependency, range) in dependencies {
+                    constraints.insert(
+                        ProvidesPackage::Package(dependency),
+                        ProvidesSet::from_versions(range),
+                    );
+                }
+                for (capability, range) in self.provider.get_capability_dependencies(p, v)? {
+                    let providers = self
+                        .providers(&capability)?
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(_, (_, _, provided))| match provided {
+                            Some(provided) => range.contains(provided),
+                            None => range == DP::VS::full(),
+                        })
+                        .map(|(index, (package, version, _))| (index, (package, version)))
+                        .collect();
+                    let providers = ProvidesSet {
+                        versions: DP::VS::empty(),
+                        providers,
+                        complement: false,
+                    };
+                    constraints
+                        .entry(ProvidesPackage::Capability(capability))
+                        .and_modify(|existing: &mut ProvidesSet<_, _>| {
+                            *existing = existing.intersection(&providers)
+                        })
+                        .or_insert(providers);
+                }
+            }
+            (ProvidesPackage::Capability(_), ProvidesVersion::Provider(_, p, v)) => {
+                constraints.insert(
+                    ProvidesPackage::Package(p.clone()),
+                    ProvidesSet::from_versions(DP::VS::singleton(v.clone())),
+                );
+            }
+            // The solver only picks versions of packages and providers of capabilities.
+            _ => {}
+        }
+        Ok(Dependencies::Available(constraints))
+    }
+}
+
+/// A solution found by [resolve_with_provides].
+#[derive(Debug, Clone)]
+pub struct ProvidesSolution<P: Package, V, C: Package> {
+    /// The selected packages and versions, including the providers.
+    pub solution: Map<P, V>,
+    /// The provider chosen for each required capability.
+    pub providers: Map<C, (P, V)>,
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// choosing a provider for each capability it depends on.
+#[allow(clippy::type_complexity)]
+pub fn resolve_with_provides<DP: ProvidesDependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<ProvidesSolution<DP::P, DP::V, DP::C>, PubGrubError<WithProvides<'_, DP>>> {
+    let provider = WithProvides::new(dependency_provider);
+    let solution = resolve(
+        &provider,
+        ProvidesPackage::Package(package),
+        ProvidesVersion::Version(version.into()),
+    )?;
+    let mut selected = Map::default();
+    let mut providers = Map::default();
+    for (package, version) in solution {
+        match (package, version) {
+            (ProvidesPackage::Package(p), ProvidesVersion::Version(v)) => {
+                selected.insert(p, v);
+            }
+            (ProvidesPackage::Capability(c), ProvidesVersion::Provider(_, p, v)) => {
+                providers.insert(c, (p, v));
+            }
+            _ => {}
+        }
+    }
+    Ok(ProvidesSolution {
+        solution: selected,
+        providers,
+    })
+}
@@ -0,0 +1,276 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Recommended dependencies: dependencies that are dropped instead of failing the resolution.
+//!
+//! Debian packages distinguish `Depends` from `Recommends`: a recommended package is installed
+//! along with the package recommending it unless it can't be, in which case the installation
+//! goes on without it. A [RecommendsDependencyProvider] declares such dependencies next to the
+//! required ones. [resolve_with_recommendations] first resolves the required dependencies, then
+//! tries the recommendations of the selected versions one at a time, keeping each one with which
+//! a solution still exists.
+
+use alloc::vec::Vec;
+
+use crate::delegate::delegate_provider;
+use crate::{resolve, Dependencies, DependencyProvider, Map, Package, PubGrubError, VersionSet};
+
+/// A [DependencyProvider] whose versions can also recommend dependencies.
+pub trait RecommendsDependencyProvider: DependencyProvider {
+    /// The recommended dependencies of `version` of `package`, included when possible.
+    ///
+    /// They are tried in this order, so an earlier recommendation wins over a later one
+    /// conflicting with it.
+    #[allow(clippy::type_complexity)]
+    fn get_recommendations(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Vec<(Self::P, Self::VS)>, Self::Err>;
+}
+
+/// A dependency recommended by a version of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recommendation<P: Package, VS: VersionSet> {
+    /// The package recommending the dependency.
+    pub package: P,
+    /// The version of [package](Self::package) recommending the dependency.
+    pub version: VS::V,
+    /// The recommended package.
+    pub dependency: P,
+    /// The recommended versions of [dependency](Self::dependency).
+    pub range: VS,
+}
+
+/// A [DependencyProvider] adding the recommendations kept so far to the required dependencies.
+struct WithRecommendations<'a, DP: RecommendsDependencyProvider> {
+    provider: &'a DP,
+    kept: &'a [Recommendation<DP::P, DP::VS>],
+}
+
+impl<DP: RecommendsDependencyProvider> DependencyProvider for WithRecommendations<'_, DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, choose_version,
+        choose_version_in, should_cancel, prefetch, on_conflict, on_backtrack, known_versions,
+    );
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        let mut dependencies = self.provider.get_dependencies(package, version)?;
+        if let Dependencies::Available(constraints) = &mut dependencies {
+            for recommendation in self.kept {
+                if recommendation.package != *package || recommendation.version != *version {
+                    continue;
+                }
+                constraints
+                    .entry(recommendation.dependency.clone())
+                    .and_modify(|range| *range = range.intersection(&recommendation.range))
+                    .or_insert_with(|| recommendation.range.clone());
+            }
+        }
+        Ok(dependencies)
+    }
+
+    fn versions_sharing_dependencies(&self, package: &Self::P, version: &Self::V) -> Self::VS {
+        // A recommendation kept for a version only applies to it.
+        if self
+            .kept
+            .iter()
+            .any(|recommendation| recommendation.package == *package)
+        {
+            return Self::VS::singleton(version.clone());
+        }
+        self.provider
+            .versions_sharing_dependencies(package, version)
+    }
+}
+
+/// A solution found by [resolve_with_recommendations], with the recommendations it follows.
+#[derive(Debug, Clone)]
+pub struct RecommendedSolution<P: Package, VS: VersionSet> {
+    /// The selected packages and versions.
+    pub solution: Map<P, VS::V>,
+    /// Recommendations of the selected versions satisfied by the solution.
+    pub honored: Vec<Recommendation<P, VS>>,
+    /// Recommendations of the selected versions dropped because no solution satisfies them.
+    pub dropped: Vec<Recommendation<P, VS>>,
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// including the recommended dependencies of the selected versions whenever possible.
+///
+/// Recommendations are tried in the order of the selected packages, then in the order of
+/// [get_recommendations](RecommendsDependencyProvider::get_recommendations), each one on top of
+/// those kept before it, so an earlier recommendation wins over a later one conflicting with it.
+/// Only a failure of the required dependencies is an error.
+///
+/// ```
+/// # use std::convert::Infallible;
+/// # use pubgrub::{
+/// #     resolve_with_recommendations, Dependencies, DependencyProvider,
+/// #     OfflineDependencyProvider, PackageResolutionStatistics, Ranges, RecommendsDependencyProvider,
+/// # };
+/// #
+/// struct Apt(OfflineDependencyProvider<&'static str, Ranges<u32>>);
+///
+/// impl DependencyProvider for Apt {
+///     // Forward to the offline provider.
+/// #   type P = &'static str;
+/// #   type V = u32;
+/// #   type VS = Ranges<u32>;
+/// #   type M = String;
+/// #   type Priority = <OfflineDependencyProvider<&'static str, Ranges<u32>> as DependencyProvider>::Priority;
+/// #   type Err = Infallible;
+/// #
+/// #   fn prioritize(&self, p: &&'static str, r: &Ranges<u32>, s: &PackageResolutionStatistics) -> Self::Priority {
+/// #       self.0.prioritize(p, r, s)
+/// #   }
+/// #
+/// #   fn choose_version(&self, p: &&'static str, r: &Ranges<u32>) -> Result<Option<u32>, Infallible> {
+/// #       self.0.choose_version(p, r)
+/// #   }
+/// #
+/// #   fn get_dependencies(&self, p: &&'static str, v: &u32) -> Result<Dependencies<&'static str, Ranges<u32>, String>, Infallible> {
+/// #       self.0.get_dependencies(p, v)
+/// #   }
+/// }
+///
+/// impl RecommendsDependencyProvider for Apt {
+///     fn get_recommendations(&self, package: &&'static str, _version: &u32) -> Result<Vec<(&'static str, Ranges<u32>)>, Infallible> {
+///         let mut recommendations = Vec::new();
+///         if *package == "vim" {
+///             recommendations.push(("vim-doc", Ranges::full()));
+///             // No such version: dropped.
+///             recommendations.push(("vim-runtime", Ranges::higher_than(10u32)));
+///         }
+///         Ok(recommendations)
+///     }
+/// }
+///
+/// let mut dependency_provider = OfflineDependencyProvider::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("vim", Ranges::full())]);
+/// dependency_provider.add_dependencies("vim", 9u32, [("vim-runtime", Ranges::full())]);
+/// dependency_provider.add_dependencies("vim-runtime", 9u32, []);
+/// dependency_provider.add_dependencies("vim-doc", 9u32, []);
+///
+/// let recommended = resolve_with_recommendations(&Apt(dependency_provider), "root", 1u32).unwrap();
+/// assert_eq!(recommended.solution["vim-doc"], 9);
+/// assert_eq!(recommended.solution["vim-runtime"], 9);
+/// assert_eq!(recommended.honored.len(), 1);
+/// assert_eq!(recommended.dropped[0].dependency, "vim-runtime");
+/// ```
+pub fn resolve_with_recommendations<DP: RecommendsDependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<RecommendedSolution<DP::P, DP::VS>, PubGrubError<DP>>
+where
+    DP::P: Ord,
+{
+    let version = version.into();
+    let mut kept = Vec::new();
+    let mut dropped = Vec::new();
+    let mut pending = Vec::new();
+    let mut solution = resolve(
+        &WithRecommendations {
+            provider: dependency_provider,
+            kept: &kept,
+        },
+        package.clone(),
+        version.clone(),
+    )
+    .map_err(PubGrubError::into_provider)?;
+
+    loop {
+        add_recommendations(
+            dependency_provider,
+            &solution,
+            &kept,
+            &dropped,
+            &mut pending,
+        )?;
+        let Some(recommendation) = pending.pop() else {
+            break;
+        };
+        kept.push(recommendation);
+        let provider = WithRecommendations {
+            provider: dependency_provider,
+            kept: &kept,
+        };
+        let resolved = resolve(&provider, package.clone(), version.clone())
+            .map_err(PubGrubError::into_provider);
+        match resolved {
+            Ok(with_recommendation) => solution = with_recommendation,
+            Err(PubGrubError::NoSolution(_)) => dropped.extend(kept.pop()),
+            Err(err) => return Err(err),
+        }
+    }
+
+    let selected = |recommendation: &Recommendation<DP::P, DP::VS>| {
+        solution.get(&recommendation.package) == Some(&recommendation.version)
+    };
+    kept.retain(selected);
+    dropped.retain(selected);
+    Ok(RecommendedSolution {
+        solution,
+        honored: kept,
+        dropped,
+    })
+}
+
+/// Queue the recommendations of the selected versions not tried yet, the first one found last.
+///
+/// The packages are visited in order, so that the result doesn't depend on the order of the
+/// solution map.
+fn add_recommendations<DP: RecommendsDependencyProvider>(
+    dependency_provider: &DP,
+    solution: &Map<DP::P, DP::V>,
+    kept: &[Recommendation<DP::P, DP::VS>],
+    dropped: &[Recommendation<DP::P, DP::VS>],
+    pending: &mut Vec<Recommendation<DP::P, DP::VS>>,
+) -> Result<(), PubGrubError<DP>>
+where
+    DP::P: Ord,
+{
+    let mut selected: Vec<_> = solution.iter().collect();
+    selected.sort_unstable_by_key(|(package, _)| *package);
+    let mut found = Vec::new();
+    for (package, version) in selected {
+        let recommendations = dependency_provider
+            .get_recommendations(package, version)
+            .map_err(|source| PubGrubError::ErrorRetrievingDependencies {
+                package: package.clone(),
+                version: version.clone(),
+                source,
+                required_by: Vec::new(),
+                decided: Vec::new(),
+            })?;
+        for (dependency, range) in recommendations {
+            let recommendation = Recommendation {
+                package: package.clone(),
+                version: version.clone(),
+                dependency,
+                range,
+            };
+            let tried = kept
+                .iter()
+                .chain(dropped)
+                .chain(pending.iter())
+                .any(|other| *other == recommendation);
+            if !tried {
+                found.push(recommendation);
+            }
+        }
+    }
+    pending.splice(0..0, found.into_iter().rev());
+    Ok(())
+}
@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Record the answers of a provider to replay a resolution offline.
+//!
+//! A resolution failing against a registry may not fail anymore once new versions are
+//! published, or may depend on network errors. Resolving through a [RecordingProvider] keeps the
+//! dependencies of every version the solver looked at in an [OfflineDependencyProvider], which
+//! reproduces the resolution without the registry, in a test for example.
+
+use core::cell::RefCell;
+
+use crate::delegate::delegate_provider;
+use crate::{Dependencies, DependencyProvider, OfflineDependencyProvider};
+
+/// A [DependencyProvider] forwarding to another one, recording the versions it chooses and their
+/// dependencies.
+///
+/// Replaying a resolution with the [snapshot](Self::snapshot) gives the same result as long as
+/// the recorded provider chooses the newest allowed version, like [OfflineDependencyProvider]
+/// does. Versions with [Unavailable](Dependencies::Unavailable) dependencies are not recorded,
+/// so the snapshot doesn't contain them at all.
+pub struct RecordingProvider<'a, DP: DependencyProvider> {
+    provider: &'a DP,
+    snapshot: RefCell<OfflineDependencyProvider<DP::P, DP::VS>>,
+}
+
+impl<'a, DP: DependencyProvider> RecordingProvider<'a, DP> {
+    /// Record the answers of `provider`, starting from an empty snapshot.
+    pub fn new(provider: &'a DP) -> Self {
+        Self {
+            provider,
+            snapshot: RefCell::new(OfflineDependencyProvider::new()),
+        }
+    }
+
+    /// The versions chosen so far, with their dependencies.
+    pub fn snapshot(&self) -> OfflineDependencyProvider<DP::P, DP::VS> {
+        self.snapshot.borrow().clone()
+    }
+
+    /// Stops recording, returning the [snapshot](Self::snapshot).
+    pub fn into_snapshot(self) -> OfflineDependencyProvider<DP::P, DP::VS> {
+        self.snapshot.into_inner()
+    }
+}
+
+impl<DP: DependencyProvider> DependencyProvider for RecordingProvider<'_, DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, choose_version,
+        choose_version_in, should_cancel, prefetch, on_conflict, on_backtrack, known_versions,
+        versions_sharing_dependencies,
+    );
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        let dependencies = self.provider.get_dependencies(package, version)?;
+        if let Dependencies::Available(constraints) = &dependencies {
+            self.snapshot.borrow_mut().add_dependencies(
+                package.clone(),
+                version.clone(),
+                constraints.iter().map(|(p, vs)| (p.clone(), vs.clone())),
+            );
+        }
+        Ok(dependencies)
+    }
+}
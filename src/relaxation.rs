@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Suggest which requirements of the root package to relax when there is no solution.
+//!
+//! A report explains why the requirements can't be satisfied together, but the user usually
+//! wants to know what to change in their manifest. [suggest_relaxations] looks for the direct
+//! dependencies of the root package taking part in the failure, and resolves again with each
+//! of them allowing any version, to find the versions that would make the resolution succeed.
+
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+
+use crate::solver::Solver;
+use crate::{
+    Dependencies, DependencyProvider, DerivationTree, External, Package, PubGrubError, VersionSet,
+};
+
+/// A requirement of the root package that prevents a solution, found by
+/// [suggest_relaxations].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relaxation<P: Package, VS: VersionSet> {
+    /// The dependency of the root package.
+    pub package: P,
+    /// The versions of [package](Self::package) currently required.
+    pub requirement: VS,
+    /// The version selected by a resolution allowing any version of [package](Self::package).
+    pub version: VS::V,
+    /// The requirement also allowing [version](Self::version), with which a solution exists.
+    pub relaxed: VS,
+}
+
+impl<P: Package, VS: VersionSet> Display for Relaxation<P, VS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "requiring {} {} instead of {} would allow a solution with {} {}",
+            self.package, self.relaxed, self.requirement, self.package, self.version
+        )
+    }
+}
+
+/// The requirements of `version` of `package` that can be relaxed for a solution to exist, given
+/// the `derivation_tree` of its failed resolution.
+///
+/// Each direct dependency taking part in the failure, in the order it first appears in the tree,
+/// is tried on its own: the resolution is run again with that dependency allowing any version.
+/// If it succeeds, the dependency is suggested with its requirement extended to the selected
+/// version. Failures needing several requirements to be relaxed at once are not suggested, and
+/// an empty list is returned then.
+///
+/// ```
+/// # use pubgrub::{resolve, suggest_relaxations, OfflineDependencyProvider, PubGrubError, Ranges};
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies(
+///     "root",
+///     1u32,
+///     [("foo", Ranges::singleton(1u32)), ("bar", Ranges::full())],
+/// );
+/// dependency_provider.add_dependencies("foo", 1u32, []);
+/// dependency_provider.add_dependencies("foo", 2u32, []);
+/// dependency_provider.add_dependencies("bar", 1u32, [("foo", Ranges::singleton(2u32))]);
+///
+/// let Err(PubGrubError::NoSolution(tree)) = resolve(&dependency_provider, "root", 1u32)
+/// else {
+///     unreachable!()
+/// };
+/// let relaxations = suggest_relaxations(&dependency_provider, "root", 1u32, &tree).unwrap();
+/// assert_eq!(
+///     relaxations[0].to_string(),
+///     "requiring foo ==1 | ==2 instead of ==1 would allow a solution with foo 2"
+/// );
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn suggest_relaxations<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    derivation_tree: &DerivationTree<DP::P, DP::VS, DP::M>,
+) -> Result<Vec<Relaxation<DP::P, DP::VS>>, PubGrubError<DP>> {
+    let version = version.into();
+    let dependencies = dependency_provider
+        .get_dependencies(&package, &version)
+        .map_err(|err| PubGrubError::ErrorRetrievingDependencies {
+            package: package.clone(),
+            version: version.clone(),
+            source: err,
+            required_by: Vec::new(),
+            decided: Vec::new(),
+        })?;
+    let Dependencies::Available(dependencies) = dependencies else {
+        return Ok(Vec::new());
+    };
+
+    let mut culprits = Vec::new();
+    root_dependencies_in(derivation_tree, &package, &mut culprits);
+
+    let mut relaxations = Vec::new();
+    for culprit in culprits {
+        let Some(requirement) = dependencies.get(culprit) else {
+            continue;
+        };
+        let mut relaxed_dependencies = dependencies.clone();
+        relaxed_dependencies.insert(culprit.clone(), DP::VS::full());
+        let mut solver = Solver::new(package.clone(), version.clone());
+        let result = solver
+            .add_root_dependencies(relaxed_dependencies)
+            .map_err(PubGrubError::NoSolution)
+            .and_then(|()| solver.solve(dependency_provider));
+        match result {
+            Ok(solution) => {
+                let selected = solution[culprit].clone();
+                relaxations.push(Relaxation {
+                    package: culprit.clone(),
+                    requirement: requirement.clone(),
+                    relaxed: requirement.union(&DP::VS::singleton(selected.clone())),
+                    version: selected,
+                });
+            }
+            Err(PubGrubError::NoSolution(_)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(relaxations)
+}
+
+/// The dependencies of `root` in the tree, in the order they first appear.
+fn root_dependencies_in<'a, P: Package, VS: VersionSet, M: Eq + Clone + fmt::Debug + Display>(
+    tree: &'a DerivationTree<P, VS, M>,
+    root: &P,
+    dependencies: &mut Vec<&'a P>,
+) {
+    match tree {
+        DerivationTree::External(External::FromDependencyOf(p, _, dependency, _)) => {
+            if p == root && !dependencies.contains(&dependency) {
+                dependencies.push(dependency);
+            }
+        }
+        DerivationTree::External(_) => {}
+        DerivationTree::Derived(derived) => {
+            root_dependencies_in(&derived.cause1, root, dependencies);
+            root_dependencies_in(&derived.cause2, root, dependencies);
+        }
+    }
+}
@@ -3,11 +3,17 @@
 //! Build a report as clear as possible as to why
 //! dependency solving failed.
 
-use std::fmt::{self, Debug, Display};
-use std::ops::Deref;
-use std::sync::Arc;
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt::{self, Debug, Display};
+use core::ops::Deref;
 
-use crate::{Map, Package, Set, Term, VersionSet};
+use crate::{Map, Package, Ranges, Set, Term, VersionSet};
 
 /// Reporter trait.
 pub trait Reporter<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
@@ -28,6 +34,14 @@ pub trait Reporter<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display>
 
 /// Derivation tree resulting in the impossibility to solve the dependencies of our root package.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "P: serde::Serialize, VS: serde::Serialize, VS::V: serde::Serialize, M: serde::Serialize",
+        deserialize = "P: serde::Deserialize<'de>, VS: serde::Deserialize<'de>, VS::V: serde::Deserialize<'de>, M: serde::Deserialize<'de>"
+    ))
+)]
 pub enum DerivationTree<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
     /// External incompatibility.
     External(External<P, VS, M>),
@@ -35,8 +49,120 @@ pub enum DerivationTree<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Disp
     Derived(Derived<P, VS, M>),
 }
 
+/// The state of [DerivationTree::merge_duplicate_derived], with the trees identified by address.
+struct Dedup<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
+    externals: Vec<Arc<DerivationTree<P, VS, M>>>,
+    /// The shared derived incompatibilities, by the addresses of their shared causes.
+    #[allow(clippy::type_complexity)]
+    derived: Map<
+        (
+            *const DerivationTree<P, VS, M>,
+            *const DerivationTree<P, VS, M>,
+        ),
+        Vec<Arc<DerivationTree<P, VS, M>>>,
+    >,
+    /// The shared copy of each derived incompatibility of the original tree.
+    #[allow(clippy::type_complexity)]
+    rebuilt: Map<*const DerivationTree<P, VS, M>, Arc<DerivationTree<P, VS, M>>>,
+    /// How many times each shared derived incompatibility is a cause.
+    uses: Map<*const DerivationTree<P, VS, M>, usize>,
+    /// The smallest [shared_id](Derived::shared_id) not in the original tree.
+    next_id: usize,
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Dedup<P, VS, M> {
+    /// A copy of `tree` with one copy for each distinct subtree.
+    fn share(&mut self, tree: &Arc<DerivationTree<P, VS, M>>) -> Arc<DerivationTree<P, VS, M>> {
+        let derived = match tree.as_ref() {
+            DerivationTree::External(external) => {
+                let existing = self.externals.iter().find(
+                    |shared| matches!(shared.as_ref(), DerivationTree::External(e) if e == external),
+                );
+                if let Some(shared) = existing {
+                    return shared.clone();
+                }
+                self.externals.push(tree.clone());
+                return tree.clone();
+            }
+            DerivationTree::Derived(derived) => derived,
+        };
+        let shared = match self.rebuilt.get(&Arc::as_ptr(tree)) {
+            Some(shared) => shared.clone(),
+            None => {
+                if let Some(id) = derived.shared_id {
+                    self.next_id = self.next_id.max(id + 1);
+                }
+                let cause1 = self.share(&derived.cause1);
+                let cause2 = self.share(&derived.cause2);
+                let identical = self
+                    .derived
+                    .entry((Arc::as_ptr(&cause1), Arc::as_ptr(&cause2)))
+                    .or_default();
+                let existing = identical.iter().find(|shared| {
+                    matches!(shared.as_ref(), DerivationTree::Derived(d) if d.terms == derived.terms)
+                });
+                let shared = match existing {
+                    Some(shared) => shared.clone(),
+                    None => {
+                        let shared = Arc::new(DerivationTree::Derived(Derived {
+                            terms: derived.terms.clone(),
+                            shared_id: derived.shared_id,
+                            cause1,
+                            cause2,
+                        }));
+                        identical.push(shared.clone());
+                        shared
+                    }
+                };
+                self.rebuilt.insert(Arc::as_ptr(tree), shared.clone());
+                shared
+            }
+        };
+        *self.uses.entry(Arc::as_ptr(&shared)).or_default() += 1;
+        shared
+    }
+
+    /// A copy of the shared `tree` with a [shared_id](Derived::shared_id) for each derived
+    /// incompatibility used several times.
+    #[allow(clippy::type_complexity)]
+    fn number(
+        &mut self,
+        tree: &Arc<DerivationTree<P, VS, M>>,
+        numbered: &mut Map<*const DerivationTree<P, VS, M>, Arc<DerivationTree<P, VS, M>>>,
+    ) -> Arc<DerivationTree<P, VS, M>> {
+        let DerivationTree::Derived(derived) = tree.as_ref() else {
+            return tree.clone();
+        };
+        if let Some(copy) = numbered.get(&Arc::as_ptr(tree)) {
+            return copy.clone();
+        }
+        let shared_id = derived.shared_id.or_else(|| {
+            (self.uses[&Arc::as_ptr(tree)] > 1).then(|| {
+                self.next_id += 1;
+                self.next_id - 1
+            })
+        });
+        let copy = Arc::new(DerivationTree::Derived(Derived {
+            terms: derived.terms.clone(),
+            shared_id,
+            cause1: self.number(&derived.cause1, numbered),
+            cause2: self.number(&derived.cause2, numbered),
+        }));
+        numbered.insert(Arc::as_ptr(tree), copy.clone());
+        copy
+    }
+}
+
 /// Incompatibility that is not derived from other incompatibilities.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "P: serde::Serialize, VS: serde::Serialize, VS::V: serde::Serialize, M: serde::Serialize",
+        deserialize = "P: serde::Deserialize<'de>, VS: serde::Deserialize<'de>, VS::V: serde::Deserialize<'de>, M: serde::Deserialize<'de>"
+    ))
+)]
 pub enum External<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
     /// Initial incompatibility aiming at picking the root package for the first decision.
     NotRoot(P, VS::V),
@@ -46,10 +172,82 @@ pub enum External<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
     FromDependencyOf(P, VS, P, VS),
     /// The package is unusable for reasons outside pubgrub.
     Custom(P, VS, M),
+    /// The versions are denied by a policy of the caller, see
+    /// [resolve_with_policies](crate::resolve_with_policies).
+    Denied(P, VS, M),
+    /// The versions of two packages can't be selected together, for a reason declared by the
+    /// caller, see [resolve_with_conflicts](crate::resolve_with_conflicts).
+    Conflict(P, VS, P, VS, M),
+}
+
+/// The kind of an incompatibility of a [DerivationTree], without its fields.
+///
+/// Error messages can match on it exhaustively instead of parsing the report: a new kind is a
+/// breaking change.
+///
+/// ```
+/// # use pubgrub::{resolve, IncompatibilityKind, OfflineDependencyProvider, PubGrubError, Ranges};
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("menu", Ranges::singleton(2u32))]);
+///
+/// let Err(PubGrubError::NoSolution(tree)) = resolve(&dependency_provider, "root", 1u32) else {
+///     unreachable!()
+/// };
+/// let hint = match tree.kind() {
+///     IncompatibilityKind::NoVersions => "check the spelling and the registry",
+///     IncompatibilityKind::Unavailable => "check the network",
+///     IncompatibilityKind::Derived => "relax the requirements",
+///     IncompatibilityKind::NotRoot
+///     | IncompatibilityKind::Dependency
+///     | IncompatibilityKind::Denied
+///     | IncompatibilityKind::Conflict => "",
+/// };
+/// assert_eq!(hint, "relax the requirements");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IncompatibilityKind {
+    /// [External::NotRoot].
+    NotRoot,
+    /// [External::NoVersions]: there are no versions in the set.
+    NoVersions,
+    /// [External::FromDependencyOf]: a dependency of a version.
+    Dependency,
+    /// [External::Custom]: versions unusable for a reason of the provider or the caller, such as
+    /// [unavailable dependencies](crate::Dependencies::Unavailable) or a
+    /// [constraint](crate::State::add_constraint).
+    Unavailable,
+    /// [External::Denied].
+    Denied,
+    /// [External::Conflict].
+    Conflict,
+    /// [DerivationTree::Derived]: derived from two other incompatibilities.
+    Derived,
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> External<P, VS, M> {
+    /// The kind of the incompatibility.
+    pub fn kind(&self) -> IncompatibilityKind {
+        match self {
+            Self::NotRoot(..) => IncompatibilityKind::NotRoot,
+            Self::NoVersions(..) => IncompatibilityKind::NoVersions,
+            Self::FromDependencyOf(..) => IncompatibilityKind::Dependency,
+            Self::Custom(..) => IncompatibilityKind::Unavailable,
+            Self::Denied(..) => IncompatibilityKind::Denied,
+            Self::Conflict(..) => IncompatibilityKind::Conflict,
+        }
+    }
 }
 
 /// Incompatibility derived from two others.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "P: serde::Serialize, VS: serde::Serialize, VS::V: serde::Serialize, M: serde::Serialize",
+        deserialize = "P: serde::Deserialize<'de>, VS: serde::Deserialize<'de>, VS::V: serde::Deserialize<'de>, M: serde::Deserialize<'de>"
+    ))
+)]
 pub struct Derived<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
     /// Terms of the incompatibility.
     pub terms: Map<P, Term<VS>>,
@@ -65,18 +263,27 @@ pub struct Derived<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display>
 }
 
 impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> DerivationTree<P, VS, M> {
+    /// The kind of the incompatibility at the root of the tree, see [IncompatibilityKind].
+    pub fn kind(&self) -> IncompatibilityKind {
+        match self {
+            Self::External(external) => external.kind(),
+            Self::Derived(_) => IncompatibilityKind::Derived,
+        }
+    }
+
     /// Get all packages referred to in the derivation tree.
     pub fn packages(&self) -> Set<&P> {
         let mut packages = Set::default();
         match self {
             Self::External(external) => match external {
-                External::FromDependencyOf(p, _, p2, _) => {
+                External::FromDependencyOf(p, _, p2, _) | External::Conflict(p, _, p2, _, _) => {
                     packages.insert(p);
                     packages.insert(p2);
                 }
                 External::NoVersions(p, _)
                 | External::NotRoot(p, _)
-                | External::Custom(p, _, _) => {
+                | External::Custom(p, _, _)
+                | External::Denied(p, _, _) => {
                     packages.insert(p);
                 }
             },
@@ -130,6 +337,132 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> DerivationTree
         }
     }
 
+    /// Hide the packages for which `irrelevant` returns true, like the virtual packages a
+    /// package manager adds for extras or platforms, where the tree allows it.
+    ///
+    /// A derived incompatibility that doesn't mention such packages, but whose derivation does,
+    /// is replaced with an external dependency when it has the form of one: `foo` depending on
+    /// `foo[extra]` which depends on `bar`, is reported as `foo` depending on `bar`. Prune after
+    /// [collapse_no_versions](Self::collapse_no_versions), which removes the versions of
+    /// `foo[extra]` that don't exist from the derivation.
+    pub fn prune(&mut self, irrelevant: &impl Fn(&P) -> bool) {
+        let Self::Derived(derived) = self else {
+            return;
+        };
+        Arc::make_mut(&mut derived.cause1).prune(irrelevant);
+        Arc::make_mut(&mut derived.cause2).prune(irrelevant);
+        if derived.terms.keys().any(irrelevant) {
+            return;
+        }
+        let mentions_irrelevant = |cause: &Self| cause.packages().into_iter().any(irrelevant);
+        if !mentions_irrelevant(&derived.cause1) && !mentions_irrelevant(&derived.cause2) {
+            return;
+        }
+        let mut terms = derived.terms.iter();
+        let (Some(term1), Some(term2), None) = (terms.next(), terms.next(), terms.next()) else {
+            return;
+        };
+        let ((package, versions), (dependency, requirement)) = match (term1, term2) {
+            ((p, Term::Positive(versions)), (dep, Term::Negative(requirement)))
+            | ((dep, Term::Negative(requirement)), (p, Term::Positive(versions))) => {
+                ((p, versions), (dep, requirement))
+            }
+            _ => return,
+        };
+        *self = Self::External(External::FromDependencyOf(
+            package.clone(),
+            versions.clone(),
+            dependency.clone(),
+            requirement.clone(),
+        ));
+    }
+
+    /// Share the identical external incompatibilities of the tree.
+    ///
+    /// The same dependency is often an external cause in several branches of the tree, after
+    /// this it is stored once and the repetitions can be recognized with [Arc::ptr_eq] when
+    /// writing a report. [shared_id](Derived::shared_id)s are kept.
+    pub fn merge_duplicate_externals(&mut self) {
+        let Self::Derived(derived) = self else {
+            return;
+        };
+        let mut externals = Vec::new();
+        let mut rebuilt = Vec::new();
+        derived.cause1 = Self::share_externals(&derived.cause1, &mut externals, &mut rebuilt);
+        derived.cause2 = Self::share_externals(&derived.cause2, &mut externals, &mut rebuilt);
+    }
+
+    /// A copy of `tree` using the first equal one of `externals`, and the same copy for each
+    /// derived incompatibility already in `rebuilt`, so that shared subtrees stay shared.
+    #[allow(clippy::type_complexity)]
+    fn share_externals(
+        tree: &Arc<Self>,
+        externals: &mut Vec<Arc<Self>>,
+        rebuilt: &mut Vec<(*const Self, Arc<Self>)>,
+    ) -> Arc<Self> {
+        match tree.as_ref() {
+            Self::External(external) => {
+                let existing = externals
+                    .iter()
+                    .find(|shared| matches!(shared.as_ref(), Self::External(e) if e == external));
+                match existing {
+                    Some(shared) => shared.clone(),
+                    None => {
+                        externals.push(tree.clone());
+                        tree.clone()
+                    }
+                }
+            }
+            Self::Derived(derived) => {
+                if let Some((_, copy)) = rebuilt.iter().find(|(p, _)| *p == Arc::as_ptr(tree)) {
+                    return copy.clone();
+                }
+                let copy = Arc::new(Self::Derived(Derived {
+                    terms: derived.terms.clone(),
+                    shared_id: derived.shared_id,
+                    cause1: Self::share_externals(&derived.cause1, externals, rebuilt),
+                    cause2: Self::share_externals(&derived.cause2, externals, rebuilt),
+                }));
+                rebuilt.push((Arc::as_ptr(tree), copy.clone()));
+                copy
+            }
+        }
+    }
+
+    /// Share the identical derived incompatibilities of the tree, numbering the repeated ones.
+    ///
+    /// The same conflict is often derived again in another branch, as a new incompatibility with
+    /// the same terms and causes. After this, it is stored once with a
+    /// [shared_id](Derived::shared_id), so that reporters explain it once and refer to it the
+    /// other times. The identical externals are shared as with
+    /// [merge_duplicate_externals](Self::merge_duplicate_externals).
+    pub fn merge_duplicate_derived(&mut self) {
+        if let Self::External(_) = self {
+            return;
+        }
+        *self = self.merged_duplicate_derived().0;
+    }
+
+    /// The tree after [merge_duplicate_derived](Self::merge_duplicate_derived), with the
+    /// smallest [shared_id](Derived::shared_id) not in it.
+    fn merged_duplicate_derived(&self) -> (Self, usize) {
+        let tree = Arc::new(self.clone());
+        let mut dedup = Dedup {
+            externals: Vec::new(),
+            derived: Map::default(),
+            rebuilt: Map::default(),
+            uses: Map::default(),
+            next_id: 0,
+        };
+        let tree = dedup.share(&tree);
+        let mut numbered = Map::default();
+        let tree = dedup.number(&tree, &mut numbered);
+        drop(numbered);
+        let next_id = dedup.next_id;
+        drop(dedup);
+        (Arc::unwrap_or_clone(tree), next_id)
+    }
+
     fn merge_no_versions(self, package: P, set: VS) -> Option<Self> {
         match self {
             // TODO: take care of the Derived case.
@@ -144,6 +477,28 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> DerivationTree
             DerivationTree::External(External::Custom(_, r, reason)) => Some(
                 DerivationTree::External(External::Custom(package, set.union(&r), reason)),
             ),
+            DerivationTree::External(External::Denied(_, r, reason)) => Some(
+                DerivationTree::External(External::Denied(package, set.union(&r), reason)),
+            ),
+            DerivationTree::External(External::Conflict(p1, r1, p2, r2, reason)) => {
+                if p1 == package {
+                    Some(DerivationTree::External(External::Conflict(
+                        p1,
+                        r1.union(&set),
+                        p2,
+                        r2,
+                        reason,
+                    )))
+                } else {
+                    Some(DerivationTree::External(External::Conflict(
+                        p1,
+                        r1,
+                        p2,
+                        r2.union(&set),
+                        reason,
+                    )))
+                }
+            }
             DerivationTree::External(External::FromDependencyOf(p1, r1, p2, r2)) => {
                 if p1 == package {
                     Some(DerivationTree::External(External::FromDependencyOf(
@@ -165,49 +520,346 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> DerivationTree
     }
 }
 
-impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Display for External<P, VS, M> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> DerivationTree<P, VS, M> {
+    /// The newest version excluded by each dependency of the tree, among the `versions` that
+    /// exist.
+    ///
+    /// `versions` returns the versions of a package, in any order. A report saying that no
+    /// version of `foo` works is clearer when it adds that `foo 2.1.0` exists, but that `bar 3`
+    /// requires `foo <2`: the user may have expected `2.1.0` to be selected. Each dependency
+    /// gives at most one near miss, even when several of its versions were excluded.
+    ///
+    /// ```
+    /// # use pubgrub::{resolve, DefaultStringReporter, OfflineDependencyProvider, PubGrubError,
+    /// #               Ranges, Reporter};
+    /// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+    /// dependency_provider.add_dependencies("root", 1u32, [("bar", Ranges::full())]);
+    /// dependency_provider.add_dependencies("bar", 3u32, [("foo", Ranges::strictly_lower_than(2u32))]);
+    /// dependency_provider.add_dependencies("foo", 2u32, []);
+    ///
+    /// let Err(PubGrubError::NoSolution(tree)) = resolve(&dependency_provider, "root", 1u32)
+    /// else {
+    ///     unreachable!()
+    /// };
+    /// let near_misses = tree.near_misses(&|package| {
+    ///     dependency_provider.versions(package).into_iter().flatten().copied()
+    /// });
+    /// assert_eq!(
+    ///     near_misses[0].to_string(),
+    ///     "foo 2 exists but is excluded by bar ==3 depending on foo <2"
+    /// );
+    /// ```
+    pub fn near_misses<F, I>(&self, versions: &F) -> Vec<NearMiss<P, VS>>
+    where
+        F: Fn(&P) -> I,
+        I: IntoIterator,
+        I::Item: Borrow<VS::V>,
+    {
+        let mut near_misses = Vec::new();
+        self.collect_near_misses(versions, &mut near_misses);
+        near_misses
+    }
+
+    fn collect_near_misses<F, I>(&self, versions: &F, near_misses: &mut Vec<NearMiss<P, VS>>)
+    where
+        F: Fn(&P) -> I,
+        I: IntoIterator,
+        I::Item: Borrow<VS::V>,
+    {
         match self {
-            Self::NotRoot(package, version) => {
-                write!(f, "we are solving dependencies of {} {}", package, version)
-            }
-            Self::NoVersions(package, set) => {
-                if set == &VS::full() {
-                    write!(f, "there is no available version for {}", package)
-                } else {
-                    write!(f, "there is no version of {} in {}", package, set)
+            Self::External(External::FromDependencyOf(p, set, dependency, requirement)) => {
+                let newest_excluded = versions(dependency)
+                    .into_iter()
+                    .filter(|v| !requirement.contains(v.borrow()))
+                    .map(|v| v.borrow().clone())
+                    .max();
+                let Some(version) = newest_excluded else {
+                    return;
+                };
+                let near_miss = NearMiss {
+                    package: dependency.clone(),
+                    version,
+                    dependent: p.clone(),
+                    dependent_versions: set.clone(),
+                    requirement: requirement.clone(),
+                };
+                // Shared incompatibilities are visited once per occurrence.
+                if !near_misses.contains(&near_miss) {
+                    near_misses.push(near_miss);
                 }
             }
-            Self::Custom(package, set, metadata) => {
-                if set == &VS::full() {
-                    write!(
-                        f,
-                        "dependencies of {} are unavailable {}",
-                        package, metadata
-                    )
-                } else {
-                    write!(
-                        f,
-                        "dependencies of {} at version {} are unavailable {}",
-                        package, set, metadata
-                    )
+            Self::External(_) => {}
+            Self::Derived(derived) => {
+                derived.cause1.collect_near_misses(versions, near_misses);
+                derived.cause2.collect_near_misses(versions, near_misses);
+            }
+        }
+    }
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> DerivationTree<P, VS, M> {
+    /// The metadata of the [Custom](External::Custom) incompatibilities of the tree, each with
+    /// the packages and versions it made unavailable.
+    ///
+    /// Identical metadata is only listed once, in the order it first appears in the tree, so
+    /// that a registry returning the same error for many packages can be reported once:
+    ///
+    /// ```
+    /// # use pubgrub::{resolve, Dependencies, DependencyProvider, OfflineDependencyProvider,
+    /// #               PubGrubError, Ranges};
+    /// # use pubgrub::{Map, PackageResolutionStatistics};
+    /// # type NumVS = Ranges<u32>;
+    /// # struct Unauthorized(OfflineDependencyProvider<&'static str, NumVS>);
+    /// # impl DependencyProvider for Unauthorized {
+    /// #     type P = &'static str;
+    /// #     type V = u32;
+    /// #     type VS = NumVS;
+    /// #     type M = String;
+    /// #     type Priority = u32;
+    /// #     type Err = std::convert::Infallible;
+    /// #     fn prioritize(&self, _: &&'static str, _: &NumVS, _: &PackageResolutionStatistics) -> u32 { 0 }
+    /// #     fn choose_version(&self, p: &&'static str, r: &NumVS) -> Result<Option<u32>, Self::Err> {
+    /// #         self.0.choose_version(p, r)
+    /// #     }
+    /// #     fn get_dependencies(&self, p: &&'static str, v: &u32) -> Result<Dependencies<&'static str, NumVS, String>, Self::Err> {
+    /// #         if *p == "root" {
+    /// #             return self.0.get_dependencies(p, v);
+    /// #         }
+    /// #         Ok(Dependencies::Unavailable("HTTP 403 from registry".to_string()))
+    /// #     }
+    /// # }
+    /// let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    /// dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    /// dependency_provider.add_dependencies("foo", 1u32, []);
+    /// dependency_provider.add_dependencies("foo", 2u32, []);
+    /// // A provider failing to download the metadata of any package but root.
+    /// let dependency_provider = Unauthorized(dependency_provider);
+    ///
+    /// let Err(PubGrubError::NoSolution(tree)) = resolve(&dependency_provider, "root", 1u32)
+    /// else {
+    ///     unreachable!()
+    /// };
+    /// let reasons = tree.custom_reasons();
+    /// assert_eq!(reasons.len(), 1);
+    /// let (reason, unavailable) = &reasons[0];
+    /// assert_eq!(reason.as_str(), "HTTP 403 from registry");
+    /// assert_eq!(unavailable.len(), 2);
+    /// ```
+    pub fn custom_reasons(&self) -> Vec<(&M, Vec<(&P, &VS)>)> {
+        let mut reasons: Vec<(&M, Vec<(&P, &VS)>)> = Vec::new();
+        let mut stack = vec![self];
+        while let Some(tree) = stack.pop() {
+            match tree {
+                Self::External(External::Custom(package, set, reason)) => {
+                    let unavailable = match reasons.iter_mut().find(|(r, _)| *r == reason) {
+                        Some((_, unavailable)) => unavailable,
+                        None => {
+                            reasons.push((reason, Vec::new()));
+                            &mut reasons.last_mut().unwrap().1
+                        }
+                    };
+                    // Shared incompatibilities are visited once per occurrence.
+                    if !unavailable.contains(&(package, set)) {
+                        unavailable.push((package, set));
+                    }
+                }
+                Self::External(_) => {}
+                Self::Derived(derived) => {
+                    // Visit the first cause first.
+                    stack.push(&derived.cause2);
+                    stack.push(&derived.cause1);
                 }
             }
-            Self::FromDependencyOf(p, set_p, dep, set_dep) => {
-                if set_p == &VS::full() && set_dep == &VS::full() {
-                    write!(f, "{} depends on {}", p, dep)
-                } else if set_p == &VS::full() {
-                    write!(f, "{} depends on {} {}", p, dep, set_dep)
-                } else if set_dep == &VS::full() {
-                    write!(f, "{} {} depends on {}", p, set_p, dep)
-                } else {
-                    write!(f, "{} {} depends on {} {}", p, set_p, dep, set_dep)
+        }
+        reasons
+    }
+}
+
+/// A version that exists but was excluded by a dependency, found by
+/// [DerivationTree::near_misses].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearMiss<P: Package, VS: VersionSet> {
+    /// The package of the excluded version.
+    pub package: P,
+    /// The excluded version.
+    pub version: VS::V,
+    /// The package whose dependency excluded the version.
+    pub dependent: P,
+    /// The versions of [dependent](Self::dependent) with that dependency.
+    pub dependent_versions: VS,
+    /// The versions of [package](Self::package) allowed by the dependency.
+    pub requirement: VS,
+}
+
+impl<P: Package, VS: VersionSet> Display for NearMiss<P, VS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} exists but is excluded by {} {} depending on {} {}",
+            self.package,
+            self.version,
+            self.dependent,
+            self.dependent_versions,
+            self.package,
+            self.requirement
+        )
+    }
+}
+
+impl<P: Package, V: Debug + Display + Clone + Ord, M: Eq + Clone + Debug + Display>
+    DerivationTree<P, Ranges<V>, M>
+{
+    /// Simplify the ranges of the tree with [Ranges::simplify], knowing the versions that exist.
+    ///
+    /// `versions` returns the sorted versions of a package. A simplified range contains the same
+    /// existing versions, but may contain more or less of the others, so the report reads
+    /// `<2.0` rather than `<1.9.4 | >1.9.4, <2.0` when `1.9.4` doesn't exist.
+    ///
+    /// ```
+    /// # use pubgrub::{resolve, DefaultStringReporter, OfflineDependencyProvider, PubGrubError,
+    /// #               Ranges, Reporter};
+    /// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+    /// let not_4 = Ranges::strictly_lower_than(4u32).union(&Ranges::between(5u32, 20u32));
+    /// dependency_provider.add_dependencies("root", 1u32, [("a", not_4)]);
+    /// dependency_provider.add_dependencies("a", 2u32, [("b", Ranges::singleton(1u32))]);
+    /// dependency_provider.add_dependencies("a", 10u32, [("b", Ranges::singleton(1u32))]);
+    /// dependency_provider.add_dependencies("a", 30u32, []);
+    /// dependency_provider.add_dependencies("b", 2u32, []);
+    ///
+    /// let Err(PubGrubError::NoSolution(mut tree)) = resolve(&dependency_provider, "root", 1u32)
+    /// else {
+    ///     unreachable!()
+    /// };
+    /// tree.collapse_no_versions();
+    /// tree.simplify_ranges(&|package| {
+    ///     dependency_provider.versions(package).into_iter().flatten().copied()
+    /// });
+    /// assert_eq!(
+    ///     DefaultStringReporter::report(&tree),
+    ///     "Because a <20 depends on b ==1 and root ==1 depends on a <20, root ==1 is forbidden."
+    /// );
+    /// ```
+    pub fn simplify_ranges<F, I>(&mut self, versions: &F)
+    where
+        F: Fn(&P) -> I,
+        I: IntoIterator,
+        I::Item: Borrow<V>,
+    {
+        let simplify = |package: &P, range: &mut Ranges<V>| {
+            *range = range.simplify(versions(package).into_iter())
+        };
+        match self {
+            Self::External(External::NotRoot(_, _)) => {}
+            Self::External(External::NoVersions(p, range))
+            | Self::External(External::Custom(p, range, _))
+            | Self::External(External::Denied(p, range, _)) => simplify(p, range),
+            Self::External(External::FromDependencyOf(p1, range1, p2, range2))
+            | Self::External(External::Conflict(p1, range1, p2, range2, _)) => {
+                simplify(p1, range1);
+                simplify(p2, range2);
+            }
+            Self::Derived(derived) => {
+                for (package, term) in derived.terms.iter_mut() {
+                    match term {
+                        Term::Positive(range) | Term::Negative(range) => simplify(package, range),
+                    }
                 }
+                Arc::make_mut(&mut derived.cause1).simplify_ranges(versions);
+                Arc::make_mut(&mut derived.cause2).simplify_ranges(versions);
             }
         }
     }
 }
 
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> DerivationTree<P, VS, M> {
+    /// The derivation tree in the DOT language of [Graphviz](https://graphviz.org/).
+    ///
+    /// External incompatibilities are the leaves, drawn as boxes, and derived incompatibilities
+    /// point to their two causes. Incompatibilities shared by several derivations are drawn
+    /// once.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph {\n");
+        let mut shared = Map::default();
+        let mut next_node = 0;
+        self.write_dot_node(&mut dot, &mut shared, &mut next_node);
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Write the node of this tree and its causes, returning the number of the node.
+    fn write_dot_node(
+        &self,
+        dot: &mut String,
+        shared: &mut Map<usize, usize>,
+        next_node: &mut usize,
+    ) -> usize {
+        use core::fmt::Write;
+
+        let derived = match self {
+            Self::External(external) => {
+                let node = *next_node;
+                *next_node += 1;
+                writeln!(
+                    dot,
+                    "    {node} [shape=box, label={}];",
+                    dot_quoted(external)
+                )
+                .unwrap();
+                return node;
+            }
+            Self::Derived(derived) => derived,
+        };
+        if let Some(node) = derived.shared_id.and_then(|id| shared.get(&id)) {
+            return *node;
+        }
+        let node = *next_node;
+        *next_node += 1;
+        if let Some(id) = derived.shared_id {
+            shared.insert(id, node);
+        }
+        let terms = ReportFormatter::<P, VS, M>::format_terms(
+            &DefaultStringReportFormatter,
+            &derived.terms,
+        );
+        writeln!(dot, "    {node} [label={}];", dot_quoted(terms)).unwrap();
+        for cause in [&derived.cause1, &derived.cause2] {
+            let cause = cause.write_dot_node(dot, shared, next_node);
+            writeln!(dot, "    {node} -> {cause};").unwrap();
+        }
+        node
+    }
+}
+
+/// A string in the DOT language, escaping quotes and backslashes.
+pub(crate) fn dot_quoted(label: impl Display) -> String {
+    let label = label.to_string();
+    format!("\"{}\"", label.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The report of [DefaultStringReporter], so that a [NoSolutionError](crate::NoSolutionError)
+/// explains itself when printed in a chain of errors.
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Display
+    for DerivationTree<P, VS, M>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&DefaultStringReporter::report(self))
+    }
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> core::error::Error
+    for DerivationTree<P, VS, M>
+{
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Display for External<P, VS, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&ReportFormatter::<P, VS, M>::format_external(
+            &DefaultStringReportFormatter,
+            self,
+        ))
+    }
+}
+
 /// Trait for formatting outputs in the reporter.
 pub trait ReportFormatter<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
     /// Output type of the report.
@@ -270,41 +922,492 @@ pub trait ReportFormatter<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Di
         external: &External<P, VS, M>,
         current_terms: &Map<P, Term<VS>>,
     ) -> Self::Output;
+
+    /// Highlight the conclusion of the report, the terms concluded by its last line as formatted
+    /// by [format_terms](Self::format_terms).
+    ///
+    /// The default returns it as is.
+    fn format_conclusion(&self, conclusion: Self::Output) -> Self::Output {
+        conclusion
+    }
+}
+
+/// How packages, versions and version sets are displayed in the sentences of the default reporter.
+///
+/// Implementing this trait also implements [ReportFormatter], with [String] output.
+///
+/// All methods default to the [Display] implementation of the item, so that an implementation
+/// only needs to override the ones it wants to change, for example rendering `>=1.2, <2` instead
+/// of the interval syntax of version sets.
+pub trait ReportStyle<P: Package, VS: VersionSet> {
+    /// Display a package.
+    fn package(&self, package: &P) -> String {
+        package.to_string()
+    }
+
+    /// Display a version.
+    fn version(&self, version: &VS::V) -> String {
+        version.to_string()
+    }
+
+    /// Display a version set.
+    fn version_set(&self, set: &VS) -> String {
+        set.to_string()
+    }
+
+    /// Display a term.
+    fn term(&self, term: &Term<VS>) -> String {
+        match term {
+            Term::Positive(set) => self.version_set(set),
+            Term::Negative(set) => format!("Not ( {} )", self.version_set(set)),
+        }
+    }
+
+    /// Refer to the explanation marked with the line reference `ref_id`.
+    fn line_ref(&self, ref_id: usize) -> String {
+        format!("({ref_id})")
+    }
+
+    /// Highlight the conclusion of the report, see [ReportFormatter::format_conclusion].
+    fn conclusion(&self, conclusion: String) -> String {
+        conclusion
+    }
+
+    /// A sentence concluding `conclusion` from `causes`, starting a chain of explanations or,
+    /// with `and`, continuing the previous sentence.
+    fn sentence(&self, and: bool, causes: &[String], conclusion: &str) -> String {
+        self.phrase(Phrase::Because {
+            and,
+            causes,
+            conclusion,
+        })
+    }
+
+    /// Display an [External::Custom] incompatibility, with the metadata explaining why the
+    /// versions in `set` of `package` are unavailable.
+    ///
+    /// The default appends the metadata to the sentence, override it to put the reason first,
+    /// like `foo is unavailable: HTTP 403 from registry`.
+    fn unavailable(&self, package: &P, set: &VS, reason: &dyn Display) -> String {
+        self.phrase(Phrase::Unavailable {
+            package: &self.package(package),
+            versions: (set != &VS::full())
+                .then(|| self.version_set(set))
+                .as_deref(),
+            reason: &reason.to_string(),
+        })
+    }
+
+    /// Word a phrase of the report, whose packages and versions are already displayed.
+    ///
+    /// The default is [Phrase::english], override it to translate the reports.
+    fn phrase(&self, phrase: Phrase<'_>) -> String {
+        phrase.english()
+    }
+}
+
+/// The phrases the sentences of the default reporter are made of, separating the structure of
+/// the report from its wording.
+///
+/// Packages, versions and version sets are displayed by the [ReportStyle] beforehand, so
+/// translations only provide the surrounding words, through [ReportStyle::phrase]:
+///
+/// ```
+/// # use pubgrub::{Phrase, Ranges, ReportStyle};
+/// struct French;
+///
+/// impl ReportStyle<&str, Ranges<u32>> for French {
+///     fn phrase(&self, phrase: Phrase<'_>) -> String {
+///         match phrase {
+///             Phrase::SolvingFailed => "la résolution a échoué".to_string(),
+///             Phrase::Forbidden { package, versions } => format!("{package} {versions} est interdit"),
+///             // Fall back to English for the phrases not translated yet.
+///             phrase => phrase.english(),
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Phrase<'a> {
+    /// The root package is required, see [External::NotRoot].
+    NotRoot {
+        /// The root package.
+        package: &'a str,
+        /// Its version.
+        version: &'a str,
+    },
+    /// There is no version of a package in a set, see [External::NoVersions].
+    NoVersions {
+        /// The package.
+        package: &'a str,
+        /// The versions, `None` for all of them.
+        versions: Option<&'a str>,
+    },
+    /// Versions of a package are unavailable, see [External::Custom].
+    Unavailable {
+        /// The package.
+        package: &'a str,
+        /// The versions, `None` for all of them.
+        versions: Option<&'a str>,
+        /// The metadata of the incompatibility.
+        reason: &'a str,
+    },
+    /// Versions of a package are denied by a policy, see [External::Denied].
+    Denied {
+        /// The package.
+        package: &'a str,
+        /// The versions, `None` for all of them.
+        versions: Option<&'a str>,
+        /// The reason of the policy.
+        reason: &'a str,
+    },
+    /// Versions of two packages conflict, see [External::Conflict].
+    Conflict {
+        /// The first package.
+        package: &'a str,
+        /// Its versions, `None` for all of them.
+        versions: Option<&'a str>,
+        /// The package it conflicts with.
+        other: &'a str,
+        /// Its versions, `None` for all of them.
+        other_versions: Option<&'a str>,
+        /// The reason of the conflict.
+        reason: &'a str,
+    },
+    /// Versions of a package depend on a package, see [External::FromDependencyOf].
+    DependsOn {
+        /// The depending package.
+        package: &'a str,
+        /// Its versions, `None` for all of them.
+        versions: Option<&'a str>,
+        /// The dependency.
+        dependency: &'a str,
+        /// The versions of the dependency required, `None` for any of them.
+        requirement: Option<&'a str>,
+    },
+    /// No version of a package in a set can be selected.
+    Forbidden {
+        /// The package.
+        package: &'a str,
+        /// The versions.
+        versions: &'a str,
+    },
+    /// A package must be selected at a version of a set.
+    Mandatory {
+        /// The package.
+        package: &'a str,
+        /// The versions.
+        versions: &'a str,
+    },
+    /// Terms that can't all be satisfied, each a package followed by its term.
+    Incompatible {
+        /// The terms, sorted.
+        terms: &'a [String],
+    },
+    /// The conclusion of the report when the root package is forbidden by other packages.
+    SolvingFailed,
+    /// The last line of a report with [ReportLimits], when lines were left out.
+    MoreReasons {
+        /// The number of lines left out.
+        count: usize,
+    },
+    /// A sentence, see [ReportStyle::sentence].
+    Because {
+        /// Whether it continues the previous sentence.
+        and: bool,
+        /// The causes.
+        causes: &'a [String],
+        /// What is concluded from the causes.
+        conclusion: &'a str,
+    },
+}
+
+impl Phrase<'_> {
+    /// The phrase in English, as worded by [DefaultStringReporter].
+    pub fn english(&self) -> String {
+        match *self {
+            Self::NotRoot { package, version } => {
+                format!("we are solving dependencies of {package} {version}")
+            }
+            Self::NoVersions {
+                package,
+                versions: None,
+            } => format!("there is no available version for {package}"),
+            Self::NoVersions {
+                package,
+                versions: Some(versions),
+            } => format!("there is no version of {package} in {versions}"),
+            Self::Unavailable {
+                package,
+                versions: None,
+                reason,
+            } => format!("dependencies of {package} are unavailable {reason}"),
+            Self::Unavailable {
+                package,
+                versions: Some(versions),
+                reason,
+            } => {
+                format!("dependencies of {package} at version {versions} are unavailable {reason}")
+            }
+            Self::Denied {
+                package,
+                versions: None,
+                reason,
+            } => format!("{package} is denied ({reason})"),
+            Self::Denied {
+                package,
+                versions: Some(versions),
+                reason,
+            } => format!("{package} {versions} is denied ({reason})"),
+            Self::Conflict {
+                package,
+                versions,
+                other,
+                other_versions,
+                reason,
+            } => {
+                let with_versions = |package: &str, versions: Option<&str>| match versions {
+                    Some(versions) => format!("{package} {versions}"),
+                    None => package.to_string(),
+                };
+                format!(
+                    "{} conflicts with {} ({reason})",
+                    with_versions(package, versions),
+                    with_versions(other, other_versions)
+                )
+            }
+            Self::DependsOn {
+                package,
+                versions,
+                dependency,
+                requirement,
+            } => {
+                let mut phrase = package.to_string();
+                if let Some(versions) = versions {
+                    phrase = format!("{phrase} {versions}");
+                }
+                phrase = format!("{phrase} depends on {dependency}");
+                if let Some(requirement) = requirement {
+                    phrase = format!("{phrase} {requirement}");
+                }
+                phrase
+            }
+            Self::Forbidden { package, versions } => format!("{package} {versions} is forbidden"),
+            Self::Mandatory { package, versions } => format!("{package} {versions} is mandatory"),
+            Self::Incompatible { terms } => terms.join(", ") + " are incompatible",
+            Self::SolvingFailed => "version solving failed".into(),
+            Self::MoreReasons { count: 1 } => "… and 1 more reason".into(),
+            Self::MoreReasons { count } => format!("… and {count} more reasons"),
+            Self::Because {
+                and,
+                causes,
+                conclusion,
+            } => {
+                let because = if and { "And because" } else { "Because" };
+                format!("{because} {}, {conclusion}.", causes.join(" and "))
+            }
+        }
+    }
 }
 
 /// Default formatter for the default reporter.
 #[derive(Default, Debug)]
 pub struct DefaultStringReportFormatter;
 
-impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> ReportFormatter<P, VS, M>
-    for DefaultStringReportFormatter
+impl<P: Package, VS: VersionSet> ReportStyle<P, VS> for DefaultStringReportFormatter {}
+
+/// A [ReportStyle] highlighting the packages, the versions and the conclusion of another style
+/// with ANSI escape codes, for terminals.
+///
+/// The highlights are [SGR](https://en.wikipedia.org/wiki/ANSI_escape_code#SGR) parameters, such
+/// as `1` for bold or `32` for green, applied to the output of the wrapped style, so they can be
+/// combined with a translation or with another display of the version sets.
+///
+/// ```
+/// # use pubgrub::{resolve, AnsiStyle, DefaultStringReporter, OfflineDependencyProvider,
+/// #               PubGrubError, Ranges, Reporter};
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::singleton(2u32))]);
+///
+/// let Err(PubGrubError::NoSolution(tree)) = resolve(&dependency_provider, "root", 1u32)
+/// else {
+///     unreachable!()
+/// };
+/// let report = DefaultStringReporter::report_with_formatter(&tree, &AnsiStyle::default());
+/// assert!(report.contains("\x1b[1mfoo\x1b[0m in \x1b[36m==2\x1b[0m"));
+/// // Only highlight the conclusion.
+/// let conclusion = AnsiStyle {
+///     package: "",
+///     versions: "",
+///     ..AnsiStyle::default()
+/// };
+/// assert_eq!(
+///     DefaultStringReporter::report_with_formatter(&tree, &conclusion),
+///     "Because there is no version of foo in ==2 and root ==1 depends on foo ==2, \
+///     \x1b[1;31mroot ==1 is forbidden\x1b[0m."
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnsiStyle<S = DefaultStringReportFormatter> {
+    /// The style whose output is highlighted.
+    pub style: S,
+    /// The highlight of the packages, bold by default.
+    pub package: &'static str,
+    /// The highlight of the versions and version sets, cyan by default.
+    pub versions: &'static str,
+    /// The highlight of the conclusion of the report, bold red by default.
+    pub conclusion: &'static str,
+}
+
+impl<S> AnsiStyle<S> {
+    /// Highlight the output of `style` with the default highlights.
+    pub fn new(style: S) -> Self {
+        Self {
+            style,
+            package: "1",
+            versions: "36",
+            conclusion: "1;31",
+        }
+    }
+
+    fn highlight(highlight: &str, text: String) -> String {
+        if highlight.is_empty() {
+            return text;
+        }
+        // Restore the highlight after the ones inside the text.
+        let text = text.replace("\x1b[0m", &format!("\x1b[0m\x1b[{highlight}m"));
+        format!("\x1b[{highlight}m{text}\x1b[0m")
+    }
+}
+
+impl Default for AnsiStyle {
+    fn default() -> Self {
+        Self::new(DefaultStringReportFormatter)
+    }
+}
+
+impl<P: Package, VS: VersionSet, S: ReportStyle<P, VS>> ReportStyle<P, VS> for AnsiStyle<S> {
+    fn package(&self, package: &P) -> String {
+        Self::highlight(self.package, self.style.package(package))
+    }
+
+    fn version(&self, version: &VS::V) -> String {
+        Self::highlight(self.versions, self.style.version(version))
+    }
+
+    fn version_set(&self, set: &VS) -> String {
+        Self::highlight(self.versions, self.style.version_set(set))
+    }
+
+    fn line_ref(&self, ref_id: usize) -> String {
+        self.style.line_ref(ref_id)
+    }
+
+    fn sentence(&self, and: bool, causes: &[String], conclusion: &str) -> String {
+        self.style.sentence(and, causes, conclusion)
+    }
+
+    fn conclusion(&self, conclusion: String) -> String {
+        Self::highlight(self.conclusion, self.style.conclusion(conclusion))
+    }
+
+    fn phrase(&self, phrase: Phrase<'_>) -> String {
+        self.style.phrase(phrase)
+    }
+}
+
+/// The sentences of the default reporter, with packages and version sets displayed by a
+/// [ReportStyle].
+impl<P, VS, M, S> ReportFormatter<P, VS, M> for S
+where
+    P: Package,
+    VS: VersionSet,
+    M: Eq + Clone + Debug + Display,
+    S: ReportStyle<P, VS>,
 {
     type Output = String;
 
     fn format_external(&self, external: &External<P, VS, M>) -> String {
-        external.to_string()
+        let versions = |set: &VS| (set != &VS::full()).then(|| self.version_set(set));
+        match external {
+            External::NotRoot(package, version) => self.phrase(Phrase::NotRoot {
+                package: &self.package(package),
+                version: &self.version(version),
+            }),
+            External::NoVersions(package, set) => self.phrase(Phrase::NoVersions {
+                package: &self.package(package),
+                versions: versions(set).as_deref(),
+            }),
+            External::Custom(package, set, metadata) => self.unavailable(package, set, metadata),
+            External::Denied(package, set, reason) => self.phrase(Phrase::Denied {
+                package: &self.package(package),
+                versions: versions(set).as_deref(),
+                reason: &reason.to_string(),
+            }),
+            External::Conflict(p, set_p, other, set_other, reason) => {
+                self.phrase(Phrase::Conflict {
+                    package: &self.package(p),
+                    versions: versions(set_p).as_deref(),
+                    other: &self.package(other),
+                    other_versions: versions(set_other).as_deref(),
+                    reason: &reason.to_string(),
+                })
+            }
+            External::FromDependencyOf(p, set_p, dep, set_dep) => self.phrase(Phrase::DependsOn {
+                package: &self.package(p),
+                versions: versions(set_p).as_deref(),
+                dependency: &self.package(dep),
+                requirement: versions(set_dep).as_deref(),
+            }),
+        }
     }
 
     fn format_terms(&self, terms: &Map<P, Term<VS>>) -> Self::Output {
         let terms_vec: Vec<_> = terms.iter().collect();
         match terms_vec.as_slice() {
-            [] => "version solving failed".into(),
+            [] => self.phrase(Phrase::SolvingFailed),
             // TODO: special case when that unique package is root.
-            [(package, Term::Positive(range))] => format!("{} {} is forbidden", package, range),
-            [(package, Term::Negative(range))] => format!("{} {} is mandatory", package, range),
-            [(p1, Term::Positive(r1)), (p2, Term::Negative(r2))] => self.format_external(
-                &External::<_, _, M>::FromDependencyOf(p1, r1.clone(), p2, r2.clone()),
-            ),
-            [(p1, Term::Negative(r1)), (p2, Term::Positive(r2))] => self.format_external(
-                &External::<_, _, M>::FromDependencyOf(p2, r2.clone(), p1, r1.clone()),
-            ),
+            [(package, Term::Positive(range))] => self.phrase(Phrase::Forbidden {
+                package: &self.package(package),
+                versions: &self.version_set(range),
+            }),
+            [(package, Term::Negative(range))] => self.phrase(Phrase::Mandatory {
+                package: &self.package(package),
+                versions: &self.version_set(range),
+            }),
+            [(p1, Term::Positive(r1)), (p2, Term::Negative(r2))] => {
+                self.format_external(&External::<_, _, M>::FromDependencyOf(
+                    (*p1).clone(),
+                    r1.clone(),
+                    (*p2).clone(),
+                    r2.clone(),
+                ))
+            }
+            [(p1, Term::Negative(r1)), (p2, Term::Positive(r2))] => {
+                self.format_external(&External::<_, _, M>::FromDependencyOf(
+                    (*p2).clone(),
+                    r2.clone(),
+                    (*p1).clone(),
+                    r1.clone(),
+                ))
+            }
             slice => {
-                let str_terms: Vec<_> = slice.iter().map(|(p, t)| format!("{} {}", p, t)).collect();
-                str_terms.join(", ") + " are incompatible"
+                let mut str_terms: Vec<_> = slice
+                    .iter()
+                    .map(|(p, t)| format!("{} {}", self.package(p), self.term(t)))
+                    .collect();
+                // Independent of the iteration order of the map, for reproducible reports.
+                str_terms.sort_unstable();
+                self.phrase(Phrase::Incompatible { terms: &str_terms })
             }
         }
     }
 
+    fn format_conclusion(&self, conclusion: String) -> String {
+        self.conclusion(conclusion)
+    }
+
     /// Simplest case, we just combine two external incompatibilities.
     fn explain_both_external(
         &self,
@@ -313,11 +1416,13 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> ReportFormatte
         current_terms: &Map<P, Term<VS>>,
     ) -> String {
         // TODO: order should be chosen to make it more logical.
-        format!(
-            "Because {} and {}, {}.",
-            self.format_external(external1),
-            self.format_external(external2),
-            ReportFormatter::<P, VS, M>::format_terms(self, current_terms)
+        self.sentence(
+            false,
+            &[
+                self.format_external(external1),
+                self.format_external(external2),
+            ],
+            &ReportFormatter::<P, VS, M>::format_terms(self, current_terms),
         )
     }
 
@@ -331,13 +1436,13 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> ReportFormatte
         current_terms: &Map<P, Term<VS>>,
     ) -> String {
         // TODO: order should be chosen to make it more logical.
-        format!(
-            "Because {} ({}) and {} ({}), {}.",
-            ReportFormatter::<P, VS, M>::format_terms(self, &derived1.terms),
-            ref_id1,
-            ReportFormatter::<P, VS, M>::format_terms(self, &derived2.terms),
-            ref_id2,
-            ReportFormatter::<P, VS, M>::format_terms(self, current_terms)
+        self.sentence(
+            false,
+            &[
+                format_ref(self, ref_id1, derived1),
+                format_ref(self, ref_id2, derived2),
+            ],
+            &ReportFormatter::<P, VS, M>::format_terms(self, current_terms),
         )
     }
 
@@ -352,12 +1457,13 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> ReportFormatte
         current_terms: &Map<P, Term<VS>>,
     ) -> String {
         // TODO: order should be chosen to make it more logical.
-        format!(
-            "Because {} ({}) and {}, {}.",
-            ReportFormatter::<P, VS, M>::format_terms(self, &derived.terms),
-            ref_id,
-            self.format_external(external),
-            ReportFormatter::<P, VS, M>::format_terms(self, current_terms)
+        self.sentence(
+            false,
+            &[
+                format_ref(self, ref_id, derived),
+                self.format_external(external),
+            ],
+            &ReportFormatter::<P, VS, M>::format_terms(self, current_terms),
         )
     }
 
@@ -367,10 +1473,10 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> ReportFormatte
         external: &External<P, VS, M>,
         current_terms: &Map<P, Term<VS>>,
     ) -> String {
-        format!(
-            "And because {}, {}.",
-            self.format_external(external),
-            ReportFormatter::<P, VS, M>::format_terms(self, current_terms)
+        self.sentence(
+            true,
+            &[self.format_external(external)],
+            &ReportFormatter::<P, VS, M>::format_terms(self, current_terms),
         )
     }
 
@@ -381,11 +1487,10 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> ReportFormatte
         derived: &Derived<P, VS, M>,
         current_terms: &Map<P, Term<VS>>,
     ) -> String {
-        format!(
-            "And because {} ({}), {}.",
-            ReportFormatter::<P, VS, M>::format_terms(self, &derived.terms),
-            ref_id,
-            ReportFormatter::<P, VS, M>::format_terms(self, current_terms)
+        self.sentence(
+            true,
+            &[format_ref(self, ref_id, derived)],
+            &ReportFormatter::<P, VS, M>::format_terms(self, current_terms),
         )
     }
 
@@ -396,48 +1501,405 @@ impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> ReportFormatte
         external: &External<P, VS, M>,
         current_terms: &Map<P, Term<VS>>,
     ) -> String {
-        format!(
-            "And because {} and {}, {}.",
-            self.format_external(prior_external),
-            self.format_external(external),
-            ReportFormatter::<P, VS, M>::format_terms(self, current_terms)
+        self.sentence(
+            true,
+            &[
+                self.format_external(prior_external),
+                self.format_external(external),
+            ],
+            &ReportFormatter::<P, VS, M>::format_terms(self, current_terms),
         )
     }
 }
 
-/// Default reporter able to generate an explanation as a [String].
-pub struct DefaultStringReporter {
+/// The conclusion of an already explained incompatibility, with its line reference.
+fn format_ref<P, VS, M, S>(style: &S, ref_id: usize, derived: &Derived<P, VS, M>) -> String
+where
+    P: Package,
+    VS: VersionSet,
+    M: Eq + Clone + Debug + Display,
+    S: ReportStyle<P, VS>,
+{
+    let terms = ReportFormatter::<P, VS, M>::format_terms(style, &derived.terms);
+    if ref_id == 0 {
+        // Not explained in the report, see `ReportLimits`.
+        return terms;
+    }
+    format!("{terms} {}", style.line_ref(ref_id))
+}
+
+/// One line of explanation in a report: the causes and the incompatibility concluded from them.
+///
+/// Each variant corresponds to a method of [ReportFormatter], and `current_terms` are the terms of
+/// the concluded incompatibility.
+#[derive(Debug, Clone)]
+pub enum Explanation<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
+    /// The whole derivation tree is a single external incompatibility.
+    External(External<P, VS, M>),
+    /// Two external incompatibilities, see [ReportFormatter::explain_both_external].
+    BothExternal {
+        /// First cause.
+        external1: External<P, VS, M>,
+        /// Second cause.
+        external2: External<P, VS, M>,
+        /// Terms of the concluded incompatibility.
+        current_terms: Map<P, Term<VS>>,
+    },
+    /// Two already explained incompatibilities, see [ReportFormatter::explain_both_ref].
+    BothRef {
+        /// Line reference of the first cause.
+        ref_id1: usize,
+        /// First cause.
+        derived1: Derived<P, VS, M>,
+        /// Line reference of the second cause.
+        ref_id2: usize,
+        /// Second cause.
+        derived2: Derived<P, VS, M>,
+        /// Terms of the concluded incompatibility.
+        current_terms: Map<P, Term<VS>>,
+    },
+    /// An already explained incompatibility and an external one,
+    /// see [ReportFormatter::explain_ref_and_external].
+    RefAndExternal {
+        /// Line reference of the derived cause.
+        ref_id: usize,
+        /// Derived cause.
+        derived: Derived<P, VS, M>,
+        /// External cause.
+        external: External<P, VS, M>,
+        /// Terms of the concluded incompatibility.
+        current_terms: Map<P, Term<VS>>,
+    },
+    /// The conclusion of the previous line and an external incompatibility,
+    /// see [ReportFormatter::and_explain_external].
+    AndExternal {
+        /// External cause.
+        external: External<P, VS, M>,
+        /// Terms of the concluded incompatibility.
+        current_terms: Map<P, Term<VS>>,
+    },
+    /// The conclusion of the previous line and an already explained incompatibility,
+    /// see [ReportFormatter::and_explain_ref].
+    AndRef {
+        /// Line reference of the derived cause.
+        ref_id: usize,
+        /// Derived cause.
+        derived: Derived<P, VS, M>,
+        /// Terms of the concluded incompatibility.
+        current_terms: Map<P, Term<VS>>,
+    },
+    /// The conclusion of the previous line and two external incompatibilities,
+    /// see [ReportFormatter::and_explain_prior_and_external].
+    AndPriorAndExternal {
+        /// First external cause.
+        prior_external: External<P, VS, M>,
+        /// Second external cause.
+        external: External<P, VS, M>,
+        /// Terms of the concluded incompatibility.
+        current_terms: Map<P, Term<VS>>,
+    },
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Explanation<P, VS, M> {
+    /// The terms of the concluded incompatibility.
+    fn current_terms(&self) -> Option<&Map<P, Term<VS>>> {
+        match self {
+            Self::External(_) => None,
+            Self::BothExternal { current_terms, .. }
+            | Self::BothRef { current_terms, .. }
+            | Self::RefAndExternal { current_terms, .. }
+            | Self::AndExternal { current_terms, .. }
+            | Self::AndRef { current_terms, .. }
+            | Self::AndPriorAndExternal { current_terms, .. } => Some(current_terms),
+        }
+    }
+
+    /// The line references of the causes.
+    fn ref_ids_mut(&mut self) -> impl Iterator<Item = &mut usize> {
+        let (ref_id1, ref_id2) = match self {
+            Self::BothRef {
+                ref_id1, ref_id2, ..
+            } => (Some(ref_id1), Some(ref_id2)),
+            Self::RefAndExternal { ref_id, .. } | Self::AndRef { ref_id, .. } => {
+                (Some(ref_id), None)
+            }
+            _ => (None, None),
+        };
+        ref_id1.into_iter().chain(ref_id2)
+    }
+
+    /// Format the explanation with the corresponding method of a [ReportFormatter].
+    pub fn format<F: ReportFormatter<P, VS, M>>(&self, formatter: &F) -> F::Output {
+        match self {
+            Self::External(external) => formatter.format_external(external),
+            Self::BothExternal {
+                external1,
+                external2,
+                current_terms,
+            } => formatter.explain_both_external(external1, external2, current_terms),
+            Self::BothRef {
+                ref_id1,
+                derived1,
+                ref_id2,
+                derived2,
+                current_terms,
+            } => formatter.explain_both_ref(*ref_id1, derived1, *ref_id2, derived2, current_terms),
+            Self::RefAndExternal {
+                ref_id,
+                derived,
+                external,
+                current_terms,
+            } => formatter.explain_ref_and_external(*ref_id, derived, external, current_terms),
+            Self::AndExternal {
+                external,
+                current_terms,
+            } => formatter.and_explain_external(external, current_terms),
+            Self::AndRef {
+                ref_id,
+                derived,
+                current_terms,
+            } => formatter.and_explain_ref(*ref_id, derived, current_terms),
+            Self::AndPriorAndExternal {
+                prior_external,
+                external,
+                current_terms,
+            } => formatter.and_explain_prior_and_external(prior_external, external, current_terms),
+        }
+    }
+}
+
+/// A line of the report generated by [StructuredReporter].
+#[derive(Debug, Clone)]
+pub enum ReportLine<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
+    /// An explanation, with the line reference that later lines use to refer to its conclusion.
+    Explanation(Explanation<P, VS, M>, Option<usize>),
+    /// Separates two chains of explanations.
+    Separator,
+}
+
+/// Reporter generating the explanation as a list of [ReportLine].
+///
+/// Contrary to [DefaultStringReporter], packages and version sets are kept intact in the lines,
+/// so that tools can render them in their own style.
+/// [DefaultStringReporter] formats the same lines.
+pub struct StructuredReporter<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
     /// Number of explanations already with a line reference.
     ref_count: usize,
     /// Shared nodes that have already been marked with a line reference.
     /// The incompatibility ids are the keys, and the line references are the values.
     shared_with_ref: Map<usize, usize>,
+    /// Shared nodes that are referred to by line reference, the others are explained again.
+    referenced: Set<usize>,
     /// Accumulated lines of the report already generated.
-    lines: Vec<String>,
+    lines: Vec<ReportLine<P, VS, M>>,
 }
 
-impl DefaultStringReporter {
+/// When [StructuredReporter] refers to an incompatibility that was already explained with a
+/// numbered line reference, rather than explaining it again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineRefs {
+    /// Explain an incompatibility used several times once, and refer to it afterwards.
+    #[default]
+    Always,
+    /// Explain an incompatibility again each time it is used.
+    ///
+    /// Line references are still needed when an incompatibility is derived from two
+    /// incompatibilities that are themselves derived, to conclude from the first one after
+    /// explaining the second.
+    Never,
+    /// Refer to the incompatibilities used at least this many times, explain the others again.
+    MinUses(usize),
+}
+
+/// How much of the explanation [StructuredReporter::report_with_limits] keeps, for a concise
+/// summary of the failure.
+///
+/// The lines closest to the conclusion are kept, and the report of
+/// [DefaultStringReporter::report_with_limits] ends with the number of lines left out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReportLimits {
+    /// Only explain the derived incompatibilities at most this deep in the derivation tree, the
+    /// causes of the failure being at depth 1.
+    pub max_depth: Option<usize>,
+    /// Keep at most this many lines, the last ones.
+    pub max_lines: Option<usize>,
+}
+
+impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> StructuredReporter<P, VS, M> {
     /// Initialize the reporter.
-    fn new() -> Self {
+    fn new(referenced: Set<usize>) -> Self {
         Self {
             ref_count: 0,
             shared_with_ref: Map::default(),
+            referenced,
             lines: Vec::new(),
         }
     }
 
-    fn build_recursive<
-        P: Package,
-        VS: VersionSet,
-        M: Eq + Clone + Debug + Display,
-        F: ReportFormatter<P, VS, M, Output = String>,
-    >(
-        &mut self,
-        derived: &Derived<P, VS, M>,
-        formatter: &F,
-    ) {
-        self.build_recursive_helper(derived, formatter);
+    /// Generate the lines explaining the resolution failure described by the derivation tree.
+    pub fn report(derivation_tree: &DerivationTree<P, VS, M>) -> Vec<ReportLine<P, VS, M>> {
+        Self::report_with_line_refs(derivation_tree, LineRefs::Always)
+    }
+
+    /// Same as [report](Self::report), introducing line references according to `line_refs`.
+    pub fn report_with_line_refs(
+        derivation_tree: &DerivationTree<P, VS, M>,
+        line_refs: LineRefs,
+    ) -> Vec<ReportLine<P, VS, M>> {
+        Self::report_with_limits(derivation_tree, line_refs, ReportLimits::default())
+    }
+
+    /// Same as [report_with_line_refs](Self::report_with_line_refs), leaving out the lines
+    /// beyond the `limits`.
+    ///
+    /// The incompatibilities that are not explained, because they are too deep in the tree or
+    /// their lines were left out, are referred to with the line reference 0.
+    pub fn report_with_limits(
+        derivation_tree: &DerivationTree<P, VS, M>,
+        line_refs: LineRefs,
+        limits: ReportLimits,
+    ) -> Vec<ReportLine<P, VS, M>> {
+        let (derivation_tree, mut next_id) = match derivation_tree {
+            DerivationTree::External(external) => {
+                let lines = vec![ReportLine::Explanation(
+                    Explanation::External(external.clone()),
+                    None,
+                )];
+                return Self::limit_lines(lines, limits.max_lines);
+            }
+            DerivationTree::Derived(_) => derivation_tree.merged_duplicate_derived(),
+        };
+        let mut unexplained = Map::default();
+        let derivation_tree = match limits.max_depth {
+            Some(max_depth) => Self::limit_depth(
+                &Arc::new(derivation_tree),
+                max_depth.saturating_add(1),
+                &mut next_id,
+                &mut unexplained,
+            ),
+            None => Arc::new(derivation_tree),
+        };
+        let DerivationTree::Derived(derived) = derivation_tree.as_ref() else {
+            unreachable!()
+        };
+        let min_uses = match line_refs {
+            LineRefs::Always => 1,
+            LineRefs::Never => usize::MAX,
+            LineRefs::MinUses(min_uses) => min_uses,
+        };
+        // The causes of the unexplained incompatibilities are not counted.
+        let mut uses: Map<usize, usize> = unexplained.values().map(|id| (*id, 1)).collect();
         if let Some(id) = derived.shared_id {
+            uses.insert(id, 1);
+        }
+        Self::count_uses(derived, &mut uses);
+        let mut referenced: Set<usize> = uses
+            .into_iter()
+            .filter(|(_, count)| *count >= min_uses)
+            .map(|(id, _)| id)
+            .collect();
+        referenced.extend(unexplained.values());
+        let mut reporter = Self::new(referenced);
+        reporter.shared_with_ref = unexplained.into_values().map(|id| (id, 0)).collect();
+        reporter.build_recursive(derived);
+        Self::limit_lines(reporter.lines, limits.max_lines)
+    }
+
+    /// A copy of `tree` where the derived incompatibilities at `depth` or deeper have a new
+    /// [shared_id](Derived::shared_id), starting from `next_id`, recorded in `unexplained`.
+    #[allow(clippy::type_complexity)]
+    fn limit_depth(
+        tree: &Arc<DerivationTree<P, VS, M>>,
+        depth: usize,
+        next_id: &mut usize,
+        unexplained: &mut Map<*const DerivationTree<P, VS, M>, usize>,
+    ) -> Arc<DerivationTree<P, VS, M>> {
+        let DerivationTree::Derived(derived) = tree.as_ref() else {
+            return tree.clone();
+        };
+        let (shared_id, cause1, cause2) = match depth.checked_sub(1) {
+            Some(depth) => (
+                derived.shared_id,
+                Self::limit_depth(&derived.cause1, depth, next_id, unexplained),
+                Self::limit_depth(&derived.cause2, depth, next_id, unexplained),
+            ),
+            None => {
+                let id = *unexplained.entry(Arc::as_ptr(tree)).or_insert_with(|| {
+                    *next_id += 1;
+                    *next_id - 1
+                });
+                (Some(id), derived.cause1.clone(), derived.cause2.clone())
+            }
+        };
+        Arc::new(DerivationTree::Derived(Derived {
+            terms: derived.terms.clone(),
+            shared_id,
+            cause1,
+            cause2,
+        }))
+    }
+
+    /// Keep the last `max_lines` explanations, referring to the others with the line reference 0.
+    fn limit_lines(
+        mut lines: Vec<ReportLine<P, VS, M>>,
+        max_lines: Option<usize>,
+    ) -> Vec<ReportLine<P, VS, M>> {
+        let explanations = lines
+            .iter()
+            .filter(|line| matches!(line, ReportLine::Explanation(..)))
+            .count();
+        let Some(left_out) = max_lines.and_then(|max_lines| explanations.checked_sub(max_lines))
+        else {
+            return lines;
+        };
+        let mut removed = Set::default();
+        let mut cut = lines.len();
+        let mut seen = 0;
+        for (i, line) in lines.iter().enumerate() {
+            if let ReportLine::Explanation(_, line_ref) = line {
+                if seen == left_out {
+                    cut = i;
+                    break;
+                }
+                removed.extend(*line_ref);
+                seen += 1;
+            }
+        }
+        // Also drop the separators before the first line kept.
+        lines.drain(..cut);
+        for line in &mut lines {
+            if let ReportLine::Explanation(explanation, _) = line {
+                for ref_id in explanation.ref_ids_mut() {
+                    if removed.contains(ref_id) {
+                        *ref_id = 0;
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    /// Count how many times each shared incompatibility is a cause, without counting again the
+    /// causes of those already seen, which are not explained again.
+    fn count_uses(derived: &Derived<P, VS, M>, uses: &mut Map<usize, usize>) {
+        for cause in [&derived.cause1, &derived.cause2] {
+            let DerivationTree::Derived(cause) = cause.as_ref() else {
+                continue;
+            };
+            if let Some(id) = cause.shared_id {
+                let count = uses.entry(id).or_default();
+                *count += 1;
+                if *count > 1 {
+                    continue;
+                }
+            }
+            Self::count_uses(cause, uses);
+        }
+    }
+
+    fn build_recursive(&mut self, derived: &Derived<P, VS, M>) {
+        self.build_recursive_helper(derived);
+        if let Some(id) = self.shared_id(derived) {
             #[allow(clippy::map_entry)] // `add_line_ref` not compatible with proposed fix.
             if !self.shared_with_ref.contains_key(&id) {
                 self.add_line_ref();
@@ -446,61 +1908,58 @@ impl DefaultStringReporter {
         };
     }
 
-    fn build_recursive_helper<
-        P: Package,
-        VS: VersionSet,
-        M: Eq + Clone + Debug + Display,
-        F: ReportFormatter<P, VS, M, Output = String>,
-    >(
-        &mut self,
-        current: &Derived<P, VS, M>,
-        formatter: &F,
-    ) {
+    fn build_recursive_helper(&mut self, current: &Derived<P, VS, M>) {
         match (current.cause1.deref(), current.cause2.deref()) {
             (DerivationTree::External(external1), DerivationTree::External(external2)) => {
                 // Simplest case, we just combine two external incompatibilities.
-                self.lines.push(formatter.explain_both_external(
-                    external1,
-                    external2,
-                    &current.terms,
-                ));
+                self.push(Explanation::BothExternal {
+                    external1: external1.clone(),
+                    external2: external2.clone(),
+                    current_terms: current.terms.clone(),
+                });
             }
             (DerivationTree::Derived(derived), DerivationTree::External(external)) => {
                 // One cause is derived, so we explain this first
                 // then we add the one-line external part
                 // and finally conclude with the current incompatibility.
-                self.report_one_each(derived, external, &current.terms, formatter);
+                self.report_one_each(derived, external, &current.terms);
             }
             (DerivationTree::External(external), DerivationTree::Derived(derived)) => {
-                self.report_one_each(derived, external, &current.terms, formatter);
+                self.report_one_each(derived, external, &current.terms);
             }
             (DerivationTree::Derived(derived1), DerivationTree::Derived(derived2)) => {
                 // This is the most complex case since both causes are also derived.
                 match (
-                    self.line_ref_of(derived1.shared_id),
-                    self.line_ref_of(derived2.shared_id),
+                    self.line_ref_of(self.shared_id(derived1)),
+                    self.line_ref_of(self.shared_id(derived2)),
                 ) {
                     // If both causes already have been referenced (shared_id),
                     // the explanation simply uses those references.
-                    (Some(ref1), Some(ref2)) => self.lines.push(formatter.explain_both_ref(
-                        ref1,
-                        derived1,
-                        ref2,
-                        derived2,
-                        &current.terms,
-                    )),
+                    (Some(ref1), Some(ref2)) => self.push(Explanation::BothRef {
+                        ref_id1: ref1,
+                        derived1: derived1.clone(),
+                        ref_id2: ref2,
+                        derived2: derived2.clone(),
+                        current_terms: current.terms.clone(),
+                    }),
                     // Otherwise, if one only has a line number reference,
                     // we recursively call the one without reference and then
                     // add the one with reference to conclude.
                     (Some(ref1), None) => {
-                        self.build_recursive(derived2, formatter);
-                        self.lines
-                            .push(formatter.and_explain_ref(ref1, derived1, &current.terms));
+                        self.build_recursive(derived2);
+                        self.push(Explanation::AndRef {
+                            ref_id: ref1,
+                            derived: derived1.clone(),
+                            current_terms: current.terms.clone(),
+                        });
                     }
                     (None, Some(ref2)) => {
-                        self.build_recursive(derived1, formatter);
-                        self.lines
-                            .push(formatter.and_explain_ref(ref2, derived2, &current.terms));
+                        self.build_recursive(derived1);
+                        self.push(Explanation::AndRef {
+                            ref_id: ref2,
+                            derived: derived2.clone(),
+                            current_terms: current.terms.clone(),
+                        });
                     }
                     // Finally, if no line reference exists yet,
                     // we call recursively the first one and then,
@@ -510,20 +1969,20 @@ impl DefaultStringReporter {
                     //     recursively call on the second node,
                     //     and finally conclude.
                     (None, None) => {
-                        self.build_recursive(derived1, formatter);
-                        if derived1.shared_id.is_some() {
-                            self.lines.push("".into());
-                            self.build_recursive(current, formatter);
+                        self.build_recursive(derived1);
+                        if self.shared_id(derived1).is_some() {
+                            self.lines.push(ReportLine::Separator);
+                            self.build_recursive(current);
                         } else {
                             self.add_line_ref();
                             let ref1 = self.ref_count;
-                            self.lines.push("".into());
-                            self.build_recursive(derived2, formatter);
-                            self.lines.push(formatter.and_explain_ref(
-                                ref1,
-                                derived1,
-                                &current.terms,
-                            ));
+                            self.lines.push(ReportLine::Separator);
+                            self.build_recursive(derived2);
+                            self.push(Explanation::AndRef {
+                                ref_id: ref1,
+                                derived: derived1.clone(),
+                                current_terms: current.terms.clone(),
+                            });
                         }
                     }
                 }
@@ -535,114 +1994,229 @@ impl DefaultStringReporter {
     ///
     /// The result will depend on the fact that the derived incompatibility
     /// has already been explained or not.
-    fn report_one_each<
-        P: Package,
-        VS: VersionSet,
-        M: Eq + Clone + Debug + Display,
-        F: ReportFormatter<P, VS, M, Output = String>,
-    >(
+    fn report_one_each(
         &mut self,
         derived: &Derived<P, VS, M>,
         external: &External<P, VS, M>,
         current_terms: &Map<P, Term<VS>>,
-        formatter: &F,
     ) {
-        match self.line_ref_of(derived.shared_id) {
-            Some(ref_id) => self.lines.push(formatter.explain_ref_and_external(
+        match self.line_ref_of(self.shared_id(derived)) {
+            Some(ref_id) => self.push(Explanation::RefAndExternal {
                 ref_id,
-                derived,
-                external,
-                current_terms,
-            )),
-            None => self.report_recurse_one_each(derived, external, current_terms, formatter),
+                derived: derived.clone(),
+                external: external.clone(),
+                current_terms: current_terms.clone(),
+            }),
+            None => self.report_recurse_one_each(derived, external, current_terms),
         }
     }
 
     /// Report one derived (without a line ref yet) and one external.
-    fn report_recurse_one_each<
-        P: Package,
-        VS: VersionSet,
-        M: Eq + Clone + Debug + Display,
-        F: ReportFormatter<P, VS, M, Output = String>,
-    >(
+    fn report_recurse_one_each(
         &mut self,
         derived: &Derived<P, VS, M>,
         external: &External<P, VS, M>,
         current_terms: &Map<P, Term<VS>>,
-        formatter: &F,
     ) {
         match (derived.cause1.deref(), derived.cause2.deref()) {
             // If the derived cause has itself one external prior cause,
             // we can chain the external explanations.
-            (DerivationTree::Derived(prior_derived), DerivationTree::External(prior_external)) => {
-                self.build_recursive(prior_derived, formatter);
-                self.lines.push(formatter.and_explain_prior_and_external(
-                    prior_external,
-                    external,
-                    current_terms,
-                ));
-            }
-            // If the derived cause has itself one external prior cause,
-            // we can chain the external explanations.
-            (DerivationTree::External(prior_external), DerivationTree::Derived(prior_derived)) => {
-                self.build_recursive(prior_derived, formatter);
-                self.lines.push(formatter.and_explain_prior_and_external(
-                    prior_external,
-                    external,
-                    current_terms,
-                ));
+            (DerivationTree::Derived(prior_derived), DerivationTree::External(prior_external))
+            | (DerivationTree::External(prior_external), DerivationTree::Derived(prior_derived)) => {
+                self.build_recursive(prior_derived);
+                self.push(Explanation::AndPriorAndExternal {
+                    prior_external: prior_external.clone(),
+                    external: external.clone(),
+                    current_terms: current_terms.clone(),
+                });
             }
             _ => {
-                self.build_recursive(derived, formatter);
-                self.lines
-                    .push(formatter.and_explain_external(external, current_terms));
+                self.build_recursive(derived);
+                self.push(Explanation::AndExternal {
+                    external: external.clone(),
+                    current_terms: current_terms.clone(),
+                });
             }
         }
     }
 
     // Helper functions ########################################################
 
+    fn push(&mut self, explanation: Explanation<P, VS, M>) {
+        self.lines.push(ReportLine::Explanation(explanation, None));
+    }
+
     fn add_line_ref(&mut self) {
         let new_count = self.ref_count + 1;
         self.ref_count = new_count;
-        if let Some(line) = self.lines.last_mut() {
-            *line = format!("{} ({})", line, new_count);
+        if let Some(ReportLine::Explanation(_, line_ref)) = self.lines.last_mut() {
+            *line_ref = Some(new_count);
         }
     }
 
+    /// The id of a shared incompatibility that is referred to by line reference.
+    fn shared_id(&self, derived: &Derived<P, VS, M>) -> Option<usize> {
+        derived.shared_id.filter(|id| self.referenced.contains(id))
+    }
+
     fn line_ref_of(&self, shared_id: Option<usize>) -> Option<usize> {
         shared_id.and_then(|id| self.shared_with_ref.get(&id).cloned())
     }
 }
 
+/// Default reporter able to generate an explanation as a [String].
+pub struct DefaultStringReporter;
+
 impl<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> Reporter<P, VS, M>
     for DefaultStringReporter
 {
     type Output = String;
 
     fn report(derivation_tree: &DerivationTree<P, VS, M>) -> Self::Output {
-        let formatter = DefaultStringReportFormatter;
-        match derivation_tree {
-            DerivationTree::External(external) => formatter.format_external(external),
-            DerivationTree::Derived(derived) => {
-                let mut reporter = Self::new();
-                reporter.build_recursive(derived, &formatter);
-                reporter.lines.join("\n")
-            }
-        }
+        Self::report_with_formatter(derivation_tree, &DefaultStringReportFormatter)
     }
 
     fn report_with_formatter(
         derivation_tree: &DerivationTree<P, VS, M>,
         formatter: &impl ReportFormatter<P, VS, M, Output = Self::Output>,
     ) -> Self::Output {
-        match derivation_tree {
-            DerivationTree::External(external) => formatter.format_external(external),
-            DerivationTree::Derived(derived) => {
-                let mut reporter = Self::new();
-                reporter.build_recursive(derived, formatter);
-                reporter.lines.join("\n")
+        Self::format_lines(
+            &StructuredReporter::report(derivation_tree),
+            formatter,
+            |line_ref| format!("({line_ref})"),
+        )
+    }
+}
+
+impl DefaultStringReporter {
+    /// Generate a report with the sentences of a [ReportStyle], introducing line references
+    /// according to `line_refs`.
+    ///
+    /// ```
+    /// # use pubgrub::{DefaultStringReportFormatter, DefaultStringReporter, DerivationTree, LineRefs};
+    /// # use pubgrub::Ranges;
+    /// # fn report(derivation_tree: &DerivationTree<&str, Ranges<u32>, String>) -> String {
+    /// DefaultStringReporter::report_with_line_refs(
+    ///     derivation_tree,
+    ///     &DefaultStringReportFormatter,
+    ///     // Explain again the incompatibilities used only twice.
+    ///     LineRefs::MinUses(3),
+    /// )
+    /// # }
+    /// ```
+    pub fn report_with_line_refs<P, VS, M>(
+        derivation_tree: &DerivationTree<P, VS, M>,
+        style: &impl ReportStyle<P, VS>,
+        line_refs: LineRefs,
+    ) -> String
+    where
+        P: Package,
+        VS: VersionSet,
+        M: Eq + Clone + Debug + Display,
+    {
+        Self::format_lines(
+            &StructuredReporter::report_with_line_refs(derivation_tree, line_refs),
+            style,
+            |line_ref| style.line_ref(line_ref),
+        )
+    }
+
+    /// Generate a report keeping the lines within `limits`, ending with the number of lines left
+    /// out if any.
+    ///
+    /// ```
+    /// # use pubgrub::{DefaultStringReportFormatter, DefaultStringReporter, DerivationTree, LineRefs,
+    /// #               ReportLimits};
+    /// # use pubgrub::Ranges;
+    /// # fn report(derivation_tree: &DerivationTree<&str, Ranges<u32>, String>, verbose: bool) -> String {
+    /// let limits = if verbose {
+    ///     ReportLimits::default()
+    /// } else {
+    ///     ReportLimits {
+    ///         max_depth: Some(2),
+    ///         max_lines: Some(10),
+    ///     }
+    /// };
+    /// DefaultStringReporter::report_with_limits(
+    ///     derivation_tree,
+    ///     &DefaultStringReportFormatter,
+    ///     LineRefs::Always,
+    ///     limits,
+    /// )
+    /// # }
+    /// ```
+    pub fn report_with_limits<P, VS, M>(
+        derivation_tree: &DerivationTree<P, VS, M>,
+        style: &impl ReportStyle<P, VS>,
+        line_refs: LineRefs,
+        limits: ReportLimits,
+    ) -> String
+    where
+        P: Package,
+        VS: VersionSet,
+        M: Eq + Clone + Debug + Display,
+    {
+        let lines = StructuredReporter::report_with_limits(derivation_tree, line_refs, limits);
+        let mut report = Self::format_lines(&lines, style, |line_ref| style.line_ref(line_ref));
+        if limits != ReportLimits::default() {
+            let explanations = |lines: &[ReportLine<P, VS, M>]| {
+                lines
+                    .iter()
+                    .filter(|line| matches!(line, ReportLine::Explanation(..)))
+                    .count()
+            };
+            let all = StructuredReporter::report_with_line_refs(derivation_tree, line_refs);
+            let count = explanations(&all).saturating_sub(explanations(&lines));
+            if count > 0 {
+                if !report.is_empty() {
+                    report.push('\n');
+                }
+                report.push_str(&style.phrase(Phrase::MoreReasons { count }));
+            }
+        }
+        report
+    }
+
+    fn format_lines<P, VS, M>(
+        lines: &[ReportLine<P, VS, M>],
+        formatter: &impl ReportFormatter<P, VS, M, Output = String>,
+        format_line_ref: impl Fn(usize) -> String,
+    ) -> String
+    where
+        P: Package,
+        VS: VersionSet,
+        M: Eq + Clone + Debug + Display,
+    {
+        let mut formatted: Vec<String> = lines
+            .iter()
+            .map(|line| match line {
+                ReportLine::Explanation(explanation, None) => explanation.format(formatter),
+                ReportLine::Explanation(explanation, Some(line_ref)) => {
+                    format!(
+                        "{} {}",
+                        explanation.format(formatter),
+                        format_line_ref(*line_ref)
+                    )
+                }
+                ReportLine::Separator => String::new(),
+            })
+            .collect();
+        let last = lines
+            .iter()
+            .rposition(|line| matches!(line, ReportLine::Explanation(..)));
+        if let Some(last) = last {
+            let ReportLine::Explanation(explanation, _) = &lines[last] else {
+                unreachable!()
+            };
+            if let Some(current_terms) = explanation.current_terms() {
+                let conclusion = formatter.format_terms(current_terms);
+                let line = &mut formatted[last];
+                if let Some(start) = line.rfind(&conclusion) {
+                    let highlighted = formatter.format_conclusion(conclusion.clone());
+                    line.replace_range(start..start + conclusion.len(), &highlighted);
+                }
             }
         }
+        formatted.join("\n")
     }
 }
@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Cargo-style version requirements.
+
+use alloc::string::{String, ToString};
+
+use thiserror::Error;
+
+use crate::{Ranges, SemanticVersion};
+
+/// Error parsing a version requirement with [parse_requirement].
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RequirementParseError {
+    /// The requirement does not contain any comparator.
+    #[error("requirement '{requirement}' is empty")]
+    Empty {
+        /// Requirement that was being parsed.
+        requirement: String,
+    },
+    /// A comma separated part of the requirement could not be parsed.
+    #[error("cannot parse '{comparator}' in requirement '{requirement}'")]
+    InvalidComparator {
+        /// Requirement that was being parsed.
+        requirement: String,
+        /// Comparator where parsing failed.
+        comparator: String,
+    },
+}
+
+/// A version where the minor and patch numbers may be missing or wildcards, like `1.2` or `1.*`.
+#[derive(Debug, Clone, Copy)]
+struct Partial {
+    major: u32,
+    minor: Option<u32>,
+    patch: Option<u32>,
+    /// Whether a missing number was written as a wildcard.
+    wildcard: bool,
+}
+
+impl Partial {
+    fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.split('.');
+        let mut wildcard = false;
+        let mut number = || match parts.next() {
+            None => Some(None),
+            Some("*" | "x" | "X") => {
+                wildcard = true;
+                Some(None)
+            }
+            Some(part) => part.parse::<u32>().ok().map(Some),
+        };
+        let major = number()?;
+        let minor = number()?;
+        let patch = number()?;
+        if parts.next().is_some() || (minor.is_none() && patch.is_some()) {
+            return None;
+        }
+        Some(Self {
+            major: major?,
+            minor,
+            patch,
+            wildcard,
+        })
+    }
+
+    /// The smallest matching version.
+    fn lowest(self) -> SemanticVersion {
+        SemanticVersion::new(self.major, self.minor.unwrap_or(0), self.patch.unwrap_or(0))
+    }
+
+    /// The smallest version greater than all matching versions.
+    fn after(self) -> SemanticVersion {
+        match (self.minor, self.patch) {
+            (None, _) => self.lowest().bump_major(),
+            (Some(_), None) => self.lowest().bump_minor(),
+            (Some(_), Some(_)) => self.lowest().bump_patch(),
+        }
+    }
+
+    /// All the versions matching the partial version.
+    fn matching(self) -> Ranges<SemanticVersion> {
+        match self.patch {
+            Some(_) => Ranges::singleton(self.lowest()),
+            None => Ranges::between(self.lowest(), self.after()),
+        }
+    }
+
+    /// The versions compatible with the partial version according to the caret operator.
+    fn caret(self) -> Ranges<SemanticVersion> {
+        let upper = match (self.major, self.minor, self.patch) {
+            (0, Some(0), Some(_)) => self.lowest().bump_patch(),
+            (0, Some(_), _) => self.lowest().bump_minor(),
+            _ => self.lowest().bump_major(),
+        };
+        Ranges::between(self.lowest(), upper)
+    }
+
+    /// The versions compatible with the partial version according to the tilde operator.
+    fn tilde(self) -> Ranges<SemanticVersion> {
+        let upper = match self.minor {
+            None => self.lowest().bump_major(),
+            Some(_) => self.lowest().bump_minor(),
+        };
+        Ranges::between(self.lowest(), upper)
+    }
+}
+
+/// Parse a Cargo-style version requirement into the set of matching [SemanticVersion].
+///
+/// The requirement is a comma separated list of comparators, that must all match. Comparators
+/// use the operators `^` (the default), `~`, `=`, `>`, `>=`, `<` and `<=` followed by a version
+/// where the minor and patch numbers may be omitted, or are wildcards `1.*`, `1.2.*` or `*`.
+/// The semantics are the ones of
+/// [Cargo](https://doc.rust-lang.org/cargo/reference/specifying-dependencies.html),
+/// except for pre-releases that [SemanticVersion] does not have.
+///
+/// ```
+/// # use pubgrub::{parse_requirement, Ranges, SemanticVersion};
+/// assert_eq!(
+///     parse_requirement("^0.2.3").unwrap(),
+///     Ranges::between((0, 2, 3), (0, 3, 0)),
+/// );
+/// assert_eq!(
+///     parse_requirement(">=1.2, <1.5").unwrap(),
+///     Ranges::between((1, 2, 0), (1, 5, 0)),
+/// );
+/// ```
+pub fn parse_requirement(
+    requirement: &str,
+) -> Result<Ranges<SemanticVersion>, RequirementParseError> {
+    if requirement.trim().is_empty() {
+        return Err(RequirementParseError::Empty {
+            requirement: requirement.to_string(),
+        });
+    }
+    requirement
+        .split(',')
+        .try_fold(Ranges::full(), |set, comparator| {
+            let range = parse_comparator(comparator.trim()).ok_or_else(|| {
+                RequirementParseError::InvalidComparator {
+                    requirement: requirement.to_string(),
+                    comparator: comparator.trim().to_string(),
+                }
+            })?;
+            Ok(set.intersection(&range))
+        })
+}
+
+fn parse_comparator(comparator: &str) -> Option<Ranges<SemanticVersion>> {
+    if comparator == "*" || comparator == "x" || comparator == "X" {
+        return Some(Ranges::full());
+    }
+    // Longest operators first so that `>=` is not parsed as `>`.
+    let operators = [">=", "<=", "^", "~", "=", ">", "<"];
+    let (operator, version) = operators
+        .iter()
+        .find_map(|op| comparator.strip_prefix(op).map(|rest| (*op, rest)))
+        .unwrap_or(("^", comparator));
+    let version = Partial::parse(version.trim_start())?;
+    Some(match operator {
+        // Wildcards match exactly, `1.2.*` is `=1.2`.
+        "^" | "~" if version.wildcard => version.matching(),
+        "^" => version.caret(),
+        "~" => version.tilde(),
+        "=" => version.matching(),
+        ">" => Ranges::higher_than(version.after()),
+        ">=" => Ranges::higher_than(version.lowest()),
+        "<" => Ranges::strictly_lower_than(version.lowest()),
+        "<=" => Ranges::strictly_lower_than(version.after()),
+        _ => unreachable!(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cargo_requirements() {
+        let parse = |req: &str| parse_requirement(req).unwrap();
+        let between = |a: (u32, u32, u32), b: (u32, u32, u32)| Ranges::between(a, b);
+        // Caret, with or without the operator.
+        assert_eq!(parse("^1.2.3"), between((1, 2, 3), (2, 0, 0)));
+        assert_eq!(parse("1.2.3"), between((1, 2, 3), (2, 0, 0)));
+        assert_eq!(parse("^1.2"), between((1, 2, 0), (2, 0, 0)));
+        assert_eq!(parse("^1"), between((1, 0, 0), (2, 0, 0)));
+        assert_eq!(parse("^0.2.3"), between((0, 2, 3), (0, 3, 0)));
+        assert_eq!(parse("^0.2"), between((0, 2, 0), (0, 3, 0)));
+        assert_eq!(parse("^0.0.3"), between((0, 0, 3), (0, 0, 4)));
+        assert_eq!(parse("^0.0"), between((0, 0, 0), (0, 1, 0)));
+        assert_eq!(parse("^0"), between((0, 0, 0), (1, 0, 0)));
+        // Tilde.
+        assert_eq!(parse("~1.2.3"), between((1, 2, 3), (1, 3, 0)));
+        assert_eq!(parse("~1.2"), between((1, 2, 0), (1, 3, 0)));
+        assert_eq!(parse("~1"), between((1, 0, 0), (2, 0, 0)));
+        assert_eq!(parse("~0.3"), between((0, 3, 0), (0, 4, 0)));
+        // Wildcards.
+        assert_eq!(parse("*"), Ranges::full());
+        assert_eq!(parse("1.*"), between((1, 0, 0), (2, 0, 0)));
+        assert_eq!(parse("1.2.*"), between((1, 2, 0), (1, 3, 0)));
+        assert_eq!(parse("0.*"), between((0, 0, 0), (1, 0, 0)));
+        assert_eq!(parse("0.1.x"), between((0, 1, 0), (0, 2, 0)));
+        // Comparisons.
+        assert_eq!(parse("=1.2.3"), Ranges::singleton((1, 2, 3)));
+        assert_eq!(parse("=1.2"), between((1, 2, 0), (1, 3, 0)));
+        assert_eq!(parse(">1.2.3"), Ranges::higher_than((1, 2, 4)));
+        assert_eq!(parse(">1.2"), Ranges::higher_than((1, 3, 0)));
+        assert_eq!(parse(">=1.2"), Ranges::higher_than((1, 2, 0)));
+        assert_eq!(parse("<1.2"), Ranges::strictly_lower_than((1, 2, 0)));
+        assert_eq!(parse("<=1.2"), Ranges::strictly_lower_than((1, 3, 0)));
+        assert_eq!(parse(">= 1.2, < 1.5"), between((1, 2, 0), (1, 5, 0)));
+        // Errors.
+        assert_eq!(
+            parse_requirement(""),
+            Err(RequirementParseError::Empty {
+                requirement: "".to_owned()
+            })
+        );
+        assert_eq!(
+            parse_requirement(">=1.2, 1.a"),
+            Err(RequirementParseError::InvalidComparator {
+                requirement: ">=1.2, 1.a".to_owned(),
+                comparator: "1.a".to_owned(),
+            })
+        );
+        assert!(parse_requirement("1.*.3").is_err());
+        assert!(parse_requirement("1.2.3.4").is_err());
+    }
+}
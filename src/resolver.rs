@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A builder for the options of a resolution.
+//!
+//! Each option of the solver started as its own function, [resolve_with_options],
+//! [resolve_with_limits], [resolve_with_strategy], [resolve_with_preferences] and so on, which
+//! can't be combined. A [Resolver] gathers them on one value, configured with `with_*` methods,
+//! and runs a resolution with all of them. [resolve] is a resolver without any option.
+//!
+//! [resolve]: crate::resolve
+//! [resolve_with_options]: crate::resolve_with_options
+//! [resolve_with_limits]: crate::resolve_with_limits
+//! [resolve_with_strategy]: crate::resolve_with_strategy
+//! [resolve_with_preferences]: crate::resolve_with_preferences
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::delegate::delegate_provider;
+use crate::solver::Solver;
+use crate::strategy::{choose_with_strategy, direct_dependencies};
+use crate::{
+    DependencyProvider, ListVersions, Map, PartialSolutionView, PubGrubError, ResolutionLimits,
+    ResolveOptions, SelectedDependencies, Set, VersionSet, VersionStrategy,
+};
+
+/// How a [Resolver] chooses versions with a [VersionStrategy], requiring [ListVersions].
+type ChooseWithStrategy<DP> = fn(
+    &DP,
+    &VersionStrategy<<DP as DependencyProvider>::P, <DP as DependencyProvider>::V>,
+    &Set<<DP as DependencyProvider>::P>,
+    &<DP as DependencyProvider>::P,
+    &<DP as DependencyProvider>::VS,
+    Option<&dyn PartialSolutionView<<DP as DependencyProvider>::P, <DP as DependencyProvider>::VS>>,
+) -> Result<
+    Option<<DP as DependencyProvider>::V>,
+    <DP as DependencyProvider>::Err,
+>;
+
+/// The options of a resolution, see [resolve](Self::resolve).
+///
+/// ```
+/// # use pubgrub::{Map, OfflineDependencyProvider, Ranges, Resolver, VersionStrategy};
+/// #
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("log", Ranges::full()), ("serde", Ranges::full())]);
+/// for version in 1..=3u32 {
+///     dependency_provider.add_dependencies("log", version, []);
+///     dependency_provider.add_dependencies("serde", version, []);
+/// }
+///
+/// let locked: Map<_, _> = [("log", 2u32)].into_iter().collect();
+/// let solution = Resolver::new(&dependency_provider)
+///     .with_max_steps(1000)
+///     .with_strategy(VersionStrategy::Minimal)
+///     .with_locked(&locked)
+///     .with_constraint("serde", Ranges::higher_than(2u32), "CVE-1".to_string())
+///     .resolve("root", 1u32)
+///     .unwrap();
+/// assert_eq!(solution["log"], 2);
+/// assert_eq!(solution["serde"], 2);
+/// ```
+pub struct Resolver<'a, DP: DependencyProvider> {
+    provider: &'a DP,
+    options: ResolveOptions,
+    limits: ResolutionLimits,
+    #[allow(clippy::type_complexity)]
+    strategy: Option<(VersionStrategy<DP::P, DP::V>, ChooseWithStrategy<DP>)>,
+    locked: Option<&'a Map<DP::P, DP::V>>,
+    constraints: Vec<(DP::P, DP::VS, DP::M)>,
+}
+
+impl<'a, DP: DependencyProvider> Resolver<'a, DP> {
+    /// Resolve with `provider`, without any option.
+    pub fn new(provider: &'a DP) -> Self {
+        Self {
+            provider,
+            options: ResolveOptions::default(),
+            limits: ResolutionLimits::default(),
+            strategy: None,
+            locked: None,
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Use the budget and heuristics of `options`, see [resolve_with_options](crate::resolve_with_options).
+    pub fn with_options(mut self, options: ResolveOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Fail with [PubGrubError::Timeout] after `timeout`, see [ResolveOptions::timeout].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Fail with [PubGrubError::Timeout] after `steps` steps, see [ResolveOptions::max_steps].
+    pub fn with_max_steps(mut self, steps: u32) -> Self {
+        self.options.max_steps = Some(steps);
+        self
+    }
+
+    /// Fail with [PubGrubError::LimitExceeded] beyond `limits`, see
+    /// [resolve_with_limits](crate::resolve_with_limits).
+    pub fn with_limits(mut self, limits: ResolutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Choose the locked version of a package first, when it is allowed, see
+    /// [resolve_with_preferences](crate::resolve_with_preferences).
+    pub fn with_locked(mut self, locked: &'a Map<DP::P, DP::V>) -> Self {
+        self.locked = Some(locked);
+        self
+    }
+
+    /// Only allow the `versions` of `package`, for `reason`, see [State::add_constraint].
+    ///
+    /// [State::add_constraint]: crate::State::add_constraint
+    pub fn with_constraint(mut self, package: DP::P, versions: DP::VS, reason: DP::M) -> Self {
+        self.constraints.push((package, versions, reason));
+        self
+    }
+
+    /// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+    /// with all the options of the resolver.
+    pub fn resolve(
+        &self,
+        package: DP::P,
+        version: impl Into<DP::V>,
+    ) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+        let version = version.into();
+        if self.strategy.is_none() && self.locked.is_none() {
+            return self.solve(self.provider, package, version);
+        }
+        let mut direct = Set::default();
+        if let Some((VersionStrategy::DirectMinimal, _)) = &self.strategy {
+            direct = direct_dependencies(self.provider, &package, &version)?;
+        }
+        let provider = Configured {
+            provider: self.provider,
+            strategy: self
+                .strategy
+                .as_ref()
+                .map(|(strategy, choose)| (strategy, *choose)),
+            direct,
+            locked: self.locked,
+        };
+        self.solve(&provider, package, version)
+            .map_err(PubGrubError::into_provider)
+    }
+
+    /// Run the solver with the options and constraints of the resolver over `provider`, the
+    /// provider of the resolver or a wrapper of it.
+    fn solve<Inner>(
+        &self,
+        provider: &Inner,
+        package: DP::P,
+        version: DP::V,
+    ) -> Result<SelectedDependencies<Inner>, PubGrubError<Inner>>
+    where
+        Inner: DependencyProvider<P = DP::P, V = DP::V, VS = DP::VS, M = DP::M>,
+    {
+        let mut solver = Solver::new(package, version);
+        solver.options = self.options;
+        solver.limits = self.limits;
+        if let Some(packages) = self.options.expected_packages {
+            solver.state.reserve(packages.get() as usize);
+        }
+        for (package, versions, reason) in &self.constraints {
+            solver
+                .state
+                .add_constraint(package.clone(), versions.clone(), reason.clone());
+        }
+        solver.solve(provider)
+    }
+}
+
+impl<DP: ListVersions> Resolver<'_, DP> {
+    /// Choose versions according to `strategy`, see
+    /// [resolve_with_strategy](crate::resolve_with_strategy).
+    pub fn with_strategy(mut self, strategy: VersionStrategy<DP::P, DP::V>) -> Self {
+        self.strategy = Some((strategy, choose_with_strategy::<DP>));
+        self
+    }
+}
+
+/// A [DependencyProvider] choosing versions with the strategy and the locked versions of a
+/// [Resolver].
+struct Configured<'a, DP: DependencyProvider> {
+    provider: &'a DP,
+    #[allow(clippy::type_complexity)]
+    strategy: Option<(&'a VersionStrategy<DP::P, DP::V>, ChooseWithStrategy<DP>)>,
+    direct: Set<DP::P>,
+    locked: Option<&'a Map<DP::P, DP::V>>,
+}
+
+impl<DP: DependencyProvider> Configured<'_, DP> {
+    /// The locked version of `package` if it is allowed and chosen, otherwise the version chosen
+    /// by the strategy or the provider, in the partial `solution` when it is known.
+    #[allow(clippy::type_complexity)]
+    fn choose(
+        &self,
+        package: &DP::P,
+        range: &DP::VS,
+        solution: Option<&dyn PartialSolutionView<DP::P, DP::VS>>,
+    ) -> Result<Option<DP::V>, DP::Err> {
+        let choose = |range: &DP::VS| match (self.strategy, solution) {
+            (Some((strategy, choose)), _) => choose(
+                self.provider,
+                strategy,
+                &self.direct,
+                package,
+                range,
+                solution,
+            ),
+            (None, Some(solution)) => self.provider.choose_version_in(package, range, solution),
+            (None, None) => self.provider.choose_version(package, range),
+        };
+        if let Some(locked) = self.locked.and_then(|locked| locked.get(package)) {
+            if range.contains(locked) {
+                if let Some(version) = choose(&DP::VS::singleton(locked.clone()))? {
+                    return Ok(Some(version));
+                }
+            }
+        }
+        choose(range)
+    }
+}
+
+impl<DP: DependencyProvider> DependencyProvider for Configured<'_, DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, get_dependencies,
+        should_cancel, prefetch, on_conflict, on_backtrack, known_versions, get_dependencies_batch,
+        versions_sharing_dependencies,
+    );
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.choose(package, range, None)
+    }
+
+    fn choose_version_in(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        solution: &dyn PartialSolutionView<Self::P, Self::VS>,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.choose(package, range, Some(solution))
+    }
+}
@@ -0,0 +1,267 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Retry the versions that were temporarily unavailable.
+//!
+//! A download may fail or a registry may time out while the dependencies of a version are
+//! retrieved. Returning [Dependencies::Unavailable] excludes the version until the end of the
+//! resolution, while the failure may be gone a few seconds later. [resolve_with_retries] lets
+//! the provider mark such reasons as temporary, and resolves again when a version was excluded
+//! for one of them, so that the provider is asked about it one more time.
+//!
+//! Errors of the provider abort the resolution instead. A [TransientErrors] provider tells
+//! which of its errors may go away, and [resolve_with_error_retries] makes the same call again
+//! after them, within a budget of retries and after a backoff chosen by the caller.
+
+use alloc::vec::Vec;
+use core::cell::Cell;
+
+use log::info;
+
+use crate::delegate::delegate_provider;
+use crate::solver::{Solver, SolverHooks};
+use crate::{
+    resolve, Dependencies, DependencyProvider, PartialSolutionView, PubGrubError,
+    SelectedDependencies,
+};
+
+/// Keeps the dependencies to replay in the next attempt, and counts the temporary failures.
+struct Retrying<'a, DP: DependencyProvider> {
+    is_temporary: &'a dyn Fn(&DP::M) -> bool,
+    #[allow(clippy::type_complexity)]
+    dependencies: Vec<(DP::P, DP::V, Dependencies<DP::P, DP::VS, DP::M>)>,
+    temporary_failures: usize,
+}
+
+impl<DP: DependencyProvider> SolverHooks<DP> for Retrying<'_, DP> {
+    fn on_dependencies(
+        &mut self,
+        _solver: &Solver<DP>,
+        package: &DP::P,
+        version: &DP::V,
+        dependencies: &Dependencies<DP::P, DP::VS, DP::M>,
+    ) {
+        match dependencies {
+            Dependencies::Unavailable(reason) if (self.is_temporary)(reason) => {
+                self.temporary_failures += 1;
+            }
+            _ => {
+                self.dependencies
+                    .push((package.clone(), version.clone(), dependencies.clone()));
+            }
+        }
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// retrying up to `retries` times the versions that were unavailable for a temporary reason.
+///
+/// Within an attempt, a version whose dependencies are [Dependencies::Unavailable] is excluded
+/// like any other. When the attempt is over, whether it found a solution or not, and some
+/// version was excluded for a reason matching `is_temporary`, the resolution starts again: the
+/// dependencies retrieved so far are reused, only the temporarily unavailable versions are
+/// retrieved again from the provider, when they are chosen. The result of the last attempt is
+/// returned, which may still exclude versions if they kept failing.
+///
+/// ```
+/// # use std::cell::Cell;
+/// # use std::cmp::Reverse;
+/// # use pubgrub::{
+/// #     resolve_with_retries, Dependencies, DependencyProvider, OfflineDependencyProvider,
+/// #     PackageResolutionStatistics, Ranges,
+/// # };
+/// # use std::convert::Infallible;
+/// /// Fails to download `foo 2` the first time.
+/// struct Flaky {
+///     registry: OfflineDependencyProvider<&'static str, Ranges<u32>>,
+///     failed: Cell<bool>,
+/// }
+///
+/// impl DependencyProvider for Flaky {
+///     type P = &'static str;
+///     type V = u32;
+///     type VS = Ranges<u32>;
+///     type M = String;
+///     type Priority = (u32, Reverse<usize>);
+///     type Err = Infallible;
+///
+///     fn prioritize(
+///         &self,
+///         p: &Self::P,
+///         r: &Self::VS,
+///         s: &PackageResolutionStatistics,
+///     ) -> Self::Priority {
+///         self.registry.prioritize(p, r, s)
+///     }
+///
+///     fn choose_version(&self, p: &Self::P, r: &Self::VS) -> Result<Option<u32>, Infallible> {
+///         self.registry.choose_version(p, r)
+///     }
+///
+///     fn get_dependencies(
+///         &self,
+///         p: &Self::P,
+///         v: &u32,
+///     ) -> Result<Dependencies<Self::P, Self::VS, String>, Infallible> {
+///         if (*p, *v) == ("foo", 2) && !self.failed.replace(true) {
+///             return Ok(Dependencies::Unavailable("download failed".to_string()));
+///         }
+///         self.registry.get_dependencies(p, v)
+///     }
+/// }
+///
+/// let mut registry = OfflineDependencyProvider::new();
+/// registry.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+/// registry.add_dependencies("foo", 1u32, []);
+/// registry.add_dependencies("foo", 2u32, []);
+/// let provider = Flaky { registry, failed: Cell::new(false) };
+///
+/// let is_temporary = |reason: &String| reason == "download failed";
+/// let solution = resolve_with_retries(&provider, "root", 1u32, 1, is_temporary).unwrap();
+/// assert_eq!(solution["foo"], 2);
+/// ```
+pub fn resolve_with_retries<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    retries: u32,
+    is_temporary: impl Fn(&DP::M) -> bool,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let version = version.into();
+    let mut hooks = Retrying::<DP> {
+        is_temporary: &is_temporary,
+        dependencies: Vec::new(),
+        temporary_failures: 0,
+    };
+    let mut attempt = 0;
+    loop {
+        let mut solver = Solver::new(package.clone(), version.clone());
+        solver
+            .replay(&hooks.dependencies, &[])
+            .map_err(PubGrubError::NoSolution)?;
+        hooks.temporary_failures = 0;
+        let result = solver.solve_with_hooks(dependency_provider, &mut hooks);
+        let retry = matches!(result, Ok(_) | Err(PubGrubError::NoSolution(_)))
+            && hooks.temporary_failures > 0
+            && attempt < retries;
+        if !retry {
+            return result;
+        }
+        attempt += 1;
+        info!(
+            "retrying {} temporarily unavailable versions",
+            hooks.temporary_failures
+        );
+    }
+}
+
+/// A [DependencyProvider] whose errors may be transient, like a timeout or a dropped
+/// connection, which are worth retrying, unlike a malformed index.
+pub trait TransientErrors: DependencyProvider {
+    /// Whether making the same call again may succeed.
+    fn is_transient(&self, err: &Self::Err) -> bool;
+}
+
+/// A [DependencyProvider] forwarding to another one, retrying the calls that failed with a
+/// [transient](TransientErrors::is_transient) error.
+///
+/// The retries of [choose_version](DependencyProvider::choose_version) and
+/// [get_dependencies](DependencyProvider::get_dependencies) share the same budget, for the whole
+/// life of the provider. Before each retry, `backoff` is called with the number of the attempt
+/// of that call, starting at 1, and the error, for example to sleep longer and longer. Once the
+/// budget is spent, errors abort the resolution like fatal ones.
+pub struct WithRetries<'a, DP: TransientErrors, B> {
+    provider: &'a DP,
+    budget: Cell<u32>,
+    backoff: B,
+}
+
+impl<'a, DP: TransientErrors, B: Fn(u32, &DP::Err)> WithRetries<'a, DP, B> {
+    /// Retry the transient errors of `provider`, at most `budget` times in total.
+    pub fn new(provider: &'a DP, budget: u32, backoff: B) -> Self {
+        Self {
+            provider,
+            budget: Cell::new(budget),
+            backoff,
+        }
+    }
+
+    /// The number of retries left.
+    pub fn remaining(&self) -> u32 {
+        self.budget.get()
+    }
+
+    fn retry<T>(&self, mut call: impl FnMut() -> Result<T, DP::Err>) -> Result<T, DP::Err> {
+        let mut attempt = 0;
+        loop {
+            match call() {
+                Err(err) if self.budget.get() > 0 && self.provider.is_transient(&err) => {
+                    self.budget.set(self.budget.get() - 1);
+                    attempt += 1;
+                    info!("retrying after a transient error: {err}");
+                    (self.backoff)(attempt, &err);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<DP: TransientErrors, B: Fn(u32, &DP::Err)> DependencyProvider for WithRetries<'_, DP, B> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, should_cancel, prefetch,
+        on_conflict, on_backtrack, known_versions, versions_sharing_dependencies,
+    );
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.retry(|| self.provider.choose_version(package, range))
+    }
+
+    fn choose_version_in(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        solution: &dyn PartialSolutionView<Self::P, Self::VS>,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.retry(|| self.provider.choose_version_in(package, range, solution))
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        self.retry(|| self.provider.get_dependencies(package, version))
+    }
+
+    fn get_dependencies_batch(
+        &self,
+        package: &Self::P,
+        versions: &[Self::V],
+    ) -> Result<Vec<Dependencies<Self::P, Self::VS, Self::M>>, Self::Err> {
+        self.retry(|| self.provider.get_dependencies_batch(package, versions))
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// retrying the calls to the provider that failed with a transient error, see [WithRetries].
+pub fn resolve_with_error_retries<DP: TransientErrors>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    budget: u32,
+    backoff: impl Fn(u32, &DP::Err),
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let provider = WithRetries::new(dependency_provider, budget, backoff);
+    resolve(&provider, package, version).map_err(PubGrubError::into_provider)
+}
@@ -0,0 +1,389 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! [RPM](https://rpm-software-management.github.io/rpm/manual/dependencies.html) versions and
+//! version requirements.
+//!
+//! [RpmVersion] implements the ordering of `rpmvercmp`, with epochs, versions and releases, and
+//! [parse_rpm_requirement] turns the version part of a requirement like `>= 1.2-3` into a
+//! [`Ranges<RpmVersion>`](Ranges), which is a [VersionSet].
+//!
+//! [VersionSet]: crate::VersionSet
+
+use alloc::string::{String, ToString};
+use core::cmp::Ordering;
+use core::fmt::{self, Display};
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
+
+use thiserror::Error;
+
+use crate::Ranges;
+
+/// Error parsing an [RpmVersion] or a requirement.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RpmParseError {
+    /// The version is not a valid RPM version.
+    #[error("'{version}' is not a valid RPM version")]
+    InvalidVersion {
+        /// Version that was being parsed.
+        version: String,
+    },
+    /// The requirement doesn't start with one of `<`, `<=`, `=`, `>=` and `>`, or its version
+    /// is invalid.
+    #[error("cannot parse the requirement '{requirement}'")]
+    InvalidRequirement {
+        /// Requirement that was being parsed.
+        requirement: String,
+    },
+}
+
+/// The release of a version, with a marker greater than all the releases.
+#[derive(Debug, Clone)]
+enum Release {
+    None,
+    Some(String),
+    /// Only used as a range bound, greater than all the releases of a version.
+    Max,
+}
+
+/// A version of an RPM package, `[epoch:]version[-release]`.
+///
+/// Versions are compared like `rpmvercmp`: the epochs as numbers, then the versions and the
+/// releases segment by segment, ignoring the separators. Numeric segments are compared as
+/// numbers and are newer than alphabetic ones, `~` sorts before anything, even the end, and `^`
+/// sorts after the end but before any other segment. A missing epoch is `0`, and a version
+/// without release is older than all its releases.
+///
+/// ```
+/// # use pubgrub::RpmVersion;
+/// let version = |s: &str| s.parse::<RpmVersion>().unwrap();
+/// assert!(version("1.0~rc1-1") < version("1.0-1"));
+/// assert!(version("1.0-1") < version("1.0^20240101-1"));
+/// assert!(version("1.0a-1") < version("1.0.1-1"));
+/// assert!(version("1:0.5-1") > version("9.9-1"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RpmVersion {
+    epoch: u64,
+    version: String,
+    release: Release,
+}
+
+impl RpmVersion {
+    /// The epoch, `0` when not specified.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The version, `1.2` in `1:1.2-3`.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// The release, `3` in `1:1.2-3`, if any.
+    pub fn release(&self) -> Option<&str> {
+        match &self.release {
+            Release::Some(release) => Some(release),
+            Release::None | Release::Max => None,
+        }
+    }
+
+    /// The version greater than all the releases of this version.
+    fn with_max_release(&self) -> Self {
+        Self {
+            release: Release::Max,
+            ..self.clone()
+        }
+    }
+}
+
+/// A segment of a version or a release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Segment<'a> {
+    Tilde,
+    Caret,
+    /// The digits, without leading zeros.
+    Number(&'a str),
+    Alpha(&'a str),
+}
+
+/// The segments of a version or a release, without the separators.
+fn segments(s: &str) -> impl Iterator<Item = Segment<'_>> {
+    let mut rest = s.trim_start_matches(|c: char| !is_segment_char(c));
+    core::iter::from_fn(move || {
+        let segment = if let Some(after) = rest.strip_prefix('~') {
+            rest = after;
+            Segment::Tilde
+        } else if let Some(after) = rest.strip_prefix('^') {
+            rest = after;
+            Segment::Caret
+        } else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let (number, after) = rest.split_at(end);
+            rest = after;
+            Segment::Number(number.trim_start_matches('0'))
+        } else if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let (alpha, after) = rest.split_at(end);
+            rest = after;
+            Segment::Alpha(alpha)
+        } else {
+            return None;
+        };
+        rest = rest.trim_start_matches(|c: char| !is_segment_char(c));
+        Some(segment)
+    })
+}
+
+fn is_segment_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '~' || c == '^'
+}
+
+/// Compare versions or releases like `rpmvercmp`.
+fn compare(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (segments(a), segments(b));
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(Segment::Tilde), Some(Segment::Tilde)) => Ordering::Equal,
+            (Some(Segment::Tilde), _) => Ordering::Less,
+            (_, Some(Segment::Tilde)) => Ordering::Greater,
+            (Some(Segment::Caret), Some(Segment::Caret)) => Ordering::Equal,
+            (None, Some(Segment::Caret)) | (Some(Segment::Caret), Some(_)) => Ordering::Less,
+            (Some(Segment::Caret), None) | (Some(_), Some(Segment::Caret)) => Ordering::Greater,
+            // The version with more segments is newer.
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(Segment::Number(a)), Some(Segment::Number(b))) => (a.len(), a).cmp(&(b.len(), b)),
+            (Some(Segment::Number(_)), Some(Segment::Alpha(_))) => Ordering::Greater,
+            (Some(Segment::Alpha(_)), Some(Segment::Number(_))) => Ordering::Less,
+            (Some(Segment::Alpha(a)), Some(Segment::Alpha(b))) => a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl PartialEq for RpmVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RpmVersion {}
+
+impl PartialOrd for RpmVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RpmVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch
+            .cmp(&other.epoch)
+            .then_with(|| compare(&self.version, &other.version))
+            .then_with(|| match (&self.release, &other.release) {
+                (Release::Some(a), Release::Some(b)) => compare(a, b),
+                (Release::None, Release::None) | (Release::Max, Release::Max) => Ordering::Equal,
+                (Release::None, _) | (_, Release::Max) => Ordering::Less,
+                (_, Release::None) | (Release::Max, _) => Ordering::Greater,
+            })
+    }
+}
+
+impl Hash for RpmVersion {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.epoch.hash(state);
+        segments(&self.version).for_each(|segment| segment.hash(state));
+        match &self.release {
+            Release::None => 0u8.hash(state),
+            Release::Some(release) => {
+                1u8.hash(state);
+                segments(release).for_each(|segment| segment.hash(state));
+            }
+            Release::Max => 2u8.hash(state),
+        }
+    }
+}
+
+impl Display for RpmVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}:", self.epoch)?;
+        }
+        write!(f, "{}", self.version)?;
+        match &self.release {
+            Release::None => Ok(()),
+            Release::Some(release) => write!(f, "-{}", release),
+            Release::Max => write!(f, "-<max>"),
+        }
+    }
+}
+
+impl FromStr for RpmVersion {
+    type Err = RpmParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || RpmParseError::InvalidVersion {
+            version: s.to_string(),
+        };
+        let version = s.trim();
+        let (epoch, rest) = match version.split_once(':') {
+            Some((epoch, rest)) => (epoch.parse().map_err(|_| invalid())?, rest),
+            None => (0, version),
+        };
+        let (version, release) = match rest.split_once('-') {
+            Some((version, release)) => (version, Some(release)),
+            None => (rest, None),
+        };
+        let valid = |part: &str| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "._+~^".contains(c))
+        };
+        if !valid(version) || !release.is_none_or(valid) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            epoch,
+            version: version.to_string(),
+            release: match release {
+                Some(release) => Release::Some(release.to_string()),
+                None => Release::None,
+            },
+        })
+    }
+}
+
+/// Parse the version part of an RPM requirement, like `>= 1.2-3` in `Requires: foo >= 1.2-3`,
+/// into the set of matching versions.
+///
+/// The operators are `<`, `<=`, `=`, `>=` and `>`, and their spellings `==`, `=<` and `=>`. As
+/// in RPM, a requirement without release matches all the releases of its version, so `= 1.2`
+/// matches `1.2-3`, and an empty requirement matches any version.
+///
+/// ```
+/// # use pubgrub::{parse_rpm_requirement, RpmVersion};
+/// let requirement = parse_rpm_requirement("<= 1.2").unwrap();
+/// let version = |s: &str| s.parse::<RpmVersion>().unwrap();
+/// assert!(requirement.contains(&version("1.2-3.fc40")));
+/// assert!(!requirement.contains(&version("1.2.1-1")));
+/// ```
+pub fn parse_rpm_requirement(requirement: &str) -> Result<Ranges<RpmVersion>, RpmParseError> {
+    let invalid = || RpmParseError::InvalidRequirement {
+        requirement: requirement.to_string(),
+    };
+    let trimmed = requirement.trim();
+    if trimmed.is_empty() {
+        return Ok(Ranges::full());
+    }
+    // Longest operators first so that `<=` is not parsed as `<`.
+    let operators = ["<=", "=<", ">=", "=>", "==", "<", ">", "="];
+    let (operator, version) = operators
+        .iter()
+        .find_map(|op| trimmed.strip_prefix(op).map(|rest| (*op, rest)))
+        .ok_or_else(invalid)?;
+    let version: RpmVersion = version.parse().map_err(|_| invalid())?;
+    // Without release, the bounds are around all the releases of the version.
+    let (min, max) = match version.release {
+        Release::None => (version.clone(), version.with_max_release()),
+        _ => (version.clone(), version),
+    };
+    Ok(match operator {
+        "<" => Ranges::strictly_lower_than(min),
+        "<=" | "=<" => Ranges::lower_than(max),
+        "=" | "==" if min == max => Ranges::singleton(min),
+        "=" | "==" => Ranges::between(min, max),
+        ">=" | "=>" => Ranges::higher_than(min),
+        ">" => Ranges::strictly_higher_than(max),
+        _ => unreachable!(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> RpmVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for (input, normalized) in [
+            ("1.0", "1.0"),
+            ("0:1.0-1", "1.0-1"),
+            ("2:1.0-1.fc40", "2:1.0-1.fc40"),
+            ("1.0~rc1^git1-0.1", "1.0~rc1^git1-0.1"),
+        ] {
+            assert_eq!(version(input).to_string(), normalized, "{input}");
+        }
+        for invalid in ["", "a:1.0", "1.0-", "-1", "1.0-1-2", "1.0 1"] {
+            assert!(invalid.parse::<RpmVersion>().is_err(), "{invalid}");
+        }
+        assert_eq!(version("1.0-1.fc40").release(), Some("1.fc40"));
+    }
+
+    #[test]
+    fn ordering() {
+        // From the rpm test suite.
+        let ordered = [
+            "1.0~~", "1.0~rc1", "1.0", "1.0^", "1.0^git1", "1.0a", "1.0.1", "1.01.2", "1.2",
+            "1.10", "1:0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{:?}", pair);
+        }
+        assert!(version("1.0") < version("1.0-1"));
+        assert!(version("1.0-1") < version("1.0-1.fc40"));
+        assert!(version("1.0-1.el9") < version("1.0-2.el8"));
+        for (a, b) in [("1.0", "1_0"), ("0:1.0-1", "1.0-1"), ("1.001", "1.1")] {
+            assert_eq!(version(a), version(b));
+            let mut a_hasher = rustc_hash::FxHasher::default();
+            let mut b_hasher = rustc_hash::FxHasher::default();
+            version(a).hash(&mut a_hasher);
+            version(b).hash(&mut b_hasher);
+            assert_eq!(a_hasher.finish(), b_hasher.finish(), "{a} {b}");
+        }
+    }
+
+    #[test]
+    fn requirements() {
+        let matches = |requirement: &str, v: &str| {
+            parse_rpm_requirement(requirement)
+                .unwrap()
+                .contains(&version(v))
+        };
+        assert!(matches("< 1.0", "1.0~rc1-1"));
+        assert!(!matches("< 1.0", "1.0-1"));
+        assert!(matches("<= 1.0", "1.0-5"));
+        assert!(!matches("<= 1.0-4", "1.0-5"));
+        assert!(matches("= 1.0", "1.0-5"));
+        assert!(matches("== 1.0-5", "0:1.0-5"));
+        assert!(!matches("= 1.0-5", "1.0-6"));
+        assert!(matches(">= 1.0", "1.0-1"));
+        assert!(!matches("> 1.0", "1.0-1"));
+        assert!(matches("> 1.0-1", "1.0-2"));
+        assert!(matches(">1.0", "1.0.1-1"));
+        assert!(matches("", "1.0-1"));
+
+        assert_eq!(
+            parse_rpm_requirement("~ 1.0"),
+            Err(RpmParseError::InvalidRequirement {
+                requirement: "~ 1.0".to_owned(),
+            })
+        );
+        assert!(parse_rpm_requirement(">= 1.0-").is_err());
+        assert_eq!(
+            parse_rpm_requirement("<= 1.0").unwrap().to_string(),
+            "<=1.0-<max>"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Soft requirements: versions a dependency prefers without requiring them.
+//!
+//! In Maven and Gradle, `<version>1.2</version>` is a recommendation: the version is used when
+//! nothing else constrains the package, and the version declared nearest to the root wins when
+//! several packages recommend different ones. A [SoftDependencyProvider] declares such preferred
+//! versions next to the dependencies, which keep the versions they accept as hard requirements.
+//! [resolve_with_soft_requirements] chooses the preferred version of a package when its range
+//! allows it, decides these packages first so that their preference is tried before other
+//! decisions rule it out, and reports which preferences were overridden instead of failing.
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::delegate::delegate_provider;
+use crate::{
+    resolve, Dependencies, DependencyProvider, Map, Package, PackageResolutionStatistics,
+    PartialSolutionView, PubGrubError, Set, VersionSet,
+};
+
+/// A [DependencyProvider] whose versions can also prefer some versions of their dependencies.
+pub trait SoftDependencyProvider: DependencyProvider {
+    /// The preferred versions of the dependencies of `version` of `package`.
+    ///
+    /// A preferred version doesn't add a dependency: the package must also be a dependency,
+    /// whose range limits the versions that can be selected.
+    #[allow(clippy::type_complexity)]
+    fn get_soft_requirements(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Vec<(Self::P, Self::V)>, Self::Err>;
+}
+
+/// The preferred version of a package winning so far.
+#[derive(Debug, Clone)]
+struct Preferred<P, V> {
+    version: V,
+    /// The distance of the package preferring the version from the root.
+    depth: u32,
+    requested_by: (P, V),
+}
+
+struct SoftState<P, V> {
+    /// The shortest distance from the root of the packages seen so far.
+    depths: Map<P, u32>,
+    preferred: Map<P, Preferred<P, V>>,
+}
+
+/// A [DependencyProvider] choosing the preferred versions of a [SoftDependencyProvider] first.
+///
+/// When several versions are preferred for the same package, the one declared nearest to the
+/// root wins, then the first one retrieved.
+pub struct WithSoftRequirements<'a, DP: SoftDependencyProvider> {
+    provider: &'a DP,
+    state: RefCell<SoftState<DP::P, DP::V>>,
+}
+
+impl<'a, DP: SoftDependencyProvider> WithSoftRequirements<'a, DP> {
+    /// Prefer the versions of the soft requirements of `provider`.
+    pub fn new(provider: &'a DP) -> Self {
+        Self {
+            provider,
+            state: RefCell::new(SoftState {
+                depths: Map::default(),
+                preferred: Map::default(),
+            }),
+        }
+    }
+
+    fn preferred(&self, package: &DP::P, range: &DP::VS) -> Option<DP::V> {
+        let state = self.state.borrow();
+        let preferred = &state.preferred.get(package)?.version;
+        range.contains(preferred).then(|| preferred.clone())
+    }
+}
+
+impl<DP: SoftDependencyProvider> DependencyProvider for WithSoftRequirements<'_, DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    /// Packages with a preferred version in their range first.
+    type Priority = (bool, DP::Priority);
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => reprioritize_on_conflict, tie_break_key, should_cancel, prefetch, on_conflict,
+        on_backtrack, known_versions,
+    );
+
+    fn prioritize(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        package_conflicts_counts: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        (
+            self.preferred(package, range).is_some(),
+            self.provider
+                .prioritize(package, range, package_conflicts_counts),
+        )
+    }
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        if let Some(preferred) = self.preferred(package, range) {
+            let preferred_range = Self::VS::singleton(preferred);
+            if let Some(version) = self.provider.choose_version(package, &preferred_range)? {
+                return Ok(Some(version));
+            }
+        }
+        self.provider.choose_version(package, range)
+    }
+
+    fn choose_version_in(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        solution: &dyn PartialSolutionView<Self::P, Self::VS>,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        if let Some(preferred) = self.preferred(package, range) {
+            let preferred_range = Self::VS::singleton(preferred);
+            if let Some(version) =
+                self.provider
+                    .choose_version_in(package, &preferred_range, solution)?
+            {
+                return Ok(Some(version));
+            }
+        }
+        self.provider.choose_version_in(package, range, solution)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        let dependencies = self.provider.get_dependencies(package, version)?;
+        let Dependencies::Available(constraints) = &dependencies else {
+            return Ok(dependencies);
+        };
+        let soft_requirements = self.provider.get_soft_requirements(package, version)?;
+
+        let mut state = self.state.borrow_mut();
+        // The first package whose dependencies are retrieved is the root.
+        let depth = *state.depths.entry(package.clone()).or_insert(0);
+        for dependency in constraints.keys() {
+            let dependency_depth = state.depths.entry(dependency.clone()).or_insert(depth + 1);
+            *dependency_depth = (*dependency_depth).min(depth + 1);
+        }
+        for (dependency, preferred) in soft_requirements {
+            if !constraints.contains_key(&dependency) {
+                continue;
+            }
+            let nearer = state
+                .preferred
+                .get(&dependency)
+                .is_none_or(|current| depth < current.depth);
+            if nearer {
+                state.preferred.insert(
+                    dependency,
+                    Preferred {
+                        version: preferred,
+                        depth,
+                        requested_by: (package.clone(), version.clone()),
+                    },
+                );
+            }
+        }
+        Ok(dependencies)
+    }
+}
+
+/// A solution found by [resolve_with_soft_requirements], with the soft requirements it follows.
+#[derive(Debug, Clone)]
+pub struct SoftSolution<P: Package, V> {
+    /// The selected packages and versions.
+    pub solution: Map<P, V>,
+    /// Packages selected at their preferred version.
+    pub honored: Set<P>,
+    /// Packages selected at another version than their preferred one, with the preferred
+    /// version and the package version preferring it.
+    pub overridden: Map<P, (V, (P, V))>,
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// selecting the preferred versions of the soft requirements whenever they are allowed.
+///
+/// Only the soft requirements of the selected versions are reported. A preferred version is
+/// overridden when the hard requirements don't allow it, and never causes a failure.
+///
+/// ```
+/// # use std::convert::Infallible;
+/// # use pubgrub::{
+/// #     resolve_with_soft_requirements, Dependencies, DependencyProvider,
+/// #     OfflineDependencyProvider, PackageResolutionStatistics, Ranges, SoftDependencyProvider,
+/// # };
+/// #
+/// struct Maven(OfflineDependencyProvider<&'static str, Ranges<u32>>);
+///
+/// impl DependencyProvider for Maven {
+///     // Forward to the offline provider.
+/// #   type P = &'static str;
+/// #   type V = u32;
+/// #   type VS = Ranges<u32>;
+/// #   type M = String;
+/// #   type Priority = <OfflineDependencyProvider<&'static str, Ranges<u32>> as DependencyProvider>::Priority;
+/// #   type Err = Infallible;
+/// #
+/// #   fn prioritize(&self, p: &&'static str, r: &Ranges<u32>, s: &PackageResolutionStatistics) -> Self::Priority {
+/// #       self.0.prioritize(p, r, s)
+/// #   }
+/// #
+/// #   fn choose_version(&self, p: &&'static str, r: &Ranges<u32>) -> Result<Option<u32>, Infallible> {
+/// #       self.0.choose_version(p, r)
+/// #   }
+/// #
+/// #   fn get_dependencies(&self, p: &&'static str, v: &u32) -> Result<Dependencies<&'static str, Ranges<u32>, String>, Infallible> {
+/// #       self.0.get_dependencies(p, v)
+/// #   }
+/// }
+///
+/// impl SoftDependencyProvider for Maven {
+///     fn get_soft_requirements(&self, package: &&'static str, _version: &u32) -> Result<Vec<(&'static str, u32)>, Infallible> {
+///         Ok(match *package {
+///             // The root recommends log 1, nearer than the recommendation of app.
+///             "root" => vec![("log", 1)],
+///             "app" => vec![("log", 2)],
+///             _ => vec![],
+///         })
+///     }
+/// }
+///
+/// let mut dependency_provider = OfflineDependencyProvider::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("app", Ranges::full()), ("log", Ranges::full())]);
+/// dependency_provider.add_dependencies("app", 1u32, [("log", Ranges::full())]);
+/// dependency_provider.add_dependencies("log", 1u32, []);
+/// dependency_provider.add_dependencies("log", 2u32, []);
+/// dependency_provider.add_dependencies("log", 3u32, []);
+///
+/// let soft = resolve_with_soft_requirements(&Maven(dependency_provider), "root", 1u32).unwrap();
+/// assert_eq!(soft.solution["log"], 1);
+/// assert!(soft.honored.contains("log"));
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn resolve_with_soft_requirements<DP: SoftDependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<SoftSolution<DP::P, DP::V>, PubGrubError<DP>> {
+    let provider = WithSoftRequirements::new(dependency_provider);
+    let solution = resolve(&provider, package, version).map_err(PubGrubError::into_provider)?;
+
+    let mut honored = Set::default();
+    let mut overridden = Map::default();
+    for (package, preferred) in provider.state.into_inner().preferred {
+        let (requester, requester_version) = &preferred.requested_by;
+        if solution.get(requester) != Some(requester_version) {
+            continue;
+        }
+        match solution.get(&package) {
+            Some(selected) if *selected == preferred.version => {
+                honored.insert(package);
+            }
+            Some(_) => {
+                overridden.insert(package, (preferred.version, preferred.requested_by));
+            }
+            None => {}
+        }
+    }
+    Ok(SoftSolution {
+        solution,
+        honored,
+        overridden,
+    })
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Cache the solutions of whole resolutions.
+//!
+//! Build tools resolve the same manifest over and over, on every build, against a registry that
+//! rarely changed in between. A [SolutionCache] remembers the solution found for the
+//! requirements of a root package against a given state of the provider, identified by its
+//! [fingerprint](Fingerprint::fingerprint), and returns it without running the solver again
+//! as long as neither changed.
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::{resolve, Dependencies, DependencyProvider, Map, PubGrubError, SelectedDependencies};
+
+/// A [DependencyProvider] that can tell whether its answers changed.
+pub trait Fingerprint: DependencyProvider {
+    /// Identify the answers of the provider: two calls returning the same value promise the same
+    /// versions and dependencies for all the packages, for example the hash of a registry index
+    /// snapshot or the revision of a lock file.
+    fn fingerprint(&self) -> u64;
+}
+
+/// A cached solution, with what it was resolved from.
+type Entry<DP> = (
+    <DP as DependencyProvider>::V,
+    Dependencies<
+        <DP as DependencyProvider>::P,
+        <DP as DependencyProvider>::VS,
+        <DP as DependencyProvider>::M,
+    >,
+    SelectedDependencies<DP>,
+);
+
+/// The solutions of previous resolutions, keyed by the root package and the
+/// [fingerprint](Fingerprint::fingerprint) of the provider.
+///
+/// A solution is reused when the root version and its dependencies are equal to those it was
+/// resolved for, and the provider has the same fingerprint. Failed resolutions are not cached.
+///
+/// ```
+/// # use pubgrub::{Fingerprint, OfflineDependencyProvider, Ranges, SolutionCache};
+/// # use pubgrub::{Dependencies, DependencyProvider, PackageResolutionStatistics};
+/// # use std::convert::Infallible;
+/// #
+/// struct Registry {
+///     index: OfflineDependencyProvider<&'static str, Ranges<u32>>,
+///     revision: u64,
+/// }
+///
+/// impl DependencyProvider for Registry {
+///     // Forward to the index.
+/// #   type P = &'static str;
+/// #   type V = u32;
+/// #   type VS = Ranges<u32>;
+/// #   type M = String;
+/// #   type Priority = <OfflineDependencyProvider<&'static str, Ranges<u32>> as DependencyProvider>::Priority;
+/// #   type Err = Infallible;
+/// #
+/// #   fn prioritize(&self, p: &&'static str, r: &Ranges<u32>, s: &PackageResolutionStatistics) -> Self::Priority {
+/// #       self.index.prioritize(p, r, s)
+/// #   }
+/// #
+/// #   fn choose_version(&self, p: &&'static str, r: &Ranges<u32>) -> Result<Option<u32>, Infallible> {
+/// #       self.index.choose_version(p, r)
+/// #   }
+/// #
+/// #   fn get_dependencies(&self, p: &&'static str, v: &u32) -> Result<Dependencies<&'static str, Ranges<u32>, String>, Infallible> {
+/// #       self.index.get_dependencies(p, v)
+/// #   }
+/// }
+///
+/// impl Fingerprint for Registry {
+///     fn fingerprint(&self) -> u64 {
+///         self.revision
+///     }
+/// }
+///
+/// let mut index = OfflineDependencyProvider::new();
+/// index.add_dependencies("app", 1u32, [("log", Ranges::full())]);
+/// index.add_dependencies("log", 1u32, []);
+/// let mut registry = Registry { index, revision: 1 };
+///
+/// let cache = SolutionCache::new();
+/// assert_eq!(cache.resolve(&registry, "app", 1u32).unwrap()["log"], 1);
+/// assert_eq!(cache.len(), 1);
+///
+/// // A new version is published: the registry has a new fingerprint.
+/// registry.index.add_dependencies("log", 2u32, []);
+/// registry.revision = 2;
+/// assert_eq!(cache.resolve(&registry, "app", 1u32).unwrap()["log"], 2);
+/// assert_eq!(cache.len(), 2);
+/// ```
+pub struct SolutionCache<DP: DependencyProvider> {
+    #[allow(clippy::type_complexity)]
+    solutions: RefCell<Map<(DP::P, u64), Vec<Entry<DP>>>>,
+}
+
+impl<DP: Fingerprint> SolutionCache<DP> {
+    /// An empty cache.
+    pub fn new() -> Self {
+        Self {
+            solutions: RefCell::new(Map::default()),
+        }
+    }
+
+    /// The number of cached solutions.
+    pub fn len(&self) -> usize {
+        self.solutions.borrow().values().map(Vec::len).sum()
+    }
+
+    /// Whether no solution is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget all the solutions.
+    pub fn clear(&self) {
+        self.solutions.borrow_mut().clear();
+    }
+
+    /// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+    /// reusing the solution of a previous call with the same requirements and fingerprint.
+    ///
+    /// The dependencies of the root version are retrieved to compare the requirements, the
+    /// resolution only runs when no solution matches.
+    pub fn resolve(
+        &self,
+        dependency_provider: &DP,
+        package: DP::P,
+        version: impl Into<DP::V>,
+    ) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+        let version = version.into();
+        let requirements = dependency_provider
+            .get_dependencies(&package, &version)
+            .map_err(|err| PubGrubError::ErrorRetrievingDependencies {
+                package: package.clone(),
+                version: version.clone(),
+                source: err,
+                required_by: Vec::new(),
+                decided: Vec::new(),
+            })?;
+        let key = (package, dependency_provider.fingerprint());
+        if let Some(entries) = self.solutions.borrow().get(&key) {
+            let cached = entries
+                .iter()
+                .find(|(v, dependencies, _)| *v == version && *dependencies == requirements);
+            if let Some((_, _, solution)) = cached {
+                return Ok(solution.clone());
+            }
+        }
+        let solution = resolve(dependency_provider, key.0.clone(), version.clone())?;
+        self.solutions.borrow_mut().entry(key).or_default().push((
+            version,
+            requirements,
+            solution.clone(),
+        ));
+        Ok(solution)
+    }
+}
+
+impl<DP: Fingerprint> Default for SolutionCache<DP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
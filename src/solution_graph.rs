@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Render the dependency graph of a solution with [Graphviz](https://graphviz.org/).
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt::{self, Display, Write};
+
+use crate::report::dot_quoted;
+use crate::{Package, ResolvedGraph};
+
+impl<P: Package, V: Display, VS: Display> ResolvedGraph<P, V, VS> {
+    /// The graph in the DOT language of Graphviz.
+    ///
+    /// Nodes are the selected packages, labelled with their version, and edges go from a package
+    /// to its dependencies, labelled with the required versions.
+    ///
+    /// ```
+    /// # use pubgrub::{resolve_graph, OfflineDependencyProvider, Ranges};
+    /// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+    /// dependency_provider.add_dependencies("root", 1u32, [("menu", Ranges::higher_than(2u32))]);
+    /// dependency_provider.add_dependencies("menu", 2u32, []);
+    ///
+    /// let graph = resolve_graph(&dependency_provider, "root", 1u32).unwrap();
+    /// assert_eq!(
+    ///     graph.to_dot(),
+    ///     r#"digraph {
+    ///     0 [label="menu 2"];
+    ///     1 [label="root 1"];
+    ///     1 -> 0 [label=">=2"];
+    /// }
+    /// "#
+    /// );
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot)
+            .expect("writing to a string can't fail");
+        dot
+    }
+
+    fn write_dot(&self, dot: &mut impl Write) -> fmt::Result {
+        let nodes = self.topological_order();
+        let index = |package: &P| nodes.iter().position(|(p, _)| p == package);
+        writeln!(dot, "digraph {{")?;
+        for (i, (package, version)) in nodes.iter().enumerate() {
+            writeln!(
+                dot,
+                "    {i} [label={}];",
+                dot_quoted(format!("{package} {version}"))
+            )?;
+        }
+        for (i, (package, _)) in nodes.iter().enumerate() {
+            for (dependency, range) in self.dependencies(package) {
+                if let Some(j) = index(dependency) {
+                    writeln!(dot, "    {i} -> {j} [label={}];", dot_quoted(range))?;
+                }
+            }
+        }
+        writeln!(dot, "}}")
+    }
+}
@@ -58,16 +58,32 @@
 //! will compute the set of packages and versions needed
 //! to satisfy the dependencies of that package and version pair.
 //! If there is no solution, the reason will be provided as clear as possible.
+//!
+//! ## Logging
+//!
+//! The solver reports its progress with the [log](https://docs.rs/log) crate. At the `info`
+//! level, it logs the start of a resolution, every package chosen with the version selected by
+//! the provider, every incompatibility causing a conflict, every backtrack with the number of
+//! decisions undone, and the outcome of the resolution with its [ResolutionStats]. The `debug`
+//! level adds the partial solution after each unit propagation, which is much more verbose.
 
-use std::collections::BTreeSet as Set;
-use std::error::Error;
-use std::fmt::{Debug, Display};
+use alloc::collections::{BTreeMap, BTreeSet as Set};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::error::Error;
+use core::fmt::{Debug, Display};
+use core::future::Future;
+use core::marker::PhantomData;
+use core::num::NonZeroU32;
+use core::time::Duration;
 
 use log::{debug, info};
 
-use crate::internal::{Id, Incompatibility, State};
+use crate::internal::{Id, IncompDpId, Incompatibility, State};
 use crate::{
-    DependencyConstraints, Map, Package, PubGrubError, SelectedDependencies, Term, VersionSet,
+    DependencyConstraints, Map, NoSolutionError, Package, PubGrubError, Resolver,
+    SelectedDependencies, SortedDependencies, Step, Term, VersionSet,
 };
 
 /// Statistics on how often a package conflicted with other packages.
@@ -89,6 +105,11 @@ pub struct PackageResolutionStatistics {
     unit_propagation_culprit: u32,
     dependencies_affected: u32,
     dependencies_culprit: u32,
+    /// The sum of the bumps of the conflicts this package was involved in, later conflicts
+    /// bumping more.
+    pub(crate) activity: f64,
+    /// The length of the shortest chain of dependencies from a root package to this package.
+    pub(crate) depth: Option<u32>,
 }
 
 impl PackageResolutionStatistics {
@@ -105,87 +126,1216 @@ impl PackageResolutionStatistics {
             + self.dependencies_affected
             + self.dependencies_culprit
     }
+
+    /// The statistics of a package at `depth`, before any conflict.
+    pub(crate) fn with_depth(depth: Option<u32>) -> Self {
+        Self {
+            depth,
+            ..Self::default()
+        }
+    }
+
+    /// How much this package was involved in recent conflicts.
+    ///
+    /// Like the variable activity of SAT solvers, each conflict adds to the activity of its
+    /// packages, and the weight of a conflict grows exponentially with the number of conflicts
+    /// before it, so that packages that conflicted recently come first. Activities are only
+    /// meaningful compared with each other, within a resolution.
+    ///
+    /// Priorities are cached, a provider prioritizing by activity should also return `true` from
+    /// [reprioritize_on_conflict](DependencyProvider::reprioritize_on_conflict).
+    pub fn activity(&self) -> f64 {
+        self.activity
+    }
+
+    /// The number of dependencies from a root package to this package, zero for the root
+    /// packages, or `None` if the package was not required by a decided package yet.
+    ///
+    /// This is the shortest chain through the versions whose dependencies were retrieved so far,
+    /// including versions that were backtracked since.
+    pub fn depth(&self) -> Option<u32> {
+        self.depth
+    }
+}
+
+/// Read access to the partial solution of the solver, given to
+/// [choose_version_in](DependencyProvider::choose_version_in).
+///
+/// This is implemented by [State].
+pub trait PartialSolutionView<P, VS: VersionSet> {
+    /// The decided package versions, in the order of the decisions, starting with the root
+    /// package.
+    fn decisions(&self) -> Vec<(&P, &VS::V)>;
+
+    /// The version decided for a package, if any.
+    fn decision(&self, package: &P) -> Option<&VS::V>;
+
+    /// What the derivations and the decision so far require of a package: a positive term
+    /// with the allowed versions, a negative term with the forbidden versions if the package is
+    /// not required, or `None` if the package wasn't mentioned yet.
+    fn term(&self, package: &P) -> Option<&Term<VS>>;
+
+    /// The number of decisions so far.
+    fn decision_level(&self) -> u32;
+}
+
+/// Counters of the work done by the solver during a resolution.
+///
+/// Returned by [resolve_with_stats] and [State::stats], to find out why a resolution is slow.
+#[derive(Debug, Clone)]
+pub struct ResolutionStats<P> {
+    /// Number of versions decided, including the decisions later undone by backtracking.
+    pub decisions: u32,
+    /// Number of times the decisions were found to conflict with an incompatibility.
+    pub conflicts: u32,
+    /// Number of times decisions were undone.
+    pub backtracks: u32,
+    /// Number of calls to unit propagation.
+    pub unit_propagations: u32,
+    /// Number of incompatibilities derived during conflict resolution.
+    pub incompatibilities_learned: u32,
+    /// Number of times all decisions were undone to start over, see
+    /// [ResolveOptions::restart_interval].
+    pub restarts: u32,
+    /// Number of learned incompatibilities that unit propagation stopped checking, see
+    /// [ResolveOptions::max_learned_incompatibilities].
+    pub incompatibilities_removed: u32,
+    /// Time spent in [choose_version](DependencyProvider::choose_version) and
+    /// [get_dependencies](DependencyProvider::get_dependencies) for each package.
+    pub time_per_package: Map<P, Duration>,
+    /// The packages involved in conflicts, the most conflicting first, as returned by
+    /// [State::conflict_counts].
+    pub conflicting_packages: Vec<(P, u32)>,
+    /// The memory held by the solver when the statistics were taken.
+    pub memory: MemoryStats,
+}
+
+impl<P> Default for ResolutionStats<P> {
+    fn default() -> Self {
+        Self {
+            decisions: 0,
+            conflicts: 0,
+            backtracks: 0,
+            unit_propagations: 0,
+            incompatibilities_learned: 0,
+            restarts: 0,
+            incompatibilities_removed: 0,
+            time_per_package: Map::default(),
+            conflicting_packages: Vec::new(),
+            memory: MemoryStats::default(),
+        }
+    }
+}
+
+/// The memory held by a resolution, see [ResolutionStats::memory] and [State::memory_stats].
+///
+/// The solver doesn't shrink its collections during a resolution, so their capacity, from which
+/// the number of bytes is estimated, is also about the peak. The memory owned by the packages,
+/// versions and version sets themselves is not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Number of distinct packages the solver has seen.
+    pub packages: u32,
+    /// Number of incompatibilities in the store, including those no longer checked by unit
+    /// propagation, which are kept for error reporting.
+    pub incompatibilities: u32,
+    /// Number of incompatibilities checked by unit propagation, counted once for each of their
+    /// packages.
+    pub incompatibility_references: u32,
+    /// Number of packages decided or constrained in the partial solution.
+    pub assignments: u32,
+    /// Estimated number of bytes allocated by the solver.
+    pub estimated_bytes: usize,
+}
+
+/// Bounds on the size of a resolution, enforced by [resolve_with_limits].
+///
+/// A service resolving untrusted manifests can't let a pathological dependency graph run the
+/// solver for hours, these limits abort the resolution with [PubGrubError::LimitExceeded]
+/// instead. All limits are disabled by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResolutionLimits {
+    /// Maximum number of distinct packages involved in the resolution, including the packages
+    /// only mentioned as dependencies of a version that was not selected in the end.
+    pub max_packages: Option<usize>,
+    /// Maximum number of decisions made at the same time, the root package being the first one.
+    pub max_depth: Option<u32>,
+}
+
+/// The limit of [ResolutionLimits] exceeded by a resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceededLimit {
+    /// [max_packages](ResolutionLimits::max_packages), with its value.
+    Packages(usize),
+    /// [max_depth](ResolutionLimits::max_depth), with its value.
+    Depth(u32),
+}
+
+impl Display for ExceededLimit {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Packages(max) => write!(f, "the limit of {max} packages"),
+            Self::Depth(max) => write!(f, "the limit of {max} simultaneous decisions"),
+        }
+    }
+}
+
+/// Budget and heuristics of a resolution, enforced by [resolve_with_options].
+///
+/// The solver checks the budget at each step, choosing a version for one package, so a slow
+/// provider call is not interrupted. All budgets and heuristics are disabled by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResolveOptions {
+    /// Maximum wall-clock time of the resolution.
+    ///
+    /// Without the `std` feature, and on `wasm32-unknown-unknown`, there is no clock and this is
+    /// ignored.
+    pub timeout: Option<Duration>,
+    /// Maximum number of steps of the resolution.
+    pub max_steps: Option<u32>,
+    /// Decide the packages involved in recent conflicts first.
+    ///
+    /// Packages are ordered by their [activity](PackageResolutionStatistics::activity), then by
+    /// the priority of the provider, and are prioritized again after each conflict. Deciding the
+    /// conflicting packages before the others finds the versions that don't work together
+    /// sooner, which avoids backtracking over unrelated decisions on large graphs.
+    pub activity_heuristic: bool,
+    /// Start over after this many conflicts, times the terms of the Luby sequence
+    /// 1, 1, 2, 1, 1, 2, 4, 1, 1, 2, ...
+    ///
+    /// Restarts undo all the decisions but keep the learned incompatibilities, so the solver
+    /// doesn't repeat the same conflicts but can change its early decisions, which a single bad
+    /// decision on a large registry may otherwise keep for the whole resolution. Like SAT solvers,
+    /// this works best with the [activity heuristic](Self::activity_heuristic), deciding the
+    /// conflicting packages first after a restart.
+    pub restart_interval: Option<NonZeroU32>,
+    /// Clean up the learned incompatibilities when there are more than this many.
+    ///
+    /// Each conflict learns an incompatibility, and unit propagation checks all of them, which
+    /// slows down resolutions with hundreds of thousands of conflicts. The cleanup first removes
+    /// the learned incompatibilities subsumed by a newer one, then the ones that took part in a
+    /// conflict the longest time ago, until half of the maximum are left. They are implied by
+    /// the dependencies, so the solution doesn't change, and they are still kept for error
+    /// reporting. Like SAT solvers, the maximum grows by a tenth after each cleanup, so that
+    /// the resolution can't keep learning the same incompatibilities again.
+    pub max_learned_incompatibilities: Option<NonZeroU32>,
+    /// Reserve room for this many packages before the resolution starts.
+    ///
+    /// Without it, the collections of the solver start empty and grow as needed, which keeps
+    /// the footprint of small resolutions down, while large ones reallocate many times. A hint
+    /// larger than the resolution holds memory for nothing, see [ResolutionStats::memory]. The
+    /// small vectors of the solver always store up to two items without allocating.
+    pub expected_packages: Option<NonZeroU32>,
+    /// Decide the packages pinned to a single version right away.
+    ///
+    /// When the versions allowed for a package are a single version known to exist, per
+    /// [VersionSet::is_singleton] and [known_versions](DependencyProvider::known_versions),
+    /// there is nothing to choose: the package is decided before the others, without calling
+    /// [prioritize](DependencyProvider::prioritize) and
+    /// [choose_version](DependencyProvider::choose_version). This saves the calls to the
+    /// provider on resolutions from lockfiles or with many exact requirements, but the order of
+    /// the decisions changes, so the solution found may too.
+    pub decide_pinned_first: bool,
+    /// The order in which to decide the packages of the same priority.
+    pub tie_break: TieBreak,
+}
+
+/// How the solver orders the packages of the same
+/// [priority](DependencyProvider::prioritize), see [ResolveOptions::tie_break].
+///
+/// Packages are numbered in the order the solver first sees them, starting with the root
+/// package, then its dependencies, then the dependencies of the first decided dependency, and
+/// so on. For the same provider answers, each tie-break gives the same order in all the
+/// releases of this crate, so that a resolution finds the same solution after an upgrade.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// The package seen first is decided first, the order of a breadth-first search.
+    #[default]
+    InsertionOrder,
+    /// The package seen last is decided first, closer to a depth-first search.
+    ReverseInsertionOrder,
+    /// The package with the smallest [tie_break_key](DependencyProvider::tie_break_key) is
+    /// decided first, then the package seen first among those with the same key.
+    ProviderKey,
+}
+
+/// The `i`-th term of the Luby sequence, starting from 1.
+fn luby(i: NonZeroU32) -> u32 {
+    let mut i = i.get();
+    // Find the smallest `2^k - 1 >= i`, the term is `2^(k-1)` if equal, otherwise the sequence
+    // repeats from the start.
+    loop {
+        let mut k = 1;
+        while (1u64 << k) - 1 < u64::from(i) {
+            k += 1;
+        }
+        if (1u64 << k) - 1 == u64::from(i) {
+            return 1 << (k - 1);
+        }
+        i -= (1 << (k - 1)) - 1;
+    }
+}
+
+/// What the solver did in a [step](Solver::step), with the package as its [Id].
+///
+/// The solver loop only looks for the end of the resolution, the package and the solution are
+/// only cloned out of the state for the [Step] returned to the user, see [Solver::to_step].
+pub(crate) enum StepId<P, V> {
+    Chose(Id<P>, V),
+    NoVersion(Id<P>),
+    Denied(Id<P>, V),
+    Solved,
+}
+
+/// Measures the time spent in the provider for [ResolutionStats].
+///
+/// Without the `std` feature there is no clock, and the measured time is always zero. The same
+/// goes for `wasm32-unknown-unknown`, where reading the clock panics.
+#[derive(Clone, Copy)]
+struct Stopwatch {
+    #[cfg(all(
+        feature = "std",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    start: std::time::Instant,
+}
+
+impl Stopwatch {
+    fn start() -> Self {
+        Self {
+            #[cfg(all(
+                feature = "std",
+                not(all(target_arch = "wasm32", target_os = "unknown"))
+            ))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    fn elapsed(self) -> Duration {
+        #[cfg(all(
+            feature = "std",
+            not(all(target_arch = "wasm32", target_os = "unknown"))
+        ))]
+        return self.start.elapsed();
+        #[cfg(not(all(
+            feature = "std",
+            not(all(target_arch = "wasm32", target_os = "unknown"))
+        )))]
+        Duration::ZERO
+    }
 }
 
 /// Main function of the library.
 /// Finds a set of packages satisfying dependency bounds for a given package + version pair.
+///
+/// A [Resolver] without any option, which combines the options of the other
+/// `resolve_with_*` functions.
 #[cold]
 pub fn resolve<DP: DependencyProvider>(
     dependency_provider: &DP,
     package: DP::P,
     version: impl Into<DP::V>,
 ) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
-    let mut state: State<DP> = State::init(package.clone(), version.into());
-    let mut conflict_tracker: Map<Id<DP::P>, PackageResolutionStatistics> = Map::default();
-    let mut added_dependencies: Map<Id<DP::P>, Set<DP::V>> = Map::default();
-    let mut next = state.root_package;
-    loop {
-        dependency_provider
+    Resolver::new(dependency_provider).resolve(package, version)
+}
+
+/// Same as [resolve], with the selected packages ordered by package.
+///
+/// The order of a [SelectedDependencies] depends on the platform and on the order of the
+/// decisions. A lockfile or a summary written from a sorted solution is the same byte for byte
+/// across runs and platforms.
+///
+/// ```
+/// # use pubgrub::{resolve_sorted, OfflineDependencyProvider, Ranges};
+/// #
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("zlib", Ranges::full()), ("log", Ranges::full())]);
+/// dependency_provider.add_dependencies("zlib", 1u32, []);
+/// dependency_provider.add_dependencies("log", 1u32, []);
+///
+/// let solution = resolve_sorted(&dependency_provider, "root", 1u32).unwrap();
+/// let packages: Vec<_> = solution.keys().copied().collect();
+/// assert_eq!(packages, ["log", "root", "zlib"]);
+/// ```
+pub fn resolve_sorted<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<SortedDependencies<DP>, PubGrubError<DP>>
+where
+    DP::P: Ord,
+{
+    Ok(resolve(dependency_provider, package, version)?
+        .into_iter()
+        .collect())
+}
+
+/// Same as [resolve], also returning statistics on the resolution, whether it succeeded or not.
+#[allow(clippy::type_complexity)]
+pub fn resolve_with_stats<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> (
+    Result<SelectedDependencies<DP>, PubGrubError<DP>>,
+    ResolutionStats<DP::P>,
+) {
+    let mut solver = Solver::new(package, version.into());
+    let result = solver.solve(dependency_provider);
+    (result, solver.state.stats())
+}
+
+/// Finds a set of packages satisfying the dependencies of all the `roots` at once.
+///
+/// This resolves a workspace, whose members share a single version of each dependency, without
+/// a virtual package depending on all the members. Each root is selected at its version, and the
+/// solution is empty if there are no roots.
+pub fn resolve_roots<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    roots: impl IntoIterator<Item = (DP::P, DP::V)>,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let mut roots = roots.into_iter();
+    let Some((package, version)) = roots.next() else {
+        return Ok(SelectedDependencies::<DP>::default());
+    };
+    let mut solver = Solver::new(package, version);
+    for (package, version) in roots {
+        solver.add_root(package, version)?;
+    }
+    solver.solve(dependency_provider)
+}
+
+/// Same as [resolve], failing with [PubGrubError::LimitExceeded] as soon as the resolution
+/// exceeds one of the `limits`.
+pub fn resolve_with_limits<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    limits: ResolutionLimits,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let mut solver = Solver::new(package, version.into());
+    solver.limits = limits;
+    solver.solve(dependency_provider)
+}
+
+/// Same as [resolve], failing with [PubGrubError::Timeout] once the resolution exceeds the
+/// budget of `options`, and deciding packages with the heuristics of `options`.
+pub fn resolve_with_options<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    options: ResolveOptions,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let mut solver = Solver::new(package, version.into());
+    solver.options = options;
+    if let Some(packages) = options.expected_packages {
+        solver.state.reserve(packages.get() as usize);
+    }
+    solver.solve(dependency_provider)
+}
+
+/// Same as [resolve], only allowing the versions of each package of `constraints` in the given
+/// set.
+///
+/// This imposes global constraints, such as excluding the versions affected by a security
+/// advisory, without adding them as dependencies of the root package: see
+/// [State::add_constraint].
+pub fn resolve_with_constraints<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    constraints: impl IntoIterator<Item = (DP::P, DP::VS, DP::M)>,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let mut solver = Solver::new(package, version.into());
+    for (package, versions, reason) in constraints {
+        solver.state.add_constraint(package, versions, reason);
+    }
+    solver.solve(dependency_provider)
+}
+
+/// The result of [resolve_best_effort].
+#[derive(Debug, Clone)]
+pub enum BestEffortSolution<P: Package, V, E> {
+    /// The resolution completed: the selected packages satisfy all the dependencies.
+    Complete(Map<P, V>),
+    /// The resolution was cancelled by [should_cancel](DependencyProvider::should_cancel).
+    Partial {
+        /// The decisions made so far.
+        ///
+        /// They are compatible with each other, but some dependencies of the decided packages
+        /// may not be decided yet, and finishing the resolution could have changed some of them.
+        decisions: Map<P, V>,
+        /// The error returned by [should_cancel](DependencyProvider::should_cancel).
+        reason: E,
+    },
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// returning the decisions made so far if [should_cancel](DependencyProvider::should_cancel)
+/// stops the resolution.
+///
+/// This allows time-boxed resolutions that still show a useful result when they run out of time.
+#[allow(clippy::type_complexity)]
+pub fn resolve_best_effort<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<BestEffortSolution<DP::P, DP::V, DP::Err>, PubGrubError<DP>> {
+    let mut solver = Solver::new(package, version.into());
+    match solver.solve(dependency_provider) {
+        Ok(solution) => Ok(BestEffortSolution::Complete(solution)),
+        Err(PubGrubError::ErrorInShouldCancel(reason)) => Ok(BestEffortSolution::Partial {
+            decisions: solver.propagated_decisions()?,
+            reason,
+        }),
+        Err(err) => Err(err),
+    }
+}
+
+/// What the dependencies of a package imply, found by [propagate].
+#[derive(Debug, Clone)]
+pub struct Propagation<P: Package, VS: VersionSet> {
+    /// Packages with a single version allowed by the provider and the constraints, including the
+    /// root package.
+    pub forced: Map<P, VS::V>,
+    /// The other packages that must be selected, with the versions still allowed.
+    pub required: Map<P, VS>,
+}
+
+/// Derives what the dependencies of a package imply without choosing between versions, failing
+/// with [PubGrubError::NoSolution] if they contradict each other.
+///
+/// This only runs unit propagation: a package is decided when there is a single version left to
+/// choose from, and its dependencies are propagated in turn. It finds, for example, that two
+/// direct dependencies pin different versions of the same package, or that no version of a
+/// required package exists, faster than a resolution for the "check" of an editor, but a
+/// resolution may still fail when this succeeds.
+pub fn propagate<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<Propagation<DP::P, DP::VS>, PubGrubError<DP>> {
+    let mut solver = Solver::new(package, version.into());
+    solver.propagate_forced(dependency_provider)?;
+    let required = solver
+        .state
+        .partial_solution
+        .required_packages()
+        .map(|(p, range)| (solver.state.package_store[p].clone(), range.clone()))
+        .collect();
+    Ok(Propagation {
+        forced: solver.extract_solution(),
+        required,
+    })
+}
+
+/// Bookkeeping around the [State] shared by [resolve] and [resolve_async].
+///
+/// The provider is queried by the callers, so that the same solver steps can be driven
+/// synchronously or asynchronously.
+pub(crate) struct Solver<DP: DependencyProvider> {
+    pub(crate) state: State<DP>,
+    added_dependencies: Map<Id<DP::P>, Set<DP::V>>,
+    /// The package to run unit propagation from at the next step.
+    next: Id<DP::P>,
+    pub(crate) limits: ResolutionLimits,
+    pub(crate) options: ResolveOptions,
+    /// Number of steps since the start of the resolution, and when it started.
+    steps: u32,
+    start: Stopwatch,
+    /// How much the next conflict adds to the activity of its packages.
+    activity_increment: f64,
+    /// Whether to prioritize all undecided packages again after a conflict.
+    reprioritize_on_conflict: bool,
+    /// The number of conflicts of the next restart.
+    next_restart: Option<u32>,
+    /// The number of learned incompatibilities of the next cleanup.
+    learned_limit: Option<usize>,
+    /// The dependencies returned with those of another version, until they are needed.
+    #[allow(clippy::type_complexity)]
+    batched_dependencies: Map<Id<DP::P>, BTreeMap<DP::V, Dependencies<DP::P, DP::VS, DP::M>>>,
+}
+
+impl<DP: DependencyProvider> Solver<DP> {
+    pub(crate) fn new(package: DP::P, version: DP::V) -> Self {
+        Self::from_state(State::init(package, version))
+    }
+
+    /// A solver starting from the incompatibilities and decisions of `state`.
+    pub(crate) fn from_state(state: State<DP>) -> Self {
+        Self {
+            next: state.root_package,
+            state,
+            added_dependencies: Map::default(),
+            limits: ResolutionLimits::default(),
+            options: ResolveOptions::default(),
+            steps: 0,
+            start: Stopwatch::start(),
+            activity_increment: 1.0,
+            reprioritize_on_conflict: false,
+            next_restart: None,
+            learned_limit: None,
+            batched_dependencies: Map::default(),
+        }
+    }
+
+    /// Initialize a solver for a new resolution of the same root package and version.
+    ///
+    /// The incompatibilities learned by this solver that do not depend on the dependencies
+    /// of the root package are carried over, and the dependencies already retrieved for other
+    /// packages are not requested again from the provider.
+    pub(crate) fn carry_over(&self) -> Self {
+        let state = self.state.carry_over();
+        let mut added_dependencies = self.added_dependencies.clone();
+        added_dependencies.remove(&state.root_package);
+        Self {
+            next: state.root_package,
+            state,
+            added_dependencies,
+            limits: self.limits,
+            options: self.options,
+            steps: 0,
+            start: Stopwatch::start(),
+            activity_increment: 1.0,
+            reprioritize_on_conflict: self.reprioritize_on_conflict,
+            next_restart: None,
+            learned_limit: None,
+            batched_dependencies: self.batched_dependencies.clone(),
+        }
+    }
+
+    /// Add another root package, solved together with the main one.
+    pub(crate) fn add_root(
+        &mut self,
+        package: DP::P,
+        version: DP::V,
+    ) -> Result<(), NoSolutionError<DP>> {
+        let root = self.state.add_root(package, version);
+        self.unit_propagation(root)
+    }
+
+    /// Decide the root package with the given dependencies, instead of querying the provider.
+    pub(crate) fn add_root_dependencies(
+        &mut self,
+        dependencies: DependencyConstraints<DP::P, DP::VS>,
+    ) -> Result<(), NoSolutionError<DP>> {
+        let root = self.state.root_package;
+        self.unit_propagation(root)?;
+        let version = self.state.root_version().clone();
+        self.added_dependencies
+            .entry(root)
+            .or_default()
+            .insert(version.clone());
+        let versions = DP::VS::singleton(version.clone());
+        self.add_dependencies(
+            root,
+            version,
+            versions,
+            Dependencies::Available(dependencies),
+        );
+        Ok(())
+    }
+
+    /// Run the solver loop until a solution is found or resolution fails.
+    pub(crate) fn solve(
+        &mut self,
+        dependency_provider: &DP,
+    ) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+        self.solve_with_hooks(dependency_provider, &mut ())
+    }
+
+    /// Same as [solve](Self::solve), with `hooks` in the solver loop.
+    pub(crate) fn solve_with_hooks(
+        &mut self,
+        dependency_provider: &DP,
+        hooks: &mut impl SolverHooks<DP>,
+    ) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+        self.begin(dependency_provider);
+        let result = self.solve_steps(dependency_provider, hooks);
+        self.log_outcome(&result, self.start.elapsed());
+        result
+    }
+
+    /// Start the clock of the resolution with `dependency_provider`.
+    pub(crate) fn begin(&mut self, dependency_provider: &DP) {
+        self.reprioritize_on_conflict = dependency_provider.reprioritize_on_conflict();
+        self.log_start();
+        self.start = Stopwatch::start();
+    }
+
+    fn solve_steps(
+        &mut self,
+        dependency_provider: &DP,
+        hooks: &mut impl SolverHooks<DP>,
+    ) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+        loop {
+            if let StepId::Solved = self.step(dependency_provider, hooks)? {
+                return Ok(self.extract_solution());
+            }
+        }
+    }
+
+    /// Propagate the last decision, then choose a version for the next package and add its
+    /// dependencies.
+    pub(crate) fn step(
+        &mut self,
+        dependency_provider: &DP,
+        hooks: &mut impl SolverHooks<DP>,
+    ) -> Result<StepId<DP::P, DP::V>, PubGrubError<DP>> {
+        let provider = SyncProvider(dependency_provider);
+        let Some((next, pinned)) = self.prepare_step(&provider, hooks)? else {
+            return Ok(StepId::Solved);
+        };
+        let decision = match pinned {
+            Some(v) => Some(v),
+            None => {
+                let start = Stopwatch::start();
+                let decision = dependency_provider
+                    .choose_version_in(
+                        &self.state.package_store[next],
+                        self.range(next),
+                        &self.state,
+                    )
+                    .map_err(|err| self.state.error_choosing_version(next, err))?;
+                self.state.record_time(next, start.elapsed());
+                decision
+            }
+        };
+        let (step, new_version) = self.decide(next, decision, hooks);
+        let Some(v) = new_version else {
+            return Ok(step);
+        };
+        let dependencies = self.retrieve_dependencies(dependency_provider, next, &v)?;
+        self.add_retrieved(&provider, hooks, next, v, dependencies);
+        Ok(step)
+    }
+
+    /// The [Step] returned to the user for a `step` of the solver.
+    pub(crate) fn to_step(&self, step: StepId<DP::P, DP::V>) -> Step<DP::P, DP::V> {
+        let package = |id| self.state.package_store[id].clone();
+        match step {
+            StepId::Chose(id, version) => Step::Chose(package(id), version),
+            StepId::NoVersion(id) => Step::NoVersion(package(id)),
+            StepId::Denied(id, version) => Step::Denied(package(id), version),
+            StepId::Solved => Step::Solved(self.extract_solution()),
+        }
+    }
+
+    /// The first part of a [step](Self::step), up to the choice of a version: propagate the
+    /// last decision, then pick the next package to decide.
+    ///
+    /// Returns the package with its version if it is pinned, or `None` if the partial solution
+    /// is a total solution.
+    #[allow(clippy::type_complexity)]
+    fn prepare_step(
+        &mut self,
+        provider: &impl ProviderCalls<DP>,
+        hooks: &mut impl SolverHooks<DP>,
+    ) -> Result<Option<(Id<DP::P>, Option<DP::V>)>, PubGrubError<DP>> {
+        provider
             .should_cancel()
             .map_err(|err| PubGrubError::ErrorInShouldCancel(err))?;
 
+        let propagated = self.unit_propagation(self.next);
+        self.notify_backtracks(provider);
+        propagated?;
+        self.check_limits()?;
+        if self.check_restart() {
+            provider.on_backtrack(1);
+        }
+        self.check_learned();
+        hooks
+            .on_step(self)
+            .map_err(|err| PubGrubError::ErrorInShouldCancel(err))?;
+
+        let Some(highest_priority_pkg) = self.pick_highest_priority_pkg(
+            |p, r, statistics| provider.prioritize(p, r, statistics),
+            |p| provider.tie_break_key(p),
+        ) else {
+            return Ok(None);
+        };
+        self.next = highest_priority_pkg;
+        let next = self.next;
+        let pinned = self
+            .options
+            .decide_pinned_first
+            .then(|| {
+                self.state
+                    .partial_solution
+                    .pinned_version(next, self.range(next))
+            })
+            .flatten();
+        Ok(Some((next, pinned)))
+    }
+
+    /// The middle part of a [step](Self::step): apply the version chosen for `next`.
+    ///
+    /// Returns the step, with the version if its dependencies must be retrieved to end it.
+    #[allow(clippy::type_complexity)]
+    fn decide(
+        &mut self,
+        next: Id<DP::P>,
+        decision: Option<DP::V>,
+        hooks: &mut impl SolverHooks<DP>,
+    ) -> (StepId<DP::P, DP::V>, Option<DP::V>) {
+        let Some(version) = decision else {
+            self.add_decision(next, None);
+            return (StepId::NoVersion(next), None);
+        };
+
+        if let Some(reason) = hooks.denied(&self.state.package_store[next], &version) {
+            let denied = Incompatibility::denied(next, DP::VS::singleton(version.clone()), reason);
+            self.state.add_incompatibility(denied);
+            return (StepId::Denied(next, version), None);
+        }
+        let new_version = self.add_decision(next, Some(version.clone()));
+        (StepId::Chose(next, version), new_version)
+    }
+
+    /// The last part of a [step](Self::step): add the `dependencies` retrieved for version `v`
+    /// of `next`.
+    fn add_retrieved(
+        &mut self,
+        provider: &impl ProviderCalls<DP>,
+        hooks: &mut impl SolverHooks<DP>,
+        next: Id<DP::P>,
+        v: DP::V,
+        dependencies: Dependencies<DP::P, DP::VS, DP::M>,
+    ) {
+        if let Dependencies::Available(dependencies) = &dependencies {
+            provider.prefetch(dependencies);
+            self.add_known_versions(provider, dependencies);
+        }
+        let package = &self.state.package_store[next];
+        hooks.on_dependencies(self, package, &v, &dependencies);
+        let versions = provider.versions_sharing_dependencies(package, &v);
+        self.add_dependencies(next, v, versions, dependencies);
+    }
+
+    /// The dependencies of `version` of `package`, from a previous batch or from the provider,
+    /// see [DependencyProvider::get_dependencies_batch].
+    #[allow(clippy::type_complexity)]
+    fn retrieve_dependencies(
+        &mut self,
+        dependency_provider: &DP,
+        package: Id<DP::P>,
+        version: &DP::V,
+    ) -> Result<Dependencies<DP::P, DP::VS, DP::M>, PubGrubError<DP>> {
+        if let Some(dependencies) = self.take_batched(package, version) {
+            return Ok(dependencies);
+        }
+        let versions = self.batch_versions(package, version);
+        let start = Stopwatch::start();
+        let batch = dependency_provider
+            .get_dependencies_batch(&self.state.package_store[package], &versions)
+            .map_err(|err| {
+                self.state
+                    .error_retrieving_dependencies(package, version.clone(), err)
+            })?;
+        self.state.record_time(package, start.elapsed());
+        Ok(self.add_batch(package, versions, batch))
+    }
+
+    /// The dependencies of `version` of `package` if they were returned with those of another
+    /// version.
+    #[allow(clippy::type_complexity)]
+    fn take_batched(
+        &mut self,
+        package: Id<DP::P>,
+        version: &DP::V,
+    ) -> Option<Dependencies<DP::P, DP::VS, DP::M>> {
+        self.batched_dependencies
+            .get_mut(&package)
+            .and_then(|batch| batch.remove(version))
+    }
+
+    /// The versions of `package` to retrieve the dependencies of with those of `version`: the
+    /// known versions whose dependencies were not retrieved yet, newest first, after `version`.
+    fn batch_versions(&self, package: Id<DP::P>, version: &DP::V) -> Vec<DP::V> {
+        let mut versions = vec![version.clone()];
+        if let Some(known) = self.state.partial_solution.known_versions(package) {
+            let added = &self.added_dependencies[&package];
+            let batched = self.batched_dependencies.get(&package);
+            versions.extend(
+                known
+                    .iter()
+                    .rev()
+                    .filter(|v| {
+                        !added.contains(*v) && !batched.is_some_and(|batch| batch.contains_key(*v))
+                    })
+                    .cloned(),
+            );
+        }
+        versions
+    }
+
+    /// Keep the dependencies of the other `versions` of `package` for later, and return those of
+    /// the first one.
+    fn add_batch(
+        &mut self,
+        package: Id<DP::P>,
+        versions: Vec<DP::V>,
+        batch: Vec<Dependencies<DP::P, DP::VS, DP::M>>,
+    ) -> Dependencies<DP::P, DP::VS, DP::M> {
+        if batch.is_empty() {
+            panic!(
+                "`get_dependencies_batch` returned no dependencies for package {} at {}",
+                self.state.package_store[package], versions[0]
+            );
+        }
+        let mut batch = versions.into_iter().zip(batch);
+        let (_, dependencies) = batch.next().expect("the batch is not empty");
+        self.batched_dependencies
+            .entry(package)
+            .or_default()
+            .extend(batch);
+        dependencies
+    }
+
+    /// The decided package versions, in the order of the decisions.
+    pub(crate) fn decisions(&self) -> Vec<(DP::P, DP::V)> {
+        self.state
+            .partial_solution
+            .extract_solution()
+            .map(|(p, v)| (self.state.package_store[p].clone(), v))
+            .collect()
+    }
+
+    /// Add the dependencies retrieved by a previous resolution, then decide the same versions
+    /// again, in the same order.
+    ///
+    /// Replaying stops at the first decision that is not allowed anymore.
+    pub(crate) fn replay<'a>(
+        &mut self,
+        dependencies: impl IntoIterator<Item = &'a (DP::P, DP::V, Dependencies<DP::P, DP::VS, DP::M>)>,
+        decisions: impl IntoIterator<Item = &'a (DP::P, DP::V)>,
+    ) -> Result<(), NoSolutionError<DP>>
+    where
+        DP: 'a,
+    {
+        for (p, v, dependencies) in dependencies {
+            let p = self.state.package_store.alloc(p.clone());
+            if !self
+                .added_dependencies
+                .entry(p)
+                .or_default()
+                .insert(v.clone())
+            {
+                continue;
+            }
+            match dependencies {
+                Dependencies::Unavailable(reason) => {
+                    self.state
+                        .add_incompatibility(Incompatibility::custom_version(
+                            p,
+                            v.clone(),
+                            reason.clone(),
+                        ));
+                }
+                Dependencies::Available(constraints) => {
+                    self.state.add_incompatibility_from_dependencies(
+                        p,
+                        DP::VS::singleton(v.clone()),
+                        constraints.iter().map(|(p, vs)| (p.clone(), vs.clone())),
+                    );
+                }
+            }
+        }
+        for (p, v) in decisions {
+            self.unit_propagation(self.next)?;
+            let p = self.state.package_store.alloc(p.clone());
+            let allowed = self.state.partial_solution.undecided_range(p);
+            if !allowed.is_some_and(|range| range.contains(v)) {
+                break;
+            }
+            self.state.add_decision(p, v.clone());
+            self.next = p;
+        }
+        Ok(())
+    }
+
+    /// Decide the packages with a single version left, until there are none.
+    fn propagate_forced(&mut self, dependency_provider: &DP) -> Result<(), PubGrubError<DP>> {
+        // The ranges already found to allow several versions.
+        let mut checked: Map<Id<DP::P>, DP::VS> = Map::default();
+        loop {
+            dependency_provider
+                .should_cancel()
+                .map_err(|err| PubGrubError::ErrorInShouldCancel(err))?;
+
+            let propagated = self.unit_propagation(self.next);
+            self.notify_backtracks(&SyncProvider(dependency_provider));
+            propagated?;
+            self.check_limits()?;
+
+            let candidates: Vec<(Id<DP::P>, DP::VS)> = self
+                .state
+                .partial_solution
+                .required_packages()
+                .filter(|(p, range)| checked.get(p) != Some(*range))
+                .map(|(p, range)| (p, range.clone()))
+                .collect();
+            let mut forced = None;
+            for (p, range) in candidates {
+                let choose = |range: &DP::VS| {
+                    dependency_provider
+                        .choose_version(&self.state.package_store[p], range)
+                        .map_err(|err| self.state.error_choosing_version(p, err))
+                };
+                let Some(v) = choose(&range)? else {
+                    forced = Some((p, None));
+                    break;
+                };
+                let others = range.intersection(&DP::VS::singleton(v.clone()).complement());
+                if choose(&others)?.is_none() {
+                    forced = Some((p, Some(v)));
+                    break;
+                }
+                checked.insert(p, range);
+            }
+            let Some((next, decision)) = forced else {
+                return Ok(());
+            };
+            self.next = next;
+
+            let Some(v) = self.add_decision(next, decision) else {
+                continue;
+            };
+            let dependencies = self.retrieve_dependencies(dependency_provider, next, &v)?;
+            self.add_retrieved(
+                &SyncProvider(dependency_provider),
+                &mut (),
+                next,
+                v,
+                dependencies,
+            );
+        }
+    }
+
+    /// The decisions made so far, once the last decision has been propagated.
+    ///
+    /// Decisions can be added without checking their dependencies, the propagation removes
+    /// those that conflict with each other.
+    fn propagated_decisions(&mut self) -> Result<SelectedDependencies<DP>, NoSolutionError<DP>> {
+        self.unit_propagation(self.next)?;
+        Ok(self.extract_solution())
+    }
+
+    /// Count a step, and check that the resolution is still within its limits and budget.
+    fn check_limits(&mut self) -> Result<(), PubGrubError<DP>> {
+        self.steps += 1;
+        let out_of_steps = self.options.max_steps.is_some_and(|max| self.steps > max);
+        let out_of_time = self
+            .options
+            .timeout
+            .is_some_and(|timeout| self.start.elapsed() > timeout);
+        if out_of_steps || out_of_time {
+            return Err(PubGrubError::Timeout {
+                stats: self.state.stats(),
+            });
+        }
+        if let Some(max) = self.limits.max_packages {
+            if self.state.package_store.len() > max {
+                return Err(PubGrubError::LimitExceeded(ExceededLimit::Packages(max)));
+            }
+        }
+        if let Some(max) = self.limits.max_depth {
+            if self.state.partial_solution.current_decision_level().0 > max {
+                return Err(PubGrubError::LimitExceeded(ExceededLimit::Depth(max)));
+            }
+        }
+        Ok(())
+    }
+
+    fn log_start(&self) {
+        info!(
+            "Resolving {} @ {}",
+            self.state.package_store[self.state.root_package],
+            self.state.root_version()
+        );
+    }
+
+    /// Summarize the resolution in the logs, with the [ResolutionStats] that explain slow solves.
+    fn log_outcome(
+        &self,
+        result: &Result<SelectedDependencies<DP>, PubGrubError<DP>>,
+        elapsed: Duration,
+    ) {
+        let outcome = match result {
+            Ok(_) => "succeeded",
+            Err(PubGrubError::NoSolution(_)) => "found no solution",
+            Err(_) => "failed",
+        };
+        let stats = &self.state.stats;
+        info!(
+            "Resolution {} in {:?} after {} decisions, {} conflicts, {} backtracks and {} learned incompatibilities",
+            outcome,
+            elapsed,
+            stats.decisions,
+            stats.conflicts,
+            stats.backtracks,
+            stats.incompatibilities_learned
+        );
+    }
+
+    /// Undo all decisions if there were enough conflicts since the last restart.
+    ///
+    /// Returns whether the solver restarted.
+    fn check_restart(&mut self) -> bool {
+        let Some(interval) = self.options.restart_interval else {
+            return false;
+        };
+        let conflicts = self.state.stats.conflicts;
+        let next_restart = *self
+            .next_restart
+            .get_or_insert_with(|| interval.get().saturating_mul(luby(NonZeroU32::MIN)));
+        if conflicts < next_restart {
+            return false;
+        }
+        let restarted = self.state.restart();
+        if restarted {
+            info!("restart after {conflicts} conflicts");
+        }
+        let restarts = self.state.stats.restarts;
+        self.next_restart = Some(
+            conflicts.saturating_add(
+                interval
+                    .get()
+                    .saturating_mul(luby(NonZeroU32::MIN.saturating_add(restarts))),
+            ),
+        );
+        restarted
+    }
+
+    /// Tell the provider about the conflicts found and the backtracks done since the last call.
+    fn notify_backtracks(&mut self, provider: &impl ProviderCalls<DP>) {
+        for (incompat, level) in core::mem::take(&mut self.state.backtracks) {
+            let terms: Vec<(DP::P, Term<DP::VS>)> = self.state.incompatibility_store[incompat]
+                .iter()
+                .map(|(p, term)| (self.state.package_store[p].clone(), term.clone()))
+                .collect();
+            provider.on_conflict(&terms);
+            provider.on_backtrack(level.0);
+        }
+    }
+
+    /// Clean up the learned incompatibilities if there are too many, see
+    /// [ResolveOptions::max_learned_incompatibilities].
+    fn check_learned(&mut self) {
+        let Some(max) = self.options.max_learned_incompatibilities else {
+            return;
+        };
+        let limit = *self.learned_limit.get_or_insert(max.get() as usize);
+        if self.state.learned_count() <= limit {
+            return;
+        }
+        let subsumed = self.state.remove_subsumed();
+        let evicted = self.state.evict_learned(limit / 2);
+        info!("removed {subsumed} subsumed and {evicted} inactive learned incompatibilities");
+        self.learned_limit = Some(limit.saturating_add(limit / 10 + 1));
+    }
+
+    /// Run unit propagation from `next` and record the conflicts it found.
+    fn unit_propagation(&mut self, next: Id<DP::P>) -> Result<(), NoSolutionError<DP>> {
         info!(
             "unit_propagation: {:?} = '{}'",
-            &next, state.package_store[next]
+            &next, self.state.package_store[next]
         );
-        let satisfier_causes = state.unit_propagation(next)?;
+        let satisfier_causes = self.state.unit_propagation(next)?;
         for (affected, incompat) in satisfier_causes {
-            conflict_tracker
+            self.state
+                .conflict_tracker
                 .entry(affected)
                 .or_default()
                 .unit_propagation_affected += 1;
-            for (conflict_package, _) in state.incompatibility_store[incompat].iter() {
+            for (conflict_package, _) in self.state.incompatibility_store[incompat].iter() {
                 if conflict_package == affected {
                     continue;
                 }
-                conflict_tracker
+                self.state
+                    .conflict_tracker
                     .entry(conflict_package)
                     .or_default()
                     .unit_propagation_culprit += 1;
             }
+            self.bump_activity(incompat);
         }
 
         debug!(
             "Partial solution after unit propagation: {}",
-            state.partial_solution.display(&state.package_store)
+            self.state
+                .partial_solution
+                .display(&self.state.package_store)
         );
+        Ok(())
+    }
 
-        let Some((highest_priority_pkg, term_intersection)) =
-            state.partial_solution.pick_highest_priority_pkg(|p, r| {
-                dependency_provider.prioritize(
-                    &state.package_store[p],
-                    r,
-                    conflict_tracker.entry(p).or_default(),
-                )
+    /// The next package to decide, or `None` if the partial solution is a total solution.
+    fn pick_highest_priority_pkg(
+        &mut self,
+        mut prioritize: impl FnMut(&DP::P, &DP::VS, &PackageResolutionStatistics) -> DP::Priority,
+        mut tie_break_key: impl FnMut(&DP::P) -> u64,
+    ) -> Option<Id<DP::P>> {
+        let package_store = &self.state.package_store;
+        let conflict_tracker = &mut self.state.conflict_tracker;
+        let activity_heuristic = self.options.activity_heuristic;
+        let tie_break = self.options.tie_break;
+        self.state
+            .partial_solution
+            .pick_highest_priority_pkg(self.options.decide_pinned_first, |p, r| {
+                let statistics = conflict_tracker.entry(p).or_default();
+                // Activities are not negative, so their bits are ordered like them.
+                let activity = if activity_heuristic {
+                    statistics.activity.to_bits()
+                } else {
+                    0
+                };
+                let priority = prioritize(&package_store[p], r, statistics);
+                let order = p.into_raw() as u32;
+                let tie = match tie_break {
+                    TieBreak::InsertionOrder => (0, order),
+                    TieBreak::ReverseInsertionOrder => (0, u32::MAX - order),
+                    TieBreak::ProviderKey => (tie_break_key(&package_store[p]), order),
+                };
+                (activity, priority, Reverse(tie))
             })
-        else {
-            return Ok(state
-                .partial_solution
-                .extract_solution()
-                .map(|(p, v)| (state.package_store[p].clone(), v))
-                .collect());
-        };
-        next = highest_priority_pkg;
+            .map(|(p, _)| p)
+    }
 
-        let decision = dependency_provider
-            .choose_version(&state.package_store[next], term_intersection)
-            .map_err(|err| PubGrubError::ErrorChoosingVersion {
-                package: state.package_store[next].clone(),
-                source: err,
-            })?;
+    /// The range a version must be chosen from for an undecided package.
+    fn range(&self, package: Id<DP::P>) -> &DP::VS {
+        self.state
+            .partial_solution
+            .term_intersection_for_package(package)
+            .expect("a package must have derivations before being decided")
+            .unwrap_positive()
+    }
+
+    fn extract_solution(&self) -> SelectedDependencies<DP> {
+        self.state
+            .partial_solution
+            .extract_solution()
+            .map(|(p, v)| (self.state.package_store[p].clone(), v))
+            .collect()
+    }
 
+    /// Apply the version chosen by the provider for `next`.
+    ///
+    /// Returns the version if its dependencies still need to be retrieved.
+    fn add_decision(&mut self, next: Id<DP::P>, decision: Option<DP::V>) -> Option<DP::V> {
         info!(
             "DP chose: {:?} = '{}' @ {:?}",
-            &next, state.package_store[next], decision
+            &next, self.state.package_store[next], decision
         );
 
         // Pick the next compatible version.
+        let term_intersection = self.range(next);
         let v = match decision {
             None => {
                 let inc =
                     Incompatibility::no_versions(next, Term::Positive(term_intersection.clone()));
-                state.add_incompatibility(inc);
-                continue;
+                self.state.add_incompatibility(inc);
+                return None;
             }
             Some(x) => x,
         };
@@ -193,68 +1343,354 @@ pub fn resolve<DP: DependencyProvider>(
         if !term_intersection.contains(&v) {
             panic!(
                 "`choose_version` picked an incompatible version for package {}, {} is not in {}",
-                state.package_store[next], v, term_intersection
+                self.state.package_store[next], v, term_intersection
             );
         }
 
-        let is_new_dependency = added_dependencies
+        let is_new_dependency = self
+            .added_dependencies
             .entry(next)
             .or_default()
             .insert(v.clone());
 
         if is_new_dependency {
-            // Retrieve that package dependencies.
-            let p = next;
-            let dependencies = dependency_provider
-                .get_dependencies(&state.package_store[p], &v)
-                .map_err(|err| PubGrubError::ErrorRetrievingDependencies {
-                    package: state.package_store[p].clone(),
-                    version: v.clone(),
-                    source: err,
-                })?;
-
-            let dependencies = match dependencies {
-                Dependencies::Unavailable(reason) => {
-                    state.add_incompatibility(Incompatibility::custom_version(
-                        p,
-                        v.clone(),
-                        reason,
-                    ));
-                    continue;
-                }
-                Dependencies::Available(x) => x,
-            };
-
-            // Add that package and version if the dependencies are not problematic.
-            if let Some(conflict) =
-                state.add_package_version_dependencies(p, v.clone(), dependencies)
-            {
-                conflict_tracker.entry(p).or_default().dependencies_affected += 1;
-                for (incompat_package, _) in state.incompatibility_store[conflict].iter() {
-                    if incompat_package == p {
-                        continue;
-                    }
-                    conflict_tracker
-                        .entry(incompat_package)
-                        .or_default()
-                        .dependencies_culprit += 1;
-                }
-            }
+            Some(v)
         } else {
             // `dep_incompats` are already in `incompatibilities` so we know there are not satisfied
             // terms and can add the decision directly.
             info!(
                 "add_decision (not first time): {:?} = '{}' @ {}",
-                &next, state.package_store[next], v
+                &next, self.state.package_store[next], v
             );
-            state.partial_solution.add_decision(next, v);
+            self.state.add_decision(next, v);
+            None
+        }
+    }
+
+    /// Add the dependencies retrieved for a new package version.
+    fn add_dependencies(
+        &mut self,
+        p: Id<DP::P>,
+        v: DP::V,
+        versions: DP::VS,
+        dependencies: Dependencies<DP::P, DP::VS, DP::M>,
+    ) {
+        let dependencies = match dependencies {
+            Dependencies::Unavailable(reason) => {
+                self.state
+                    .add_incompatibility(Incompatibility::custom_version(p, v, reason));
+                return;
+            }
+            Dependencies::Available(x) => x,
+        };
+
+        if !versions.contains(&v) {
+            panic!(
+                "`versions_sharing_dependencies` of package {} at {} doesn't contain it: {}",
+                self.state.package_store[p], v, versions
+            );
+        }
+
+        // Add that package and version if the dependencies are not problematic.
+        if let Some(conflict) = self
+            .state
+            .add_shared_dependencies(p, v, versions, dependencies)
+        {
+            self.state
+                .conflict_tracker
+                .entry(p)
+                .or_default()
+                .dependencies_affected += 1;
+            for (incompat_package, _) in self.state.incompatibility_store[conflict].iter() {
+                if incompat_package == p {
+                    continue;
+                }
+                self.state
+                    .conflict_tracker
+                    .entry(incompat_package)
+                    .or_default()
+                    .dependencies_culprit += 1;
+            }
+            self.bump_activity(conflict);
+        }
+    }
+
+    /// Ask the provider for the versions of the new packages among `dependencies`.
+    fn add_known_versions(
+        &mut self,
+        provider: &impl ProviderCalls<DP>,
+        dependencies: &DependencyConstraints<DP::P, DP::VS>,
+    ) {
+        for package in dependencies.keys() {
+            // Dependencies are allocated in the same order when they are added.
+            let id = match self.state.package_store.get(package) {
+                Some(id) => id,
+                None => self.state.package_store.alloc(package.clone()),
+            };
+            if !self.state.partial_solution.has_known_versions(id) {
+                let versions = provider.known_versions(package);
+                self.state.partial_solution.add_known_versions(id, versions);
+            }
+        }
+    }
+
+    /// Add to the activity of the packages of a conflict, more than previous conflicts did.
+    fn bump_activity(&mut self, conflict: IncompDpId<DP>) {
+        for (package, _) in self.state.incompatibility_store[conflict].iter() {
+            self.state
+                .conflict_tracker
+                .entry(package)
+                .or_default()
+                .activity += self.activity_increment;
+        }
+        self.activity_increment /= 0.95;
+        if self.activity_increment > 1e100 {
+            // Scale everything down before running out of floating point range.
+            for statistics in self.state.conflict_tracker.values_mut() {
+                statistics.activity *= 1e-100;
+            }
+            self.activity_increment *= 1e-100;
+        }
+        if self.reprioritize_on_conflict || self.options.activity_heuristic {
+            self.state.partial_solution.outdate_priorities();
+        }
+    }
+}
+
+/// What the callers of the solver loop can add to it.
+pub(crate) trait SolverHooks<DP: DependencyProvider> {
+    /// Called with the dependencies of each new package version, before adding them.
+    fn on_dependencies(
+        &mut self,
+        _solver: &Solver<DP>,
+        _package: &DP::P,
+        _version: &DP::V,
+        _dependencies: &Dependencies<DP::P, DP::VS, DP::M>,
+    ) {
+    }
+
+    /// Why a version chosen by the provider must not be selected, if it must not.
+    fn denied(&self, _package: &DP::P, _version: &DP::V) -> Option<DP::M> {
+        None
+    }
+
+    /// Called before each decision, cancelling the resolution on error.
+    fn on_step(&mut self, _solver: &Solver<DP>) -> Result<(), DP::Err> {
+        Ok(())
+    }
+}
+
+impl<DP: DependencyProvider> SolverHooks<DP> for () {}
+
+/// The calls to the provider that the solver loop makes without waiting for an answer, so that
+/// [step](Solver::step) and [resolve_async] share the parts of the loop in between the calls
+/// to choose versions and retrieve dependencies.
+pub(crate) trait ProviderCalls<DP: DependencyProvider> {
+    fn should_cancel(&self) -> Result<(), DP::Err>;
+
+    fn prioritize(
+        &self,
+        package: &DP::P,
+        range: &DP::VS,
+        package_conflicts_counts: &PackageResolutionStatistics,
+    ) -> DP::Priority;
+
+    fn tie_break_key(&self, package: &DP::P) -> u64;
+
+    fn on_conflict(&self, terms: &[(DP::P, Term<DP::VS>)]);
+
+    fn on_backtrack(&self, to_level: u32);
+
+    fn prefetch(&self, dependencies: &DependencyConstraints<DP::P, DP::VS>);
+
+    fn known_versions(&self, package: &DP::P) -> Option<Vec<DP::V>>;
+
+    fn versions_sharing_dependencies(&self, package: &DP::P, version: &DP::V) -> DP::VS;
+}
+
+/// The [ProviderCalls] of a [DependencyProvider].
+struct SyncProvider<'a, DP>(&'a DP);
+
+impl<DP: DependencyProvider> ProviderCalls<DP> for SyncProvider<'_, DP> {
+    fn should_cancel(&self) -> Result<(), DP::Err> {
+        self.0.should_cancel()
+    }
+
+    fn prioritize(
+        &self,
+        package: &DP::P,
+        range: &DP::VS,
+        package_conflicts_counts: &PackageResolutionStatistics,
+    ) -> DP::Priority {
+        self.0.prioritize(package, range, package_conflicts_counts)
+    }
+
+    fn tie_break_key(&self, package: &DP::P) -> u64 {
+        self.0.tie_break_key(package)
+    }
+
+    fn on_conflict(&self, terms: &[(DP::P, Term<DP::VS>)]) {
+        self.0.on_conflict(terms)
+    }
+
+    fn on_backtrack(&self, to_level: u32) {
+        self.0.on_backtrack(to_level)
+    }
+
+    fn prefetch(&self, dependencies: &DependencyConstraints<DP::P, DP::VS>) {
+        self.0.prefetch(dependencies)
+    }
+
+    fn known_versions(&self, package: &DP::P) -> Option<Vec<DP::V>> {
+        self.0.known_versions(package)
+    }
+
+    fn versions_sharing_dependencies(&self, package: &DP::P, version: &DP::V) -> DP::VS {
+        self.0.versions_sharing_dependencies(package, version)
+    }
+}
+
+/// The [ProviderCalls] of an [AsyncDependencyProvider].
+struct AsyncProvider<'a, DP>(&'a DP);
+
+impl<DP: AsyncDependencyProvider> ProviderCalls<AsyncProviderTypes<DP>> for AsyncProvider<'_, DP> {
+    fn should_cancel(&self) -> Result<(), DP::Err> {
+        self.0.should_cancel()
+    }
+
+    fn prioritize(
+        &self,
+        package: &DP::P,
+        range: &DP::VS,
+        package_conflicts_counts: &PackageResolutionStatistics,
+    ) -> DP::Priority {
+        self.0.prioritize(package, range, package_conflicts_counts)
+    }
+
+    fn tie_break_key(&self, package: &DP::P) -> u64 {
+        self.0.tie_break_key(package)
+    }
+
+    fn on_conflict(&self, terms: &[(DP::P, Term<DP::VS>)]) {
+        self.0.on_conflict(terms)
+    }
+
+    fn on_backtrack(&self, to_level: u32) {
+        self.0.on_backtrack(to_level)
+    }
+
+    fn prefetch(&self, dependencies: &DependencyConstraints<DP::P, DP::VS>) {
+        self.0.prefetch(dependencies)
+    }
+
+    fn known_versions(&self, package: &DP::P) -> Option<Vec<DP::V>> {
+        self.0.known_versions(package)
+    }
+
+    fn versions_sharing_dependencies(&self, package: &DP::P, version: &DP::V) -> DP::VS {
+        self.0.versions_sharing_dependencies(package, version)
+    }
+}
+
+/// Asynchronous variant of [resolve].
+///
+/// The provider is queried through [AsyncDependencyProvider], so that network-backed registries
+/// can fetch versions and dependencies without blocking a thread. The solver itself is
+/// sequential: only one provider call is in flight at a time.
+///
+/// This crate does not depend on any async runtime, the returned future can be driven by
+/// any executor.
+#[cold]
+pub async fn resolve_async<DP: AsyncDependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> Result<SelectedDependencies<AsyncProviderTypes<DP>>, PubGrubError<AsyncProviderTypes<DP>>> {
+    let mut solver: Solver<AsyncProviderTypes<DP>> = Solver::new(package, version.into());
+    solver.reprioritize_on_conflict = dependency_provider.reprioritize_on_conflict();
+    solver.log_start();
+    let start = Stopwatch::start();
+    solver.start = start;
+    let result = solve_steps_async(&mut solver, dependency_provider).await;
+    solver.log_outcome(&result, start.elapsed());
+    result
+}
+
+async fn solve_steps_async<DP: AsyncDependencyProvider>(
+    solver: &mut Solver<AsyncProviderTypes<DP>>,
+    dependency_provider: &DP,
+) -> Result<SelectedDependencies<AsyncProviderTypes<DP>>, PubGrubError<AsyncProviderTypes<DP>>> {
+    loop {
+        if let StepId::Solved = solver.step_async(dependency_provider).await? {
+            return Ok(solver.extract_solution());
         }
     }
 }
 
+impl<DP: AsyncDependencyProvider> Solver<AsyncProviderTypes<DP>> {
+    /// Same as [step](Self::step), awaiting the provider to choose versions and retrieve
+    /// dependencies.
+    #[allow(clippy::type_complexity)]
+    async fn step_async(
+        &mut self,
+        dependency_provider: &DP,
+    ) -> Result<StepId<DP::P, DP::V>, PubGrubError<AsyncProviderTypes<DP>>> {
+        let provider = AsyncProvider(dependency_provider);
+        let Some((next, pinned)) = self.prepare_step(&provider, &mut ())? else {
+            return Ok(StepId::Solved);
+        };
+        let decision = match pinned {
+            Some(v) => Some(v),
+            None => {
+                let start = Stopwatch::start();
+                let decision = dependency_provider
+                    .choose_version_in(
+                        &self.state.package_store[next],
+                        self.range(next),
+                        &self.state,
+                    )
+                    .await
+                    .map_err(|err| self.state.error_choosing_version(next, err))?;
+                self.state.record_time(next, start.elapsed());
+                decision
+            }
+        };
+        let (step, new_version) = self.decide(next, decision, &mut ());
+        let Some(v) = new_version else {
+            return Ok(step);
+        };
+        let dependencies = match self.take_batched(next, &v) {
+            Some(dependencies) => dependencies,
+            None => {
+                let versions = self.batch_versions(next, &v);
+                let start = Stopwatch::start();
+                let batch = dependency_provider
+                    .get_dependencies_batch(&self.state.package_store[next], &versions)
+                    .await
+                    .map_err(|err| {
+                        self.state
+                            .error_retrieving_dependencies(next, v.clone(), err)
+                    })?;
+                self.state.record_time(next, start.elapsed());
+                self.add_batch(next, versions, batch)
+            }
+        };
+        self.add_retrieved(&provider, &mut (), next, v, dependencies);
+        Ok(step)
+    }
+}
+
 /// An enum used by [DependencyProvider] that holds information about package dependencies.
 /// For each [Package] there is a set of versions allowed as a dependency.
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "P: serde::Serialize, VS: serde::Serialize, M: serde::Serialize",
+        deserialize = "P: serde::Deserialize<'de>, VS: serde::Deserialize<'de>, M: serde::Deserialize<'de>"
+    ))
+)]
 pub enum Dependencies<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
     /// Package dependencies are unavailable with the reason why they are missing.
     Unavailable(M),
@@ -282,7 +1718,7 @@ pub trait DependencyProvider {
     /// The type returned from `prioritize`. The resolver does not care what type this is
     /// as long as it can pick a largest one and clone it.
     ///
-    /// [`Reverse`](std::cmp::Reverse) can be useful if you want to pick the package with
+    /// [`Reverse`] can be useful if you want to pick the package with
     /// the fewest versions that match the outstanding constraint.
     type Priority: Ord + Clone;
 
@@ -313,14 +1749,20 @@ pub trait DependencyProvider {
     /// constraint) and packages with more conflicts first.
     ///
     /// The `package_conflicts_counts` argument provides access to some other heuristics that
-    /// are production users have found useful. Although the exact meaning/efficacy of those
-    /// arguments may change.
+    /// are production users have found useful: the
+    /// [conflict count](PackageResolutionStatistics::conflict_count), the
+    /// [activity](PackageResolutionStatistics::activity) and the
+    /// [depth](PackageResolutionStatistics::depth) of the package. Although the exact
+    /// meaning/efficacy of those arguments may change. The number of versions left in `range`
+    /// is known to the provider, [OfflineDependencyProvider](crate::OfflineDependencyProvider)
+    /// decides the packages with fewer versions first.
     ///
     /// The function is called once for each new package and then cached until we detect a
     /// (potential) change to `range`, otherwise it is cached, assuming that the priority only
     /// depends on the arguments to this function.
     ///
-    /// If two packages have the same priority, PubGrub will bias toward a breadth first search.
+    /// If two packages have the same priority, PubGrub will bias toward a breadth first search,
+    /// see [TieBreak] for the other orders.
     fn prioritize(
         &self,
         package: &Self::P,
@@ -330,6 +1772,27 @@ pub trait DependencyProvider {
         package_conflicts_counts: &PackageResolutionStatistics,
     ) -> Self::Priority;
 
+    /// Whether to call [prioritize](Self::prioritize) again for all the undecided packages after
+    /// each conflict.
+    ///
+    /// Priorities are cached while the range of a package doesn't change, which is enough for
+    /// priorities depending on the conflicts of the package itself. Priorities comparing the
+    /// [activity](PackageResolutionStatistics::activity) of packages change with every conflict,
+    /// at the cost of prioritizing every package again. Defaults to `false`.
+    fn reprioritize_on_conflict(&self) -> bool {
+        false
+    }
+
+    /// The key ordering the packages of the same priority with [TieBreak::ProviderKey], the
+    /// smallest first, for example a hash of the package name stable across resolutions.
+    ///
+    /// Unlike the priority, the key of a package never changes during a resolution. Returns `0`
+    /// by default, keeping the order in which the solver saw the packages.
+    fn tie_break_key(&self, package: &Self::P) -> u64 {
+        let _ = package;
+        0
+    }
+
     /// Once the resolver has found the highest `Priority` package from all potential valid
     /// packages, it needs to know what version of that package to use. The most common pattern
     /// is to select the largest version that the range contains.
@@ -348,6 +1811,22 @@ pub trait DependencyProvider {
         version: &Self::V,
     ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err>;
 
+    /// Same as [choose_version](Self::choose_version), with the decisions and derivations of the
+    /// solver so far, for choices depending on the other packages.
+    ///
+    /// For example, a provider could prefer the versions sharing a major version with the
+    /// decided packages of the same family. The solver calls this method, which defaults to
+    /// [choose_version](Self::choose_version).
+    fn choose_version_in(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        solution: &dyn PartialSolutionView<Self::P, Self::VS>,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        let _ = solution;
+        self.choose_version(package, range)
+    }
+
     /// This is called fairly regularly during the resolution,
     /// if it returns an Err then resolution will be terminated.
     /// This is helpful if you want to add some form of early termination like a timeout,
@@ -356,4 +1835,294 @@ pub trait DependencyProvider {
     fn should_cancel(&self) -> Result<(), Self::Err> {
         Ok(())
     }
+
+    /// Called when the solver finds a conflict, with the terms of the incompatibility it learned
+    /// from it: no solution satisfies all of them at once.
+    ///
+    /// The solver then backtracks, see [on_backtrack](Self::on_backtrack). Together, the two
+    /// callbacks let a provider follow the search, for example to gather its own statistics on
+    /// the packages taking part in conflicts. Does nothing by default.
+    fn on_conflict(&self, terms: &[(Self::P, Term<Self::VS>)]) {
+        let _ = terms;
+    }
+
+    /// Called when the solver backtracks after a conflict or a restart, keeping only its first
+    /// `to_level` decisions, as counted by [PartialSolutionView::decision_level].
+    ///
+    /// The other decisions and what was derived from them are undone, so a provider caching
+    /// results speculatively for these decisions can invalidate them. Does nothing by default.
+    fn on_backtrack(&self, to_level: u32) {
+        let _ = to_level;
+    }
+
+    /// Called with the dependencies of each new package version retrieved with
+    /// [get_dependencies](Self::get_dependencies), the packages the solver is likely to query
+    /// next.
+    ///
+    /// The solver queries the provider sequentially. A provider backed by the network can use
+    /// this hook to start fetching the versions and dependencies of these packages
+    /// concurrently, so that they are in its cache when the solver asks for them.
+    /// Does nothing by default.
+    fn prefetch(&self, dependencies: &DependencyConstraints<Self::P, Self::VS>) {
+        let _ = dependencies;
+    }
+
+    /// The versions of `package`, if they are known without extra work, for example from an
+    /// index already in memory.
+    ///
+    /// The solver compiles the terms on packages with known versions into bitsets over these
+    /// versions, which makes unit propagation much faster on large graphs. The versions are
+    /// only used to speed up the solver, missing some of them is not an error. It is called once
+    /// per package, when it first appears in dependencies. Returns `None` by default.
+    fn known_versions(&self, package: &Self::P) -> Option<Vec<Self::V>> {
+        let _ = package;
+        None
+    }
+
+    /// Retrieves the dependencies of several versions of a package at once.
+    ///
+    /// The solver needs the dependencies of `versions[0]`, the others are the
+    /// [known versions](Self::known_versions) of the package whose dependencies were not
+    /// retrieved yet, newest first, which the solver may try after backtracking. A registry
+    /// serving the metadata of all the versions of a package in one response can answer for all
+    /// of them at once, instead of being asked again for each version.
+    ///
+    /// Returns the dependencies in the order of `versions`, at least those of the first one,
+    /// and the solver keeps the others until it needs them. Defaults to
+    /// [get_dependencies](Self::get_dependencies) for the first version only.
+    #[allow(clippy::type_complexity)]
+    fn get_dependencies_batch(
+        &self,
+        package: &Self::P,
+        versions: &[Self::V],
+    ) -> Result<Vec<Dependencies<Self::P, Self::VS, Self::M>>, Self::Err> {
+        Ok(vec![self.get_dependencies(package, &versions[0])?])
+    }
+
+    /// The versions of `package` with the same dependencies as `version`, including it.
+    ///
+    /// The solver adds the dependencies returned for `version` as dependencies of all these
+    /// versions at once. A conflict caused by a dependency then excludes the whole range, instead
+    /// of the solver trying each of its versions one by one. The provider promises that
+    /// [get_dependencies](Self::get_dependencies) would return the same dependencies for any of
+    /// them, for example when the dependencies only change in some releases. Defaults to
+    /// `version` alone.
+    fn versions_sharing_dependencies(&self, package: &Self::P, version: &Self::V) -> Self::VS {
+        let _ = package;
+        Self::VS::singleton(version.clone())
+    }
+}
+
+/// Asynchronous counterpart of [DependencyProvider], used by [resolve_async].
+///
+/// The associated types and the cheap, in-memory methods ([prioritize](Self::prioritize),
+/// [should_cancel](Self::should_cancel), [known_versions](Self::known_versions) and so on) are
+/// the same as for [DependencyProvider]. Choosing versions and retrieving dependencies returns
+/// futures, so that they can be backed by network requests.
+pub trait AsyncDependencyProvider {
+    /// How this provider stores the name of the packages.
+    type P: Package;
+
+    /// How this provider stores the versions of the packages.
+    type V: Debug + Display + Clone + Ord;
+
+    /// How this provider stores the version requirements for the packages.
+    type VS: VersionSet<V = Self::V>;
+
+    /// The type returned from `prioritize`, see [DependencyProvider::Priority].
+    type Priority: Ord + Clone;
+
+    /// Type for custom incompatibilities, see [DependencyProvider::M].
+    type M: Eq + Clone + Debug + Display;
+
+    /// The kind of error returned from these methods.
+    ///
+    /// Returning this signals that resolution should fail with this error.
+    type Err: Error + 'static;
+
+    /// Determine the order in which versions are chosen for packages,
+    /// see [DependencyProvider::prioritize].
+    fn prioritize(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        package_conflicts_counts: &PackageResolutionStatistics,
+    ) -> Self::Priority;
+
+    /// Whether to prioritize all the undecided packages again after each conflict,
+    /// see [DependencyProvider::reprioritize_on_conflict].
+    fn reprioritize_on_conflict(&self) -> bool {
+        false
+    }
+
+    /// The key ordering packages of the same priority, see [DependencyProvider::tie_break_key].
+    fn tie_break_key(&self, package: &Self::P) -> u64 {
+        let _ = package;
+        0
+    }
+
+    /// Select the version to use for the highest priority package,
+    /// see [DependencyProvider::choose_version].
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> impl Future<Output = Result<Option<Self::V>, Self::Err>>;
+
+    /// Retrieves the package dependencies,
+    /// see [DependencyProvider::get_dependencies].
+    #[allow(clippy::type_complexity)]
+    fn get_dependencies(
+        &self,
+        package: &Self::P,
+        version: &Self::V,
+    ) -> impl Future<Output = Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err>>;
+
+    /// Same as [choose_version](Self::choose_version), with the decisions and derivations of the
+    /// resolution so far, see [DependencyProvider::choose_version_in].
+    fn choose_version_in(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        solution: &dyn PartialSolutionView<Self::P, Self::VS>,
+    ) -> impl Future<Output = Result<Option<Self::V>, Self::Err>> {
+        let _ = solution;
+        self.choose_version(package, range)
+    }
+
+    /// Retrieves the dependencies of several versions of a package at once,
+    /// see [DependencyProvider::get_dependencies_batch].
+    #[allow(clippy::type_complexity)]
+    fn get_dependencies_batch(
+        &self,
+        package: &Self::P,
+        versions: &[Self::V],
+    ) -> impl Future<Output = Result<Vec<Dependencies<Self::P, Self::VS, Self::M>>, Self::Err>>
+    {
+        async move { Ok(vec![self.get_dependencies(package, &versions[0]).await?]) }
+    }
+
+    /// This is called fairly regularly during the resolution,
+    /// if it returns an Err then resolution will be terminated,
+    /// see [DependencyProvider::should_cancel].
+    fn should_cancel(&self) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    /// Called when the solver finds a conflict, see [DependencyProvider::on_conflict].
+    fn on_conflict(&self, terms: &[(Self::P, Term<Self::VS>)]) {
+        let _ = terms;
+    }
+
+    /// Called when the solver backtracks, see [DependencyProvider::on_backtrack].
+    fn on_backtrack(&self, to_level: u32) {
+        let _ = to_level;
+    }
+
+    /// Called with the dependencies of each new package version retrieved,
+    /// see [DependencyProvider::prefetch].
+    fn prefetch(&self, dependencies: &DependencyConstraints<Self::P, Self::VS>) {
+        let _ = dependencies;
+    }
+
+    /// The versions of `package`, if they are known without waiting,
+    /// see [DependencyProvider::known_versions].
+    fn known_versions(&self, package: &Self::P) -> Option<Vec<Self::V>> {
+        let _ = package;
+        None
+    }
+
+    /// The versions of `package` with the same dependencies as `version`, including it,
+    /// see [DependencyProvider::versions_sharing_dependencies].
+    fn versions_sharing_dependencies(&self, package: &Self::P, version: &Self::V) -> Self::VS {
+        let _ = package;
+        Self::VS::singleton(version.clone())
+    }
+}
+
+/// The types of an [AsyncDependencyProvider] seen as a [DependencyProvider].
+///
+/// The solver state and the error types are generic over [DependencyProvider], this adapter lets
+/// [resolve_async] share them with [resolve]. It is never used to query packages: its
+/// [DependencyProvider] methods are unreachable.
+pub struct AsyncProviderTypes<DP>(PhantomData<fn() -> DP>);
+
+impl<DP: AsyncDependencyProvider> DependencyProvider for AsyncProviderTypes<DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type Priority = DP::Priority;
+    type M = DP::M;
+    type Err = DP::Err;
+
+    fn prioritize(
+        &self,
+        _package: &Self::P,
+        _range: &Self::VS,
+        _package_conflicts_counts: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        unreachable!("`AsyncProviderTypes` is never instantiated")
+    }
+
+    fn choose_version(
+        &self,
+        _package: &Self::P,
+        _range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        unreachable!("`AsyncProviderTypes` is never instantiated")
+    }
+
+    fn get_dependencies(
+        &self,
+        _package: &Self::P,
+        _version: &Self::V,
+    ) -> Result<Dependencies<Self::P, Self::VS, Self::M>, Self::Err> {
+        unreachable!("`AsyncProviderTypes` is never instantiated")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn luby_sequence() {
+        let terms: Vec<u32> = (1..=15)
+            .map(|i| luby(NonZeroU32::new(i).unwrap()))
+            .collect();
+        assert_eq!(terms, [1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn denied_version_step() {
+        use crate::{OfflineDependencyProvider, Ranges, VersionPolicies};
+
+        let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+        dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+        dependency_provider.add_dependencies("foo", 1u32, []);
+        dependency_provider.add_dependencies("foo", 2u32, []);
+        let mut policies = VersionPolicies::new();
+        policies.deny("foo", "CVE-2024-0001".to_string(), |v: &u32| *v == 2);
+
+        let mut hooks = &policies;
+        let mut solver = Solver::new("root", 1u32);
+        let mut steps = Vec::new();
+        loop {
+            let step = solver.step(&dependency_provider, &mut hooks).unwrap();
+            let step = solver.to_step(step);
+            if let Step::Solved(solution) = step {
+                assert_eq!(solution["foo"], 1);
+                break;
+            }
+            steps.push(step);
+        }
+        assert_eq!(
+            steps,
+            [
+                Step::Chose("root", 1),
+                Step::Denied("foo", 2),
+                Step::Chose("foo", 1)
+            ]
+        );
+    }
 }
@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Run the solver on a [State] prepared beforehand, all at once or one step at a time.
+//!
+//! [resolve] builds its [State] from the root package and runs the solver loop until the end.
+//! Splitting the two phases lets the caller seed the state first, for example with global
+//! [constraints](State::add_constraint), other [roots](State::add_root) or
+//! [dependencies known in advance](State::add_package_version_dependencies), then
+//! [resolve_state] runs the same loop as [resolve]. Preferences are a matter of the provider,
+//! see [WithPreferences](crate::WithPreferences).
+//!
+//! A [SteppingResolver] runs the loop one decision at a time instead, so that a debugger or a
+//! visualizer can show the [State] between the steps.
+//!
+//! [resolve]: crate::resolve
+
+use alloc::vec::Vec;
+
+use crate::internal::State;
+use crate::solver::Solver;
+use crate::{DependencyProvider, Map, Package, PubGrubError, SelectedDependencies, TraceEvent};
+
+/// Finds a set of packages satisfying the dependencies of the root package of `state`, on top
+/// of the incompatibilities already added to `state`.
+///
+/// ```
+/// # use pubgrub::{resolve_state, OfflineDependencyProvider, Ranges, State};
+/// #
+/// type NumVS = Ranges<u32>;
+///
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("zlib", Ranges::full())]);
+/// dependency_provider.add_dependencies("zlib", 1u32, []);
+/// dependency_provider.add_dependencies("zlib", 2u32, []);
+///
+/// let mut state = State::<OfflineDependencyProvider<&str, NumVS>>::init("root", 1u32);
+/// state.add_constraint("zlib", Ranges::strictly_lower_than(2u32), "CVE-1".to_string());
+/// let solution = resolve_state(&dependency_provider, state).unwrap();
+/// assert_eq!(solution["zlib"], 1);
+/// ```
+pub fn resolve_state<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    state: State<DP>,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    Solver::from_state(state).solve(dependency_provider)
+}
+
+/// What the solver did in a [step](SteppingResolver::step).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step<P: Package, V> {
+    /// The provider chose a version of the package, whose dependencies were added.
+    ///
+    /// The version is decided unless it conflicts with the decisions so far, in which case the
+    /// next step derives why and backtracks.
+    Chose(P, V),
+    /// There is no version of the package left in the allowed range, and the next step
+    /// backtracks.
+    NoVersion(P),
+    /// The provider chose a version of the package denied by a policy, which is forbidden
+    /// instead of decided, and the next step chooses again.
+    Denied(P, V),
+    /// All the required packages are decided: the resolution is over with this solution.
+    Solved(Map<P, V>),
+}
+
+/// Run the solver loop on a [State] one step at a time.
+///
+/// Each call to [step](Self::step) propagates the last decision then decides one more package,
+/// like an iteration of the loop of [resolve_state], and [state](Self::state) shows the
+/// partial solution and the incompatibilities in between. The resolution is over after
+/// [Step::Solved] or an error, after which the resolver shouldn't be stepped again.
+///
+/// ```
+/// # use pubgrub::{OfflineDependencyProvider, PartialSolutionView, Ranges, State, Step, SteppingResolver};
+/// #
+/// type NumVS = Ranges<u32>;
+///
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("zlib", Ranges::full())]);
+/// dependency_provider.add_dependencies("zlib", 1u32, []);
+///
+/// let mut resolver = SteppingResolver::new(State::init("root", 1u32));
+/// assert_eq!(resolver.step(&dependency_provider).unwrap(), Step::Chose("root", 1));
+/// assert_eq!(resolver.step(&dependency_provider).unwrap(), Step::Chose("zlib", 1));
+/// assert_eq!(resolver.state().decision_level(), 2);
+/// let Step::Solved(solution) = resolver.step(&dependency_provider).unwrap() else {
+///     unreachable!()
+/// };
+/// assert_eq!(solution.len(), 2);
+/// ```
+pub struct SteppingResolver<DP: DependencyProvider> {
+    solver: Solver<DP>,
+    started: bool,
+}
+
+impl<DP: DependencyProvider> SteppingResolver<DP> {
+    /// Prepare the resolution of the root package of `state`.
+    pub fn new(state: State<DP>) -> Self {
+        Self {
+            solver: Solver::from_state(state),
+            started: false,
+        }
+    }
+
+    /// The state of the solver after the last step.
+    pub fn state(&self) -> &State<DP> {
+        &self.solver.state
+    }
+
+    /// Record the [trace](TraceEvent) of the next steps, see [take_trace](Self::take_trace).
+    pub fn record_trace(&mut self) {
+        self.solver.state.record_trace();
+    }
+
+    /// The events of the trace since the last call, none unless
+    /// [record_trace](Self::record_trace) was called.
+    #[allow(clippy::type_complexity)]
+    pub fn take_trace(&mut self) -> Vec<TraceEvent<DP::P, DP::VS, DP::M>> {
+        self.solver.state.take_trace()
+    }
+
+    /// Run one step of the solver loop.
+    #[allow(clippy::type_complexity)]
+    pub fn step(
+        &mut self,
+        dependency_provider: &DP,
+    ) -> Result<Step<DP::P, DP::V>, PubGrubError<DP>> {
+        if !self.started {
+            self.solver.begin(dependency_provider);
+            self.started = true;
+        }
+        let step = self.solver.step(dependency_provider, &mut ())?;
+        Ok(self.solver.to_step(step))
+    }
+
+    /// Run the remaining steps of the solver loop, until the end of the resolution.
+    pub fn finish(
+        &mut self,
+        dependency_provider: &DP,
+    ) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+        loop {
+            if let Step::Solved(solution) = self.step(dependency_provider)? {
+                return Ok(solution);
+            }
+        }
+    }
+}
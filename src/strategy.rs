@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Choose versions with a strategy instead of the provider's own preference.
+//!
+//! Checking that the lower bounds of the requirements are correct, for example in CI, needs a
+//! resolution that picks the oldest versions rather than the newest ones. Instead of
+//! implementing [choose_version](DependencyProvider::choose_version) once per strategy, a
+//! provider can implement [ListVersions] and be resolved with any [VersionStrategy] through
+//! [resolve_with_strategy].
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::delegate::delegate_provider;
+use crate::{
+    resolve, Dependencies, DependencyProvider, PartialSolutionView, PubGrubError,
+    SelectedDependencies, Set, VersionSet,
+};
+
+/// A [DependencyProvider] that can list all the versions of a package.
+pub trait ListVersions: DependencyProvider {
+    /// All the versions of a package, in any order.
+    fn list_versions(
+        &self,
+        package: &Self::P,
+    ) -> Result<impl Iterator<Item = Self::V> + '_, Self::Err>;
+}
+
+/// Which version to pick among the versions of a package allowed by the constraints.
+pub enum VersionStrategy<P, V> {
+    /// Delegate to [choose_version](DependencyProvider::choose_version) of the provider,
+    /// usually the newest version.
+    Maximal,
+    /// The oldest version.
+    Minimal,
+    /// The oldest version for the direct dependencies of the root package,
+    /// [Maximal](Self::Maximal) for the others.
+    DirectMinimal,
+    /// The greatest version according to a comparator, which also receives the package.
+    #[allow(clippy::type_complexity)]
+    Custom(Box<dyn Fn(&P, &V, &V) -> Ordering>),
+}
+
+/// A [DependencyProvider] choosing versions according to a [VersionStrategy].
+pub struct WithStrategy<'a, DP: ListVersions> {
+    provider: &'a DP,
+    strategy: VersionStrategy<DP::P, DP::V>,
+    /// Packages resolved with [Minimal](VersionStrategy::Minimal) under
+    /// [DirectMinimal](VersionStrategy::DirectMinimal).
+    direct: Set<DP::P>,
+}
+
+impl<'a, DP: ListVersions> WithStrategy<'a, DP> {
+    /// Choose versions of `provider` according to `strategy`.
+    ///
+    /// With [DirectMinimal](VersionStrategy::DirectMinimal), all packages are resolved with
+    /// [Maximal](VersionStrategy::Maximal) until [direct](Self::direct) is set;
+    /// [resolve_with_strategy] does that.
+    pub fn new(provider: &'a DP, strategy: VersionStrategy<DP::P, DP::V>) -> Self {
+        Self {
+            provider,
+            strategy,
+            direct: Set::default(),
+        }
+    }
+
+    /// Set the direct dependencies used by [DirectMinimal](VersionStrategy::DirectMinimal).
+    pub fn direct(mut self, direct: Set<DP::P>) -> Self {
+        self.direct = direct;
+        self
+    }
+}
+
+impl<DP: ListVersions> DependencyProvider for WithStrategy<'_, DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, get_dependencies,
+        should_cancel, prefetch, on_conflict, on_backtrack, known_versions,
+        versions_sharing_dependencies, get_dependencies_batch,
+    );
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        choose_with_strategy(
+            self.provider,
+            &self.strategy,
+            &self.direct,
+            package,
+            range,
+            None,
+        )
+    }
+
+    fn choose_version_in(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        solution: &dyn PartialSolutionView<Self::P, Self::VS>,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        choose_with_strategy(
+            self.provider,
+            &self.strategy,
+            &self.direct,
+            package,
+            range,
+            Some(solution),
+        )
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// choosing versions according to `strategy`.
+pub fn resolve_with_strategy<DP: ListVersions>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    strategy: VersionStrategy<DP::P, DP::V>,
+) -> Result<SelectedDependencies<DP>, PubGrubError<DP>> {
+    let version = version.into();
+    let mut direct = Set::default();
+    if let VersionStrategy::DirectMinimal = strategy {
+        direct = direct_dependencies(dependency_provider, &package, &version)?;
+    }
+    let provider = WithStrategy::new(dependency_provider, strategy).direct(direct);
+    resolve(&provider, package, version).map_err(PubGrubError::into_provider)
+}
+
+/// The version of `package` in `range` chosen by `strategy`, `direct` being the direct
+/// dependencies of the root package.
+///
+/// The choices left to the provider go through
+/// [choose_version_in](DependencyProvider::choose_version_in) when the partial `solution` is
+/// known.
+pub(crate) fn choose_with_strategy<DP: ListVersions>(
+    provider: &DP,
+    strategy: &VersionStrategy<DP::P, DP::V>,
+    direct: &Set<DP::P>,
+    package: &DP::P,
+    range: &DP::VS,
+    solution: Option<&dyn PartialSolutionView<DP::P, DP::VS>>,
+) -> Result<Option<DP::V>, DP::Err> {
+    let versions = || -> Result<_, DP::Err> {
+        Ok(provider
+            .list_versions(package)?
+            .filter(|v| range.contains(v)))
+    };
+    let provider_choice = || match solution {
+        Some(solution) => provider.choose_version_in(package, range, solution),
+        None => provider.choose_version(package, range),
+    };
+    match strategy {
+        VersionStrategy::Maximal => provider_choice(),
+        VersionStrategy::DirectMinimal if !direct.contains(package) => provider_choice(),
+        VersionStrategy::Minimal | VersionStrategy::DirectMinimal => Ok(versions()?.min()),
+        VersionStrategy::Custom(compare) => Ok(versions()?.max_by(|a, b| compare(package, a, b))),
+    }
+}
+
+/// The direct dependencies of `version` of the root `package`, for
+/// [DirectMinimal](VersionStrategy::DirectMinimal).
+pub(crate) fn direct_dependencies<DP: DependencyProvider>(
+    provider: &DP,
+    package: &DP::P,
+    version: &DP::V,
+) -> Result<Set<DP::P>, PubGrubError<DP>> {
+    let dependencies = provider.get_dependencies(package, version).map_err(|err| {
+        PubGrubError::ErrorRetrievingDependencies {
+            package: package.clone(),
+            version: version.clone(),
+            source: err,
+            required_by: Vec::new(),
+            decided: Vec::new(),
+        }
+    })?;
+    Ok(match dependencies {
+        Dependencies::Available(dependencies) => dependencies.into_keys().collect(),
+        Dependencies::Unavailable(_) => Set::default(),
+    })
+}
@@ -2,8 +2,14 @@
 
 //! A term is the fundamental unit of operation of the PubGrub algorithm.
 //! It is a positive or negative expression regarding a set of versions.
+//!
+//! The solver keeps one term per package in the partial solution, the intersection of the terms
+//! derived so far, and compares it with the terms of the incompatibilities with
+//! [relation_with](Term::relation_with). The same algebra is public so that providers and tools
+//! can reason about the terms of [conflicts](crate::DependencyProvider::on_conflict)
+//! and [derivation trees](crate::DerivationTree).
 
-use std::fmt::{self, Display};
+use core::fmt::{self, Display};
 
 use crate::VersionSet;
 
@@ -17,7 +23,25 @@ use crate::VersionSet;
 /// allows for a solution that does not have that package selected.
 /// Specifically, `Positive(VS::empty())` means that there was a conflict (we need to select a version for the package
 /// but can't pick any), while `Negative(VS::full())` would mean it is fine as long as we don't select the package.
+///
+/// ```
+/// # use pubgrub::{Ranges, Relation, Term};
+/// #
+/// let at_least_2 = Term::Positive(Ranges::higher_than(2u32));
+/// let not_3 = Term::Negative(Ranges::singleton(3u32));
+/// let known = at_least_2.intersection(&not_3);
+/// assert!(known.contains(&4));
+/// assert!(!known.contains(&3));
+///
+/// assert_eq!(Term::Positive(Ranges::higher_than(1u32)).relation_with(&known), Relation::Satisfied);
+/// assert_eq!(Term::exact(1u32).relation_with(&known), Relation::Contradicted);
+/// assert_eq!(Term::exact(4u32).relation_with(&known), Relation::Inconclusive);
+/// // Not selecting the package satisfies `not_3` but not `at_least_2`.
+/// assert!(not_3.negate().is_positive());
+/// assert!(!at_least_2.satisfied_by(&not_3));
+/// ```
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Term<VS: VersionSet> {
     /// For example, `1.0.0 <= v < 2.0.0` is a positive expression
     /// that is evaluated true if a version is selected
@@ -32,22 +56,22 @@ pub enum Term<VS: VersionSet> {
 /// Base methods.
 impl<VS: VersionSet> Term<VS> {
     /// A term that is always true.
-    pub(crate) fn any() -> Self {
+    pub fn any() -> Self {
         Self::Negative(VS::empty())
     }
 
     /// A term that is never true.
-    pub(crate) fn empty() -> Self {
+    pub fn empty() -> Self {
         Self::Positive(VS::empty())
     }
 
     /// A positive term containing exactly that version.
-    pub(crate) fn exact(version: VS::V) -> Self {
+    pub fn exact(version: VS::V) -> Self {
         Self::Positive(VS::singleton(version))
     }
 
     /// Simply check if a term is positive.
-    pub(crate) fn is_positive(&self) -> bool {
+    pub fn is_positive(&self) -> bool {
         match self {
             Self::Positive(_) => true,
             Self::Negative(_) => false,
@@ -57,7 +81,7 @@ impl<VS: VersionSet> Term<VS> {
     /// Negate a term.
     /// Evaluation of a negated term always returns
     /// the opposite of the evaluation of the original one.
-    pub(crate) fn negate(&self) -> Self {
+    pub fn negate(&self) -> Self {
         match self {
             Self::Positive(set) => Self::Negative(set.clone()),
             Self::Negative(set) => Self::Positive(set.clone()),
@@ -65,7 +89,7 @@ impl<VS: VersionSet> Term<VS> {
     }
 
     /// Evaluate a term regarding a given choice of version.
-    pub(crate) fn contains(&self, v: &VS::V) -> bool {
+    pub fn contains(&self, v: &VS::V) -> bool {
         match self {
             Self::Positive(set) => set.contains(v),
             Self::Negative(set) => !set.contains(v),
@@ -83,7 +107,7 @@ impl<VS: VersionSet> Term<VS> {
 
     /// Unwrap the set contained in a negative term.
     /// Will panic if used on a positive set.
-    pub(crate) fn unwrap_negative(&self) -> &VS {
+    pub fn unwrap_negative(&self) -> &VS {
         match self {
             Self::Negative(set) => set,
             _ => panic!("Positive term cannot unwrap negative set"),
@@ -97,7 +121,7 @@ impl<VS: VersionSet> Term<VS> {
     ///
     /// The intersection is negative (unselected package is allowed)
     /// if all terms are negative.
-    pub(crate) fn intersection(&self, other: &Self) -> Self {
+    pub fn intersection(&self, other: &Self) -> Self {
         match (self, other) {
             (Self::Positive(r1), Self::Positive(r2)) => Self::Positive(r1.intersection(r2)),
             (Self::Positive(p), Self::Negative(n)) | (Self::Negative(n), Self::Positive(p)) => {
@@ -110,7 +134,7 @@ impl<VS: VersionSet> Term<VS> {
     /// Check whether two terms are mutually exclusive.
     ///
     /// An optimization for the native implementation of checking whether the intersection of two sets is empty.
-    pub(crate) fn is_disjoint(&self, other: &Self) -> bool {
+    pub fn is_disjoint(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Positive(r1), Self::Positive(r2)) => r1.is_disjoint(r2),
             // Unselected package is allowed in both terms, so they are never disjoint.
@@ -125,7 +149,7 @@ impl<VS: VersionSet> Term<VS> {
 
     /// Compute the union of two terms.
     /// If at least one term is negative, the union is also negative (unselected package is allowed).
-    pub(crate) fn union(&self, other: &Self) -> Self {
+    pub fn union(&self, other: &Self) -> Self {
         match (self, other) {
             (Self::Positive(r1), Self::Positive(r2)) => Self::Positive(r1.union(r2)),
             (Self::Positive(p), Self::Negative(n)) | (Self::Negative(n), Self::Positive(p)) => {
@@ -138,7 +162,7 @@ impl<VS: VersionSet> Term<VS> {
     /// Indicate if this term is a subset of another term.
     /// Just like for sets, we say that t1 is a subset of t2
     /// if and only if t1 ∩ t2 = t1.
-    pub(crate) fn subset_of(&self, other: &Self) -> bool {
+    pub fn subset_of(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Positive(r1), Self::Positive(r2)) => r1.subset_of(r2),
             (Self::Positive(r1), Self::Negative(r2)) => r1.is_disjoint(r2),
@@ -154,7 +178,10 @@ impl<VS: VersionSet> Term<VS> {
 ///
 /// As a shorthand, we say that a term v
 /// satisfies or contradicts a term t if {v} satisfies or contradicts it.
-pub(crate) enum Relation {
+///
+/// Returned by [Term::relation_with].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
     /// We say that a set of terms S "satisfies" a term t
     /// if t must be true whenever every term in S is true.
     Satisfied,
@@ -174,8 +201,7 @@ impl<VS: VersionSet> Term<VS> {
     ///
     /// It turns out that this can also be expressed with set operations:
     ///    S satisfies t if and only if  ⋂ S ⊆ t
-    #[cfg(test)]
-    fn satisfied_by(&self, terms_intersection: &Self) -> bool {
+    pub fn satisfied_by(&self, terms_intersection: &Self) -> bool {
         terms_intersection.subset_of(self)
     }
 
@@ -187,14 +213,13 @@ impl<VS: VersionSet> Term<VS> {
     /// It turns out that this can also be expressed with set operations:
     ///    S contradicts t if and only if ⋂ S is disjoint with t
     ///    S contradicts t if and only if  (⋂ S) ⋂ t = ∅
-    #[cfg(test)]
-    fn contradicted_by(&self, terms_intersection: &Self) -> bool {
+    pub fn contradicted_by(&self, terms_intersection: &Self) -> bool {
         terms_intersection.intersection(self) == Self::empty()
     }
 
     /// Check if a set of terms satisfies or contradicts a given term.
     /// Otherwise the relation is inconclusive.
-    pub(crate) fn relation_with(&self, other_terms_intersection: &Self) -> Relation {
+    pub fn relation_with(&self, other_terms_intersection: &Self) -> Relation {
         if other_terms_intersection.subset_of(self) {
             Relation::Satisfied
         } else if self.is_disjoint(other_terms_intersection) {
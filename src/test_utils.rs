@@ -0,0 +1,192 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! [Proptest](https://docs.rs/proptest) strategies and invariants, with the `test_utils` feature.
+//!
+//! These are the generators the solver is tested with. Resolvers built on pubgrub can use them to
+//! fuzz their own providers and version sets: [registry_strategy] generates registries that
+//! usually have deep dependency trees, and [check_version_set] asserts that the operations of a
+//! [VersionSet] agree with [contains](VersionSet::contains), which the solver relies on.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use proptest::collection::{btree_map, btree_set, vec};
+use proptest::prelude::*;
+use proptest::sample::Index;
+use proptest::string::string_regex;
+
+use crate::{OfflineDependencyProvider, Package, Ranges, Term, VersionSet};
+
+pub use version_ranges::proptest_strategy as ranges_strategy;
+
+type NumVS = Ranges<u32>;
+
+/// Positive and negative terms of [ranges_strategy].
+pub fn term_strategy() -> impl Strategy<Value = Term<NumVS>> {
+    prop_oneof![
+        ranges_strategy().prop_map(Term::Negative),
+        ranges_strategy().prop_map(Term::Positive),
+    ]
+}
+
+/// Short package names, never `root` nor `bad`.
+pub fn string_names() -> impl Strategy<Value = String> {
+    string_regex("[A-Za-z][A-Za-z0-9_-]{0,5}")
+        .unwrap()
+        .prop_filter("reserved names", |n| {
+            // root is the name of the thing being compiled
+            // so it would be confusing to have it in the index
+            // bad is a name reserved for a dep that won't work
+            n != "root" && n != "bad"
+        })
+}
+
+/// This generates a random registry index, with the names of `name`, and the packages and
+/// versions worth resolving in it.
+///
+/// Unlike vec((Name, Ver, vec((Name, VerRq), ..), ..)
+/// This strategy has a high probability of having valid dependencies
+#[allow(clippy::type_complexity)]
+pub fn registry_strategy<N: Package + Ord>(
+    name: impl Strategy<Value = N>,
+) -> impl Strategy<Value = (OfflineDependencyProvider<N, NumVS>, Vec<(N, u32)>)> {
+    let max_crates = 40;
+    let max_versions = 15;
+    let shrinkage = 40;
+    let complicated_len = 10usize;
+
+    let a_version = ..(max_versions as u32);
+
+    let list_of_versions = btree_set(a_version, 1..=max_versions)
+        .prop_map(move |ver| ver.into_iter().collect::<Vec<_>>());
+
+    let list_of_crates_with_versions = btree_map(name, list_of_versions, 1..=max_crates);
+
+    // each version of each crate can depend on each crate smaller then it.
+    // In theory shrinkage should be 2, but in practice we get better trees with a larger value.
+    let max_deps = max_versions * (max_crates * (max_crates - 1)) / shrinkage;
+
+    let raw_version_range = (any::<Index>(), any::<Index>());
+    let raw_dependency = (any::<Index>(), any::<Index>(), raw_version_range);
+
+    fn order_index(a: Index, b: Index, size: usize) -> (usize, usize) {
+        use core::cmp::{max, min};
+        let (a, b) = (a.index(size), b.index(size));
+        (min(a, b), max(a, b))
+    }
+
+    let list_of_raw_dependency = vec(raw_dependency, ..=max_deps);
+
+    // By default a package depends only on other packages that have a smaller name,
+    // this helps make sure that all things in the resulting index are DAGs.
+    // If this is true then the DAG is maintained with grater instead.
+    let reverse_alphabetical = any::<bool>().no_shrink();
+
+    (
+        list_of_crates_with_versions,
+        list_of_raw_dependency,
+        reverse_alphabetical,
+        1..(complicated_len + 1),
+    )
+        .prop_map(
+            move |(crate_vers_by_name, raw_dependencies, reverse_alphabetical, complicated_len)| {
+                let mut list_of_pkgid: Vec<((N, u32), Vec<(N, NumVS)>)> = crate_vers_by_name
+                    .iter()
+                    .flat_map(|(name, vers)| vers.iter().map(move |&x| ((name.clone(), x), vec![])))
+                    .collect();
+                let len_all_pkgid = list_of_pkgid.len();
+                for (a, b, (c, d)) in raw_dependencies {
+                    let (a, b) = order_index(a, b, len_all_pkgid);
+                    let (a, b) = if reverse_alphabetical { (b, a) } else { (a, b) };
+                    let ((dep_name, _), _) = list_of_pkgid[a].to_owned();
+                    if list_of_pkgid[b].0 .0 == dep_name {
+                        continue;
+                    }
+                    let s = &crate_vers_by_name[&dep_name];
+                    let s_last_index = s.len() - 1;
+                    let (c, d) = order_index(c, d, s.len() + 1);
+
+                    list_of_pkgid[b].1.push((
+                        dep_name,
+                        if c > s_last_index {
+                            Ranges::empty()
+                        } else if c == 0 && d >= s_last_index {
+                            Ranges::full()
+                        } else if c == 0 {
+                            Ranges::strictly_lower_than(s[d] + 1)
+                        } else if d >= s_last_index {
+                            Ranges::higher_than(s[c])
+                        } else if c == d {
+                            Ranges::singleton(s[c])
+                        } else {
+                            Ranges::between(s[c], s[d] + 1)
+                        },
+                    ));
+                }
+
+                let mut dependency_provider = OfflineDependencyProvider::<N, NumVS>::new();
+
+                let complicated_len = core::cmp::min(complicated_len, list_of_pkgid.len());
+                let complicated: Vec<_> = if reverse_alphabetical {
+                    &list_of_pkgid[..complicated_len]
+                } else {
+                    &list_of_pkgid[(list_of_pkgid.len() - complicated_len)..]
+                }
+                .iter()
+                .map(|(x, _)| (x.0.clone(), x.1))
+                .collect();
+
+                for ((name, ver), deps) in list_of_pkgid {
+                    dependency_provider.add_dependencies(name, ver, deps);
+                }
+
+                (dependency_provider, complicated)
+            },
+        )
+}
+
+/// Panics if the operations of `VersionSet` on `s1` and `s2` disagree with
+/// [contains](VersionSet::contains) for `v`, or with each other.
+///
+/// Checking against a single version misses some bugs but is cheap enough to run on many
+/// random sets, with the versions of interest for the sets under test.
+pub fn check_version_set<VS: VersionSet>(s1: &VS, s2: &VS, v: &VS::V) {
+    let (in1, in2) = (s1.contains(v), s2.contains(v));
+    assert!(!VS::empty().contains(v), "the empty set contains {v}");
+    assert!(VS::full().contains(v), "the full set doesn't contain {v}");
+    assert!(
+        VS::singleton(v.clone()).contains(v),
+        "the singleton doesn't contain {v}"
+    );
+    assert_eq!(
+        s1.complement().contains(v),
+        !in1,
+        "complement of {s1} for {v}"
+    );
+    assert_eq!(
+        s1.intersection(s2).contains(v),
+        in1 && in2,
+        "intersection of {s1} and {s2} for {v}"
+    );
+    assert_eq!(
+        s1.union(s2).contains(v),
+        in1 || in2,
+        "union of {s1} and {s2} for {v}"
+    );
+    assert_eq!(
+        s1.is_disjoint(s2),
+        s1.intersection(s2) == VS::empty(),
+        "is_disjoint of {s1} and {s2}"
+    );
+    assert_eq!(
+        s1.subset_of(s2),
+        s1.intersection(s2) == *s1,
+        "subset_of of {s1} and {s2}"
+    );
+    assert_eq!(
+        s1.complement().complement(),
+        *s1,
+        "double complement of {s1}"
+    );
+}
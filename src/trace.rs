@@ -0,0 +1,333 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Record what the solver does, step by step, for visualizations and debugging.
+//!
+//! The logs of the solver explain a resolution to someone who already knows PubGrub. A trace
+//! records the same work as data instead: each decision, each term derived by unit propagation
+//! with the incompatibility causing it, each conflict, each incompatibility learned from it and
+//! each backtrack, in order. With the `serde` feature the events serialize, for example to feed
+//! a web page replaying the resolution to teach the algorithm or to find where it went wrong.
+//!
+//! [resolve_with_trace] records the trace of a whole resolution, and
+//! [SteppingResolver::record_trace](crate::SteppingResolver::record_trace) the events of each
+//! step of an interactive one. [replay_trace] checks that a resolution still goes the same way
+//! as a recorded trace, to reproduce a bug or find the change of the solver behind a regression.
+
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+
+use thiserror::Error;
+
+use crate::internal::{Arena, DecisionLevel, HashArena, Id, IncompDpId, Incompatibility};
+use crate::solver::Solver;
+use crate::{
+    DependencyProvider, External, Package, PubGrubError, SelectedDependencies, Set, Term,
+    VersionSet,
+};
+
+/// An event of the trace of a resolution, see [resolve_with_trace].
+///
+/// Incompatibilities are numbered, and an [Incompatibility](TraceEvent::Incompatibility) event
+/// introduces each one before the first event mentioning it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "P: serde::Serialize, VS: serde::Serialize, VS::V: serde::Serialize, M: serde::Serialize",
+        deserialize = "P: serde::Deserialize<'de>, VS: serde::Deserialize<'de>, VS::V: serde::Deserialize<'de>, M: serde::Deserialize<'de>"
+    ))
+)]
+pub enum TraceEvent<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
+    /// A new incompatibility: the terms can't all be satisfied at once.
+    Incompatibility {
+        /// The number of the incompatibility in the other events.
+        id: usize,
+        /// The terms of the incompatibility.
+        terms: Vec<(P, Term<VS>)>,
+        /// Where the incompatibility comes from.
+        origin: TraceOrigin<P, VS, M>,
+    },
+    /// A version of a package was decided.
+    Decision {
+        /// The decided package.
+        package: P,
+        /// The decided version.
+        version: VS::V,
+        /// The number of decisions, including this one.
+        level: u32,
+    },
+    /// Unit propagation derived a term from an incompatibility and the terms so far.
+    Derivation {
+        /// The package of the derived term.
+        package: P,
+        /// The derived term.
+        term: Term<VS>,
+        /// The incompatibility all the other terms of which are satisfied.
+        cause: usize,
+        /// The number of decisions the derivation depends on.
+        level: u32,
+    },
+    /// The terms so far satisfy an incompatibility: the solver starts conflict resolution.
+    Conflict {
+        /// The satisfied incompatibility.
+        incompatibility: usize,
+    },
+    /// The solver undid its decisions after the first `level` ones, after a conflict or a
+    /// restart.
+    Backtrack {
+        /// The number of decisions kept.
+        level: u32,
+    },
+}
+
+/// Where an incompatibility of a [trace](TraceEvent::Incompatibility) comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "P: serde::Serialize, VS: serde::Serialize, VS::V: serde::Serialize, M: serde::Serialize",
+        deserialize = "P: serde::Deserialize<'de>, VS: serde::Deserialize<'de>, VS::V: serde::Deserialize<'de>, M: serde::Deserialize<'de>"
+    ))
+)]
+pub enum TraceOrigin<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
+    /// The root package, a dependency or an answer of the provider.
+    External(External<P, VS, M>),
+    /// Derived by conflict resolution from two earlier incompatibilities.
+    Derived(usize, usize),
+}
+
+/// What the solver did, in the order it did it, before the ids are resolved.
+#[derive(Clone)]
+pub(crate) enum TracePoint<DP: DependencyProvider> {
+    Decision(Id<DP::P>, DP::V, DecisionLevel),
+    Derivation(Id<DP::P>, IncompDpId<DP>, DecisionLevel),
+    Conflict(IncompDpId<DP>),
+    Learned(IncompDpId<DP>),
+    Backtrack(DecisionLevel),
+}
+
+/// The trace recorded by a [State](crate::State), see [resolve_with_trace].
+#[derive(Clone)]
+pub(crate) struct TraceLog<DP: DependencyProvider> {
+    points: Vec<TracePoint<DP>>,
+    /// The incompatibilities already introduced by an event.
+    introduced: Set<IncompDpId<DP>>,
+}
+
+impl<DP: DependencyProvider> TraceLog<DP> {
+    pub(crate) fn new() -> Self {
+        Self {
+            points: Vec::new(),
+            introduced: Set::default(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, point: TracePoint<DP>) {
+        self.points.push(point);
+    }
+
+    /// The events recorded since the last call.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn take(
+        &mut self,
+        store: &Arena<Incompatibility<DP::P, DP::VS, DP::M>>,
+        package_store: &HashArena<DP::P>,
+    ) -> Vec<TraceEvent<DP::P, DP::VS, DP::M>> {
+        let mut events = Vec::new();
+        for point in core::mem::take(&mut self.points) {
+            match point {
+                TracePoint::Decision(package, version, level) => {
+                    events.push(TraceEvent::Decision {
+                        package: package_store[package].clone(),
+                        version,
+                        level: level.0,
+                    });
+                }
+                TracePoint::Derivation(package, cause, level) => {
+                    self.introduce(cause, store, package_store, &mut events);
+                    let term = store[cause]
+                        .get(package)
+                        .expect("the cause of a derivation has a term for its package")
+                        .negate();
+                    events.push(TraceEvent::Derivation {
+                        package: package_store[package].clone(),
+                        term,
+                        cause: cause.into_raw(),
+                        level: level.0,
+                    });
+                }
+                TracePoint::Conflict(incompat) => {
+                    self.introduce(incompat, store, package_store, &mut events);
+                    events.push(TraceEvent::Conflict {
+                        incompatibility: incompat.into_raw(),
+                    });
+                }
+                TracePoint::Learned(incompat) => {
+                    self.introduce(incompat, store, package_store, &mut events);
+                }
+                TracePoint::Backtrack(level) => {
+                    events.push(TraceEvent::Backtrack { level: level.0 });
+                }
+            }
+        }
+        events
+    }
+
+    /// Add the event introducing `incompat` if there is none yet, after those of its causes.
+    #[allow(clippy::type_complexity)]
+    fn introduce(
+        &mut self,
+        incompat: IncompDpId<DP>,
+        store: &Arena<Incompatibility<DP::P, DP::VS, DP::M>>,
+        package_store: &HashArena<DP::P>,
+        events: &mut Vec<TraceEvent<DP::P, DP::VS, DP::M>>,
+    ) {
+        // Derivation chains can be long, walk them without recursion.
+        let mut stack = alloc::vec![incompat];
+        while let Some(&id) = stack.last() {
+            if self.introduced.contains(&id) {
+                stack.pop();
+                continue;
+            }
+            let causes = store[id].causes();
+            if let Some((cause1, cause2)) = causes {
+                let pending: Vec<_> = [cause2, cause1]
+                    .into_iter()
+                    .filter(|cause| !self.introduced.contains(cause))
+                    .collect();
+                if !pending.is_empty() {
+                    stack.extend(pending);
+                    continue;
+                }
+            }
+            stack.pop();
+            self.introduced.insert(id);
+            let origin = match causes {
+                Some((cause1, cause2)) => {
+                    TraceOrigin::Derived(cause1.into_raw(), cause2.into_raw())
+                }
+                None => TraceOrigin::External(
+                    store[id]
+                        .external(package_store)
+                        .expect("an incompatibility without causes is external"),
+                ),
+            };
+            events.push(TraceEvent::Incompatibility {
+                id: id.into_raw(),
+                terms: store[id]
+                    .iter()
+                    .map(|(p, term)| (package_store[p].clone(), term.clone()))
+                    .collect(),
+                origin,
+            });
+        }
+    }
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// also returning the [trace](TraceEvent) of the resolution, whether it succeeded or not.
+///
+/// ```
+/// # use pubgrub::{resolve_with_trace, OfflineDependencyProvider, Ranges, TraceEvent};
+/// #
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("menu", Ranges::full())]);
+/// dependency_provider.add_dependencies("menu", 1u32, []);
+///
+/// let (solution, trace) = resolve_with_trace(&dependency_provider, "root", 1u32);
+/// assert_eq!(solution.unwrap().len(), 2);
+/// let decisions: Vec<_> = trace
+///     .iter()
+///     .filter_map(|event| match event {
+///         TraceEvent::Decision { package, level, .. } => Some((*package, *level)),
+///         _ => None,
+///     })
+///     .collect();
+/// assert_eq!(decisions, [("root", 1), ("menu", 2)]);
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn resolve_with_trace<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+) -> (
+    Result<SelectedDependencies<DP>, PubGrubError<DP>>,
+    Vec<TraceEvent<DP::P, DP::VS, DP::M>>,
+) {
+    let mut solver = Solver::new(package, version.into());
+    solver.state.record_trace();
+    let result = solver.solve(dependency_provider);
+    (result, solver.state.take_trace())
+}
+
+/// The first difference between a trace and its replay, see [replay_trace].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error(
+    "the replay differs from the trace at event {index}: expected {expected:?}, found {found:?}"
+)]
+pub struct TraceMismatch<P: Package, VS: VersionSet, M: Eq + Clone + Debug + Display> {
+    /// The position of the first different event.
+    pub index: usize,
+    /// The event of the recorded trace, `None` if the replay has more events.
+    pub expected: Option<TraceEvent<P, VS, M>>,
+    /// The event of the replay, `None` if the recorded trace has more events.
+    pub found: Option<TraceEvent<P, VS, M>>,
+}
+
+/// Resolve again the root package of a recorded `trace`, and check that the solver does the same
+/// thing at each step.
+///
+/// The provider should give the same answers as during the recording, for example an
+/// [OfflineDependencyProvider](crate::OfflineDependencyProvider) filled by a
+/// [RecordingProvider](crate::RecordingProvider). The root package is the one of the first
+/// event, the [NotRoot](External::NotRoot) incompatibility introduced by every trace.
+///
+/// ```
+/// # use pubgrub::{replay_trace, resolve_with_trace, OfflineDependencyProvider, Ranges};
+/// #
+/// let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+/// dependency_provider.add_dependencies("root", 1u32, [("menu", Ranges::full())]);
+/// dependency_provider.add_dependencies("menu", 1u32, []);
+///
+/// let (_, trace) = resolve_with_trace(&dependency_provider, "root", 1u32);
+/// assert!(replay_trace(&dependency_provider, &trace).is_ok());
+///
+/// // A new version changes the decisions.
+/// dependency_provider.add_dependencies("menu", 2u32, []);
+/// assert!(replay_trace(&dependency_provider, &trace).is_err());
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn replay_trace<DP: DependencyProvider>(
+    dependency_provider: &DP,
+    trace: &[TraceEvent<DP::P, DP::VS, DP::M>],
+) -> Result<(), TraceMismatch<DP::P, DP::VS, DP::M>> {
+    let root = match trace.first() {
+        Some(TraceEvent::Incompatibility {
+            origin: TraceOrigin::External(External::NotRoot(package, version)),
+            ..
+        }) => (package.clone(), version.clone()),
+        first => {
+            return Err(TraceMismatch {
+                index: 0,
+                expected: first.cloned(),
+                found: None,
+            })
+        }
+    };
+    let (_, replayed) = resolve_with_trace(dependency_provider, root.0, root.1);
+    let index = trace
+        .iter()
+        .zip(&replayed)
+        .position(|(expected, found)| expected != found)
+        .unwrap_or(trace.len().min(replayed.len()));
+    if index == trace.len() && index == replayed.len() {
+        return Ok(());
+    }
+    Err(TraceMismatch {
+        index,
+        expected: trace.get(index).cloned(),
+        found: replayed.get(index).cloned(),
+    })
+}
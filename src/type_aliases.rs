@@ -2,19 +2,35 @@
 
 //! Publicly exported type aliases.
 
+use alloc::collections::BTreeMap;
+
 use crate::DependencyProvider;
 
 /// Map implementation used by the library.
+#[cfg(feature = "std")]
 pub type Map<K, V> = rustc_hash::FxHashMap<K, V>;
 
+/// Map implementation used by the library.
+#[cfg(not(feature = "std"))]
+pub type Map<K, V> = hashbrown::HashMap<K, V, rustc_hash::FxBuildHasher>;
+
 /// Set implementation used by the library.
+#[cfg(feature = "std")]
 pub type Set<V> = rustc_hash::FxHashSet<V>;
 
+/// Set implementation used by the library.
+#[cfg(not(feature = "std"))]
+pub type Set<V> = hashbrown::HashSet<V, rustc_hash::FxBuildHasher>;
+
 /// Concrete dependencies picked by the library during [resolve](crate::solver::resolve)
 /// from [DependencyConstraints].
 pub type SelectedDependencies<DP> =
     Map<<DP as DependencyProvider>::P, <DP as DependencyProvider>::V>;
 
+/// Concrete dependencies picked by [resolve_sorted](crate::resolve_sorted), ordered by package.
+pub type SortedDependencies<DP> =
+    BTreeMap<<DP as DependencyProvider>::P, <DP as DependencyProvider>::V>;
+
 /// Holds information about all possible versions a given package can accept.
 /// There is a difference in semantics between an empty map
 /// inside [DependencyConstraints] and [Dependencies::Unavailable](crate::solver::Dependencies::Unavailable):
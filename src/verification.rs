@@ -1,11 +1,28 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use pubgrub::{
-    Dependencies, DependencyProvider, Map, OfflineDependencyProvider, Package, PubGrubError,
-    SelectedDependencies, VersionSet,
-};
+//! Check solutions against a SAT encoding of the problem, with the `verification` feature.
+//!
+//! Resolution can be reduced to the SAT problem, which is how the solver is tested: on small
+//! registries, a SAT solver finds a solution whenever one exists, so pubgrub must find one too,
+//! and the solutions of pubgrub must satisfy the SAT formula. Resolvers built on pubgrub can run
+//! the same checks on their own providers, for example on CI with [registry
+//! strategies](crate::test_utils) or with recorded registries.
+//!
+//! The encoding has one variable per version and lists all the versions of every package, so it
+//! is only practical for a few hundred versions.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+
 use varisat::ExtendFormula;
 
+use crate::{
+    Dependencies, DependencyProvider, ListVersions, Map, OfflineDependencyProvider, Package,
+    PubGrubError, SelectedDependencies, VersionSet,
+};
+
+/// Ensure that at most one of `vars` is true.
 fn sat_at_most_one(solver: &mut impl ExtendFormula, vars: &[varisat::Var]) {
     if vars.len() <= 1 {
         return;
@@ -41,22 +58,62 @@ pub struct SatResolve<P: Package, VS: VersionSet> {
 }
 
 impl<P: Package, VS: VersionSet> SatResolve<P, VS> {
+    /// Encode all the packages of `dp`.
     pub fn new(dp: &OfflineDependencyProvider<P, VS>) -> Self {
+        let mut versions: Map<P, Vec<VS::V>> = Map::default();
+        for p in dp.packages() {
+            versions.insert(p.clone(), dp.versions(p).unwrap().cloned().collect());
+        }
+        match Self::encode(versions, |p, v| dp.get_dependencies(p, v)) {
+            Ok(sat) => sat,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Encode the packages of `dp` reachable from `package`, through the dependencies of all their
+    /// versions.
+    ///
+    /// Unavailable versions can't be selected.
+    pub fn from_provider<DP>(dp: &DP, package: &P) -> Result<Self, DP::Err>
+    where
+        DP: ListVersions<P = P, VS = VS, V = VS::V>,
+    {
+        let mut versions: Map<P, Vec<VS::V>> = Map::default();
+        let mut queue = vec![package.clone()];
+        while let Some(p) = queue.pop() {
+            if versions.contains_key(&p) {
+                continue;
+            }
+            let versions_for_p: Vec<VS::V> = dp.list_versions(&p)?.collect();
+            for v in &versions_for_p {
+                if let Dependencies::Available(deps) = dp.get_dependencies(&p, v)? {
+                    queue.extend(deps.into_keys().filter(|dep| !versions.contains_key(dep)));
+                }
+            }
+            versions.insert(p, versions_for_p);
+        }
+        Self::encode(versions, |p, v| dp.get_dependencies(p, v))
+    }
+
+    fn encode<M: Eq + Clone + Debug + Display, E>(
+        versions: Map<P, Vec<VS::V>>,
+        mut get_dependencies: impl FnMut(&P, &VS::V) -> Result<Dependencies<P, VS, M>, E>,
+    ) -> Result<Self, E> {
         let mut cnf = varisat::CnfFormula::new();
 
         let mut all_versions = vec![];
         let mut all_versions_by_p: Map<P, Vec<(VS::V, varisat::Var)>> = Map::default();
 
-        for p in dp.packages() {
+        for (p, versions) in versions {
             let mut versions_for_p = vec![];
-            for v in dp.versions(p).unwrap() {
+            for v in versions {
                 let new_var = cnf.new_var();
                 all_versions.push((p.clone(), v.clone(), new_var));
                 versions_for_p.push(new_var);
                 all_versions_by_p
                     .entry(p.clone())
                     .or_default()
-                    .push((v.clone(), new_var));
+                    .push((v, new_var));
             }
             // no two versions of the same package
             sat_at_most_one(&mut cnf, &versions_for_p);
@@ -64,8 +121,11 @@ impl<P: Package, VS: VersionSet> SatResolve<P, VS> {
 
         // active packages need each of there `deps` to be satisfied
         for (p, v, var) in &all_versions {
-            let deps = match dp.get_dependencies(p, v).unwrap() {
-                Dependencies::Unavailable(_) => panic!(),
+            let deps = match get_dependencies(p, v)? {
+                Dependencies::Unavailable(_) => {
+                    cnf.add_clause(&[var.negative()]);
+                    continue;
+                }
                 Dependencies::Available(d) => d,
             };
             for (p1, range) in &deps {
@@ -93,12 +153,13 @@ impl<P: Package, VS: VersionSet> SatResolve<P, VS> {
             .solve()
             .expect("docs say it can't error in default config");
 
-        Self {
+        Ok(Self {
             solver,
             all_versions_by_p,
-        }
+        })
     }
 
+    /// Whether there is a solution selecting version `ver` of `name`.
     pub fn resolve(&mut self, name: &P, ver: &VS::V) -> bool {
         if let Some(vers) = self.all_versions_by_p.get(name) {
             if let Some((_, var)) = vers.iter().find(|(v, _)| v == ver) {
@@ -115,6 +176,9 @@ impl<P: Package, VS: VersionSet> SatResolve<P, VS> {
         }
     }
 
+    /// Whether `pids` satisfies the dependencies of all of its packages.
+    ///
+    /// Packages that were not encoded are ignored.
     pub fn is_valid_solution<DP: DependencyProvider<P = P, VS = VS, V = VS::V>>(
         &mut self,
         pids: &SelectedDependencies<DP>,
@@ -135,6 +199,8 @@ impl<P: Package, VS: VersionSet> SatResolve<P, VS> {
             .expect("docs say it can't error in default config")
     }
 
+    /// Panics if the result of resolving version `v` of `p` disagrees with the SAT solver: either
+    /// the solution is invalid, or resolution failed even though there is a solution.
     pub fn check_resolve<DP: DependencyProvider<P = P, VS = VS, V = VS::V>>(
         &mut self,
         res: &Result<SelectedDependencies<DP>, PubGrubError<DP>>,
@@ -2,8 +2,11 @@
 
 //! Traits and implementations to create and compare versions.
 
-use std::fmt::{self, Debug, Display};
-use std::str::FromStr;
+use alloc::string::{String, ToString};
+use core::cmp::Ordering;
+use core::fmt::{self, Debug, Display};
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
 
 use thiserror::Error;
 
@@ -220,3 +223,94 @@ impl Display for SemanticVersion {
         write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
     }
 }
+
+/// A version compared by a key and displayed as its text, for custom version schemes.
+///
+/// Ranges and the solver only need versions to be ordered, cloned and displayed. Schemes like
+/// calendar or Debian versions usually order versions by parsing them, so that `1.10` is newer
+/// than `1.9`, while the text is kept for reports. Versions with the same key are the same
+/// version, even if their texts differ, such as `1.0` and `1.00`.
+///
+/// ```
+/// # use pubgrub::{resolve, KeyedVersion, OfflineDependencyProvider, Ranges};
+/// fn version(text: &str) -> KeyedVersion<Vec<u32>> {
+///     let key = text.split('.').map(|part| part.parse().unwrap()).collect();
+///     KeyedVersion::new(key, text)
+/// }
+///
+/// let mut dependency_provider =
+///     OfflineDependencyProvider::<&str, Ranges<KeyedVersion<Vec<u32>>>>::new();
+/// dependency_provider.add_dependencies(
+///     "root",
+///     version("1.0"),
+///     [("foo", Ranges::higher_than(version("1.9")))],
+/// );
+/// dependency_provider.add_dependencies("foo", version("1.9"), []);
+/// dependency_provider.add_dependencies("foo", version("1.10"), []);
+///
+/// let solution = resolve(&dependency_provider, "root", version("1.00")).unwrap();
+/// assert_eq!(solution["foo"].to_string(), "1.10");
+/// ```
+#[derive(Clone)]
+pub struct KeyedVersion<K> {
+    key: K,
+    text: String,
+}
+
+impl<K> KeyedVersion<K> {
+    /// The version with the ordering `key`, displayed as `text`.
+    pub fn new(key: K, text: impl Into<String>) -> Self {
+        Self {
+            key,
+            text: text.into(),
+        }
+    }
+
+    /// The key ordering the version.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// The text of the version.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl<K: PartialEq> PartialEq for KeyedVersion<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Eq> Eq for KeyedVersion<K> {}
+
+impl<K: PartialOrd> PartialOrd for KeyedVersion<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+impl<K: Ord> Ord for KeyedVersion<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl<K: Hash> Hash for KeyedVersion<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state)
+    }
+}
+
+impl<K> Debug for KeyedVersion<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.text, f)
+    }
+}
+
+impl<K> Display for KeyedVersion<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::fmt::{Debug, Display};
+use core::fmt::{Debug, Display};
 
 use crate::Ranges;
 
@@ -77,6 +77,15 @@ pub trait VersionSet: Debug + Display + Clone + Eq {
     fn subset_of(&self, other: &Self) -> bool {
         self == &self.intersection(other)
     }
+
+    /// The only version in the set, if it contains exactly one.
+    ///
+    /// This is a hint for the solver, which decides the packages pinned to a single version
+    /// right away with [decide_pinned_first](crate::ResolveOptions::decide_pinned_first). The
+    /// default returns `None`, which disables it.
+    fn is_singleton(&self) -> Option<&Self::V> {
+        None
+    }
 }
 
 /// [`Ranges`] contains optimized implementations of all operations.
@@ -118,4 +127,8 @@ impl<T: Debug + Display + Clone + Eq + Ord> VersionSet for Ranges<T> {
     fn subset_of(&self, other: &Self) -> bool {
         Ranges::subset_of(self, other)
     }
+
+    fn is_singleton(&self) -> Option<&Self::V> {
+        Ranges::as_singleton(self)
+    }
 }
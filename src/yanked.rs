@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Avoid yanked versions.
+//!
+//! Registries usually let authors yank a broken release: it is kept so that existing lockfiles
+//! still resolve, but new resolutions should not pick it. Marking these versions
+//! [Unavailable](crate::Dependencies::Unavailable) forbids them entirely, even when nothing else
+//! satisfies the constraints. [resolve_with_yanked] instead tries the other versions first and
+//! only picks a yanked version as a last resort, or when the lockfile pins it.
+
+use alloc::vec::Vec;
+
+use crate::delegate::delegate_provider;
+use crate::{
+    resolve, DependencyProvider, Map, Package, PartialSolutionView, PubGrubError, VersionSet,
+};
+
+/// A [DependencyProvider] that knows which versions were yanked.
+pub trait YankedVersions: DependencyProvider {
+    /// Whether `version` of `package` was yanked.
+    fn is_yanked(&self, package: &Self::P, version: &Self::V) -> Result<bool, Self::Err>;
+}
+
+/// A [DependencyProvider] choosing yanked versions only when no other version is allowed.
+///
+/// The version chosen is the one the provider chooses once yanked versions are excluded from the
+/// range, or if there is none, the first yanked version it chose.
+pub struct WithYanked<'a, DP: YankedVersions> {
+    provider: &'a DP,
+    locked: &'a Map<DP::P, DP::V>,
+}
+
+impl<'a, DP: YankedVersions> WithYanked<'a, DP> {
+    /// Avoid the yanked versions of `provider`, except the versions in `locked`.
+    pub fn new(provider: &'a DP, locked: &'a Map<DP::P, DP::V>) -> Self {
+        Self { provider, locked }
+    }
+
+    fn is_avoided(&self, package: &DP::P, version: &DP::V) -> Result<bool, DP::Err> {
+        if self.locked.get(package) == Some(version) {
+            return Ok(false);
+        }
+        self.provider.is_yanked(package, version)
+    }
+
+    /// The first version `choose` picks in `range` that isn't avoided, or the first avoided one.
+    fn choose(
+        &self,
+        package: &DP::P,
+        range: &DP::VS,
+        choose: impl Fn(&DP::VS) -> Result<Option<DP::V>, DP::Err>,
+    ) -> Result<Option<DP::V>, DP::Err> {
+        let mut range = range.clone();
+        let mut last_resort = None;
+        while let Some(version) = choose(&range)? {
+            if !self.is_avoided(package, &version)? {
+                return Ok(Some(version));
+            }
+            range = range.intersection(&DP::VS::singleton(version.clone()).complement());
+            last_resort.get_or_insert(version);
+        }
+        Ok(last_resort)
+    }
+}
+
+impl<DP: YankedVersions> DependencyProvider for WithYanked<'_, DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    delegate_provider!(
+        provider => prioritize, reprioritize_on_conflict, tie_break_key, get_dependencies,
+        should_cancel, prefetch, on_conflict, on_backtrack, known_versions,
+        versions_sharing_dependencies, get_dependencies_batch,
+    );
+
+    fn choose_version(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.choose(package, range, |range| {
+            self.provider.choose_version(package, range)
+        })
+    }
+
+    fn choose_version_in(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        solution: &dyn PartialSolutionView<Self::P, Self::VS>,
+    ) -> Result<Option<Self::V>, Self::Err> {
+        self.choose(package, range, |range| {
+            self.provider.choose_version_in(package, range, solution)
+        })
+    }
+}
+
+/// A solution found by [resolve_with_yanked].
+#[derive(Debug, Clone)]
+pub struct YankedSolution<P: Package, V> {
+    /// The selected packages and versions.
+    pub solution: Map<P, V>,
+    /// The selected packages whose version was yanked, with that version.
+    pub yanked: Map<P, V>,
+}
+
+/// Finds a set of packages satisfying dependency bounds for a given package + version pair,
+/// selecting yanked versions only when no other version of the package works.
+///
+/// The yanked versions in `locked` are not avoided, but they are not preferred either.
+pub fn resolve_with_yanked<DP: YankedVersions>(
+    dependency_provider: &DP,
+    package: DP::P,
+    version: impl Into<DP::V>,
+    locked: &Map<DP::P, DP::V>,
+) -> Result<YankedSolution<DP::P, DP::V>, PubGrubError<DP>> {
+    let provider = WithYanked::new(dependency_provider, locked);
+    let solution = resolve(&provider, package, version).map_err(PubGrubError::into_provider)?;
+
+    let mut yanked = Map::default();
+    for (package, version) in &solution {
+        if dependency_provider
+            .is_yanked(package, version)
+            .map_err(|err| PubGrubError::ErrorChoosingVersion {
+                package: package.clone(),
+                source: err,
+                required_by: Vec::new(),
+                decided: Vec::new(),
+            })?
+        {
+            yanked.insert(package.clone(), version.clone());
+        }
+    }
+    Ok(YankedSolution { solution, yanked })
+}
@@ -1,14 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use pubgrub::{
-    resolve, DefaultStringReporter, Map, OfflineDependencyProvider, PubGrubError, Ranges,
-    Reporter as _, SemanticVersion, Set,
+    resolve, resolve_with_policies, DefaultStringReportFormatter, DefaultStringReporter,
+    DerivationTree, Derived, Explanation, External, HashArena, Id, LineRefs, Map,
+    OfflineDependencyProvider, Phrase, PubGrubError, Ranges, ReportFormatter, ReportLimits,
+    ReportLine, ReportStyle, Reporter as _, SemanticVersion, Set, StructuredReporter, Term,
+    VersionPolicies,
 };
 
 type NumVS = Ranges<u32>;
 type SemVS = Ranges<SemanticVersion>;
 
 use std::io::Write;
+use std::sync::Arc;
 
 use log::LevelFilter;
 
@@ -251,3 +255,492 @@ And because there is no version of foo in <1 | >1, <2 | >2, <3 | >3, <4 | >4, <5
         Set::from_iter(&["root", "foo", "bar"])
     );
 }
+
+#[test]
+fn structured_report() {
+    let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, vec![("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 1u32, vec![("bar", Ranges::full())]);
+
+    let Err(PubGrubError::NoSolution(mut derivation_tree)) =
+        resolve(&dependency_provider, "root", 1u32)
+    else {
+        unreachable!()
+    };
+    derivation_tree.collapse_no_versions();
+    let lines = StructuredReporter::report(&derivation_tree);
+    let [ReportLine::Explanation(
+        Explanation::BothExternal {
+            external1,
+            external2,
+            current_terms,
+        },
+        None,
+    )] = lines.as_slice()
+    else {
+        panic!("unexpected report: {lines:?}")
+    };
+    assert!(matches!(
+        external1,
+        External::FromDependencyOf("foo", _, "bar", _)
+    ));
+    assert!(matches!(
+        external2,
+        External::FromDependencyOf("root", _, "foo", _)
+    ));
+    assert_eq!(current_terms.keys().collect::<Vec<_>>(), [&"root"]);
+}
+
+#[test]
+fn custom_report_style() {
+    /// Quote package names, keep the default display of version sets.
+    struct Quoted;
+
+    impl ReportStyle<&str, NumVS> for Quoted {
+        fn package(&self, package: &&str) -> String {
+            format!("`{package}`")
+        }
+    }
+
+    let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, vec![("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 1u32, vec![("bar", Ranges::full())]);
+
+    let Err(PubGrubError::NoSolution(mut derivation_tree)) =
+        resolve(&dependency_provider, "root", 1u32)
+    else {
+        unreachable!()
+    };
+    derivation_tree.collapse_no_versions();
+    assert_eq!(
+        DefaultStringReporter::report_with_formatter(&derivation_tree, &Quoted),
+        "Because `foo` depends on `bar` and `root` ==1 depends on `foo`, `root` ==1 is forbidden."
+    );
+}
+
+#[test]
+fn report_terms_are_sorted() {
+    let terms: Map<&str, Term<NumVS>> = ["c", "a", "d", "b"]
+        .into_iter()
+        .map(|p| (p, Term::Positive(Ranges::singleton(1u32))))
+        .collect();
+    let formatted: String =
+        ReportFormatter::<_, _, String>::format_terms(&DefaultStringReportFormatter, &terms);
+    assert_eq!(formatted, "a ==1, b ==1, c ==1, d ==1 are incompatible");
+}
+
+#[test]
+fn custom_reason_first() {
+    /// Put the reason a package is unavailable first.
+    struct ReasonFirst;
+
+    impl ReportStyle<&str, NumVS> for ReasonFirst {
+        fn unavailable(&self, package: &&str, _: &NumVS, reason: &dyn std::fmt::Display) -> String {
+            format!("{package} is unavailable: {reason}")
+        }
+    }
+
+    let external = External::Custom("foo", Ranges::full(), "HTTP 403 from registry".to_string());
+    assert_eq!(
+        ReasonFirst.format_external(&external),
+        "foo is unavailable: HTTP 403 from registry"
+    );
+    assert_eq!(
+        DefaultStringReportFormatter.format_external(&external),
+        "dependencies of foo are unavailable HTTP 403 from registry"
+    );
+}
+
+#[test]
+fn denied_versions_report() {
+    let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 1u32, []);
+    dependency_provider.add_dependencies("foo", 2u32, []);
+
+    let mut policies = VersionPolicies::new();
+    policies.deny("foo", "CVE-2024-0001".to_string(), |v: &u32| *v == 2);
+    policies.allow_only("foo", "pre-1.0".to_string(), |v: &u32| *v >= 2);
+
+    let Err(PubGrubError::NoSolution(tree)) =
+        resolve_with_policies(&dependency_provider, "root", 1u32, &policies)
+    else {
+        panic!("expected no solution");
+    };
+    let report = DefaultStringReporter::report(&tree);
+    assert!(
+        report.contains("foo ==2 is denied (CVE-2024-0001)"),
+        "{report}"
+    );
+    assert!(report.contains("foo ==1 is denied (pre-1.0)"), "{report}");
+}
+
+#[test]
+fn prune_and_merge_derivation_tree() {
+    let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    dependency_provider.add_dependencies(
+        "root",
+        1u32,
+        [("foo", Ranges::full()), ("bar", Ranges::full())],
+    );
+    // `foo[extra]` stands for the extra of `foo`, it depends on `foo` at the same version.
+    dependency_provider.add_dependencies("foo", 1u32, [("foo[extra]", Ranges::full())]);
+    dependency_provider.add_dependencies(
+        "foo[extra]",
+        1u32,
+        [
+            ("foo", Ranges::singleton(1u32)),
+            ("baz", Ranges::singleton(1u32)),
+        ],
+    );
+    dependency_provider.add_dependencies("bar", 1u32, [("baz", Ranges::singleton(2u32))]);
+    dependency_provider.add_dependencies("baz", 1u32, []);
+    dependency_provider.add_dependencies("baz", 2u32, []);
+
+    let Err(PubGrubError::NoSolution(mut derivation_tree)) =
+        resolve(&dependency_provider, "root", 1u32)
+    else {
+        unreachable!()
+    };
+    derivation_tree.collapse_no_versions();
+    let mut pruned = derivation_tree.clone();
+    pruned.prune(&|package| package.ends_with("[extra]"));
+    assert_eq!(
+        DefaultStringReporter::report(&pruned),
+        "Because foo depends on baz ==1 and bar ==1 depends on baz ==2, bar ==1, foo * are incompatible.\n\
+        And because root ==1 depends on foo and root ==1 depends on bar, root ==1 is forbidden."
+    );
+
+    // The same dependency, explained in two branches.
+    type Tree = DerivationTree<&'static str, NumVS, String>;
+    let dependency = || {
+        Arc::new(Tree::External(External::FromDependencyOf(
+            "foo",
+            Ranges::full(),
+            "bar",
+            Ranges::singleton(1u32),
+        )))
+    };
+    let derived = |terms: Map<&'static str, Term<NumVS>>, cause1, cause2| {
+        Arc::new(Tree::Derived(Derived {
+            terms,
+            shared_id: None,
+            cause1,
+            cause2,
+        }))
+    };
+    let no_bar = || {
+        Arc::new(DerivationTree::External(External::NoVersions(
+            "bar",
+            Ranges::full(),
+        )))
+    };
+    let not_foo: Map<_, _> = [("foo", Term::Positive(Ranges::full()))]
+        .into_iter()
+        .collect();
+    let mut tree = derived(
+        Map::default(),
+        derived(not_foo.clone(), dependency(), no_bar()),
+        derived(not_foo, dependency(), no_bar()),
+    )
+    .as_ref()
+    .clone();
+    let report = DefaultStringReporter::report(&tree);
+    tree.merge_duplicate_externals();
+    assert_eq!(DefaultStringReporter::report(&tree), report);
+    let DerivationTree::Derived(root) = &tree else {
+        unreachable!()
+    };
+    let (DerivationTree::Derived(branch1), DerivationTree::Derived(branch2)) =
+        (root.cause1.as_ref(), root.cause2.as_ref())
+    else {
+        unreachable!()
+    };
+    assert!(Arc::ptr_eq(&branch1.cause1, &branch2.cause1));
+    assert!(Arc::ptr_eq(&branch1.cause2, &branch2.cause2));
+}
+
+#[test]
+fn report_line_refs() {
+    type Tree = DerivationTree<&'static str, NumVS, String>;
+    let external = |p, dep| {
+        Arc::new(Tree::External(External::FromDependencyOf(
+            p,
+            Ranges::full(),
+            dep,
+            Ranges::full(),
+        )))
+    };
+    let derived = |terms: Vec<(&'static str, Term<NumVS>)>, shared_id, cause1, cause2| {
+        Arc::new(Tree::Derived(Derived {
+            terms: terms.into_iter().collect(),
+            shared_id,
+            cause1,
+            cause2,
+        }))
+    };
+    let no_foo = derived(
+        vec![("foo", Term::Positive(Ranges::full()))],
+        Some(0),
+        external("foo", "bar"),
+        Arc::new(Tree::External(External::NoVersions("bar", Ranges::full()))),
+    );
+    // `foo` is forbidden, so both `a` and `b` are.
+    let no_a = derived(
+        vec![("a", Term::Positive(Ranges::full()))],
+        None,
+        no_foo.clone(),
+        external("a", "foo"),
+    );
+    let no_b = derived(
+        vec![("b", Term::Positive(Ranges::full()))],
+        None,
+        no_foo,
+        external("b", "foo"),
+    );
+    let tree = derived(vec![], None, no_a, no_b);
+
+    /// Terse line references.
+    struct Brackets;
+    impl ReportStyle<&str, NumVS> for Brackets {
+        fn line_ref(&self, ref_id: usize) -> String {
+            format!("[{ref_id}]")
+        }
+    }
+
+    assert_eq!(
+        DefaultStringReporter::report_with_line_refs(&tree, &Brackets, LineRefs::Always),
+        "Because foo depends on bar and there is no available version for bar, foo * is forbidden. [1]\n\
+        And because a depends on foo, a * is forbidden. [2]\n\
+        \n\
+        Because foo * is forbidden [1] and b depends on foo, b * is forbidden.\n\
+        And because a * is forbidden [2], version solving failed."
+    );
+    let explained_twice =
+        "Because foo depends on bar and there is no available version for bar, foo * is forbidden.\n\
+        And because a depends on foo, a * is forbidden. (1)\n\
+        \n\
+        Because foo depends on bar and there is no available version for bar, foo * is forbidden.\n\
+        And because b depends on foo, b * is forbidden.\n\
+        And because a * is forbidden (1), version solving failed.";
+    for line_refs in [LineRefs::Never, LineRefs::MinUses(3)] {
+        assert_eq!(
+            DefaultStringReporter::report_with_line_refs(
+                tree.as_ref(),
+                &DefaultStringReportFormatter,
+                line_refs
+            ),
+            explained_twice
+        );
+    }
+}
+
+#[test]
+fn report_identical_subtrees_once() {
+    type Tree = DerivationTree<&'static str, NumVS, String>;
+    let external = |p, dep| {
+        Arc::new(Tree::External(External::FromDependencyOf(
+            p,
+            Ranges::full(),
+            dep,
+            Ranges::full(),
+        )))
+    };
+    let derived = |terms: Vec<(&'static str, Term<NumVS>)>, cause1, cause2| {
+        Arc::new(Tree::Derived(Derived {
+            terms: terms.into_iter().collect(),
+            shared_id: None,
+            cause1,
+            cause2,
+        }))
+    };
+    // `foo` is forbidden in both branches, derived twice.
+    let no_foo = || {
+        derived(
+            vec![("foo", Term::Positive(Ranges::full()))],
+            external("foo", "bar"),
+            Arc::new(Tree::External(External::NoVersions("bar", Ranges::full()))),
+        )
+    };
+    let no_a = derived(
+        vec![("a", Term::Positive(Ranges::full()))],
+        no_foo(),
+        external("a", "foo"),
+    );
+    let no_b = derived(
+        vec![("b", Term::Positive(Ranges::full()))],
+        no_foo(),
+        external("b", "foo"),
+    );
+    let tree = derived(vec![], no_a, no_b);
+
+    assert_eq!(
+        DefaultStringReporter::report(&tree),
+        "Because foo depends on bar and there is no available version for bar, foo * is forbidden. (1)\n\
+        And because a depends on foo, a * is forbidden. (2)\n\
+        \n\
+        Because foo * is forbidden (1) and b depends on foo, b * is forbidden.\n\
+        And because a * is forbidden (2), version solving failed."
+    );
+}
+
+#[test]
+fn report_limits() {
+    type Tree = DerivationTree<&'static str, NumVS, String>;
+    let external = |p, dep| {
+        Arc::new(Tree::External(External::FromDependencyOf(
+            p,
+            Ranges::full(),
+            dep,
+            Ranges::full(),
+        )))
+    };
+    let derived = |terms: Vec<(&'static str, Term<NumVS>)>, shared_id, cause1, cause2| {
+        Arc::new(Tree::Derived(Derived {
+            terms: terms.into_iter().collect(),
+            shared_id,
+            cause1,
+            cause2,
+        }))
+    };
+    let no_foo = derived(
+        vec![("foo", Term::Positive(Ranges::full()))],
+        Some(0),
+        external("foo", "bar"),
+        Arc::new(Tree::External(External::NoVersions("bar", Ranges::full()))),
+    );
+    let no_a = derived(
+        vec![("a", Term::Positive(Ranges::full()))],
+        None,
+        no_foo.clone(),
+        external("a", "foo"),
+    );
+    let no_b = derived(
+        vec![("b", Term::Positive(Ranges::full()))],
+        None,
+        no_foo,
+        external("b", "foo"),
+    );
+    let tree = derived(vec![], None, no_a, no_b);
+    let report = |limits| {
+        DefaultStringReporter::report_with_limits(
+            tree.as_ref(),
+            &DefaultStringReportFormatter,
+            LineRefs::Always,
+            limits,
+        )
+    };
+
+    assert_eq!(
+        report(ReportLimits::default()),
+        DefaultStringReporter::report(tree.as_ref())
+    );
+    assert_eq!(
+        report(ReportLimits {
+            max_depth: Some(1),
+            max_lines: None,
+        }),
+        "Because foo * is forbidden and a depends on foo, a * is forbidden. (1)\n\
+        \n\
+        Because foo * is forbidden and b depends on foo, b * is forbidden.\n\
+        And because a * is forbidden (1), version solving failed.\n\
+        … and 1 more reason"
+    );
+    assert_eq!(
+        report(ReportLimits {
+            max_depth: None,
+            max_lines: Some(2),
+        }),
+        "Because foo * is forbidden and b depends on foo, b * is forbidden.\n\
+        And because a * is forbidden, version solving failed.\n\
+        … and 2 more reasons"
+    );
+}
+
+#[test]
+fn translated_report() {
+    struct French;
+
+    impl ReportStyle<&str, NumVS> for French {
+        fn phrase(&self, phrase: Phrase<'_>) -> String {
+            match phrase {
+                Phrase::NoVersions {
+                    package,
+                    versions: Some(versions),
+                } => format!("il n'y a pas de version de {package} dans {versions}"),
+                Phrase::DependsOn {
+                    package,
+                    versions,
+                    dependency,
+                    requirement,
+                } => {
+                    let versions = versions.map(|v| format!(" {v}")).unwrap_or_default();
+                    let requirement = requirement.map(|r| format!(" {r}")).unwrap_or_default();
+                    format!("{package}{versions} dépend de {dependency}{requirement}")
+                }
+                Phrase::Forbidden { package, versions } => {
+                    format!("{package} {versions} est interdit")
+                }
+                Phrase::Because {
+                    and,
+                    causes,
+                    conclusion,
+                } => {
+                    let because = if and { "Et comme" } else { "Comme" };
+                    format!("{because} {}, {conclusion}.", causes.join(" et "))
+                }
+                phrase => phrase.english(),
+            }
+        }
+    }
+
+    let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 1u32, [("bar", Ranges::full())]);
+    dependency_provider.add_dependencies("bar", 1u32, [("foo", Ranges::singleton(2u32))]);
+
+    let Err(PubGrubError::NoSolution(derivation_tree)) =
+        resolve(&dependency_provider, "root", 1u32)
+    else {
+        unreachable!()
+    };
+    println!(
+        "{}",
+        DefaultStringReporter::report_with_formatter(&derivation_tree, &French)
+    );
+}
+
+#[test]
+fn interned_packages() {
+    /// Show interned packages with their name.
+    struct Names<'a>(&'a HashArena<String>);
+
+    impl ReportStyle<Id<String>, NumVS> for Names<'_> {
+        fn package(&self, package: &Id<String>) -> String {
+            self.0[*package].clone()
+        }
+    }
+
+    let mut names = HashArena::new();
+    let [root, foo, bar] = ["root", "foo", "bar"].map(|name| names.alloc(name.to_string()));
+    assert_eq!(names.get("foo"), Some(foo));
+    assert_eq!(names.get("baz"), None);
+
+    let mut dependency_provider = OfflineDependencyProvider::<Id<String>, NumVS>::new();
+    dependency_provider.add_dependencies(root, 1u32, [(foo, Ranges::full())]);
+    dependency_provider.add_dependencies(foo, 1u32, [(bar, Ranges::full())]);
+    let Err(PubGrubError::NoSolution(mut derivation_tree)) =
+        resolve(&dependency_provider, root, 1u32)
+    else {
+        unreachable!()
+    };
+    derivation_tree.collapse_no_versions();
+    assert_eq!(
+        DefaultStringReporter::report_with_formatter(&derivation_tree, &Names(&names)),
+        "Because foo depends on bar and root ==1 depends on foo, root ==1 is forbidden."
+    );
+
+    dependency_provider.add_dependencies(bar, 2u32, []);
+    let solution = resolve(&dependency_provider, root, 1u32).unwrap();
+    assert_eq!(solution[&bar], 2);
+}
@@ -2,24 +2,36 @@
 
 #![allow(clippy::type_complexity)]
 
+extern crate alloc;
+
 use std::collections::BTreeSet as Set;
 use std::convert::Infallible;
 use std::fmt::{Debug, Display};
+use std::num::NonZeroU32;
 
-use proptest::collection::{btree_map, btree_set, vec};
+use proptest::collection::vec;
 use proptest::prelude::*;
 use proptest::sample::Index;
-use proptest::string::string_regex;
 
 use pubgrub::{
-    resolve, DefaultStringReporter, Dependencies, DependencyProvider, DerivationTree, External,
-    OfflineDependencyProvider, Package, PackageResolutionStatistics, PubGrubError, Ranges,
-    Reporter, SelectedDependencies, VersionSet,
+    resolve, resolve_async, resolve_best_effort, resolve_iter, resolve_optimal,
+    resolve_with_learning, resolve_with_options, AsyncDependencyProvider, BestEffortSolution,
+    Conflict, ConflictLearning, DefaultStringReporter, Dependencies, DependencyProvider,
+    DerivationTree, External, IncrementalResolver, ListVersions, Map, OfflineDependencyProvider,
+    Package, PackageResolutionStatistics, PartialSolutionView, PubGrubError, Ranges, Reporter,
+    ResolveOptions, SelectedDependencies, State, SteppingResolver, Term, VersionSet,
 };
 
-use crate::sat_dependency_provider::SatResolve;
+use crate::test_utils::{check_version_set, ranges_strategy, registry_strategy, string_names};
+use crate::verification::SatResolve;
 
-mod sat_dependency_provider;
+// Built from the sources of the `test_utils` and `verification` modules, so that the suite runs
+// without enabling their features.
+#[allow(dead_code)]
+#[path = "../src/test_utils.rs"]
+mod test_utils;
+#[path = "../src/verification.rs"]
+mod verification;
 
 /// The same as [OfflineDependencyProvider] but takes versions from the opposite end:
 /// if [OfflineDependencyProvider] returns versions from newest to oldest, this returns them from oldest to newest.
@@ -107,6 +119,10 @@ impl<DP: DependencyProvider> DependencyProvider for TimeoutDependencyProvider<DP
         self.dp.choose_version(package, range)
     }
 
+    fn known_versions(&self, package: &DP::P) -> Option<Vec<DP::V>> {
+        self.dp.known_versions(package)
+    }
+
     type Priority = DP::Priority;
 
     fn prioritize(
@@ -126,6 +142,44 @@ impl<DP: DependencyProvider> DependencyProvider for TimeoutDependencyProvider<DP
     type M = DP::M;
 }
 
+/// The same as DP but only knows every other version of the packages.
+struct SomeKnownVersions<DP>(DP);
+
+impl<DP: DependencyProvider> DependencyProvider for SomeKnownVersions<DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type M = DP::M;
+    type Priority = DP::Priority;
+    type Err = DP::Err;
+
+    fn prioritize(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        package_statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.0.prioritize(package, range, package_statistics)
+    }
+
+    fn choose_version(&self, package: &DP::P, range: &DP::VS) -> Result<Option<DP::V>, DP::Err> {
+        self.0.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        p: &DP::P,
+        v: &DP::V,
+    ) -> Result<Dependencies<DP::P, DP::VS, DP::M>, DP::Err> {
+        self.0.get_dependencies(p, v)
+    }
+
+    fn known_versions(&self, package: &DP::P) -> Option<Vec<DP::V>> {
+        let versions = self.0.known_versions(package)?;
+        Some(versions.into_iter().step_by(2).collect())
+    }
+}
+
 fn timeout_resolve<DP: DependencyProvider>(
     dependency_provider: DP,
     name: DP::P,
@@ -143,130 +197,199 @@ fn timeout_resolve<DP: DependencyProvider>(
 
 type NumVS = Ranges<u32>;
 
-#[test]
-#[should_panic]
-fn should_cancel_can_panic() {
-    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
-    dependency_provider.add_dependencies(0, 0u32, [(666, Ranges::full())]);
+/// Learns every derived incompatibility, and goes back to the root decision on every other
+/// conflict.
+struct RestartEveryOtherConflict;
 
-    // Run the algorithm.
-    let _ = resolve(
-        &TimeoutDependencyProvider::new(dependency_provider, 1),
-        0,
-        0u32,
-    );
+impl ConflictLearning for RestartEveryOtherConflict {
+    fn learn_intermediate(&self) -> bool {
+        true
+    }
+
+    fn backtrack_level(&self, conflict: &Conflict) -> u32 {
+        if conflict.conflicts.is_multiple_of(2) {
+            1
+        } else {
+            conflict.backtrack_level
+        }
+    }
 }
 
-fn string_names() -> impl Strategy<Value = String> {
-    string_regex("[A-Za-z][A-Za-z0-9_-]{0,5}")
-        .unwrap()
-        .prop_filter("reserved names", |n| {
-            // root is the name of the thing being compiled
-            // so it would be confusing to have it in the index
-            // bad is a name reserved for a dep that won't work
-            n != "root" && n != "bad"
-        })
+#[derive(Debug, thiserror::Error)]
+#[error("out of time")]
+struct OutOfTime;
+
+/// Cancels the resolution after a fixed number of calls to `should_cancel`.
+struct CancelAfter {
+    dp: OfflineDependencyProvider<u16, NumVS>,
+    call_count: std::cell::Cell<u64>,
+    max_calls: u64,
 }
 
-/// This generates a random registry index.
-/// Unlike vec((Name, Ver, vec((Name, VerRq), ..), ..)
-/// This strategy has a high probability of having valid dependencies
-pub fn registry_strategy<N: Package + Ord>(
-    name: impl Strategy<Value = N>,
-) -> impl Strategy<Value = (OfflineDependencyProvider<N, NumVS>, Vec<(N, u32)>)> {
-    let max_crates = 40;
-    let max_versions = 15;
-    let shrinkage = 40;
-    let complicated_len = 10usize;
+impl DependencyProvider for CancelAfter {
+    type P = u16;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority = <OfflineDependencyProvider<u16, NumVS> as DependencyProvider>::Priority;
+    type Err = OutOfTime;
 
-    let a_version = ..(max_versions as u32);
+    fn prioritize(
+        &self,
+        package: &u16,
+        range: &NumVS,
+        package_statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.dp.prioritize(package, range, package_statistics)
+    }
 
-    let list_of_versions = btree_set(a_version, 1..=max_versions)
-        .prop_map(move |ver| ver.into_iter().collect::<Vec<_>>());
+    fn choose_version(&self, package: &u16, range: &NumVS) -> Result<Option<u32>, OutOfTime> {
+        Ok(self.dp.choose_version(package, range).unwrap())
+    }
 
-    let list_of_crates_with_versions = btree_map(name, list_of_versions, 1..=max_crates);
+    fn get_dependencies(
+        &self,
+        package: &u16,
+        version: &u32,
+    ) -> Result<Dependencies<u16, NumVS, String>, OutOfTime> {
+        Ok(self.dp.get_dependencies(package, version).unwrap())
+    }
 
-    // each version of each crate can depend on each crate smaller then it.
-    // In theory shrinkage should be 2, but in practice we get better trees with a larger value.
-    let max_deps = max_versions * (max_crates * (max_crates - 1)) / shrinkage;
+    fn should_cancel(&self) -> Result<(), OutOfTime> {
+        let calls = self.call_count.get();
+        if calls >= self.max_calls {
+            return Err(OutOfTime);
+        }
+        self.call_count.set(calls + 1);
+        Ok(())
+    }
+}
+
+/// Exposes a [DependencyProvider] through [AsyncDependencyProvider],
+/// yielding once before answering each query.
+struct AsyncWrapper<DP>(DP);
 
-    let raw_version_range = (any::<Index>(), any::<Index>());
-    let raw_dependency = (any::<Index>(), any::<Index>(), raw_version_range);
+impl<DP: DependencyProvider> AsyncDependencyProvider for AsyncWrapper<DP> {
+    type P = DP::P;
+    type V = DP::V;
+    type VS = DP::VS;
+    type Priority = DP::Priority;
+    type M = DP::M;
+    type Err = DP::Err;
 
-    fn order_index(a: Index, b: Index, size: usize) -> (usize, usize) {
-        use std::cmp::{max, min};
-        let (a, b) = (a.index(size), b.index(size));
-        (min(a, b), max(a, b))
+    fn prioritize(
+        &self,
+        package: &Self::P,
+        range: &Self::VS,
+        package_statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.0.prioritize(package, range, package_statistics)
     }
 
-    let list_of_raw_dependency = vec(raw_dependency, ..=max_deps);
+    async fn choose_version(
+        &self,
+        package: &DP::P,
+        range: &DP::VS,
+    ) -> Result<Option<DP::V>, DP::Err> {
+        YieldOnce(false).await;
+        self.0.choose_version(package, range)
+    }
 
-    // By default a package depends only on other packages that have a smaller name,
-    // this helps make sure that all things in the resulting index are DAGs.
-    // If this is true then the DAG is maintained with grater instead.
-    let reverse_alphabetical = any::<bool>().no_shrink();
+    async fn get_dependencies(
+        &self,
+        package: &DP::P,
+        version: &DP::V,
+    ) -> Result<Dependencies<DP::P, DP::VS, DP::M>, DP::Err> {
+        YieldOnce(false).await;
+        self.0.get_dependencies(package, version)
+    }
 
-    (
-        list_of_crates_with_versions,
-        list_of_raw_dependency,
-        reverse_alphabetical,
-        1..(complicated_len + 1),
-    )
-        .prop_map(
-            move |(crate_vers_by_name, raw_dependencies, reverse_alphabetical, complicated_len)| {
-                let mut list_of_pkgid: Vec<((N, u32), Vec<(N, NumVS)>)> = crate_vers_by_name
-                    .iter()
-                    .flat_map(|(name, vers)| vers.iter().map(move |&x| ((name.clone(), x), vec![])))
-                    .collect();
-                let len_all_pkgid = list_of_pkgid.len();
-                for (a, b, (c, d)) in raw_dependencies {
-                    let (a, b) = order_index(a, b, len_all_pkgid);
-                    let (a, b) = if reverse_alphabetical { (b, a) } else { (a, b) };
-                    let ((dep_name, _), _) = list_of_pkgid[a].to_owned();
-                    if list_of_pkgid[b].0 .0 == dep_name {
-                        continue;
-                    }
-                    let s = &crate_vers_by_name[&dep_name];
-                    let s_last_index = s.len() - 1;
-                    let (c, d) = order_index(c, d, s.len() + 1);
-
-                    list_of_pkgid[b].1.push((
-                        dep_name,
-                        if c > s_last_index {
-                            Ranges::empty()
-                        } else if c == 0 && d >= s_last_index {
-                            Ranges::full()
-                        } else if c == 0 {
-                            Ranges::strictly_lower_than(s[d] + 1)
-                        } else if d >= s_last_index {
-                            Ranges::higher_than(s[c])
-                        } else if c == d {
-                            Ranges::singleton(s[c])
-                        } else {
-                            Ranges::between(s[c], s[d] + 1)
-                        },
-                    ));
-                }
+    async fn choose_version_in(
+        &self,
+        package: &DP::P,
+        range: &DP::VS,
+        solution: &dyn PartialSolutionView<DP::P, DP::VS>,
+    ) -> Result<Option<DP::V>, DP::Err> {
+        YieldOnce(false).await;
+        self.0.choose_version_in(package, range, solution)
+    }
 
-                let mut dependency_provider = OfflineDependencyProvider::<N, NumVS>::new();
+    async fn get_dependencies_batch(
+        &self,
+        package: &DP::P,
+        versions: &[DP::V],
+    ) -> Result<Vec<Dependencies<DP::P, DP::VS, DP::M>>, DP::Err> {
+        YieldOnce(false).await;
+        self.0.get_dependencies_batch(package, versions)
+    }
 
-                let complicated_len = std::cmp::min(complicated_len, list_of_pkgid.len());
-                let complicated: Vec<_> = if reverse_alphabetical {
-                    &list_of_pkgid[..complicated_len]
-                } else {
-                    &list_of_pkgid[(list_of_pkgid.len() - complicated_len)..]
-                }
-                .iter()
-                .map(|(x, _)| (x.0.clone(), x.1))
-                .collect();
+    fn on_conflict(&self, terms: &[(DP::P, Term<DP::VS>)]) {
+        self.0.on_conflict(terms)
+    }
 
-                for ((name, ver), deps) in list_of_pkgid {
-                    dependency_provider.add_dependencies(name, ver, deps);
-                }
+    fn on_backtrack(&self, to_level: u32) {
+        self.0.on_backtrack(to_level)
+    }
+
+    fn known_versions(&self, package: &DP::P) -> Option<Vec<DP::V>> {
+        self.0.known_versions(package)
+    }
+
+    fn versions_sharing_dependencies(&self, package: &DP::P, version: &DP::V) -> DP::VS {
+        self.0.versions_sharing_dependencies(package, version)
+    }
+}
+
+/// A future that is pending the first time it is polled.
+struct YieldOnce(bool);
+
+impl std::future::Future for YieldOnce {
+    type Output = ();
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<()> {
+        if self.0 {
+            std::task::Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    }
+}
+
+/// Minimal executor, polling the future until it completes.
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    loop {
+        if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn should_cancel_can_panic() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies(0, 0u32, [(666, Ranges::full())]);
 
-                (dependency_provider, complicated)
-            },
-        )
+    // Run the algorithm.
+    let _ = resolve(
+        &TimeoutDependencyProvider::new(dependency_provider, 1),
+        0,
+        0u32,
+    );
+}
+
+proptest! {
+    #[test]
+    fn ranges_are_a_version_set(r1 in ranges_strategy(), r2 in ranges_strategy(), v in any::<u32>()) {
+        check_version_set(&r1, &r2, &v);
+    }
 }
 
 /// Ensures that generator makes registries with large dependency trees.
@@ -456,6 +579,120 @@ proptest! {
         }
     }
 
+    #[test]
+    /// Encoding only the packages reachable from the root is enough to check the root.
+    fn prop_sat_from_provider_errors_the_same(
+        (dependency_provider, cases) in registry_strategy(0u16..665)
+    )  {
+        for (name, ver) in cases {
+            let mut sat = SatResolve::from_provider(&dependency_provider, &name).unwrap();
+            let res = timeout_resolve(dependency_provider.clone(), name, ver);
+            sat.check_resolve(&res, &name, &ver);
+        }
+    }
+
+    #[test]
+    /// Learning every derived incompatibility and restarting after conflicts
+    /// still finds a solution exactly when there is one.
+    fn prop_sat_errors_the_same_with_restarts(
+        (dependency_provider, cases) in registry_strategy(0u16..665)
+    )  {
+        let mut sat = SatResolve::new(&dependency_provider);
+        for (name, ver) in cases {
+            let res = resolve_with_learning(
+                &TimeoutDependencyProvider::new(dependency_provider.clone(), 50_000),
+                name,
+                ver,
+                RestartEveryOtherConflict,
+            );
+            sat.check_resolve(&res, &name, &ver);
+        }
+    }
+
+    #[test]
+    /// Removing learned incompatibilities still finds a solution exactly when there is one.
+    fn prop_sat_errors_the_same_with_few_learned_incompatibilities(
+        (dependency_provider, cases) in registry_strategy(0u16..665)
+    )  {
+        let mut sat = SatResolve::new(&dependency_provider);
+        let options = ResolveOptions {
+            max_learned_incompatibilities: Some(NonZeroU32::new(2).unwrap()),
+            ..ResolveOptions::default()
+        };
+        for (name, ver) in cases {
+            let res = resolve_with_options(
+                &TimeoutDependencyProvider::new(dependency_provider.clone(), 50_000),
+                name,
+                ver,
+                options,
+            );
+            sat.check_resolve(&res, &name, &ver);
+        }
+    }
+
+    #[test]
+    fn prop_sat_errors_the_same_deciding_pinned_first(
+        (dependency_provider, cases) in registry_strategy(0u16..665)
+    )  {
+        let mut sat = SatResolve::new(&dependency_provider);
+        let options = ResolveOptions {
+            decide_pinned_first: true,
+            ..ResolveOptions::default()
+        };
+        for (name, ver) in cases {
+            let res = resolve_with_options(
+                &TimeoutDependencyProvider::new(dependency_provider.clone(), 50_000),
+                name,
+                ver,
+                options,
+            );
+            sat.check_resolve(&res, &name, &ver);
+        }
+    }
+
+    #[test]
+    /// The solutions enumerated after the first one are valid and distinct.
+    fn prop_sat_enumerated_solutions_are_valid(
+        (dependency_provider, cases) in registry_strategy(0u16..665)
+    )  {
+        let mut sat = SatResolve::new(&dependency_provider);
+        for (name, ver) in cases {
+            let provider = TimeoutDependencyProvider::new(dependency_provider.clone(), 50_000);
+            let mut solutions = resolve_iter(&provider, name, ver);
+            let first = solutions.next().unwrap();
+            sat.check_resolve(&first, &name, &ver);
+            let mut found = Vec::from_iter(first.ok());
+            for solution in solutions.take(4) {
+                let Ok(solution) = solution else {
+                    break;
+                };
+                prop_assert!(sat.is_valid_solution::<TimeoutDependencyProvider<OfflineDependencyProvider<u16, NumVS>>>(&solution));
+                prop_assert_eq!(solution.get(&name), Some(&ver));
+                prop_assert!(!found.contains(&solution));
+                found.push(solution);
+            }
+        }
+    }
+
+    #[test]
+    fn prop_sat_optimal_solutions_are_valid_and_no_worse(
+        (dependency_provider, cases) in registry_strategy(0u16..665)
+    )  {
+        let mut sat = SatResolve::new(&dependency_provider);
+        let cost = |_: &u16, v: &u32| u64::from(*v);
+        for (name, ver) in cases {
+            let provider = TimeoutDependencyProvider::new(dependency_provider.clone(), 50_000);
+            let Ok(first) = resolve(&provider, name, ver) else {
+                continue;
+            };
+            let Ok(optimal) = resolve_optimal(&provider, name, ver, cost, Some(8)) else {
+                break;
+            };
+            prop_assert!(sat.is_valid_solution::<TimeoutDependencyProvider<OfflineDependencyProvider<u16, NumVS>>>(&optimal.solution));
+            prop_assert!(optimal.cost <= first.iter().map(|(p, v)| cost(p, v)).sum());
+        }
+    }
+
     #[test]
     fn prop_errors_the_same_with_only_report_dependencies(
         (dependency_provider, cases) in registry_strategy(0u16..665)
@@ -486,6 +723,126 @@ proptest! {
         }
     }
 
+    #[test]
+    /// Driving the solver through [AsyncDependencyProvider] must not change the result.
+    fn prop_async_same_as_sync(
+        (dependency_provider, cases) in registry_strategy(0u16..665)
+    )  {
+        let async_provider = AsyncWrapper(dependency_provider.clone());
+        for (name, ver) in cases {
+            let sync = timeout_resolve(dependency_provider.clone(), name, ver);
+            let r#async = block_on(resolve_async(&async_provider, name, ver));
+            match (&sync, &r#async) {
+                (Ok(l), Ok(r)) => prop_assert_eq!(l, r),
+                (Err(PubGrubError::NoSolution(derivation_l)), Err(PubGrubError::NoSolution(derivation_r))) => {
+                    prop_assert_eq!(
+                        DefaultStringReporter::report(derivation_l),
+                        DefaultStringReporter::report(derivation_r)
+                    )},
+                _ => panic!("not the same result")
+            }
+        }
+    }
+
+    #[test]
+    /// Stepping through the solver loop must not change the result.
+    fn prop_stepping_same_as_resolve(
+        (dependency_provider, cases) in registry_strategy(0u16..665)
+    )  {
+        let timeout_provider = TimeoutDependencyProvider::new(dependency_provider.clone(), 50_000);
+        for (name, ver) in cases {
+            let resolved = timeout_resolve(dependency_provider.clone(), name, ver);
+            let stepped = SteppingResolver::new(State::init(name, ver)).finish(&timeout_provider);
+            match (&resolved, &stepped) {
+                (Ok(l), Ok(r)) => prop_assert_eq!(l, r),
+                (Err(PubGrubError::NoSolution(derivation_l)), Err(PubGrubError::NoSolution(derivation_r))) => {
+                    prop_assert_eq!(
+                        DefaultStringReporter::report(derivation_l),
+                        DefaultStringReporter::report(derivation_r)
+                    )},
+                _ => panic!("not the same result")
+            }
+        }
+    }
+
+    #[test]
+    /// Known versions only make the solver faster, even if some versions are not known.
+    fn prop_same_with_some_known_versions(
+        (dependency_provider, cases) in registry_strategy(0u16..665)
+    )  {
+        for (name, ver) in cases {
+            let all_known = timeout_resolve(dependency_provider.clone(), name, ver);
+            let some_known =
+                timeout_resolve(SomeKnownVersions(dependency_provider.clone()), name, ver);
+            match (&all_known, &some_known) {
+                (Ok(l), Ok(r)) => prop_assert_eq!(l, r),
+                (Err(PubGrubError::NoSolution(derivation_l)), Err(PubGrubError::NoSolution(derivation_r))) => {
+                    prop_assert_eq!(
+                        DefaultStringReporter::report(derivation_l),
+                        DefaultStringReporter::report(derivation_r)
+                    )},
+                _ => panic!("not the same result")
+            }
+        }
+    }
+
+    #[test]
+    /// Reusing the incompatibilities learned while resolving other root dependencies
+    /// does not change the existence of a solution.
+    fn prop_incremental_errors_the_same(
+        (dependency_provider, cases) in registry_strategy(0u16..665)
+    )  {
+        let root = u16::MAX;
+        let mut resolver = IncrementalResolver::new(root, 0u32);
+        let mut previous = None;
+        for (name, ver) in cases {
+            if let Some(previous) = previous.replace(name) {
+                resolver.remove_dependency(&previous);
+            }
+            resolver.add_dependency(name, Ranges::singleton(ver));
+            let incremental = resolver.resolve(&dependency_provider);
+
+            let mut fresh_provider = dependency_provider.clone();
+            fresh_provider.add_dependencies(root, 0u32, [(name, Ranges::singleton(ver))]);
+            let fresh = timeout_resolve(fresh_provider, root, 0u32);
+            prop_assert_eq!(incremental.is_ok(), fresh.is_ok());
+        }
+    }
+
+    #[test]
+    /// The decisions returned when cancelling are compatible with each other,
+    /// and a resolution that is not cancelled is the normal one.
+    fn prop_best_effort_decisions_are_consistent(
+        (dependency_provider, cases) in registry_strategy(0u16..665),
+        max_calls in 0u64..20,
+    )  {
+        for (name, ver) in cases {
+            let cancel_after = CancelAfter {
+                dp: dependency_provider.clone(),
+                call_count: std::cell::Cell::new(0),
+                max_calls,
+            };
+            match resolve_best_effort(&cancel_after, name, ver) {
+                Ok(BestEffortSolution::Complete(solution)) => {
+                    prop_assert_eq!(Some(solution), timeout_resolve(dependency_provider.clone(), name, ver).ok());
+                }
+                Ok(BestEffortSolution::Partial { decisions, .. }) => {
+                    for (package, version) in &decisions {
+                        let Dependencies::Available(dependencies) = dependency_provider.get_dependencies(package, version).unwrap() else {
+                            continue;
+                        };
+                        for (dependency, range) in dependencies {
+                            if let Some(selected) = decisions.get(&dependency) {
+                                prop_assert!(range.contains(selected));
+                            }
+                        }
+                    }
+                }
+                Err(err) => prop_assert!(matches!(err, PubGrubError::NoSolution(_)), "{err:?}"),
+            }
+        }
+    }
+
     #[test]
     /// [ReverseDependencyProvider] changes what order the candidates
     /// are tried but not the existence of a solution.
@@ -1,6 +1,29 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use pubgrub::{resolve, OfflineDependencyProvider, PubGrubError, Ranges};
+use std::cell::{Cell, RefCell};
+use std::convert::Infallible;
+use std::num::NonZeroU32;
+use std::ops::Bound;
+use std::sync::Arc;
+
+use pubgrub::{
+    find_cycles, propagate, resolve, resolve_acyclic, resolve_async, resolve_from_checkpoint,
+    resolve_frozen, resolve_graph, resolve_roots, resolve_sorted, resolve_universal,
+    resolve_with_conflicts, resolve_with_constraints, resolve_with_duplicates,
+    resolve_with_environment, resolve_with_error_retries, resolve_with_features,
+    resolve_with_limits, resolve_with_options, resolve_with_overrides, resolve_with_preferences,
+    resolve_with_provides, resolve_with_recommendations, resolve_with_stats, resolve_with_strategy,
+    resolve_with_yanked, AsyncDependencyProvider, CallbackDependencyProvider, CallbackError,
+    Checkpoint, DeclaredConflict, DefaultStringReporter, Dependencies, DependencyConstraints,
+    DependencyProvider, EnumeratedSet, ExceededLimit, FeatureDependencies,
+    FeatureDependencyConstraints, FeatureDependencyProvider, FeaturePackage, FrozenError,
+    IncrementalResolver, Map, Marker, MarkerDependencies, MarkerDependencyProvider, MarkerPackage,
+    MultiVersionDependencyProvider, OfflineDependencyProvider, Override,
+    PackageResolutionStatistics, PackageSlot, PartialSolutionView, ProvidesDependencyProvider,
+    PubGrubError, Ranges, RecommendsDependencyProvider, RecordingProvider, Reporter,
+    ResolutionLimits, ResolveOptions, Resolver, SemanticVersion, Set, SolutionDiff, Term, TieBreak,
+    TransientErrors, VersionSet, VersionStrategy, YankedVersions,
+};
 
 type NumVS = Ranges<u32>;
 
@@ -26,6 +49,35 @@ fn same_result_on_repeated_runs() {
     }
 }
 
+#[test]
+fn same_sorted_output_on_repeated_runs() {
+    let packages = [
+        "serde", "log", "zlib", "anyhow", "regex", "libc", "rand", "tokio",
+    ];
+    let lockfile = |packages: &[&'static str]| {
+        let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+        let dependencies = packages.iter().map(|p| (*p, Ranges::full()));
+        dependency_provider.add_dependencies("root", 1u32, dependencies);
+        for package in packages {
+            dependency_provider.add_dependencies(*package, 1u32, []);
+        }
+        let solution = resolve_sorted(&dependency_provider, "root", 1u32).unwrap();
+        solution
+            .iter()
+            .map(|(package, version)| format!("{package} = {version}\n"))
+            .collect::<String>()
+    };
+
+    // The packages are added and decided in different orders, the output is the same.
+    let expected = lockfile(&packages);
+    assert!(expected.starts_with("anyhow = 1\nlibc = 1\nlog = 1\n"));
+    for shift in 1..packages.len() {
+        let mut packages = packages;
+        packages.rotate_left(shift);
+        assert_eq!(lockfile(&packages), expected);
+    }
+}
+
 #[test]
 fn should_always_find_a_satisfier() {
     let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
@@ -50,3 +102,2631 @@ fn depend_on_self() {
     dependency_provider.add_dependencies("a", 66u32, [("a", Ranges::singleton(111u32))]);
     assert!(resolve(&dependency_provider, "a", 66u32).is_err());
 }
+
+#[test]
+fn dependency_cycles() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("a", Ranges::full())]);
+    dependency_provider.add_dependencies("a", 1u32, [("b", Ranges::full())]);
+    dependency_provider.add_dependencies("b", 1u32, [("a", Ranges::full()), ("c", Ranges::full())]);
+    dependency_provider.add_dependencies("c", 1u32, []);
+
+    let solution = resolve(&dependency_provider, "root", 1u32).unwrap();
+    let cycles = find_cycles(&dependency_provider, &solution).unwrap();
+    assert_eq!(cycles.len(), 1);
+    assert_eq!(
+        cycles[0].iter().copied().collect::<Set<_>>(),
+        Set::from_iter(["a", "b"])
+    );
+    let Err(PubGrubError::CyclicDependency { cycle }) =
+        resolve_acyclic(&dependency_provider, "root", 1u32)
+    else {
+        panic!("expected a cycle");
+    };
+    assert_eq!(cycle, cycles[0]);
+
+    dependency_provider.add_dependencies("b", 2u32, [("c", Ranges::full())]);
+    assert!(resolve_acyclic(&dependency_provider, "root", 1u32).is_ok());
+}
+
+#[test]
+fn topologically_sorted_graph() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies(
+        "root",
+        1u32,
+        [("a", Ranges::full()), ("b", Ranges::higher_than(2u32))],
+    );
+    dependency_provider.add_dependencies("a", 1u32, [("c", Ranges::full())]);
+    dependency_provider.add_dependencies("b", 1u32, []);
+    dependency_provider.add_dependencies("b", 2u32, [("c", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("c", 1u32, []);
+    dependency_provider.add_dependencies("c", 2u32, []);
+
+    let graph = resolve_graph(&dependency_provider, "root", 1u32).unwrap();
+    let order: Vec<_> = graph.topological_order().iter().map(|(p, _)| *p).collect();
+    assert_eq!(order.len(), 4);
+    let position = |package| order.iter().position(|p| *p == package).unwrap();
+    for (package, _) in graph.topological_order() {
+        for (dependency, _) in graph.dependencies(package) {
+            assert!(position(*dependency) < position(*package));
+        }
+    }
+    assert_eq!(
+        graph.dependencies(&"b"),
+        [("c", Ranges::singleton(1u32))].as_slice()
+    );
+    assert_eq!(graph.dependencies(&"c"), [].as_slice());
+    assert_eq!(graph.topological_order()[0], ("c", 1));
+}
+
+#[test]
+fn why_a_package_is_selected() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("a", Ranges::full())]);
+    dependency_provider.add_dependencies("a", 1u32, [("b", Ranges::higher_than(2u32))]);
+    dependency_provider.add_dependencies("b", 2u32, [("c", Ranges::between(1u32, 3u32))]);
+    dependency_provider.add_dependencies("c", 1u32, [("a", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("d", 1u32, []);
+
+    let graph = resolve_graph(&dependency_provider, "root", 1u32).unwrap();
+    assert_eq!(
+        graph.why(&"c"),
+        Some(vec![
+            (&"root", &1, &Ranges::full()),
+            (&"a", &1, &Ranges::higher_than(2u32)),
+            (&"b", &2, &Ranges::between(1u32, 3u32)),
+        ])
+    );
+    // The shortest chain, not the one through c.
+    assert_eq!(graph.why(&"a"), Some(vec![(&"root", &1, &Ranges::full())]));
+    assert_eq!(graph.why(&"root"), Some(vec![]));
+    assert_eq!(graph.why(&"d"), None);
+
+    let mut dependents = graph.dependents(&"a");
+    dependents.sort();
+    assert_eq!(
+        dependents,
+        [(&"c", &Ranges::singleton(1u32)), (&"root", &Ranges::full())]
+    );
+}
+
+#[test]
+fn derivation_tree_to_dot() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::higher_than(2u32))]);
+    dependency_provider.add_dependencies("foo", 1u32, []);
+
+    let Err(PubGrubError::NoSolution(tree)) = resolve(&dependency_provider, "root", 1u32) else {
+        panic!("expected no solution");
+    };
+    assert_eq!(
+        tree.to_dot(),
+        r#"digraph {
+    0 [label="root ==1 is forbidden"];
+    1 [shape=box, label="there is no version of foo in >=2"];
+    0 -> 1;
+    2 [shape=box, label="root ==1 depends on foo >=2"];
+    0 -> 2;
+}
+"#
+    );
+}
+
+#[test]
+fn no_solution_error_source() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::higher_than(2u32))]);
+
+    let err = resolve(&dependency_provider, "root", 1u32).unwrap_err();
+    // Printing the chain of errors explains the failure without running a reporter.
+    assert_eq!(
+        std::error::Error::source(&err).unwrap().to_string(),
+        "Because there is no version of foo in >=2 and root ==1 depends on foo >=2, \
+        root ==1 is forbidden."
+    );
+}
+
+#[test]
+fn incremental_resolution() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("a", 1u32, [("b", Ranges::full())]);
+    dependency_provider.add_dependencies("a", 2u32, [("b", Ranges::higher_than(2u32))]);
+    dependency_provider.add_dependencies("b", 1u32, []);
+    dependency_provider.add_dependencies("b", 2u32, []);
+    dependency_provider.add_dependencies("c", 1u32, [("b", Ranges::singleton(1u32))]);
+
+    let mut resolver = IncrementalResolver::new("root", 0u32);
+    resolver.add_dependency("a", Ranges::full());
+    let solution = resolver.resolve(&dependency_provider).unwrap();
+    assert_eq!(solution.get("a"), Some(&2));
+    assert_eq!(solution.get("b"), Some(&2));
+
+    // Adding a dependency forces a downgrade of `a`.
+    resolver.add_dependency("c", Ranges::full());
+    let solution = resolver.resolve(&dependency_provider).unwrap();
+    assert_eq!(solution.get("a"), Some(&1));
+    assert_eq!(solution.get("b"), Some(&1));
+
+    // Bumping a constraint makes resolution fail.
+    resolver.add_dependency("a", Ranges::singleton(2u32));
+    assert!(matches!(
+        resolver.resolve(&dependency_provider),
+        Err(PubGrubError::NoSolution(_))
+    ));
+
+    // Removing a dependency makes it succeed again, without the removed package.
+    resolver.remove_dependency(&"c");
+    let solution = resolver.resolve(&dependency_provider).unwrap();
+    assert_eq!(solution.get("a"), Some(&2));
+    assert_eq!(solution.get("c"), None);
+}
+
+#[test]
+fn incremental_resolution_after_removing_a_constraint() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("a", 1u32, [("b", Ranges::full())]);
+    dependency_provider.add_dependencies("a", 2u32, [("b", Ranges::higher_than(2u32))]);
+    dependency_provider.add_dependencies("b", 1u32, []);
+    dependency_provider.add_dependencies("b", 2u32, []);
+
+    let mut resolver = IncrementalResolver::new("root", 0u32);
+    resolver.add_dependency("a", Ranges::full());
+    resolver.add_constraint("b", Ranges::singleton(1u32), "pinned".to_owned());
+    let solution = resolver.resolve(&dependency_provider).unwrap();
+    assert_eq!(solution.get("a"), Some(&1));
+    assert_eq!(solution.get("b"), Some(&1));
+
+    // With both constraints, there is no solution.
+    resolver.add_constraint("a", Ranges::singleton(2u32), "pinned".to_owned());
+    assert!(matches!(
+        resolver.resolve(&dependency_provider),
+        Err(PubGrubError::NoSolution(_))
+    ));
+
+    // What was derived from the removed constraint doesn't apply anymore.
+    resolver.remove_constraint(&"b");
+    let solution = resolver.resolve(&dependency_provider).unwrap();
+    assert_eq!(solution.get("a"), Some(&2));
+    assert_eq!(solution.get("b"), Some(&2));
+}
+
+/// A small registry with features.
+///
+/// Optional dependencies are the same for all versions of a package.
+struct FeatureRegistry {
+    versions: Vec<(&'static str, u32)>,
+    dependencies: Vec<(&'static str, u32, &'static str, NumVS, Vec<&'static str>)>,
+    optional: Vec<(&'static str, &'static str, &'static str, NumVS)>,
+}
+
+impl FeatureDependencyProvider for FeatureRegistry {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type F = &'static str;
+    type M = String;
+    type Priority = u32;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        _package: &FeaturePackage<&'static str, &'static str>,
+        _range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> u32 {
+        statistics.conflict_count()
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        Ok(self
+            .versions
+            .iter()
+            .filter(|(p, v)| p == package && range.contains(v))
+            .map(|(_, v)| *v)
+            .max())
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<FeatureDependencies<&'static str, NumVS, String, &'static str>, Infallible> {
+        let mut dependencies = FeatureDependencyConstraints::default();
+        for (p, v, dep, range, features) in &self.dependencies {
+            if p == package && v == version {
+                dependencies.insert(*dep, (range.clone(), features.iter().copied().collect()));
+            }
+        }
+        let mut features = Map::default();
+        for (p, feature, dep, range) in &self.optional {
+            if p == package {
+                features
+                    .entry(*feature)
+                    .or_insert_with(FeatureDependencyConstraints::default)
+                    .insert(*dep, (range.clone(), Set::default()));
+            }
+        }
+        Ok(FeatureDependencies::Available {
+            dependencies,
+            features,
+        })
+    }
+}
+
+#[test]
+fn resolution_stats() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 2u32, [("bar", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("foo", 1u32, []);
+    dependency_provider.add_dependencies("bar", 1u32, [("foo", Ranges::singleton(1u32))]);
+
+    let (solution, stats) = resolve_with_stats(&dependency_provider, "root", 1u32);
+    assert_eq!(solution.unwrap().get("foo"), Some(&1));
+    // foo 2 and bar 1 are decided, then found to conflict and backtracked.
+    assert_eq!(stats.decisions, 4);
+    assert_eq!(stats.conflicts, 1);
+    assert_eq!(stats.backtracks, 1);
+    assert!(stats.incompatibilities_learned >= 1);
+    assert_eq!(
+        stats.time_per_package.keys().copied().collect::<Set<_>>(),
+        Set::from_iter(["root", "foo", "bar"])
+    );
+}
+
+#[test]
+fn memory_stats() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 1u32, [("bar", Ranges::full())]);
+    dependency_provider.add_dependencies("bar", 1u32, []);
+
+    let (solution, stats) = resolve_with_stats(&dependency_provider, "root", 1u32);
+    solution.unwrap();
+    assert_eq!((stats.memory.packages, stats.memory.assignments), (3, 3));
+    // The root, and the dependencies of root and foo.
+    assert_eq!(stats.memory.incompatibilities, 3);
+    assert!(stats.memory.estimated_bytes > 0);
+
+    // Reserving room for many more packages takes more memory.
+    let options = ResolveOptions {
+        expected_packages: NonZeroU32::new(1000),
+        max_steps: Some(1),
+        ..ResolveOptions::default()
+    };
+    let Err(PubGrubError::Timeout { stats: reserved }) =
+        resolve_with_options(&dependency_provider, "root", 1u32, options)
+    else {
+        panic!("expected a timeout");
+    };
+    assert!(reserved.memory.estimated_bytes > stats.memory.estimated_bytes);
+}
+
+#[test]
+fn conflicting_packages_after_failure() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 2u32, [("bar", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("foo", 1u32, [("bar", Ranges::singleton(2u32))]);
+    dependency_provider.add_dependencies("bar", 1u32, [("foo", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("bar", 2u32, [("foo", Ranges::singleton(2u32))]);
+
+    let (solution, stats) = resolve_with_stats(&dependency_provider, "root", 1u32);
+    assert!(matches!(solution, Err(PubGrubError::NoSolution(_))));
+    let packages: Set<_> = stats.conflicting_packages.iter().map(|(p, _)| *p).collect();
+    assert!(packages.contains("foo") && packages.contains("bar"));
+    assert!(!packages.contains("root"));
+    let counts: Vec<_> = stats.conflicting_packages.iter().map(|(_, c)| *c).collect();
+    assert!(counts.windows(2).all(|w| w[0] >= w[1]));
+}
+
+#[test]
+fn global_constraints() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 1u32, [("bar", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 2u32, []);
+    dependency_provider.add_dependencies("bar", 1u32, []);
+    let advisory = || {
+        (
+            "foo",
+            Ranges::strictly_lower_than(2u32),
+            "RUSTSEC-0000".to_string(),
+        )
+    };
+
+    // A constraint on a package nothing depends on doesn't select it.
+    let constraints = [advisory(), ("baz", Ranges::empty(), "yanked".to_string())];
+    let solution = resolve_with_constraints(&dependency_provider, "root", 1u32, constraints);
+    let solution = solution.unwrap();
+    assert_eq!(solution["foo"], 1);
+    assert!(!solution.contains_key("baz"));
+
+    let constraints = [advisory(), ("bar", Ranges::empty(), "yanked".to_string())];
+    let Err(PubGrubError::NoSolution(derivation_tree)) =
+        resolve_with_constraints(&dependency_provider, "root", 1u32, constraints)
+    else {
+        panic!("expected no solution");
+    };
+    let report = DefaultStringReporter::report(&derivation_tree);
+    assert!(
+        report.contains("RUSTSEC-0000") && report.contains("yanked"),
+        "{report}"
+    );
+}
+
+#[test]
+fn dependency_overrides() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies(
+        "root",
+        1u32,
+        [("foo", Ranges::full()), ("bar", Ranges::singleton(1u32))],
+    );
+    dependency_provider.add_dependencies("foo", 1u32, [("bar", Ranges::full())]);
+    dependency_provider.add_dependencies("bar", 1u32, [("baz", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("bar", 2u32, []);
+    dependency_provider.add_dependencies("baz", 1u32, []);
+    dependency_provider.add_dependencies("baz-fork", 1u32, []);
+
+    let overrides = Map::from_iter([
+        ("bar", Override::Pin("bar", Ranges::singleton(2u32))),
+        ("baz", Override::Redirect("baz-fork")),
+    ]);
+    let solution = resolve_with_overrides(&dependency_provider, "root", 1u32, &overrides).unwrap();
+    assert_eq!(solution["bar"], 2);
+    assert!(!solution.contains_key("baz"));
+
+    let overrides = Map::from_iter([("baz", Override::Redirect("baz-fork"))]);
+    let solution = resolve_with_overrides(&dependency_provider, "root", 1u32, &overrides).unwrap();
+    assert_eq!(solution["bar"], 1);
+    assert_eq!(solution["baz-fork"], 1);
+    assert!(!solution.contains_key("baz"));
+}
+
+#[test]
+fn frozen_resolution() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 1u32, []);
+    dependency_provider.add_dependencies("foo", 2u32, [("bar", Ranges::full())]);
+    dependency_provider.add_dependencies("bar", 1u32, []);
+
+    // foo 2 is newer but not in the lockfile.
+    let locked: Map<_, _> = [("root", Ranges::full()), ("foo", Ranges::singleton(1u32))]
+        .into_iter()
+        .collect();
+    let solution = resolve_frozen(&dependency_provider, "root", 1u32, &locked).unwrap();
+    assert_eq!(solution["foo"], 1);
+
+    let cached: Map<_, _> = [("root", Ranges::full()), ("foo", Ranges::full())]
+        .into_iter()
+        .collect();
+    let Err(PubGrubError::ErrorRetrievingDependencies { source, .. }) =
+        resolve_frozen(&dependency_provider, "root", 1u32, &cached)
+    else {
+        panic!("expected an unknown package");
+    };
+    assert_eq!(
+        source,
+        FrozenError::UnknownPackage {
+            package: "bar",
+            dependent: "foo",
+            version: 2
+        }
+    );
+}
+
+#[test]
+fn multiple_roots() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("app", 1u32, [("log", Ranges::full())]);
+    dependency_provider.add_dependencies("cli", 1u32, [("log", Ranges::strictly_lower_than(3u32))]);
+    dependency_provider.add_dependencies("web", 1u32, [("log", Ranges::higher_than(3u32))]);
+    for version in 1..5u32 {
+        dependency_provider.add_dependencies("log", version, []);
+    }
+
+    let solution = resolve_roots(&dependency_provider, [("app", 1), ("cli", 1)]).unwrap();
+    assert_eq!(
+        solution,
+        Map::from_iter([("app", 1), ("cli", 1), ("log", 2)])
+    );
+    assert!(resolve_roots(&dependency_provider, []).unwrap().is_empty());
+
+    let Err(PubGrubError::NoSolution(derivation_tree)) =
+        resolve_roots(&dependency_provider, [("app", 1), ("cli", 1), ("web", 1)])
+    else {
+        panic!("expected no solution");
+    };
+    let report = DefaultStringReporter::report(&derivation_tree);
+    assert!(
+        report.contains("cli 1") && report.contains("web 1"),
+        "{report}"
+    );
+}
+
+#[test]
+fn solution_diff() {
+    let old = Map::from_iter([("root", 1u32), ("a", 1), ("b", 2), ("c", 1), ("d", 1)]);
+    let new = Map::from_iter([("root", 1u32), ("a", 2), ("b", 1), ("d", 1), ("e", 3)]);
+    let diff = SolutionDiff::new(&old, &new);
+    assert_eq!(diff.added, Map::from_iter([("e", 3)]));
+    assert_eq!(diff.removed, Map::from_iter([("c", 1)]));
+    assert_eq!(diff.upgraded, Map::from_iter([("a", (1, 2))]));
+    assert_eq!(diff.downgraded, Map::from_iter([("b", (2, 1))]));
+    assert_eq!(
+        diff.to_string(),
+        "Updating a 1 -> 2\nDowngrading b 2 -> 1\nRemoving c 1\nAdding e 3\n"
+    );
+    assert!(SolutionDiff::new(&new, &new).is_empty());
+}
+
+#[test]
+fn shared_packages_and_versions() {
+    let version = |v: SemanticVersion| Arc::new(v);
+    let mut dependency_provider =
+        OfflineDependencyProvider::<Arc<str>, Ranges<Arc<SemanticVersion>>>::new();
+    let [root, foo]: [Arc<str>; 2] = ["root".into(), "foo".into()];
+    dependency_provider.add_dependencies(
+        root.clone(),
+        version((1, 0, 0).into()),
+        [(foo.clone(), Ranges::higher_than(version((1, 1, 0).into())))],
+    );
+    for v in [(1, 0, 0), (1, 2, 0), (2, 0, 0)] {
+        dependency_provider.add_dependencies(foo.clone(), version(v.into()), []);
+    }
+    let solution = resolve(&dependency_provider, root, version((1, 0, 0).into())).unwrap();
+    assert_eq!(*solution[&foo], SemanticVersion::new(2, 0, 0));
+}
+
+thread_local! {
+    static PACKAGE_CLONES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A package counting its clones in [PACKAGE_CLONES].
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct CountedPackage(u32);
+
+impl Clone for CountedPackage {
+    fn clone(&self) -> Self {
+        PACKAGE_CLONES.with(|clones| clones.set(clones.get() + 1));
+        Self(self.0)
+    }
+}
+
+impl std::fmt::Display for CountedPackage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "p{}", self.0)
+    }
+}
+
+#[test]
+fn packages_not_cloned_per_step() {
+    let mut dependency_provider = OfflineDependencyProvider::<CountedPackage, NumVS>::new();
+    let n = 100;
+    for p in 0..n {
+        let dependencies = (p + 1 < n).then(|| (CountedPackage(p + 1), Ranges::full()));
+        for v in 0..3u32 {
+            dependency_provider.add_dependencies(CountedPackage(p), v, dependencies.clone());
+        }
+    }
+
+    PACKAGE_CLONES.with(|clones| clones.set(0));
+    let solution = resolve(&dependency_provider, CountedPackage(0), 2u32).unwrap();
+    assert_eq!(solution.len(), n as usize);
+    // The provider clones the dependencies it returns, the solver clones each package once
+    // when it first appears, and again into the solution.
+    assert!(PACKAGE_CLONES.with(Cell::get) <= 3 * n as usize);
+}
+
+#[test]
+fn resolution_limits() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("a", Ranges::full())]);
+    dependency_provider.add_dependencies("a", 1u32, [("b", Ranges::full()), ("c", Ranges::full())]);
+    dependency_provider.add_dependencies("b", 1u32, [("d", Ranges::full())]);
+    dependency_provider.add_dependencies("c", 1u32, []);
+    dependency_provider.add_dependencies("d", 1u32, []);
+    let resolve = |limits| resolve_with_limits(&dependency_provider, "root", 1u32, limits);
+
+    let packages = ResolutionLimits {
+        max_packages: Some(4),
+        ..ResolutionLimits::default()
+    };
+    assert!(matches!(
+        resolve(packages),
+        Err(PubGrubError::LimitExceeded(ExceededLimit::Packages(4)))
+    ));
+    let depth = ResolutionLimits {
+        max_depth: Some(4),
+        ..ResolutionLimits::default()
+    };
+    assert!(matches!(
+        resolve(depth),
+        Err(PubGrubError::LimitExceeded(ExceededLimit::Depth(4)))
+    ));
+    let enough = ResolutionLimits {
+        max_packages: Some(5),
+        max_depth: Some(5),
+    };
+    assert_eq!(resolve(enough).unwrap().len(), 5);
+}
+
+#[test]
+fn resolution_budget() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 2u32, [("bar", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("foo", 1u32, []);
+    dependency_provider.add_dependencies("bar", 1u32, [("foo", Ranges::singleton(1u32))]);
+    let resolve = |options| resolve_with_options(&dependency_provider, "root", 1u32, options);
+
+    // Out of steps right after foo 2 and bar 1 were found to conflict.
+    let options = ResolveOptions {
+        max_steps: Some(3),
+        ..ResolveOptions::default()
+    };
+    let Err(PubGrubError::Timeout { stats }) = resolve(options) else {
+        panic!("expected a timeout");
+    };
+    assert_eq!(stats.conflicts, 1);
+    let mut packages: Vec<_> = stats.conflicting_packages.iter().map(|(p, _)| *p).collect();
+    packages.sort();
+    assert_eq!(packages, ["bar", "foo"]);
+
+    let options = ResolveOptions {
+        timeout: Some(core::time::Duration::from_secs(60)),
+        max_steps: Some(10),
+        ..ResolveOptions::default()
+    };
+    assert_eq!(resolve(options).unwrap()["foo"], 1);
+}
+
+#[test]
+fn activity_heuristic() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies(
+        "root",
+        1u32,
+        [
+            ("a", Ranges::full()),
+            ("b", Ranges::full()),
+            ("c", Ranges::full()),
+        ],
+    );
+    for v in 1..=3u32 {
+        // Only a 1 works with b 1.
+        dependency_provider.add_dependencies("a", v, [("b", Ranges::singleton(v))]);
+        dependency_provider.add_dependencies("c", v, []);
+    }
+    dependency_provider.add_dependencies("b", 1u32, [("a", Ranges::singleton(1u32))]);
+
+    let options = ResolveOptions {
+        activity_heuristic: true,
+        ..ResolveOptions::default()
+    };
+    let solution = resolve_with_options(&dependency_provider, "root", 1u32, options).unwrap();
+    assert_eq!(
+        solution,
+        resolve(&dependency_provider, "root", 1u32).unwrap()
+    );
+    assert_eq!((solution["a"], solution["b"], solution["c"]), (1, 1, 3));
+}
+
+#[test]
+fn restarts() {
+    let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    let groups = [("a", "b", "c", "d"), ("e", "f", "g", "h")];
+    let root_dependencies = groups
+        .iter()
+        .flat_map(|(p, q, _, _)| [(*p, Ranges::full()), (*q, Ranges::full())]);
+    dependency_provider.add_dependencies("root", 1u32, root_dependencies);
+    for (p, q, r, s) in groups {
+        // p 2 and q 2 conflict through r, found after both were decided.
+        dependency_provider.add_dependencies(p, 1u32, []);
+        dependency_provider.add_dependencies(p, 2u32, [(r, Ranges::singleton(1u32))]);
+        dependency_provider.add_dependencies(q, 1u32, []);
+        dependency_provider.add_dependencies(q, 2u32, [(s, Ranges::full())]);
+        dependency_provider.add_dependencies(r, 1u32, []);
+        dependency_provider.add_dependencies(r, 2u32, []);
+        dependency_provider.add_dependencies(s, 1u32, [(r, Ranges::singleton(2u32))]);
+    }
+
+    let options = ResolveOptions {
+        restart_interval: Some(NonZeroU32::MIN),
+        ..ResolveOptions::default()
+    };
+    let solution = resolve_with_options(&dependency_provider, "root", 1u32, options).unwrap();
+    for (p, q, _, _) in groups {
+        assert!(solution[p] == 1 || solution[q] == 1, "{solution:?}");
+    }
+
+    // Restarting after the first and second conflicts, then after two more.
+    let options = ResolveOptions {
+        max_steps: Some(18),
+        ..options
+    };
+    let Err(PubGrubError::Timeout { stats }) =
+        resolve_with_options(&dependency_provider, "root", 1u32, options)
+    else {
+        panic!("expected a timeout");
+    };
+    assert_eq!((stats.conflicts, stats.restarts), (4, 3));
+}
+
+#[test]
+fn few_learned_incompatibilities() {
+    let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    let groups = [
+        ("a", "b", "c", "d"),
+        ("e", "f", "g", "h"),
+        ("i", "j", "k", "l"),
+    ];
+    let root_dependencies = groups
+        .iter()
+        .flat_map(|(p, q, _, _)| [(*p, Ranges::full()), (*q, Ranges::full())]);
+    dependency_provider.add_dependencies("root", 1u32, root_dependencies);
+    for (p, q, r, s) in groups {
+        // p 2 and q 2 conflict through r, learning that they can't be selected together.
+        dependency_provider.add_dependencies(p, 1u32, []);
+        dependency_provider.add_dependencies(p, 2u32, [(r, Ranges::singleton(1u32))]);
+        dependency_provider.add_dependencies(q, 1u32, []);
+        dependency_provider.add_dependencies(q, 2u32, [(s, Ranges::full())]);
+        dependency_provider.add_dependencies(r, 1u32, []);
+        dependency_provider.add_dependencies(r, 2u32, []);
+        dependency_provider.add_dependencies(s, 1u32, [(r, Ranges::singleton(2u32))]);
+    }
+
+    let options = ResolveOptions {
+        max_learned_incompatibilities: Some(NonZeroU32::MIN),
+        ..ResolveOptions::default()
+    };
+    let solution = resolve_with_options(&dependency_provider, "root", 1u32, options).unwrap();
+    for (p, q, _, _) in groups {
+        assert!(solution[p] == 1 || solution[q] == 1, "{solution:?}");
+    }
+
+    // The two incompatibilities learned from the first two conflicts are removed.
+    let options = ResolveOptions {
+        max_steps: Some(16),
+        ..options
+    };
+    let Err(PubGrubError::Timeout { stats }) =
+        resolve_with_options(&dependency_provider, "root", 1u32, options)
+    else {
+        panic!("expected a timeout");
+    };
+    assert_eq!(
+        (
+            stats.incompatibilities_learned,
+            stats.incompatibilities_removed
+        ),
+        (4, 2)
+    );
+}
+
+#[test]
+fn resume_from_checkpoint() {
+    let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    dependency_provider.add_dependencies(
+        "root",
+        1u32,
+        [("a", Ranges::full()), ("b", Ranges::full())],
+    );
+    dependency_provider.add_dependencies("a", 1u32, [("c", Ranges::full())]);
+    dependency_provider.add_dependencies("a", 2u32, [("c", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("b", 1u32, [("c", Ranges::singleton(2u32))]);
+    dependency_provider.add_dependencies("c", 1u32, []);
+    dependency_provider.add_dependencies("c", 2u32, []);
+    let expected = resolve(&dependency_provider, "root", 1u32).unwrap();
+
+    let mut checkpoints = Vec::new();
+    let solution = resolve_from_checkpoint(
+        &dependency_provider,
+        Checkpoint::new("root", 1u32),
+        |checkpoint| checkpoints.push(checkpoint.clone()),
+    )
+    .unwrap();
+    assert_eq!(solution, expected);
+    let total = checkpoints.last().unwrap().dependencies().len();
+
+    for checkpoint in checkpoints {
+        #[cfg(feature = "serde")]
+        let json = serde_json::to_string(&checkpoint).unwrap();
+        #[cfg(feature = "serde")]
+        let checkpoint: Checkpoint<&str, NumVS, String> = serde_json::from_str(&json).unwrap();
+        let saved = checkpoint.dependencies().len();
+        // Only the dependencies retrieved after the checkpoint are requested again.
+        let recording = RecordingProvider::new(&dependency_provider);
+        let solution = resolve_from_checkpoint(&recording, checkpoint, |_| {}).unwrap();
+        assert_eq!(solution, expected);
+        let snapshot = recording.into_snapshot();
+        let requested: usize = snapshot
+            .packages()
+            .map(|p| snapshot.versions(p).unwrap().count())
+            .sum();
+        assert_eq!(saved + requested, total);
+    }
+}
+
+#[test]
+fn features_are_unified() {
+    let registry = FeatureRegistry {
+        versions: vec![
+            ("root", 0),
+            ("a", 1),
+            ("b", 1),
+            ("lib", 1),
+            ("lib", 2),
+            ("x", 1),
+        ],
+        dependencies: vec![
+            ("root", 0, "a", Ranges::full(), vec![]),
+            ("root", 0, "b", Ranges::full(), vec![]),
+            ("a", 1, "lib", Ranges::full(), vec!["json"]),
+            ("b", 1, "lib", Ranges::singleton(1u32), vec!["xml"]),
+        ],
+        optional: vec![
+            ("lib", "json", "x", Ranges::full()),
+            ("lib", "xml", "x", Ranges::full()),
+        ],
+    };
+    let solution = resolve_with_features(&registry, "root", 0u32).unwrap();
+    assert_eq!(solution["lib"], (1, Set::from_iter(["json", "xml"])));
+    assert_eq!(solution["x"], (1, Set::default()));
+    assert_eq!(solution["root"], (0, Set::default()));
+}
+
+#[test]
+fn unknown_feature() {
+    let registry = FeatureRegistry {
+        versions: vec![("root", 0), ("lib", 1)],
+        dependencies: vec![("root", 0, "lib", Ranges::full(), vec!["missing"])],
+        optional: vec![],
+    };
+    assert!(matches!(
+        resolve_with_features(&registry, "root", 0u32),
+        Err(PubGrubError::NoSolution(_))
+    ));
+}
+
+/// The operating system a dependency is required on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Os(&'static str);
+
+impl std::fmt::Display for Os {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Marker<Os> for Os {
+    fn evaluate(&self, environment: &Os) -> bool {
+        self == environment
+    }
+}
+
+/// A small registry with dependencies required on some operating systems only.
+struct MarkerRegistry {
+    versions: Vec<(&'static str, u32)>,
+    dependencies: Vec<(&'static str, u32, &'static str, NumVS, Option<Os>)>,
+}
+
+impl MarkerDependencyProvider for MarkerRegistry {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type Marker = Os;
+    type M = String;
+    type Priority = u32;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        _package: &MarkerPackage<&'static str, Os>,
+        _range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> u32 {
+        statistics.conflict_count()
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        Ok(self
+            .versions
+            .iter()
+            .filter(|(p, v)| p == package && range.contains(v))
+            .map(|(_, v)| *v)
+            .max())
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<MarkerDependencies<&'static str, NumVS, String, Os>, Infallible> {
+        let mut dependencies = DependencyConstraints::default();
+        let mut conditional = Vec::new();
+        for (p, v, dep, range, marker) in &self.dependencies {
+            if p == package && v == version {
+                match marker {
+                    Some(marker) => conditional.push((*dep, range.clone(), *marker)),
+                    None => {
+                        dependencies.insert(*dep, range.clone());
+                    }
+                }
+            }
+        }
+        Ok(MarkerDependencies::Available {
+            dependencies,
+            conditional,
+        })
+    }
+}
+
+#[test]
+fn dependencies_on_some_platforms() {
+    let registry = MarkerRegistry {
+        versions: vec![("root", 1), ("app", 1), ("openssl", 1), ("winapi", 1)],
+        dependencies: vec![
+            ("root", 1, "app", Ranges::full(), None),
+            (
+                "app",
+                1,
+                "openssl",
+                Ranges::higher_than(3u32),
+                Some(Os("linux")),
+            ),
+            ("app", 1, "winapi", Ranges::full(), Some(Os("windows"))),
+        ],
+    };
+
+    let windows = resolve_with_environment(&registry, "root", 1u32, &Os("windows")).unwrap();
+    assert_eq!(windows.get("winapi"), Some(&1));
+    assert_eq!(windows.get("openssl"), None);
+
+    let Err(PubGrubError::NoSolution(tree)) =
+        resolve_with_environment(&registry, "root", 1u32, &Os("linux"))
+    else {
+        panic!("expected no solution on linux");
+    };
+    let report = DefaultStringReporter::report(&tree);
+    assert!(report.contains("openssl (on linux) >=3"), "{report}");
+}
+
+#[test]
+fn universal_resolution() {
+    let registry = MarkerRegistry {
+        versions: vec![("root", 1), ("app", 1), ("openssl", 3), ("winapi", 1)],
+        dependencies: vec![
+            ("root", 1, "app", Ranges::full(), None),
+            (
+                "app",
+                1,
+                "openssl",
+                Ranges::higher_than(3u32),
+                Some(Os("linux")),
+            ),
+            ("app", 1, "winapi", Ranges::full(), Some(Os("windows"))),
+        ],
+    };
+    let environments = [Os("linux"), Os("windows"), Os("macos"), Os("freebsd")];
+
+    let solution = resolve_universal(&registry, "root", 1u32, &environments).unwrap();
+    // Neither marker holds on macos and freebsd, they share a resolution.
+    assert_eq!(solution.forks().len(), 3);
+    let macos = solution.for_environment(&Os("macos")).unwrap();
+    assert_eq!(Some(macos), solution.for_environment(&Os("freebsd")));
+    assert_eq!(macos.get("openssl"), None);
+    let linux = solution.for_environment(&Os("linux")).unwrap();
+    assert_eq!(linux.get("openssl"), Some(&3));
+    assert_eq!(linux.get("winapi"), None);
+
+    let winapi: Vec<_> = solution.versions(&"winapi").collect();
+    assert_eq!(winapi, vec![(&1, &[Os("windows")][..])]);
+    let app_environments: usize = solution.versions(&"app").map(|(_, e)| e.len()).sum();
+    assert_eq!(app_environments, environments.len());
+}
+
+/// An offline registry recording the packages it was asked to prefetch.
+struct Prefetching {
+    registry: OfflineDependencyProvider<&'static str, NumVS>,
+    prefetched: RefCell<Vec<&'static str>>,
+}
+
+impl DependencyProvider for Prefetching {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.registry.prioritize(package, range, statistics)
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        self.registry.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        self.registry.get_dependencies(package, version)
+    }
+
+    fn prefetch(&self, dependencies: &DependencyConstraints<&'static str, NumVS>) {
+        self.prefetched.borrow_mut().extend(dependencies.keys());
+    }
+}
+
+#[test]
+fn dependencies_are_prefetched() {
+    let mut registry = OfflineDependencyProvider::<_, NumVS>::new();
+    registry.add_dependencies("root", 1u32, [("a", Ranges::full()), ("b", Ranges::full())]);
+    registry.add_dependencies("a", 1u32, [("c", Ranges::full())]);
+    registry.add_dependencies("b", 1u32, []);
+    registry.add_dependencies("c", 1u32, []);
+    let provider = Prefetching {
+        registry,
+        prefetched: RefCell::default(),
+    };
+
+    resolve(&provider, "root", 1u32).unwrap();
+    let mut prefetched = provider.prefetched.into_inner();
+    prefetched.sort();
+    assert_eq!(prefetched, ["a", "b", "c"]);
+}
+
+/// An offline registry answering for all the versions of a package at once, recording the
+/// versions it was asked about.
+struct Batching {
+    registry: OfflineDependencyProvider<&'static str, NumVS>,
+    batches: RefCell<Vec<(&'static str, Vec<u32>)>>,
+}
+
+impl DependencyProvider for Batching {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.registry.prioritize(package, range, statistics)
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        self.registry.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        _package: &&'static str,
+        _version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        unreachable!("the dependencies are retrieved in batches")
+    }
+
+    fn known_versions(&self, package: &&'static str) -> Option<Vec<u32>> {
+        self.registry.known_versions(package)
+    }
+
+    fn get_dependencies_batch(
+        &self,
+        package: &&'static str,
+        versions: &[u32],
+    ) -> Result<Vec<Dependencies<&'static str, NumVS, String>>, Infallible> {
+        self.batches.borrow_mut().push((package, versions.to_vec()));
+        versions
+            .iter()
+            .map(|version| self.registry.get_dependencies(package, version))
+            .collect()
+    }
+}
+
+#[test]
+fn dependencies_in_batches() {
+    let mut registry = OfflineDependencyProvider::<_, NumVS>::new();
+    registry.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    registry.add_dependencies("foo", 3u32, [("bar", Ranges::singleton(2u32))]);
+    registry.add_dependencies("foo", 2u32, [("bar", Ranges::singleton(2u32))]);
+    registry.add_dependencies("foo", 1u32, []);
+    registry.add_dependencies("bar", 2u32, [("foo", Ranges::singleton(1u32))]);
+    let provider = Batching {
+        registry,
+        batches: RefCell::default(),
+    };
+
+    // Backtracking from foo 3 to foo 1 doesn't ask for the dependencies of foo again.
+    let solution = resolve(&provider, "root", 1u32).unwrap();
+    assert_eq!(solution["foo"], 1);
+    assert_eq!(
+        provider.batches.into_inner(),
+        [("root", vec![1]), ("foo", vec![3, 2, 1]), ("bar", vec![2])]
+    );
+}
+
+/// An offline registry deciding the most active packages first, recording the statistics it
+/// was given.
+struct ByActivity {
+    registry: OfflineDependencyProvider<&'static str, NumVS>,
+    reprioritize: bool,
+    prioritized: RefCell<Vec<(&'static str, Option<u32>, f64)>>,
+}
+
+impl DependencyProvider for ByActivity {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    // Activities are not negative, so their bits are ordered like them.
+    type Priority = u64;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        _range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> u64 {
+        self.prioritized
+            .borrow_mut()
+            .push((package, statistics.depth(), statistics.activity()));
+        statistics.activity().to_bits()
+    }
+
+    fn reprioritize_on_conflict(&self) -> bool {
+        self.reprioritize
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        self.registry.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        self.registry.get_dependencies(package, version)
+    }
+}
+
+#[test]
+fn prioritize_by_activity_and_depth() {
+    let mut registry = OfflineDependencyProvider::<_, NumVS>::new();
+    registry.add_dependencies("root", 1u32, [("a", Ranges::full()), ("b", Ranges::full())]);
+    registry.add_dependencies("a", 1u32, [("c", Ranges::singleton(1u32))]);
+    registry.add_dependencies("a", 2u32, [("c", Ranges::singleton(2u32))]);
+    registry.add_dependencies("b", 1u32, [("c", Ranges::singleton(1u32))]);
+    registry.add_dependencies("c", 1u32, []);
+    registry.add_dependencies("c", 2u32, []);
+
+    let mut calls = Vec::new();
+    for reprioritize in [false, true] {
+        let provider = ByActivity {
+            registry: registry.clone(),
+            reprioritize,
+            prioritized: RefCell::default(),
+        };
+        let solution = resolve(&provider, "root", 1u32).unwrap();
+        assert_eq!(solution.get("a"), Some(&1));
+        assert_eq!(solution.get("c"), Some(&1));
+
+        let prioritized = provider.prioritized.into_inner();
+        for (package, depth, _) in &prioritized {
+            let expected = match *package {
+                "root" => 0,
+                "a" | "b" => 1,
+                _ => 2,
+            };
+            assert_eq!(*depth, Some(expected), "{package}");
+        }
+        // The conflict on c involved a, b or c.
+        assert!(prioritized.iter().any(|(_, _, activity)| *activity > 0.0));
+        calls.push(prioritized.len());
+    }
+    // The undecided packages are prioritized again after the conflict.
+    assert!(calls[1] > calls[0], "{calls:?}");
+}
+
+/// An offline registry choosing the versions of the same major, the tens, as the decided
+/// packages of the same family, named by their first letter.
+struct SameMajor(OfflineDependencyProvider<&'static str, NumVS>);
+
+impl DependencyProvider for SameMajor {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.0.prioritize(package, range, statistics)
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        self.0.choose_version(package, range)
+    }
+
+    fn choose_version_in(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        solution: &dyn PartialSolutionView<&'static str, NumVS>,
+    ) -> Result<Option<u32>, Infallible> {
+        assert_eq!(solution.term(package), Some(&Term::Positive(range.clone())));
+        assert_eq!(solution.decision(package), None);
+        assert_eq!(solution.decisions().len() as u32, solution.decision_level());
+        let sibling = solution
+            .decisions()
+            .into_iter()
+            .find(|(p, _)| p.starts_with(&package[..1]));
+        if let Some((_, v)) = sibling {
+            let major = Ranges::between(v / 10 * 10, v / 10 * 10 + 10);
+            if let Some(v) = self
+                .0
+                .choose_version(package, &range.intersection(&major))?
+            {
+                return Ok(Some(v));
+            }
+        }
+        self.0.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        self.0.get_dependencies(package, version)
+    }
+}
+
+#[test]
+fn choose_version_from_decided_packages() {
+    let mut registry = OfflineDependencyProvider::<_, NumVS>::new();
+    registry.add_dependencies(
+        "root",
+        1u32,
+        [("a-core", Ranges::full()), ("a-util", Ranges::full())],
+    );
+    registry.add_dependencies("a-core", 12u32, []);
+    registry.add_dependencies("a-util", 15u32, []);
+    registry.add_dependencies("a-util", 25u32, []);
+
+    assert_eq!(resolve(&registry, "root", 1u32).unwrap()["a-util"], 25);
+    let solution = resolve(&SameMajor(registry), "root", 1u32).unwrap();
+    assert_eq!(solution["a-util"], 15);
+}
+
+#[test]
+fn resolver_chooses_version_from_decided_packages() {
+    let mut registry = OfflineDependencyProvider::<_, NumVS>::new();
+    registry.add_dependencies(
+        "root",
+        1u32,
+        [("a-core", Ranges::full()), ("a-util", Ranges::full())],
+    );
+    registry.add_dependencies("a-core", 12u32, []);
+    registry.add_dependencies("a-util", 15u32, []);
+    registry.add_dependencies("a-util", 25u32, []);
+
+    // Locked versions wrap the provider, which must still choose from the decided packages.
+    let provider = SameMajor(registry);
+    let locked = Map::default();
+    let solution = Resolver::new(&provider)
+        .with_locked(&locked)
+        .resolve("root", 1u32)
+        .unwrap();
+    assert_eq!(solution["a-util"], 15);
+}
+
+/// An offline registry that allows one version of each package per multiple of 10.
+struct MajorSlots(OfflineDependencyProvider<&'static str, NumVS>);
+
+impl DependencyProvider for MajorSlots {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.0.prioritize(package, range, statistics)
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        self.0.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        self.0.get_dependencies(package, version)
+    }
+}
+
+impl MultiVersionDependencyProvider for MajorSlots {
+    type Slot = u32;
+
+    fn slot(&self, package: &&'static str, range: &NumVS) -> u32 {
+        if *package == "single" {
+            return 0;
+        }
+        match range.bounding_range() {
+            Some((Bound::Included(v), _)) | Some((Bound::Excluded(v), _)) => v / 10,
+            _ => 0,
+        }
+    }
+
+    fn slot_range(&self, package: &&'static str, slot: &u32) -> NumVS {
+        if *package == "single" {
+            return Ranges::full();
+        }
+        Ranges::between(slot * 10, (slot + 1) * 10)
+    }
+}
+
+#[test]
+fn duplicates_in_distinct_slots() {
+    let mut dependency_provider = OfflineDependencyProvider::new();
+    dependency_provider.add_dependencies(
+        "root",
+        0u32,
+        [("a", Ranges::full()), ("b", Ranges::full())],
+    );
+    dependency_provider.add_dependencies("a", 1u32, [("log", Ranges::between(10u32, 20u32))]);
+    dependency_provider.add_dependencies("b", 1u32, [("log", Ranges::between(20u32, 30u32))]);
+    dependency_provider.add_dependencies("log", 15u32, []);
+    dependency_provider.add_dependencies("log", 25u32, []);
+    let registry = MajorSlots(dependency_provider);
+
+    let solution = resolve_with_duplicates(&registry, "root", 0u32).unwrap();
+    assert_eq!(solution[&PackageSlot::new("log", 1)], 15);
+    assert_eq!(solution[&PackageSlot::new("log", 2)], 25);
+    assert_eq!(solution.len(), 5);
+
+    // Without slots, the two requirements on `log` conflict.
+    assert!(matches!(
+        resolve(&registry, "root", 0u32),
+        Err(PubGrubError::NoSolution(_))
+    ));
+}
+
+#[test]
+fn single_slot_keeps_one_version() {
+    let mut dependency_provider = OfflineDependencyProvider::new();
+    dependency_provider.add_dependencies(
+        "root",
+        0u32,
+        [("a", Ranges::full()), ("b", Ranges::full())],
+    );
+    dependency_provider.add_dependencies("a", 1u32, [("single", Ranges::between(10u32, 20u32))]);
+    dependency_provider.add_dependencies("b", 1u32, [("single", Ranges::between(20u32, 30u32))]);
+    dependency_provider.add_dependencies("single", 15u32, []);
+    dependency_provider.add_dependencies("single", 25u32, []);
+    let registry = MajorSlots(dependency_provider);
+
+    assert!(matches!(
+        resolve_with_duplicates(&registry, "root", 0u32),
+        Err(PubGrubError::NoSolution(_))
+    ));
+}
+
+#[test]
+fn locked_versions_are_preferred() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies(
+        "root",
+        0u32,
+        [("a", Ranges::full()), ("b", Ranges::full())],
+    );
+    dependency_provider.add_dependencies("a", 1u32, []);
+    dependency_provider.add_dependencies("a", 2u32, []);
+    dependency_provider.add_dependencies("b", 1u32, []);
+    dependency_provider.add_dependencies("b", 2u32, [("c", Ranges::full())]);
+    dependency_provider.add_dependencies("c", 1u32, []);
+
+    // `b` 3 is not available anymore and `old` is not a dependency anymore.
+    let preferences = Map::from_iter([("a", 1u32), ("b", 3u32), ("old", 1u32)]);
+    let resolution =
+        resolve_with_preferences(&dependency_provider, "root", 0u32, &preferences).unwrap();
+    assert_eq!(resolution.solution["a"], 1);
+    assert_eq!(resolution.solution["b"], 2);
+    assert_eq!(resolution.kept, Set::from_iter(["a"]));
+    assert_eq!(resolution.changed, Map::from_iter([("b", 3)]));
+    assert_eq!(resolution.removed, Map::from_iter([("old", 1)]));
+}
+
+#[test]
+fn version_strategies() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 0u32, [("a", Ranges::full())]);
+    dependency_provider.add_dependencies("a", 1u32, [("b", Ranges::full())]);
+    dependency_provider.add_dependencies("a", 2u32, [("b", Ranges::full())]);
+    dependency_provider.add_dependencies("b", 1u32, []);
+    dependency_provider.add_dependencies("b", 2u32, []);
+    dependency_provider.add_dependencies("b", 3u32, []);
+
+    let solve =
+        |strategy| resolve_with_strategy(&dependency_provider, "root", 0u32, strategy).unwrap();
+    let maximal = solve(VersionStrategy::Maximal);
+    assert_eq!((maximal["a"], maximal["b"]), (2, 3));
+    let minimal = solve(VersionStrategy::Minimal);
+    assert_eq!((minimal["a"], minimal["b"]), (1, 1));
+    let direct_minimal = solve(VersionStrategy::DirectMinimal);
+    assert_eq!((direct_minimal["a"], direct_minimal["b"]), (1, 3));
+    // Prefer versions closest to 2.
+    let custom = solve(VersionStrategy::Custom(Box::new(|_, a: &u32, b: &u32| {
+        b.abs_diff(2).cmp(&a.abs_diff(2))
+    })));
+    assert_eq!((custom["a"], custom["b"]), (2, 2));
+}
+
+#[test]
+fn callback_provider() {
+    let requested = RefCell::new(Vec::new());
+    let provider = CallbackDependencyProvider::<&str, NumVS, String>::new(
+        |package| {
+            requested.borrow_mut().push(*package);
+            match *package {
+                "broken" => Err(CallbackError("registry unavailable".to_string())),
+                _ => Ok(vec![2, 1, 3]),
+            }
+        },
+        |package, version| {
+            Ok(Dependencies::Available(match (*package, *version) {
+                ("root", _) => [("a", Ranges::strictly_lower_than(3u32))]
+                    .into_iter()
+                    .collect(),
+                ("a", 2) => [("broken", Ranges::full())].into_iter().collect(),
+                _ => DependencyConstraints::default(),
+            }))
+        },
+    );
+
+    let err = resolve(&provider, "root", 1u32).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Choosing a version for broken failed, needed by a 2 -> root 1"
+    );
+    let PubGrubError::ErrorChoosingVersion {
+        package,
+        source,
+        required_by,
+        decided,
+    } = err
+    else {
+        panic!("expected the callback error");
+    };
+    assert_eq!(
+        (package, source.0.as_str()),
+        ("broken", "registry unavailable")
+    );
+    assert_eq!(required_by, [("a", 2), ("root", 1)]);
+    assert_eq!(decided, [("root", 1), ("a", 2)]);
+    // Versions are cached, except when the callback failed.
+    assert_eq!(requested.borrow().iter().filter(|p| **p == "a").count(), 1);
+}
+
+/// An offline registry where odd versions are yanked.
+struct OddYanked(OfflineDependencyProvider<&'static str, NumVS>);
+
+impl DependencyProvider for OddYanked {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.0.prioritize(package, range, statistics)
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        self.0.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        self.0.get_dependencies(package, version)
+    }
+}
+
+impl YankedVersions for OddYanked {
+    fn is_yanked(&self, _package: &&'static str, version: &u32) -> Result<bool, Infallible> {
+        Ok(version % 2 == 1)
+    }
+}
+
+#[test]
+fn yanked_versions_are_a_last_resort() {
+    let mut dependency_provider = OfflineDependencyProvider::new();
+    dependency_provider.add_dependencies("root", 0u32, [("a", Ranges::full())]);
+    dependency_provider.add_dependencies("root", 2u32, [("b", Ranges::higher_than(5u32))]);
+    for v in 1..=5u32 {
+        dependency_provider.add_dependencies("a", v, []);
+    }
+    dependency_provider.add_dependencies("b", 4u32, []);
+    dependency_provider.add_dependencies("b", 5u32, []);
+    let registry = OddYanked(dependency_provider);
+    let resolve = |root: u32, locked: &Map<&'static str, u32>| {
+        resolve_with_yanked(&registry, "root", root, locked).unwrap()
+    };
+
+    let avoided = resolve(0, &Map::default());
+    assert_eq!(avoided.solution["a"], 4);
+    assert!(avoided.yanked.is_empty());
+
+    // The only version of b allowed is yanked.
+    let last_resort = resolve(2, &Map::default());
+    assert_eq!(last_resort.solution["b"], 5);
+    assert_eq!(last_resort.yanked, Map::from_iter([("b", 5)]));
+
+    // A locked yanked version is a version like the others.
+    let locked = resolve(0, &Map::from_iter([("a", 5)]));
+    assert_eq!(locked.solution["a"], 5);
+    assert_eq!(locked.yanked, Map::from_iter([("a", 5)]));
+}
+
+#[test]
+fn record_and_replay() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("a", Ranges::full())]);
+    dependency_provider.add_dependencies("a", 1u32, []);
+    dependency_provider.add_dependencies("a", 2u32, [("b", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("b", 2u32, []);
+    dependency_provider.add_dependencies("unrelated", 1u32, []);
+
+    let recording = RecordingProvider::new(&dependency_provider);
+    let solution = resolve(&recording, "root", 1u32).unwrap();
+    let snapshot = recording.into_snapshot();
+    assert_eq!(resolve(&snapshot, "root", 1u32).unwrap(), solution);
+    // Only what the solver looked at is recorded.
+    let mut packages: Vec<_> = snapshot.packages().copied().collect();
+    packages.sort();
+    assert_eq!(packages, ["a", "root"]);
+    assert!(snapshot.versions(&"a").unwrap().eq([1, 2].iter()));
+}
+
+#[cfg(feature = "capi")]
+#[test]
+fn c_interface() {
+    use pubgrub::capi::*;
+    use std::ffi::{c_char, c_int, c_void, CStr, CString};
+
+    /// Packages with their versions and the dependencies of each version.
+    type Registry = Vec<(&'static str, u64, Vec<(&'static str, u64, u64)>)>;
+
+    fn registry<'a>(context: *mut c_void) -> &'a Registry {
+        unsafe { &*(context as *const Registry) }
+    }
+    fn name<'a>(package: *const c_char) -> &'a str {
+        unsafe { CStr::from_ptr(package) }.to_str().unwrap()
+    }
+    extern "C" fn versions(
+        context: *mut c_void,
+        package: *const c_char,
+        versions: *mut PubgrubVersions,
+    ) -> c_int {
+        for (_, version, _) in registry(context).iter().filter(|p| p.0 == name(package)) {
+            unsafe { pubgrub_versions_push(versions, *version) };
+        }
+        PUBGRUB_OK
+    }
+    extern "C" fn dependencies(
+        context: *mut c_void,
+        package: *const c_char,
+        version: u64,
+        dependencies: *mut PubgrubDependencies,
+    ) -> c_int {
+        let Some((_, _, deps)) = registry(context)
+            .iter()
+            .find(|p| p.0 == name(package) && p.1 == version)
+        else {
+            return PUBGRUB_UNAVAILABLE;
+        };
+        for (dependency, lower, upper) in deps {
+            let dependency = CString::new(*dependency).unwrap();
+            unsafe { pubgrub_dependencies_add(dependencies, dependency.as_ptr(), *lower, *upper) };
+        }
+        PUBGRUB_OK
+    }
+    let resolve = |registry: &Registry| unsafe {
+        let mut resolution = std::ptr::null_mut();
+        let code = pubgrub_resolve(
+            registry as *const Registry as *mut c_void,
+            versions,
+            dependencies,
+            c"root".as_ptr(),
+            1,
+            &mut resolution,
+        );
+        let solution: Vec<(String, u64)> = (0..pubgrub_resolution_len(resolution))
+            .map(|i| {
+                let package = name(pubgrub_resolution_package(resolution, i)).to_string();
+                (package, pubgrub_resolution_version(resolution, i))
+            })
+            .collect();
+        let message = pubgrub_resolution_message(resolution);
+        let message = (!message.is_null()).then(|| name(message).to_string());
+        pubgrub_resolution_free(resolution);
+        (code, solution, message)
+    };
+
+    let mut registry: Registry = vec![
+        ("root", 1, vec![("a", 1, u64::MAX)]),
+        ("a", 1, vec![]),
+        ("a", 2, vec![("b", 0, 2)]),
+        ("b", 1, vec![]),
+    ];
+    assert_eq!(
+        resolve(&registry),
+        (
+            PUBGRUB_OK,
+            vec![
+                ("a".to_string(), 2),
+                ("b".to_string(), 1),
+                ("root".to_string(), 1)
+            ],
+            None
+        )
+    );
+
+    registry[0].2.push(("b", 2, 3));
+    assert_eq!(
+        resolve(&registry),
+        (
+            PUBGRUB_NO_SOLUTION,
+            vec![],
+            Some("root ==1 depends on b >=2, <3".to_string())
+        )
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn offline_provider_json_round_trip() {
+    use pubgrub::SemanticVersion;
+
+    type SemVS = Ranges<SemanticVersion>;
+    let mut dependency_provider = OfflineDependencyProvider::<String, SemVS>::new();
+    dependency_provider.add_dependencies(
+        "root".to_string(),
+        (1, 0, 0),
+        [
+            ("a".to_string(), Ranges::higher_than((1, 2, 0))),
+            ("b".to_string(), Ranges::strictly_higher_than((0, 1, 0))),
+        ],
+    );
+    dependency_provider.add_dependencies("a".to_string(), (1, 2, 3), []);
+    dependency_provider.add_dependencies("b".to_string(), (0, 2, 0), []);
+
+    let json = dependency_provider.to_json_string().unwrap();
+    let parsed = OfflineDependencyProvider::<String, SemVS>::from_json_str(&json).unwrap();
+    assert_eq!(
+        resolve(&parsed, "root".to_string(), (1, 0, 0)).unwrap(),
+        resolve(&dependency_provider, "root".to_string(), (1, 0, 0)).unwrap()
+    );
+
+    let invalid = r#"{ "root": { "1.0.0": { "a": [["1.0.0", "2"]] } } }"#;
+    assert!(OfflineDependencyProvider::<String, SemVS>::from_json_str(invalid).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn derivation_tree_round_trip() {
+    use pubgrub::{DefaultStringReporter, DerivationTree, Reporter};
+
+    let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    dependency_provider.add_dependencies(
+        "root",
+        1u32,
+        [("a", Ranges::full()), ("b", Ranges::full())],
+    );
+    dependency_provider.add_dependencies("a", 1u32, [("c", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("b", 1u32, [("c", Ranges::singleton(2u32))]);
+    dependency_provider.add_dependencies("c", 1u32, []);
+    dependency_provider.add_dependencies("c", 2u32, []);
+
+    let Err(PubGrubError::NoSolution(tree)) = resolve(&dependency_provider, "root", 1u32) else {
+        panic!("expected no solution");
+    };
+    let serialized = serde_json::to_string(&tree).unwrap();
+    let deserialized: DerivationTree<String, NumVS, String> =
+        serde_json::from_str(&serialized).unwrap();
+    assert_eq!(
+        DefaultStringReporter::report(&tree),
+        DefaultStringReporter::report(&deserialized)
+    );
+}
+
+#[test]
+fn propagation_without_decisions() {
+    let mut dependency_provider = OfflineDependencyProvider::<_, NumVS>::new();
+    dependency_provider.add_dependencies(
+        "root",
+        1u32,
+        [("foo", Ranges::full()), ("bar", Ranges::singleton(1u32))],
+    );
+    dependency_provider.add_dependencies("foo", 1u32, []);
+    dependency_provider.add_dependencies("foo", 2u32, []);
+    dependency_provider.add_dependencies("bar", 1u32, [("baz", Ranges::full())]);
+    dependency_provider.add_dependencies("bar", 2u32, []);
+    // The only version of baz, so chosen without a decision.
+    dependency_provider.add_dependencies("baz", 3u32, [("foo", Ranges::higher_than(2u32))]);
+
+    let propagation = propagate(&dependency_provider, "root", 1u32).unwrap();
+    let forced: Map<_, _> = [("root", 1u32), ("bar", 1), ("baz", 3), ("foo", 2)]
+        .into_iter()
+        .collect();
+    assert_eq!(propagation.forced, forced);
+    assert!(propagation.required.is_empty());
+
+    // Two versions of foo remain.
+    dependency_provider.add_dependencies("baz", 3u32, []);
+    let propagation = propagate(&dependency_provider, "root", 1u32).unwrap();
+    assert_eq!(propagation.forced.len(), 3);
+    let required: Map<_, _> = [("foo", Ranges::full())].into_iter().collect();
+    assert_eq!(propagation.required, required);
+
+    // baz requires a version of foo that doesn't exist.
+    dependency_provider.add_dependencies("baz", 3u32, [("foo", Ranges::higher_than(3u32))]);
+    let Err(PubGrubError::NoSolution(derivation_tree)) =
+        propagate(&dependency_provider, "root", 1u32)
+    else {
+        panic!("expected no solution");
+    };
+    let report = DefaultStringReporter::report(&derivation_tree);
+    assert!(
+        report.contains("there is no version of foo in >=3"),
+        "{report}"
+    );
+}
+
+#[test]
+fn enumerated_versions() {
+    type IndexVS = EnumeratedSet<u32>;
+    let mut dependency_provider = OfflineDependencyProvider::<&str, IndexVS>::new();
+    dependency_provider.add_dependencies("root", 0u32, [("foo", IndexVS::from_versions([1, 2]))]);
+    dependency_provider.add_dependencies("foo", 2u32, [("bar", IndexVS::singleton(0))]);
+    dependency_provider.add_dependencies("foo", 1u32, [("bar", IndexVS::full())]);
+    dependency_provider.add_dependencies("foo", 3u32, []);
+    dependency_provider.add_dependencies("bar", 1u32, []);
+
+    let solution = resolve(&dependency_provider, "root", 0u32).unwrap();
+    assert_eq!(solution["foo"], 1);
+    assert_eq!(solution["bar"], 1);
+}
+
+struct Distribution {
+    packages: OfflineDependencyProvider<&'static str, NumVS>,
+    requires: Vec<(&'static str, &'static str, NumVS)>,
+    provides: Vec<(&'static str, &'static str, u32, Option<u32>)>,
+    recommends: Vec<(&'static str, &'static str, NumVS)>,
+}
+
+impl DependencyProvider for Distribution {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.packages.prioritize(package, range, statistics)
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        self.packages.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        self.packages.get_dependencies(package, version)
+    }
+}
+
+impl ProvidesDependencyProvider for Distribution {
+    type C = &'static str;
+
+    fn get_capability_dependencies(
+        &self,
+        package: &&'static str,
+        _version: &u32,
+    ) -> Result<Vec<(&'static str, NumVS)>, Infallible> {
+        Ok(self
+            .requires
+            .iter()
+            .filter(|(p, _, _)| p == package)
+            .map(|(_, c, range)| (*c, range.clone()))
+            .collect())
+    }
+
+    fn get_providers(
+        &self,
+        capability: &&'static str,
+    ) -> Result<Vec<(&'static str, u32, Option<u32>)>, Infallible> {
+        Ok(self
+            .provides
+            .iter()
+            .filter(|(c, _, _, _)| c == capability)
+            .map(|(_, p, v, provided)| (*p, *v, *provided))
+            .collect())
+    }
+}
+
+impl RecommendsDependencyProvider for Distribution {
+    fn get_recommendations(
+        &self,
+        package: &&'static str,
+        _version: &u32,
+    ) -> Result<Vec<(&'static str, NumVS)>, Infallible> {
+        Ok(self
+            .recommends
+            .iter()
+            .filter(|(p, _, _)| p == package)
+            .map(|(_, dependency, range)| (*dependency, range.clone()))
+            .collect())
+    }
+}
+
+#[test]
+fn capability_providers() {
+    let mut packages = OfflineDependencyProvider::new();
+    packages.add_dependencies("root", 1u32, [("libc", Ranges::singleton(1u32))]);
+    packages.add_dependencies("postfix", 3u32, [("libc", Ranges::singleton(2u32))]);
+    packages.add_dependencies("exim", 4u32, [("libc", Ranges::full())]);
+    packages.add_dependencies("libc", 1u32, []);
+    packages.add_dependencies("libc", 2u32, []);
+    let mut distribution = Distribution {
+        packages,
+        requires: vec![("root", "mta", Ranges::full())],
+        provides: vec![
+            ("mta", "postfix", 3, None),
+            ("mta", "exim", 4, None),
+            ("libfoo", "foo", 1, Some(1)),
+        ],
+        recommends: vec![],
+    };
+
+    // postfix is preferred, but needs another libc.
+    let solution = resolve_with_provides(&distribution, "root", 1u32).unwrap();
+    assert_eq!(solution.providers["mta"], ("exim", 4));
+    assert_eq!(solution.solution["libc"], 1);
+
+    // Unversioned provides don't satisfy versioned requirements.
+    distribution.requires = vec![("root", "mta", Ranges::higher_than(1u32))];
+    let Err(PubGrubError::NoSolution(derivation_tree)) =
+        resolve_with_provides(&distribution, "root", 1u32)
+    else {
+        panic!("expected no solution");
+    };
+    let report = DefaultStringReporter::report(&derivation_tree);
+    assert!(report.contains("root ==1 depends on mta ∅"), "{report}");
+
+    distribution.requires = vec![
+        ("root", "mta", Ranges::full()),
+        ("exim", "libfoo", Ranges::full()),
+    ];
+    distribution
+        .packages
+        .add_dependencies("exim", 4u32, [("libc", Ranges::singleton(2u32))]);
+    let Err(PubGrubError::NoSolution(derivation_tree)) =
+        resolve_with_provides(&distribution, "root", 1u32)
+    else {
+        panic!("expected no solution");
+    };
+    let report = DefaultStringReporter::report(&derivation_tree);
+    assert!(
+        report.contains("root ==1 depends on mta (postfix 3 | exim 4)"),
+        "{report}"
+    );
+}
+
+#[test]
+fn declared_conflicts() {
+    let mut dependency_provider = OfflineDependencyProvider::<&str, Ranges<u32>>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("app", Ranges::full())]);
+    dependency_provider.add_dependencies("app", 2u32, [("legacy", Ranges::full())]);
+    dependency_provider.add_dependencies("app", 1u32, []);
+    dependency_provider.add_dependencies("legacy", 1u32, []);
+
+    // Every version of legacy conflicts with app 2, so app 1 is selected instead.
+    let conflicts = [DeclaredConflict::new(
+        "legacy",
+        Ranges::full(),
+        "app",
+        Ranges::singleton(2u32),
+        "legacy patches app 1 only".to_string(),
+    )];
+    let solution = resolve_with_conflicts(&dependency_provider, "root", 1u32, &conflicts).unwrap();
+    assert_eq!(solution.get("app"), Some(&1));
+    assert_eq!(solution.get("legacy"), None);
+
+    // Without app 1, the conflict is reported with its reason.
+    let mut dependency_provider = dependency_provider;
+    dependency_provider.add_dependencies("root", 1u32, [("app", Ranges::singleton(2u32))]);
+    let Err(PubGrubError::NoSolution(tree)) =
+        resolve_with_conflicts(&dependency_provider, "root", 1u32, &conflicts)
+    else {
+        panic!("app 2 is required");
+    };
+    let report = DefaultStringReporter::report(&tree);
+    assert!(
+        report.contains("legacy conflicts with app ==2 (legacy patches app 1 only)"),
+        "{report}"
+    );
+}
+
+#[test]
+fn recommendations_dropped_on_conflict() {
+    let mut packages = OfflineDependencyProvider::new();
+    packages.add_dependencies("root", 1u32, [("editor", Ranges::full())]);
+    packages.add_dependencies("editor", 1u32, [("runtime", Ranges::singleton(2u32))]);
+    packages.add_dependencies("spell", 1u32, [("runtime", Ranges::singleton(1u32))]);
+    packages.add_dependencies("theme", 1u32, []);
+    packages.add_dependencies("runtime", 1u32, []);
+    packages.add_dependencies("runtime", 2u32, []);
+    let distribution = Distribution {
+        packages,
+        requires: vec![],
+        provides: vec![],
+        recommends: vec![
+            ("editor", "spell", Ranges::full()),
+            ("editor", "theme", Ranges::full()),
+        ],
+    };
+
+    // spell needs another runtime than editor, so only theme is installed.
+    let recommended = resolve_with_recommendations(&distribution, "root", 1u32).unwrap();
+    assert_eq!(recommended.solution["runtime"], 2);
+    assert_eq!(recommended.solution.get("spell"), None);
+    assert_eq!(recommended.solution["theme"], 1);
+    assert_eq!(recommended.honored.len(), 1);
+    assert_eq!(recommended.honored[0].dependency, "theme");
+    assert_eq!(recommended.dropped.len(), 1);
+    assert_eq!(recommended.dropped[0].dependency, "spell");
+}
+
+#[test]
+fn recommendations_in_provider_order() {
+    let mut packages = OfflineDependencyProvider::new();
+    packages.add_dependencies("root", 1u32, [("editor", Ranges::full())]);
+    packages.add_dependencies("editor", 1u32, []);
+    packages.add_dependencies("zeta", 1u32, [("runtime", Ranges::singleton(1u32))]);
+    packages.add_dependencies("alpha", 1u32, [("runtime", Ranges::singleton(2u32))]);
+    packages.add_dependencies("runtime", 1u32, []);
+    packages.add_dependencies("runtime", 2u32, []);
+    let distribution = Distribution {
+        packages,
+        requires: vec![],
+        provides: vec![],
+        recommends: vec![
+            ("editor", "zeta", Ranges::full()),
+            ("editor", "alpha", Ranges::full()),
+        ],
+    };
+
+    // zeta and alpha need different runtimes, the first recommendation wins.
+    let recommended = resolve_with_recommendations(&distribution, "root", 1u32).unwrap();
+    assert_eq!(recommended.solution["runtime"], 1);
+    assert_eq!(recommended.honored[0].dependency, "zeta");
+    assert_eq!(recommended.dropped[0].dependency, "alpha");
+}
+
+/// Record the conflicts and backtracks of the solver.
+struct Watching {
+    packages: OfflineDependencyProvider<&'static str, NumVS>,
+    events: RefCell<Vec<String>>,
+}
+
+impl DependencyProvider for Watching {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.packages.prioritize(package, range, statistics)
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        self.packages.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        self.packages.get_dependencies(package, version)
+    }
+
+    fn on_conflict(&self, terms: &[(&'static str, Term<NumVS>)]) {
+        let terms: Vec<String> = terms.iter().map(|(p, t)| format!("{p} {t}")).collect();
+        self.events.borrow_mut().push(terms.join(", "));
+    }
+
+    fn on_backtrack(&self, to_level: u32) {
+        self.events
+            .borrow_mut()
+            .push(format!("backtrack to {to_level}"));
+    }
+}
+
+#[test]
+fn conflict_and_backtrack_callbacks() {
+    let mut packages = OfflineDependencyProvider::new();
+    packages.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    packages.add_dependencies("foo", 2u32, [("bar", Ranges::singleton(1u32))]);
+    packages.add_dependencies("foo", 1u32, []);
+    packages.add_dependencies("bar", 1u32, [("foo", Ranges::singleton(1u32))]);
+    let watching = Watching {
+        packages,
+        events: RefCell::default(),
+    };
+
+    let solution = resolve(&watching, "root", 1u32).unwrap();
+    assert_eq!(solution.get("foo"), Some(&1));
+    // foo 2 and bar 1 conflict, foo 2 is ruled out and only the root decision is kept.
+    assert_eq!(watching.events.take(), ["foo ==2", "backtrack to 1"]);
+
+    let solution = block_on(resolve_async(&watching, "root", 1u32)).unwrap();
+    assert_eq!(solution.get("foo"), Some(&1));
+    assert_eq!(watching.events.take(), ["foo ==2", "backtrack to 1"]);
+}
+
+impl AsyncDependencyProvider for Watching {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        DependencyProvider::prioritize(self, package, range, statistics)
+    }
+
+    async fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        DependencyProvider::choose_version(self, package, range)
+    }
+
+    async fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        DependencyProvider::get_dependencies(self, package, version)
+    }
+
+    fn on_conflict(&self, terms: &[(&'static str, Term<NumVS>)]) {
+        DependencyProvider::on_conflict(self, terms)
+    }
+
+    fn on_backtrack(&self, to_level: u32) {
+        DependencyProvider::on_backtrack(self, to_level)
+    }
+}
+
+/// Break ties between packages with their position in `order`.
+struct Ordered {
+    packages: OfflineDependencyProvider<&'static str, NumVS>,
+    order: Vec<&'static str>,
+}
+
+impl DependencyProvider for Ordered {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.packages.prioritize(package, range, statistics)
+    }
+
+    fn tie_break_key(&self, package: &&'static str) -> u64 {
+        self.order.iter().position(|p| p == package).unwrap() as u64
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        self.packages.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        self.packages.get_dependencies(package, version)
+    }
+}
+
+#[test]
+fn tie_break() {
+    let mut packages = OfflineDependencyProvider::new();
+    packages.add_dependencies("root", 1u32, [("a", Ranges::full()), ("b", Ranges::full())]);
+    // a 2 and b 2 exclude each other: the package decided first gets its newest version.
+    packages.add_dependencies("a", 2u32, [("b", Ranges::singleton(1u32))]);
+    packages.add_dependencies("a", 1u32, []);
+    packages.add_dependencies("b", 2u32, []);
+    packages.add_dependencies("b", 1u32, []);
+    let mut provider = Ordered {
+        packages,
+        order: vec!["root", "a", "b"],
+    };
+    let resolve = |provider: &Ordered, tie_break| {
+        let options = ResolveOptions {
+            tie_break,
+            ..ResolveOptions::default()
+        };
+        let solution = resolve_with_options(provider, "root", 1u32, options).unwrap();
+        (solution["a"], solution["b"])
+    };
+
+    // Reversing the insertion order decides the other package first.
+    let first = resolve(&provider, TieBreak::InsertionOrder);
+    let reversed = resolve(&provider, TieBreak::ReverseInsertionOrder);
+    assert_ne!(first, reversed);
+    assert!([(2, 1), (1, 2)].contains(&first));
+    assert!([(2, 1), (1, 2)].contains(&reversed));
+
+    assert_eq!(resolve(&provider, TieBreak::ProviderKey), (2, 1));
+    provider.order = vec!["root", "b", "a"];
+    assert_eq!(resolve(&provider, TieBreak::ProviderKey), (1, 2));
+    // The key is only used with TieBreak::ProviderKey.
+    assert_eq!(resolve(&provider, TieBreak::InsertionOrder), first);
+}
+
+#[test]
+fn stepping_trace() {
+    use pubgrub::{State, SteppingResolver, TraceEvent, TraceOrigin};
+
+    let mut dependency_provider = OfflineDependencyProvider::<&str, NumVS>::new();
+    dependency_provider.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    dependency_provider.add_dependencies("foo", 2u32, [("bar", Ranges::singleton(1u32))]);
+    dependency_provider.add_dependencies("foo", 1u32, []);
+    dependency_provider.add_dependencies("bar", 1u32, [("foo", Ranges::singleton(1u32))]);
+
+    let mut resolver = SteppingResolver::new(State::init("root", 1u32));
+    resolver.record_trace();
+    let mut steps = Vec::new();
+    let solution = loop {
+        let step = resolver.step(&dependency_provider).unwrap();
+        steps.push(resolver.take_trace());
+        if let pubgrub::Step::Solved(solution) = step {
+            break solution;
+        }
+    };
+    assert_eq!(solution["foo"], 1);
+
+    // Each incompatibility is introduced once, before it is used.
+    let trace: Vec<_> = steps.into_iter().flatten().collect();
+    let mut introduced = Set::default();
+    for event in &trace {
+        match event {
+            TraceEvent::Incompatibility { id, origin, .. } => {
+                if let TraceOrigin::Derived(cause1, cause2) = origin {
+                    assert!(introduced.contains(cause1) && introduced.contains(cause2));
+                }
+                assert!(introduced.insert(*id));
+            }
+            TraceEvent::Derivation { cause, .. } => assert!(introduced.contains(cause)),
+            TraceEvent::Conflict { incompatibility } => {
+                assert!(introduced.contains(incompatibility))
+            }
+            TraceEvent::Decision { .. } | TraceEvent::Backtrack { .. } => {}
+        }
+    }
+
+    // foo 2 and bar 1 are decided and conflict, the solver backtracks and decides foo 1.
+    let outline: Vec<String> = trace
+        .iter()
+        .filter_map(|event| match event {
+            TraceEvent::Decision {
+                package, version, ..
+            } => Some(format!("decide {package} {version}")),
+            TraceEvent::Conflict { .. } => Some("conflict".to_string()),
+            TraceEvent::Backtrack { level } => Some(format!("backtrack to {level}")),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        outline,
+        [
+            "decide root 1",
+            "decide foo 2",
+            "decide bar 1",
+            "conflict",
+            "backtrack to 1",
+            "decide foo 1"
+        ]
+    );
+
+    #[cfg(feature = "serde")]
+    {
+        let serialized = serde_json::to_string(&trace).unwrap();
+        let deserialized: Vec<TraceEvent<String, NumVS, String>> =
+            serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.len(), trace.len());
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn resolve_workspace_members_in_parallel() {
+    use pubgrub::resolve_many;
+
+    let mut dependency_provider = OfflineDependencyProvider::<String, NumVS>::new();
+    let mut members = Vec::new();
+    for member in 0..16u32 {
+        let name = format!("member-{member}");
+        let log = if member == 7 {
+            Ranges::higher_than(3u32)
+        } else {
+            Ranges::full()
+        };
+        dependency_provider.add_dependencies(
+            name.clone(),
+            1u32,
+            [
+                ("serde".to_string(), Ranges::full()),
+                ("log".to_string(), log),
+            ],
+        );
+        members.push((name, 1u32));
+    }
+    dependency_provider.add_dependencies(
+        "serde".to_string(),
+        1u32,
+        [("log".to_string(), Ranges::full())],
+    );
+    dependency_provider.add_dependencies("log".to_string(), 1u32, []);
+    dependency_provider.add_dependencies("log".to_string(), 2u32, []);
+
+    let results = resolve_many(&dependency_provider, &members);
+    assert_eq!(results.len(), members.len());
+    for ((member, version), result) in members.iter().zip(&results) {
+        match (
+            result,
+            resolve(&dependency_provider, member.clone(), *version),
+        ) {
+            (Ok(parallel), Ok(alone)) => assert_eq!(*parallel, alone),
+            (Err(PubGrubError::NoSolution(_)), Err(PubGrubError::NoSolution(_))) => {
+                assert_eq!(member, "member-7")
+            }
+            _ => panic!("{member} resolves differently in parallel"),
+        }
+    }
+}
+
+#[test]
+fn state_moves_across_threads() {
+    use pubgrub::{State, SteppingResolver};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<State<OfflineDependencyProvider<String, NumVS>>>();
+    assert_send_sync::<SteppingResolver<OfflineDependencyProvider<String, NumVS>>>();
+
+    let mut dependency_provider = OfflineDependencyProvider::<String, NumVS>::new();
+    dependency_provider.add_dependencies(
+        "root".to_string(),
+        1u32,
+        [("foo".to_string(), Ranges::full())],
+    );
+    dependency_provider.add_dependencies("foo".to_string(), 1u32, []);
+
+    // Start the resolution on this thread and finish it on another one.
+    let mut resolver = SteppingResolver::new(State::init("root".to_string(), 1u32));
+    resolver.step(&dependency_provider).unwrap();
+    let solution = std::thread::scope(|scope| {
+        scope
+            .spawn(move || resolver.finish(&dependency_provider))
+            .join()
+            .unwrap()
+    })
+    .unwrap();
+    assert_eq!(solution["foo"], 1);
+}
+
+/// Times out on the first calls for `foo`, and fails for good on `gone`.
+struct Flaky {
+    registry: OfflineDependencyProvider<&'static str, NumVS>,
+    timeouts: RefCell<u32>,
+}
+
+impl DependencyProvider for Flaky {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = CallbackError;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        stats: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.registry.prioritize(package, range, stats)
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, CallbackError> {
+        Ok(self.registry.choose_version(package, range).unwrap())
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, CallbackError> {
+        match *package {
+            "foo" if *self.timeouts.borrow() > 0 => {
+                *self.timeouts.borrow_mut() -= 1;
+                Err(CallbackError("timeout".to_string()))
+            }
+            "gone" => Err(CallbackError("not found".to_string())),
+            _ => Ok(self.registry.get_dependencies(package, version).unwrap()),
+        }
+    }
+}
+
+impl TransientErrors for Flaky {
+    fn is_transient(&self, err: &CallbackError) -> bool {
+        err.0 == "timeout"
+    }
+}
+
+#[test]
+fn transient_errors_are_retried() {
+    let mut registry = OfflineDependencyProvider::<&str, NumVS>::new();
+    registry.add_dependencies("root", 1u32, [("foo", Ranges::full())]);
+    registry.add_dependencies("foo", 1u32, []);
+    registry.add_dependencies("app", 1u32, [("gone", Ranges::full())]);
+    registry.add_dependencies("gone", 1u32, []);
+    let provider = Flaky {
+        registry,
+        timeouts: RefCell::new(2),
+    };
+
+    let backoffs = RefCell::new(Vec::new());
+    let backoff = |attempt, _: &CallbackError| backoffs.borrow_mut().push(attempt);
+    let solution = resolve_with_error_retries(&provider, "root", 1u32, 5, backoff).unwrap();
+    assert_eq!(solution["foo"], 1);
+    assert_eq!(*backoffs.borrow(), [1, 2]);
+
+    // Retries stop when the budget is spent.
+    *provider.timeouts.borrow_mut() = 2;
+    let err = resolve_with_error_retries(&provider, "root", 1u32, 1, |_, _| {}).unwrap_err();
+    assert_eq!(err.provider_error().unwrap().0, "timeout");
+
+    // Fatal errors are not retried.
+    backoffs.borrow_mut().clear();
+    let err = resolve_with_error_retries(&provider, "app", 1u32, 5, backoff).unwrap_err();
+    assert_eq!(err.provider_error().unwrap().0, "not found");
+    assert!(backoffs.borrow().is_empty());
+}
+
+#[test]
+fn no_versions_are_merged() {
+    use pubgrub::{Incompatibility, Kind, State};
+
+    let mut state = State::<OfflineDependencyProvider<&str, NumVS>>::init("root", 1u32);
+    let foo = state.package_store.alloc("foo");
+    for version in [1u32, 2, 4] {
+        state.add_incompatibility(Incompatibility::no_versions(
+            foo,
+            Term::Positive(Ranges::singleton(version)),
+        ));
+    }
+    let ids = &state.incompatibilities[&foo];
+    assert_eq!(ids.len(), 1);
+    let Kind::NoVersions(package, versions) = &state.incompatibility_store[ids[0]].kind else {
+        panic!("expected a single no versions incompatibility");
+    };
+    assert_eq!(*package, foo);
+    let expected = Ranges::singleton(1u32)
+        .union(&Ranges::singleton(2u32))
+        .union(&Ranges::singleton(4u32));
+    assert_eq!(*versions, expected);
+}
+
+#[test]
+fn dependencies_of_versions_are_merged() {
+    use pubgrub::{Incompatibility, Kind, State};
+
+    let mut state = State::<OfflineDependencyProvider<&str, NumVS>>::init("root", 1u32);
+    let foo = state.package_store.alloc("foo");
+    let bar = state.package_store.alloc("bar");
+    for version in [11u32, 12] {
+        state.add_incompatibility(Incompatibility::from_dependency(
+            foo,
+            Ranges::singleton(version),
+            (bar, Ranges::between(10u32, 20u32)),
+        ));
+    }
+    // A dependency on another range of bar stays apart.
+    state.add_incompatibility(Incompatibility::from_dependency(
+        foo,
+        Ranges::singleton(13u32),
+        (bar, Ranges::between(10u32, 30u32)),
+    ));
+    let ids = &state.incompatibilities[&bar];
+    assert_eq!(ids.len(), 2);
+    let Kind::FromDependencyOf(_, versions, _, _) = &state.incompatibility_store[ids[0]].kind
+    else {
+        panic!("expected a dependency incompatibility");
+    };
+    let expected = Ranges::singleton(11u32).union(&Ranges::singleton(12u32));
+    assert_eq!(*versions, expected);
+}
+
+/// All the versions of `foo` have the same dependencies, and the provider says so if `shared`.
+struct SharedDependencies {
+    registry: OfflineDependencyProvider<&'static str, NumVS>,
+    shared: bool,
+    retrieved: RefCell<Vec<(&'static str, u32)>>,
+}
+
+impl DependencyProvider for SharedDependencies {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        stats: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        self.registry.prioritize(package, range, stats)
+    }
+
+    fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        self.registry.choose_version(package, range)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        self.retrieved.borrow_mut().push((*package, *version));
+        self.registry.get_dependencies(package, version)
+    }
+
+    fn versions_sharing_dependencies(&self, package: &&'static str, version: &u32) -> NumVS {
+        match *package {
+            "foo" if self.shared => Ranges::between(1u32, 51u32),
+            _ => Ranges::singleton(*version),
+        }
+    }
+}
+
+#[test]
+fn dependencies_shared_by_versions() {
+    let mut registry = OfflineDependencyProvider::<&str, NumVS>::new();
+    registry.add_dependencies(
+        "root",
+        1u32,
+        [("foo", Ranges::full()), ("bar", Ranges::full())],
+    );
+    for version in 1..=50u32 {
+        registry.add_dependencies("foo", version, [("bar", Ranges::higher_than(2u32))]);
+    }
+    registry.add_dependencies("bar", 1u32, []);
+    let mut provider = SharedDependencies {
+        registry,
+        shared: false,
+        retrieved: RefCell::new(Vec::new()),
+    };
+    let tried_foo = |provider: &SharedDependencies| {
+        assert!(matches!(
+            resolve(provider, "root", 1u32),
+            Err(PubGrubError::NoSolution(_))
+        ));
+        let retrieved = provider.retrieved.take();
+        retrieved.iter().filter(|(p, _)| *p == "foo").count()
+    };
+
+    // Each version of foo is tried, unless the provider tells they all need a newer bar.
+    assert_eq!(tried_foo(&provider), 50);
+    provider.shared = true;
+    assert_eq!(tried_foo(&provider), 1);
+
+    // The asynchronous solver shares them too.
+    let result = block_on(resolve_async(&provider, "root", 1u32));
+    assert!(matches!(result, Err(PubGrubError::NoSolution(_))));
+    let retrieved = provider.retrieved.take();
+    assert_eq!(retrieved.iter().filter(|(p, _)| *p == "foo").count(), 1);
+}
+
+impl AsyncDependencyProvider for SharedDependencies {
+    type P = &'static str;
+    type V = u32;
+    type VS = NumVS;
+    type M = String;
+    type Priority =
+        <OfflineDependencyProvider<&'static str, NumVS> as DependencyProvider>::Priority;
+    type Err = Infallible;
+
+    fn prioritize(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+        stats: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        DependencyProvider::prioritize(self, package, range, stats)
+    }
+
+    async fn choose_version(
+        &self,
+        package: &&'static str,
+        range: &NumVS,
+    ) -> Result<Option<u32>, Infallible> {
+        DependencyProvider::choose_version(self, package, range)
+    }
+
+    async fn get_dependencies(
+        &self,
+        package: &&'static str,
+        version: &u32,
+    ) -> Result<Dependencies<&'static str, NumVS, String>, Infallible> {
+        DependencyProvider::get_dependencies(self, package, version)
+    }
+
+    fn versions_sharing_dependencies(&self, package: &&'static str, version: &u32) -> NumVS {
+        DependencyProvider::versions_sharing_dependencies(self, package, version)
+    }
+}
+
+/// Run a future whose provider answers right away.
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    loop {
+        if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
@@ -32,14 +32,18 @@
 //! ## Optional features
 //!
 //! * `serde`: serialization and deserialization for the version range, given that the version type
-//!   also supports it.
+//!   also supports it. The `text` module serializes them in their textual syntax instead.
 //! * `proptest`: Exports are proptest strategy for [`Ranges<u32>`].
+//! * `std` (default): without it, the crate is `no_std`.
 
-use std::borrow::Borrow;
-use std::cmp::Ordering;
-use std::fmt::{Debug, Display, Formatter};
-use std::ops::Bound::{self, Excluded, Included, Unbounded};
-use std::ops::RangeBounds;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt::{Debug, Display, Formatter};
+use core::ops::Bound::{self, Excluded, Included, Unbounded};
+use core::ops::RangeBounds;
+use core::str::FromStr;
 
 #[cfg(any(feature = "proptest", test))]
 use proptest::prelude::*;
@@ -573,7 +577,7 @@ fn group_adjacent_locations(
 ) -> impl Iterator<Item = (Option<usize>, Option<usize>)> {
     // If the first version matched, then the lower bound of that segment is not needed
     let mut seg = locations.next().flatten().map(|ver| (None, Some(ver)));
-    std::iter::from_fn(move || {
+    core::iter::from_fn(move || {
         for ver in locations.by_ref() {
             if let Some(ver) = ver {
                 // As long as were still matching versions, we keep merging into the currently matching segment
@@ -688,8 +692,8 @@ impl<V: Ord + Clone> Ranges<V> {
                 continue;
             }
             let start = match (left_start, right_start) {
-                (Included(l), Included(r)) => Included(std::cmp::max(l, r)),
-                (Excluded(l), Excluded(r)) => Excluded(std::cmp::max(l, r)),
+                (Included(l), Included(r)) => Included(core::cmp::max(l, r)),
+                (Excluded(l), Excluded(r)) => Excluded(core::cmp::max(l, r)),
 
                 (Included(i), Excluded(e)) | (Excluded(e), Included(i)) => {
                     if i <= e {
@@ -783,7 +787,20 @@ impl<V: Ord + Clone> Ranges<V> {
     /// If the range includes a single version, it will be returned unmodified.
     /// If all the versions are contained in the original than the range will be simplified to `full`.
     ///
+    /// The simplified range never has more segments than the original: segments are merged when
+    /// no version in `versions` falls between them, and bounds beyond the first or last version
+    /// in `versions` are dropped.
+    ///
     /// If the given versions are not sorted the correctness of this function is not guaranteed.
+    ///
+    /// ```
+    /// # use version_ranges::Ranges;
+    /// // `<4 | >=5, <20`, with 4 not available.
+    /// let range: Ranges<u32> =
+    ///     Ranges::strictly_lower_than(4u32).union(&Ranges::between(5u32, 20u32));
+    /// let versions = [1u32, 3, 5, 19, 20, 25];
+    /// assert_eq!(range.simplify(versions.iter()), Ranges::strictly_lower_than(20u32));
+    /// ```
     pub fn simplify<'s, I, BV>(&self, versions: I) -> Self
     where
         I: Iterator<Item = BV> + 's,
@@ -849,9 +866,72 @@ impl<V: Ord + Clone> Ranges<V> {
     }
 
     /// Iterate over the parts of the range.
-    pub fn iter(&self) -> impl Iterator<Item = (&Bound<V>, &Bound<V>)> {
+    ///
+    /// The segments are sorted and disjoint, the last one contains the highest versions.
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (&Bound<V>, &Bound<V>)> + ExactSizeIterator {
         self.segments.iter().map(|(start, end)| (start, end))
     }
+
+    /// The segment containing `version`, if any.
+    pub fn segment_containing<Q>(&self, version: &Q) -> Option<(&Bound<V>, &Bound<V>)>
+    where
+        V: Borrow<Q>,
+        Q: ?Sized + PartialOrd,
+    {
+        self.segments
+            .binary_search_by(|segment| within_bounds(version, segment).reverse())
+            .ok()
+            .map(|i| (&self.segments[i].0, &self.segments[i].1))
+    }
+
+    /// Whether the ranges don't overlap but touch, a segment of one starting exactly where a
+    /// segment of the other ends, like `<2` and `>=2`.
+    ///
+    /// `<2` and `>=3` are not adjacent, even for integer versions, since ranges don't know about
+    /// the versions in between.
+    pub fn is_adjacent(&self, other: &Self) -> bool {
+        self.is_disjoint(other)
+            && self.union(other).segments.len() < self.segments.len() + other.segments.len()
+    }
+
+    /// The segment containing the most versions of `versions`, in any order. The highest segment
+    /// wins a tie.
+    ///
+    /// Returns [None] if no version is contained in the range.
+    ///
+    /// ```
+    /// # use version_ranges::Ranges;
+    /// # use std::ops::Bound;
+    /// let range: Ranges<u32> = Ranges::between(1u32, 3u32).union(&Ranges::higher_than(10u32));
+    /// let versions = [1u32, 2, 5, 11];
+    /// assert_eq!(
+    ///     range.widest_segment(versions.iter()),
+    ///     Some((&Bound::Included(1), &Bound::Excluded(3)))
+    /// );
+    /// ```
+    pub fn widest_segment<I, BV>(&self, versions: I) -> Option<(&Bound<V>, &Bound<V>)>
+    where
+        I: Iterator<Item = BV>,
+        BV: Borrow<V>,
+    {
+        let mut counts: SmallVec<[usize; 4]> = smallvec![0; self.segments.len()];
+        for version in versions {
+            if let Ok(i) = self
+                .segments
+                .binary_search_by(|segment| within_bounds(version.borrow(), segment).reverse())
+            {
+                counts[i] += 1;
+            }
+        }
+        let (i, _) = counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .max_by_key(|(i, count)| (**count, *i))?;
+        Some((&self.segments[i].0, &self.segments[i].1))
+    }
 }
 
 // Newtype to avoid leaking our internal representation.
@@ -1070,7 +1150,7 @@ impl<V: Ord> FromIterator<(Bound<V>, Bound<V>)> for Ranges<V> {
 // REPORT ######################################################################
 
 impl<V: Display + Eq> Display for Ranges<V> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         if self.segments.is_empty() {
             write!(f, "∅")?;
         } else {
@@ -1101,6 +1181,102 @@ impl<V: Display + Eq> Display for Ranges<V> {
     }
 }
 
+// PARSING #####################################################################
+
+/// Error parsing [`Ranges`] from their [`Display`] syntax, see [`Ranges::from_str`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRangesError<E> {
+    /// A constraint doesn't start with one of `==`, `>=`, `>`, `<=` and `<`, or a segment has
+    /// more than two constraints.
+    InvalidConstraint,
+    /// A version failed to parse.
+    InvalidVersion(E),
+}
+
+impl<E: Display> Display for ParseRangesError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidConstraint => write!(
+                f,
+                "expected a constraint starting with `==`, `>=`, `>`, `<=` or `<`"
+            ),
+            Self::InvalidVersion(err) => write!(f, "invalid version: {err}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error> std::error::Error for ParseRangesError<E> {}
+
+impl<V: Ord + Clone + FromStr> FromStr for Ranges<V> {
+    type Err = ParseRangesError<V::Err>;
+
+    /// Parse the syntax of the [`Display`] implementation, so that ranges round-trip through
+    /// text.
+    ///
+    /// Ranges are segments separated by `|`, each either `*` or one or two constraints separated
+    /// by `,`, such as `>=1.2.0, <2` or `==3.1.4`. The empty range is `∅`. Whitespace around the
+    /// separators is ignored.
+    ///
+    /// ```
+    /// # use version_ranges::Ranges;
+    /// let ranges: Ranges<u32> = ">=1, <2 | ==3".parse().unwrap();
+    /// assert_eq!(ranges, Ranges::between(1u32, 2u32).union(&Ranges::singleton(3u32)));
+    /// assert_eq!(ranges.to_string().parse::<Ranges<u32>>(), Ok(ranges));
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "∅" {
+            return Ok(Self::empty());
+        }
+        let mut ranges = Self::empty();
+        for segment in s.split('|') {
+            let segment = segment.trim();
+            if segment == "*" {
+                ranges = Self::full();
+                continue;
+            }
+            let mut constraints = segment.split(',');
+            let mut parsed = Self::parse_constraint(constraints.next().unwrap_or_default())?;
+            if let Some(constraint) = constraints.next() {
+                parsed = parsed.intersection(&Self::parse_constraint(constraint)?);
+            }
+            if constraints.next().is_some() {
+                return Err(ParseRangesError::InvalidConstraint);
+            }
+            ranges = ranges.union(&parsed);
+        }
+        Ok(ranges)
+    }
+}
+
+impl<V: Ord + Clone + FromStr> Ranges<V> {
+    /// One constraint of [`Ranges::from_str`], such as `>=1.2.0`.
+    fn parse_constraint(constraint: &str) -> Result<Self, ParseRangesError<V::Err>> {
+        let constraint = constraint.trim();
+        // The two-character operators first, `>=` also starts with `>`.
+        let (ranges, version): (fn(V) -> Self, _) =
+            if let Some(version) = constraint.strip_prefix("==") {
+                (Self::singleton, version)
+            } else if let Some(version) = constraint.strip_prefix(">=") {
+                (Self::higher_than, version)
+            } else if let Some(version) = constraint.strip_prefix("<=") {
+                (Self::lower_than, version)
+            } else if let Some(version) = constraint.strip_prefix('>') {
+                (Self::strictly_higher_than, version)
+            } else if let Some(version) = constraint.strip_prefix('<') {
+                (Self::strictly_lower_than, version)
+            } else {
+                return Err(ParseRangesError::InvalidConstraint);
+            };
+        let version = version
+            .trim()
+            .parse()
+            .map_err(ParseRangesError::InvalidVersion)?;
+        Ok(ranges(version))
+    }
+}
+
 // SERIALIZATION ###############################################################
 
 #[cfg(feature = "serde")]
@@ -1133,6 +1309,57 @@ impl<'de, V: serde::Deserialize<'de>> serde::Deserialize<'de> for Ranges<V> {
     }
 }
 
+/// Serialize [`Ranges`] as text, the syntax of [`Display`] and [`Ranges::from_str`], rather than
+/// as a list of bounds.
+///
+/// Use it with `#[serde(with = "version_ranges::text")]` on a field, for configuration files and
+/// test fixtures that people read and write, like `{ "requires": ">=1.2.0, <2 | ==3.1.4" }`.
+#[cfg(feature = "serde")]
+pub mod text {
+    use core::fmt::{Display, Formatter};
+    use core::marker::PhantomData;
+    use core::str::FromStr;
+
+    use crate::Ranges;
+
+    /// Serialize the ranges with their [`Display`] implementation.
+    pub fn serialize<V, S>(ranges: &Ranges<V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        V: Display + Eq,
+        S: serde::Serializer,
+    {
+        serializer.collect_str(ranges)
+    }
+
+    /// Deserialize the ranges with [`Ranges::from_str`].
+    pub fn deserialize<'de, V, D>(deserializer: D) -> Result<Ranges<V>, D::Error>
+    where
+        V: Ord + Clone + FromStr,
+        V::Err: Display,
+        D: serde::Deserializer<'de>,
+    {
+        struct RangesVisitor<V>(PhantomData<V>);
+
+        impl<V> serde::de::Visitor<'_> for RangesVisitor<V>
+        where
+            V: Ord + Clone + FromStr,
+            V::Err: Display,
+        {
+            type Value = Ranges<V>;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+                write!(f, "version ranges such as `>=1.2.0, <2 | ==3.1.4`")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Self::Value, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(RangesVisitor(PhantomData))
+    }
+}
+
 /// Generate version sets from a random vector of deltas between randomly inclusive or exclusive
 /// bounds.
 #[cfg(any(feature = "proptest", test))]
@@ -1220,6 +1447,24 @@ pub mod tests {
             assert_eq!(range, r);
         }
 
+        #[cfg(feature = "serde")]
+        #[test]
+        fn serde_text_round_trip(range in proptest_strategy()) {
+            #[derive(serde::Serialize, serde::Deserialize)]
+            struct Requirement(#[serde(with = "crate::text")] Ranges<u32>);
+
+            let s = ron::ser::to_string(&Requirement(range.clone())).unwrap();
+            let Requirement(r) = ron::de::from_str(&s).unwrap();
+            assert_eq!(range, r);
+        }
+
+        // Testing parsing ----------------------------------
+
+        #[test]
+        fn parse_display_round_trip(range in proptest_strategy()) {
+            assert_eq!(range.to_string().parse::<Ranges<u32>>(), Ok(range));
+        }
+
         // Testing negate ----------------------------------
 
         #[test]
@@ -1412,6 +1657,38 @@ pub mod tests {
         assert_eq!(range.contains(&version), range.contains("1"));
     }
 
+    #[test]
+    fn segments() {
+        let range: Ranges<u32> = Ranges::strictly_lower_than(2u32)
+            .union(&Ranges::between(5u32, 8u32))
+            .union(&Ranges::higher_than(10u32));
+        assert_eq!(range.iter().len(), 3);
+        assert_eq!(
+            range.iter().next_back(),
+            Some((&Bound::Included(10), &Bound::Unbounded))
+        );
+        assert_eq!(
+            range.segment_containing(&6),
+            Some((&Bound::Included(5), &Bound::Excluded(8)))
+        );
+        assert_eq!(range.segment_containing(&8), None);
+
+        let below_2: Ranges<u32> = Ranges::strictly_lower_than(2u32);
+        assert!(below_2.is_adjacent(&Ranges::higher_than(2u32)));
+        assert!(!below_2.is_adjacent(&Ranges::higher_than(3u32)));
+        assert!(!below_2.is_adjacent(&Ranges::strictly_higher_than(2u32)));
+        assert!(!Ranges::<u32>::lower_than(2u32).is_adjacent(&Ranges::higher_than(2u32)));
+        assert!(range.is_adjacent(&Ranges::between(8u32, 9u32)));
+
+        // Ties go to the highest segment.
+        let versions = [0u32, 6, 7, 12, 15, 9];
+        assert_eq!(
+            range.widest_segment(versions.iter()),
+            Some((&Bound::Included(10), &Bound::Unbounded))
+        );
+        assert_eq!(range.widest_segment([3u32, 9].iter()), None);
+    }
+
     #[test]
     fn simplify_can_take_owned() {
         let range: Ranges<u8> = Ranges::singleton(1);